@@ -5,6 +5,8 @@ use {
     crate::{
         config::{self, Config},
         encoding::Encoding,
+        gopher, local,
+        menu::LinkFormat,
         ui::Mode,
     },
     std::{error::Error, fmt, result::Result},
@@ -106,6 +108,7 @@ pub fn parse<T: AsRef<str>>(args: &[T]) -> Result<Config, ArgError> {
     let mut set_nomedia = false;
     let mut set_autoplay = false;
     let mut set_noautoplay = false;
+    let mut set_json = false;
 
     while let Some(arg) = iter.next() {
         match arg.as_ref() {
@@ -125,7 +128,19 @@ pub fn parse<T: AsRef<str>>(args: &[T]) -> Result<Config, ArgError> {
                 }
             }
             "-p" | "--print" | "-print" => cfg.mode = Mode::Print,
+            "--dump-links" | "-dump-links" => cfg.mode = Mode::DumpLinks(LinkFormat::Tsv),
+            "--json" | "-json" => set_json = true,
             "-l" | "--local" | "-local" => cfg.start = "gopher://127.0.0.1:7070".into(),
+            "--serve-local" | "-serve-local" => {
+                if let Some(dir) = iter.next() {
+                    cfg.serve_local = Some(dir.as_ref().to_string());
+                    if !got_url {
+                        cfg.start = format!("gopher://{}/1/", local::HOST);
+                    }
+                } else {
+                    return Err(ArgError::new("--serve-local expects a DIR arg"));
+                }
+            }
             "-C" | "--no-config" | "-no-config" => {}
             "-c" | "--config" | "-config" => {
                 iter.next(); // skip arg
@@ -143,6 +158,7 @@ pub fn parse<T: AsRef<str>>(args: &[T]) -> Result<Config, ArgError> {
             "--print-theme" => {
                 cfg.mode = Mode::PrintTheme;
             }
+            "--print-keys" | "-print-keys" => cfg.mode = Mode::PrintKeys,
             "-s" | "--tls" | "-tls" => {
                 if set_notls {
                     return Err(ArgError::new("can't set both --tls and --no-tls"));
@@ -223,9 +239,14 @@ pub fn parse<T: AsRef<str>>(args: &[T]) -> Result<Config, ArgError> {
                 cfg.autoplay = false;
                 set_noautoplay = true;
             }
+            "-i" | "--incognito" | "-incognito" | "--no-history" | "-no-history" => {
+                cfg.incognito = true;
+            }
             "-e" | "--encoding" | "-encoding" => {
                 if let Some(encoding) = iter.next() {
-                    cfg.encoding = Encoding::from_str(encoding.as_ref())
+                    cfg.encoding = encoding
+                        .as_ref()
+                        .parse::<Encoding>()
                         .map_err(|e| ArgError::new(e.to_string()))?;
                 } else {
                     return Err(ArgError::new("--encoding expects an ENCODING arg"));
@@ -238,7 +259,7 @@ pub fn parse<T: AsRef<str>>(args: &[T]) -> Result<Config, ArgError> {
                     return Err(ArgError::new(format!("unknown argument: {}", arg)));
                 } else {
                     got_url = true;
-                    cfg.start = arg.trim().into();
+                    cfg.start = gopher::normalize_url(arg);
                 }
             }
         }
@@ -248,18 +269,58 @@ pub fn parse<T: AsRef<str>>(args: &[T]) -> Result<Config, ArgError> {
         return Err(ArgError::new("can't set both --tor and --tls"));
     }
 
+    if set_json {
+        if !matches!(cfg.mode, Mode::DumpLinks(_)) {
+            return Err(ArgError::new("--json only works with --dump-links"));
+        }
+        cfg.mode = Mode::DumpLinks(LinkFormat::Json);
+    }
+
     #[cfg(not(test))]
     {
-        if !atty::is(atty::Stream::Stdout)
-            && !matches!(cfg.mode, Mode::Raw | Mode::Print | Mode::PrintTheme)
-        {
-            cfg.mode = Mode::NoTTY;
-        }
+        cfg.mode = tty_mode(
+            cfg.mode,
+            atty::is(atty::Stream::Stdin),
+            atty::is(atty::Stream::Stdout),
+            got_url,
+        )?;
     }
 
     Ok(cfg)
 }
 
+/// The mode to really run in, given whether stdin and stdout are
+/// terminals. Interactive mode needs both. Without a terminal to
+/// print to we print plain text, and without one to read keys from we
+/// do the same if there's a URL to print, or give up if there isn't.
+fn tty_mode(mode: Mode, stdin: bool, stdout: bool, got_url: bool) -> Result<Mode, ArgError> {
+    if matches!(
+        mode,
+        Mode::Raw
+            | Mode::Print
+            | Mode::PrintTheme
+            | Mode::PrintKeys
+            | Mode::DumpLinks(_)
+            | Mode::Version
+            | Mode::Help
+    ) {
+        return Ok(mode);
+    }
+    if !stdout {
+        return Ok(Mode::NoTTY);
+    }
+    if !stdin {
+        if got_url {
+            return Ok(Mode::NoTTY);
+        }
+        return Err(ArgError::new(
+            "stdin isn't a terminal, so phetch can't run interactively. \
+             Try --print, --raw, or --dump-links with a URL.",
+        ));
+    }
+    Ok(mode)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,7 +329,7 @@ mod tests {
     fn test_simple() {
         let cfg = parse(&["-l"]).expect("failed to parse");
         assert_eq!(cfg.start, "gopher://127.0.0.1:7070");
-        assert_eq!(cfg.wide, false);
+        assert!(!cfg.wide);
     }
 
     #[test]
@@ -289,6 +350,36 @@ mod tests {
         assert_eq!(err.to_string(), "unknown argument: sdf2.org");
     }
 
+    #[test]
+    fn test_dump_links() {
+        let cfg = parse(&["--dump-links", "sdf.org"]).expect("should work");
+        assert_eq!(cfg.mode, Mode::DumpLinks(LinkFormat::Tsv));
+        let cfg = parse(&["--json", "--dump-links", "sdf.org"]).expect("should work");
+        assert_eq!(cfg.mode, Mode::DumpLinks(LinkFormat::Json));
+        let err = parse(&["--json", "sdf.org"]).expect_err("should fail");
+        assert_eq!(err.to_string(), "--json only works with --dump-links");
+    }
+
+    #[test]
+    fn test_tty_mode() {
+        assert_eq!(tty_mode(Mode::Run, true, true, false).unwrap(), Mode::Run);
+        assert_eq!(
+            tty_mode(Mode::Run, true, false, false).unwrap(),
+            Mode::NoTTY
+        );
+        assert_eq!(tty_mode(Mode::Run, false, true, true).unwrap(), Mode::NoTTY);
+        let err = tty_mode(Mode::Run, false, true, false).unwrap_err();
+        assert!(err.to_string().contains("--print"));
+        assert_eq!(
+            tty_mode(Mode::Print, false, false, true).unwrap(),
+            Mode::Print
+        );
+        assert_eq!(
+            tty_mode(Mode::Help, false, true, false).unwrap(),
+            Mode::Help
+        );
+    }
+
     #[test]
     fn test_local() {
         let cfg = parse(&["--local"]).expect("should work");
@@ -296,7 +387,19 @@ mod tests {
 
         let cfg = parse(&["-s", "-l"]).expect("should work");
         assert_eq!(cfg.start, "gopher://127.0.0.1:7070");
-        assert_eq!(cfg.tls, true);
+        assert!(cfg.tls);
+    }
+
+    #[test]
+    fn test_serve_local() {
+        let cfg = parse(&["--serve-local", "site"]).expect("should work");
+        assert_eq!(cfg.serve_local.as_deref(), Some("site"));
+        assert_eq!(cfg.start, "gopher://local/1/");
+
+        let cfg = parse(&["gopher://local/0/about.txt", "--serve-local", "site"]).unwrap();
+        assert_eq!(cfg.start, "gopher://local/0/about.txt");
+
+        assert!(parse(&["--serve-local"]).is_err());
     }
 
     #[test]
@@ -324,6 +427,16 @@ mod tests {
         assert_eq!(cfg.mode, Mode::Help);
     }
 
+    #[test]
+    fn test_print_keys() {
+        let cfg = parse(&["--print-keys"]).expect("should work");
+        assert_eq!(cfg.mode, Mode::PrintKeys);
+        assert_eq!(
+            tty_mode(cfg.mode, false, false, false).unwrap(),
+            Mode::PrintKeys
+        );
+    }
+
     #[test]
     fn test_version() {
         let cfg = parse(&["--version"]).expect("should work");
@@ -341,15 +454,27 @@ mod tests {
         assert_eq!(err.to_string(), "can\'t set both --tls and --no-tls");
 
         let cfg = parse(&["--tor", "--no-tls"]).expect("should work");
-        assert_eq!(cfg.tor, true);
-        assert_eq!(cfg.tls, false);
+        assert!(cfg.tor);
+        assert!(!cfg.tls);
+    }
+
+    #[test]
+    fn test_incognito() {
+        let cfg = parse(&["sdf.org"]).expect("should work");
+        assert!(!cfg.incognito);
+        let cfg = parse(&["--incognito", "sdf.org"]).expect("should work");
+        assert!(cfg.incognito);
+        let cfg = parse(&["-i"]).expect("should work");
+        assert!(cfg.incognito);
+        let cfg = parse(&["--no-history"]).expect("should work");
+        assert!(cfg.incognito);
     }
 
     #[test]
     fn test_mix_and_match() {
         let cfg = parse(&["-r", "-s", "-C"]).expect("should work");
         assert_eq!(cfg.mode, Mode::Raw);
-        assert_eq!(cfg.tls, true);
+        assert!(cfg.tls);
     }
 
     #[test]
@@ -379,6 +504,6 @@ mod tests {
         );
 
         let cfg = parse(&["-C"]).expect("should work");
-        assert_eq!(cfg.tls, false);
+        assert!(!cfg.tls);
     }
 }
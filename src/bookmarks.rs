@@ -1,57 +1,172 @@
-//! Bookmarks are enabled if you create a ~/.config/phetch/ directory
-//! manually. They are stored as a simple Gophermap, `BOOKMARKS_FILE`,
-//! in that directory.
+//! Bookmarks are stored as a simple Gophermap, `BOOKMARKS_FILE`, in
+//! the phetchdir, which is created the first time one is saved.
+//!
+//! The `bookmarks` config option can list more sources to show
+//! alongside them: other Gophermaps in the phetchdir, or Gopher menus
+//! someone else publishes, which are fetched once per session.
 
-use crate::phetchdir;
-use std::io::{Read, Result};
+use crate::{gopher, phetchdir};
+use std::{
+    collections::HashMap,
+    io::{Read, Result},
+    sync::Mutex,
+};
 
-/// Bookmarks only work if you've created a ~/.config/phetch/ manually.
+/// Where bookmarks are saved, in the phetchdir.
 const BOOKMARKS_FILE: &str = "bookmarks.gph";
 
-macro_rules! dir_missing_fmt {
-    () => {
-        "i\r\ni\r
-i\r\ni\x1b[91m{error}\x1b[0m
-i\r\niBookmarks can only be saved if {dir} exists.
-i\r\niRun this in your terminal to enable bookmarking:
-i\r\nimkdir -p {dir}"
-    };
+lazy_static! {
+    /// Remote bookmark menus we've already fetched this session,
+    /// keyed by URL.
+    static ref REMOTE: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
 }
 
 /// Get all bookmarks in Gophermap format.
 pub fn as_raw_menu() -> String {
-    let path = phetchdir::path();
-    if let Err(e) = path {
-        return format!(dir_missing_fmt!(), error = e, dir = phetchdir::DIR);
+    local_menu(BOOKMARKS_FILE)
+}
+
+/// Get the bookmarks from each of `sources`, in order, as a single
+/// Gophermap. A source is either a file in the phetchdir, like
+/// `bookmarks.gph`, or the URL of a Gopher menu. Remote menus are only
+/// fetched if `check` allows their URL, like the host rules do for
+/// everything else. Ones that are blocked or can't be fetched show up
+/// as an error line instead.
+pub fn as_merged_menu(
+    sources: &[String],
+    tls: bool,
+    tor: bool,
+    check: impl Fn(&str) -> crate::error::Result<()>,
+) -> String {
+    let mut out = String::new();
+    for source in sources {
+        if is_remote(source) {
+            out.push_str(&format!("i{}:\r\ni\r\n", source));
+            match check(source).and_then(|()| remote_menu(source, tls, tor)) {
+                Ok(menu) => out.push_str(&menu),
+                Err(e) => out.push_str(&format!("3Couldn't load bookmarks: {}\r\n", e)),
+            }
+        } else {
+            out.push_str(&local_menu(source));
+        }
+        out.push_str("i\r\n");
     }
+    out
+}
+
+/// Is this bookmark source a URL, rather than a file?
+fn is_remote(source: &str) -> bool {
+    source.contains("://")
+}
 
-    let mut out = format!("i{}{}:\r\ni\r\n", phetchdir::DIR, BOOKMARKS_FILE);
+/// A Gophermap file in the phetchdir, with a header.
+fn local_menu(filename: &str) -> String {
+    let mut out = format!("i{}{}:\r\ni\r\n", phetchdir::DIR, filename);
 
-    let path = path.unwrap().join(BOOKMARKS_FILE);
-    if !path.exists() {
+    if !phetchdir::exists(filename) {
         out.push_str("iNo bookmarks yet.\r\ni\r\niUse <ctrl-s> to bookmark a page.\r\n");
         return out;
     }
 
-    match phetchdir::load(BOOKMARKS_FILE) {
-        Ok(mut reader) => {
-            if let Err(e) = reader.read_to_string(&mut out) {
-                out = format!("3{}", e);
-            }
-        }
+    match phetchdir::load(filename) {
+        Ok(reader) => match read_menu(reader) {
+            Ok(menu) => out.push_str(&menu),
+            Err(e) => out = format!("3{}", e),
+        },
         Err(e) => out = format!("3{}", e),
     }
 
     out
 }
 
+/// Read a Gophermap someone may have edited by hand, with every line
+/// ending in `\r\n` no matter if it had `\n`, `\r\n`, or nothing at
+/// all at the end of the file.
+fn read_menu(mut reader: impl Read) -> Result<String> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut menu = String::with_capacity(text.len() + 2);
+    for line in text.lines() {
+        menu.push_str(line);
+        menu.push_str("\r\n");
+    }
+    Ok(menu)
+}
+
+/// Fetch a remote bookmarks menu, or use the copy we got earlier.
+fn remote_menu(url: &str, tls: bool, tor: bool) -> crate::error::Result<String> {
+    if let Some(menu) = REMOTE.lock().unwrap().get(url) {
+        return Ok(menu.clone());
+    }
+    let (_, res) = gopher::fetch_url(url, tls, tor)?;
+    let mut menu = gopher::response_to_string(&res);
+    // drop the end of response marker, there's more to come
+    if let Some(end) = menu.rfind("\n.") {
+        if menu[end + 2..].trim().is_empty() {
+            menu.truncate(end + 1);
+        }
+    }
+    if !menu.ends_with('\n') {
+        menu.push_str("\r\n");
+    }
+    REMOTE.lock().unwrap().insert(url.to_string(), menu.clone());
+    Ok(menu)
+}
+
 /// Save a single bookmark entry.
 pub fn save(label: &str, url: &str) -> Result<()> {
+    save_to(BOOKMARKS_FILE, label, url)
+}
+
+/// Save a single bookmark entry to the collection `name`, which lives
+/// in `NAME.gph` in the phetchdir.
+pub fn save_to_collection(name: &str, label: &str, url: &str) -> Result<()> {
+    save_to(&format!("{}.gph", name), label, url)
+}
+
+/// Save a single bookmark entry to `filename` in the phetchdir,
+/// creating the phetchdir first if need be.
+fn save_to(filename: &str, label: &str, url: &str) -> Result<()> {
+    phetchdir::create()?;
     phetchdir::append(
-        BOOKMARKS_FILE,
+        filename,
         label
             .trim_start_matches("gopher://")
             .trim_end_matches("/1/"),
         url,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::SharedConfig, menu::Menu, ui::View};
+
+    #[test]
+    fn test_read_menu_line_endings() {
+        let file = "1phkt.io\t/\tphkt.io\t70\r\n\
+                    0about\t/about.txt\tphkt.io\t70\n\
+                    1floodgap\t/\tgopher.floodgap.com\t70\r\n\
+                    1local\t/\t127.0.0.1\t7070";
+        let raw = read_menu(file.as_bytes()).unwrap();
+        assert_eq!(raw.matches("\r\n").count(), 4);
+        assert!(!raw.replace("\r\n", "").contains(['\r', '\n']));
+
+        let menu = Menu::from(
+            "gopher://phetch/1/bookmarks",
+            raw,
+            SharedConfig::default(),
+            false,
+        );
+        let urls: Vec<_> = menu.links().into_iter().map(|(_, url)| url).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "gopher://phkt.io",
+                "gopher://phkt.io/0/about.txt",
+                "gopher://gopher.floodgap.com",
+                "gopher://127.0.0.1:7070",
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Pages flagged as broken with `!`, for people who keep link lists
+//! and want to come back and fix them later. Each one is appended to
+//! `BROKEN_FILE` in the phetchdir as a menu item with the time it was
+//! flagged and an optional reason, and they're all listed at
+//! gopher://phetch/1/broken.
+
+use crate::{clock, phetchdir};
+use std::{
+    io::{Read, Result},
+    path::Path,
+};
+
+/// Where broken pages are logged.
+const BROKEN_FILE: &str = "broken.gph";
+
+/// Flag the page at `url` as broken, with an optional `reason`.
+pub fn flag(url: &str, reason: &str) -> Result<()> {
+    flag_in(&phetchdir::path()?.join(BROKEN_FILE), url, reason)
+}
+
+/// Add `url` to the broken log at `path`.
+fn flag_in(path: &Path, url: &str, reason: &str) -> Result<()> {
+    let reason = reason.replace(['\t', '\r', '\n'], " ");
+    let mut label = format!("{} {}", timestamp(clock::unix_secs()), url);
+    if !reason.trim().is_empty() {
+        label.push_str(&format!(" - {}", reason.trim()));
+    }
+    phetchdir::append_to(path, &label, url)
+}
+
+/// Get all flagged pages in Gophermap format.
+pub fn as_raw_menu() -> String {
+    let mut out = format!("i{}{}:\r\ni\r\n", phetchdir::DIR, BROKEN_FILE);
+    match phetchdir::load(BROKEN_FILE) {
+        Ok(mut reader) => {
+            if let Err(e) = reader.read_to_string(&mut out) {
+                out = format!("3{}\r\n", e);
+            }
+        }
+        Err(_) if phetchdir::path().is_ok() => {
+            out.push_str("iNothing flagged yet.\r\ni\r\niUse ! to flag a broken page.\r\n")
+        }
+        Err(e) => out = format!("3{}\r\n", e),
+    }
+    out
+}
+
+/// "YYYY-MM-DD HH:MM" in UTC for `secs` since the epoch.
+fn timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let (hour, min) = (secs % 86400 / 3600, secs % 3600 / 60);
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, hour, min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::{fs, time::Duration};
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0), "1970-01-01 00:00");
+        assert_eq!(timestamp(951_782_400), "2000-02-29 00:00");
+        assert_eq!(timestamp(1_760_531_700), "2025-10-15 12:35");
+    }
+
+    #[test]
+    fn test_flag() {
+        let dir = phetchdir::TestDir::install("broken-flag");
+        let path = dir.path.join(BROKEN_FILE);
+        let clock = ManualClock::install(Duration::from_secs(1_760_531_700));
+        flag_in(&path, "gopher://phkt.io/0/gone.txt", "404\tagain\n").unwrap();
+        clock.set(Duration::ZERO);
+        flag_in(&path, "gopher://sdf.org:7070/1/old", " ").unwrap();
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            log,
+            "02025-10-15 12:35 gopher://phkt.io/0/gone.txt - 404 again\t/gone.txt\tphkt.io\t70\r\n\
+             11970-01-01 00:00 gopher://sdf.org:7070/1/old\t/old\tsdf.org\t7070\r\n"
+        );
+    }
+}
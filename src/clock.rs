@@ -0,0 +1,140 @@
+//! Where phetch gets the time. Anything that measures how long
+//! something took asks `now()`, and anything that logs a timestamp
+//! asks `unix_secs()`, instead of calling `Instant::now()` or
+//! `SystemTime::now()` itself, so tests can stop the clock with a
+//! `ManualClock` and move it forward by hand.
+//!
+//! `now()` is monotonic, so a wall clock that jumps, say when NTP
+//! corrects it, can't make a wait last forever or a request look like
+//! it took no time at all. Wall time is only used for showing it.
+//!
+//! `ManualClock` only exists in test builds, and only affects the
+//! thread that installed it, so tests running side by side don't see
+//! each other's time.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+#[cfg(test)]
+thread_local! {
+    /// The time on this thread's `ManualClock`, if one's installed.
+    static MANUAL: RefCell<Option<Rc<Cell<Duration>>>> = const { RefCell::new(None) };
+}
+
+lazy_static! {
+    /// What `now()` counts from.
+    static ref START: Instant = Instant::now();
+}
+
+/// The time on this thread's `ManualClock`, if there is one.
+fn manual() -> Option<Duration> {
+    #[cfg(test)]
+    {
+        if let Some(now) = MANUAL.with(|m| m.borrow().as_ref().map(|now| now.get())) {
+            return Some(now);
+        }
+    }
+    None
+}
+
+/// The time on a clock that only goes forward. It doesn't mean
+/// anything on its own, only compared to other `now()` times.
+pub fn now() -> Duration {
+    manual().unwrap_or_else(|| START.elapsed())
+}
+
+/// Seconds since the Unix epoch, for timestamps.
+pub fn unix_secs() -> u64 {
+    manual()
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+        })
+        .as_secs()
+}
+
+/// How long it's been since `start`, which came from `now()`.
+pub fn elapsed(start: Duration) -> Duration {
+    now().saturating_sub(start)
+}
+
+/// How long until `at`, which is a `now()` time. Zero if it's passed.
+pub fn until(at: Duration) -> Duration {
+    at.saturating_sub(now())
+}
+
+/// "HH:MM:SS" in the local time zone for `secs` since the Unix epoch.
+pub fn local_time(secs: u64) -> String {
+    let time = secs as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm we hand it.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&time, &mut tm) };
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+/// A clock for tests that only moves when told to. Installing one
+/// makes `now()` and `unix_secs()` on this thread return its time
+/// until it's dropped.
+#[cfg(test)]
+pub(crate) struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    /// Stop this thread's clock at `now`, which is also the time
+    /// since the Unix epoch.
+    pub(crate) fn install(now: Duration) -> ManualClock {
+        let now = Rc::new(Cell::new(now));
+        MANUAL.with(|m| *m.borrow_mut() = Some(now.clone()));
+        ManualClock { now }
+    }
+
+    /// Move the clock forward by `by`.
+    pub(crate) fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    /// Set the clock to `now`.
+    pub(crate) fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+#[cfg(test)]
+impl Drop for ManualClock {
+    fn drop(&mut self) {
+        MANUAL.with(|m| *m.borrow_mut() = None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let real = now();
+        assert!(unix_secs() > 1_600_000_000);
+        {
+            let clock = ManualClock::install(Duration::from_secs(100));
+            assert_eq!(unix_secs(), 100);
+            let start = now();
+            clock.advance(Duration::from_millis(1500));
+            assert_eq!(elapsed(start), Duration::from_millis(1500));
+            clock.set(Duration::from_secs(50));
+            assert_eq!(elapsed(start), Duration::ZERO);
+
+            // other threads keep real time
+            let other = std::thread::spawn(now).join().unwrap();
+            assert!(other >= real);
+        }
+        assert!(now() >= real);
+    }
+}
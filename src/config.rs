@@ -6,9 +6,10 @@
 use {
     crate::{
         encoding::Encoding,
+        gopher::{self, TCP_TIMEOUT_IN_SECS},
         phetchdir,
-        theme::{to_color, Theme},
-        ui,
+        theme::{to_color, ColorDepth, Theme, PRESETS},
+        ui::{self, Keymap},
     },
     std::{
         collections::HashMap,
@@ -24,9 +25,45 @@ pub type SharedConfig = Arc<RwLock<Config>>;
 /// phetch will look for this file on load.
 const CONFIG_FILE: &str = "phetch.conf";
 
+/// The TOML version of `CONFIG_FILE`. Used instead if it exists.
+const TOML_FILE: &str = "config.toml";
+
 /// Default start page.
 const DEFAULT_START: &str = "gopher://phetch/1/home";
 
+/// Default tag for links to the web.
+const DEFAULT_WEB_TAG: &str = "[www]";
+
+/// Default key for labeling links with `hint_chars`.
+const DEFAULT_HINT_KEY: char = '\'';
+
+/// Default letters for link hints: the home row.
+const DEFAULT_HINT_CHARS: &str = "asdfghjkl";
+
+/// Default `link_format`: a Markdown link.
+const DEFAULT_LINK_FORMAT: &str = "[{title}]({url})";
+
+/// Default tab stop width for text pages.
+const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Default search server for the Go to URL prompt. Veronica-2.
+const DEFAULT_SEARCH_URL: &str = "gopher://gopher.floodgap.com/7/v2/vs";
+
+/// Default number of pages to keep in memory.
+const DEFAULT_CACHE_SIZE: usize = 20;
+
+/// Default number of entries to keep in the history file.
+const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Default seconds between reloads of a page pinned with `L`.
+const DEFAULT_PIN_INTERVAL: u64 = 60;
+
+/// Default milliseconds to wait before showing the spinner.
+const DEFAULT_SPINNER_DELAY: u64 = 150;
+
+/// Default web proxy for sharing pages.
+const DEFAULT_WEB_PROXY: &str = "https://gopher.floodgap.com/gopher/gw?a={url}";
+
 /// Default media player.
 const DEFAULT_MEDIA_PLAYER: &str = "mpv";
 
@@ -46,12 +83,25 @@ tor no
 # Always start in wide mode. (--wide)
 wide no
 
+# Remember wide mode for each page instead of for the whole session.
+wide_per_url no
+
 # Program to use to open media files.
 media mpv
 
 # Whether to auto play media
 autoplay no
 
+# Hand the player the gopher:// URL to stream, instead of downloading
+# the file to a temporary one first. Needs a player that speaks Gopher.
+stream_media no
+
+# Don't record visited pages in history. (--incognito)
+incognito no
+
+# Most entries to keep in history.gph. The oldest go first. 0 = no limit
+history_size 1000
+
 # Use emoji indicators for TLS & Tor. (--emoji)
 emoji no
 
@@ -61,17 +111,182 @@ encoding utf8
 # Wrap text at N columns. 0 = off (--wrap)
 wrap 0
 
-# How many lines to page up/down by? 0 = full screen
+# How many lines to page up/down by? 0 = a screenful, keeping the
+# last two lines in view
 scroll 0
 
+# Show a scrollbar on the right edge of long pages.
+scrollbar no
+
+# Blank the screen and show what's loading while a page is fetched.
+placeholder no
+
+# Wait this many milliseconds before showing the loading spinner, so
+# quick requests don't flicker. 0 = show it right away
+spinner_delay 150
+
+# When a download's host can't be reached, try it again after the
+# next page loads instead of giving up.
+queue_downloads no
+
+# Keep the last N pages in memory, so going back to them is instant.
+# 0 = off
+cache_size 20
+
+# Seconds between reloads of a page pinned with `L`.
+pin_interval 60
+
+# Biggest page to load, in megabytes. Bigger ones can be downloaded
+# instead. 0 = no limit
+max_response 8
+
+# Biggest file to download, in megabytes. 0 = no limit
+max_download 0
+
+# Wait at least this many milliseconds between requests to the same
+# server, to go easy on small ones. 0 = off
+request_delay 0
+
+# Remember the addresses of this many servers for the session, so
+# they aren't looked up on every page. 0 = look up every time
+dns_cache 100
+
+# Seconds to wait for a server to answer before giving up. Slow Tor
+# routes may need more.
+timeout 10
+
+# Log every request to requests.log in ~/.config/phetch, for bug
+# reports. Setting PHETCH_LOG in the environment does the same.
+log_requests no
+
+# Leave search queries out of the request log.
+log_redact yes
+
+# Expand tabs in text pages to N columns. 0 = leave them alone
+tab_width 8
+
+# Lay out menus of short links in N columns. 0 = auto
+columns 1
+
+# Mark links that are new since your last visit to a menu.
+mark_new no
+
+# Show the current page's title in the terminal's window or tab
+# title, and put the old one back on exit.
+set_title no
+
+# Ask before opening a telnet link, showing its host, port, and login.
+confirm_telnet yes
+
+# Pass a telnet link's login to the telnet client with `-l`.
+telnet_login no
+
+# Show *bold* and _underline_ in info lines with real emphasis.
+emphasis no
+
+# Press this key in a menu to label the links on screen with letters,
+# then type a label to open its link. no = off
+hint_key '
+
+# Letters to label links with, in the order to use them.
+hint_chars asdfghjkl
+
+# Opening a link adds a new view (push) or replaces the current one.
+# same_host replaces it for menus on the same server, and pushes
+# everything else.
+open push
+
+# Where `y` copies to: clipboard, primary (X11 middle-click), or both.
+clipboard clipboard
+
+# What `M` copies for the current page. {title} and {url} are filled
+# in. Try [[{url}][{title}]] for org-mode.
+link_format [{title}]({url})
+
+# Colors the terminal can show: 16, 256, or truecolor. Theme colors
+# it can't show are swapped for the closest ones. auto = guess from
+# $COLORTERM and $TERM
+colors auto
+
+# Tag shown after links that leave gopherspace. `web_tag no` hides it.
+web_tag [www]
+
+# Tag shown after Gopher+ items. Off by default.
+# plus_tag [+]
+
+# Marker at the start of the status line for the focused view, in the
+# ui.focus color. Off by default.
+# focus_marker >
+
+# Search server to offer when what you type at the Go to URL prompt
+# doesn't look like a URL. `search_url no` turns it off.
+search_url gopher://gopher.floodgap.com/7/v2/vs
+
+# Web proxy URL that `Y` copies for sharing a page. {url} is the
+# page's encoded URL. {host}, {port}, {type}, and {selector} work too.
+web_proxy https://gopher.floodgap.com/gopher/gw?a={url}
+
+# What the status line shows when there's no message, with {title},
+# {url}, {type}, {pos} (line/total), and {links} filled in.
+# status_format {title} - {url} {pos}
+
+# Hosts whose responses start with a copy of the selector. Separate
+# them with spaces. The copy is removed. `strip_echo no` = none
+strip_echo no
+
+# Only visit these hosts. Separate them with spaces. `*` matches
+# anything, ie *.sdf.org. `allow_hosts no` = no restriction
+allow_hosts no
+
+# Never visit these hosts. Same format as `allow_hosts`.
+deny_hosts no
+
+# Download files from these hosts without asking first. Same format
+# as `allow_hosts`, plus an optional :port, ie files.phkt.io:7070
+trusted_hosts no
+
+# Selectors that want a password after a tab, as host/selector. `*`
+# works in both. You're asked for it, hidden, before they're fetched.
+ask_password no
+
+# Save URLs with the password you typed in history, so you don't have
+# to type it again. They're saved without it otherwise, and never
+# kept anywhere else, like bookmarks or the links `mark_new` remembers.
+save_passwords no
+
+# Local address to connect from, if not the default.
+# bind 192.168.1.2
+
+# Where to find bookmarks: files in ~/.config/phetch/ or URLs of
+# Gopher menus, in the order to show them. Separate them with spaces.
+# bookmarks bookmarks.gph gopher://phkt.io/1/links
+
+# Bookmark collections, as letter=name pairs. Press `s` then a letter
+# to save to ~/.config/phetch/NAME.gph. `s s` saves to bookmarks.gph.
+# collections r=reading w=work
+
 # Path to theme file, if any
 # theme ~/.config/phetch/pink.theme
 
+# Built-in theme to start from: default, light, high-contrast, or
+# mono. `C` cycles through them. Colors set below still apply.
+# theme_preset light
+
+# More keys for back, forward, open, up, down, quit, reload, or
+# bookmark, as a letter, ctrl-x, or a name like left, enter, space,
+# pgdown, or esc. The usual keys still work.
+# key.back h
+# key.forward l
+
 # Inline Theme
 ui.cursor white bold
 ui.number magenta
 ui.menu yellow
 ui.text white
+ui.new green bold
+ui.focus cyan bold
+ui.selector grey
+ui.scrollbar grey
 item.text cyan
 item.menu blue
 item.error red
@@ -83,6 +298,241 @@ item.media green underline
 item.unsupported whitebg red
 ";
 
+/// The same default config as `DEFAULT_CONFIG`, as a config.toml.
+pub const DEFAULT_TOML: &str = "## default config.toml for the phetch gopher client
+## gopher://phkt.io/1/phetch
+
+# Page to load when launched with no URL argument.
+start = \"gopher://phetch/1/home\"
+
+# Always use TLS mode. (--tls)
+tls = false
+
+# Connect using local Tor proxy. (--tor)
+tor = false
+
+# Always start in wide mode. (--wide)
+wide = false
+
+# Remember wide mode for each page instead of for the whole session.
+wide_per_url = false
+
+# Program to use to open media files.
+media = \"mpv\"
+
+# Whether to auto play media
+autoplay = false
+
+# Hand the player the gopher:// URL to stream, instead of downloading
+# the file to a temporary one first. Needs a player that speaks Gopher.
+stream_media = false
+
+# Don't record visited pages in history. (--incognito)
+incognito = false
+
+# Most entries to keep in history.gph. The oldest go first. 0 = no limit
+history_size = 1000
+
+# Use emoji indicators for TLS & Tor. (--emoji)
+emoji = false
+
+# Encoding. Only CP437 and UTF8 are supported.
+encoding = \"utf8\"
+
+# Wrap text at N columns. 0 = off (--wrap)
+wrap = 0
+
+# How many lines to page up/down by? 0 = a screenful, keeping the
+# last two lines in view
+scroll = 0
+
+# Show a scrollbar on the right edge of long pages.
+scrollbar = false
+
+# Blank the screen and show what's loading while a page is fetched.
+placeholder = false
+
+# Wait this many milliseconds before showing the loading spinner, so
+# quick requests don't flicker. 0 = show it right away
+spinner_delay = 150
+
+# When a download's host can't be reached, try it again after the
+# next page loads instead of giving up.
+queue_downloads = false
+
+# Keep the last N pages in memory, so going back to them is instant.
+# 0 = off
+cache_size = 20
+
+# Seconds between reloads of a page pinned with `L`.
+pin_interval = 60
+
+# Biggest page to load, in megabytes. Bigger ones can be downloaded
+# instead. 0 = no limit
+max_response = 8
+
+# Biggest file to download, in megabytes. 0 = no limit
+max_download = 0
+
+# Wait at least this many milliseconds between requests to the same
+# server, to go easy on small ones. 0 = off
+request_delay = 0
+
+# Remember the addresses of this many servers for the session, so
+# they aren't looked up on every page. 0 = look up every time
+dns_cache = 100
+
+# Seconds to wait for a server to answer before giving up. Slow Tor
+# routes may need more.
+timeout = 10
+
+# Log every request to requests.log in ~/.config/phetch, for bug
+# reports. Setting PHETCH_LOG in the environment does the same.
+log_requests = false
+
+# Leave search queries out of the request log.
+log_redact = true
+
+# Expand tabs in text pages to N columns. 0 = leave them alone
+tab_width = 8
+
+# Lay out menus of short links in N columns. 0 = auto
+columns = 1
+
+# Mark links that are new since your last visit to a menu.
+mark_new = false
+
+# Show the current page's title in the terminal's window or tab
+# title, and put the old one back on exit.
+set_title = false
+
+# Ask before opening a telnet link, showing its host, port, and login.
+confirm_telnet = true
+
+# Pass a telnet link's login to the telnet client with `-l`.
+telnet_login = false
+
+# Show *bold* and _underline_ in info lines with real emphasis.
+emphasis = false
+
+# Press this key in a menu to label the links on screen with letters,
+# then type a label to open its link. false = off
+hint_key = \"'\"
+
+# Letters to label links with, in the order to use them.
+hint_chars = \"asdfghjkl\"
+
+# Opening a link adds a new view (push) or replaces the current one.
+# same_host replaces it for menus on the same server, and pushes
+# everything else.
+open = \"push\"
+
+# Where `y` copies to: clipboard, primary (X11 middle-click), or both.
+clipboard = \"clipboard\"
+
+# What `M` copies for the current page. {title} and {url} are filled
+# in. Try [[{url}][{title}]] for org-mode.
+link_format = \"[{title}]({url})\"
+
+# Colors the terminal can show: 16, 256, or truecolor. Theme colors
+# it can't show are swapped for the closest ones. auto = guess from
+# $COLORTERM and $TERM
+colors = \"auto\"
+
+# Tag shown after links that leave gopherspace. `false` hides it.
+web_tag = \"[www]\"
+
+# Tag shown after Gopher+ items. Off by default.
+# plus_tag = \"[+]\"
+
+# Marker at the start of the status line for the focused view, in the
+# ui.focus color. Off by default.
+# focus_marker = \">\"
+
+# Search server to offer when what you type at the Go to URL prompt
+# doesn't look like a URL. `false` turns it off.
+search_url = \"gopher://gopher.floodgap.com/7/v2/vs\"
+
+# Web proxy URL that `Y` copies for sharing a page. {url} is the
+# page's encoded URL. {host}, {port}, {type}, and {selector} work too.
+web_proxy = \"https://gopher.floodgap.com/gopher/gw?a={url}\"
+
+# What the status line shows when there's no message, with {title},
+# {url}, {type}, {pos} (line/total), and {links} filled in.
+# status_format = \"{title} - {url} {pos}\"
+
+# Hosts whose responses start with a copy of the selector. Separate
+# them with spaces. The copy is removed. `false` = none
+strip_echo = false
+
+# Only visit these hosts. Separate them with spaces. `*` matches
+# anything, ie *.sdf.org. `false` = no restriction
+allow_hosts = false
+
+# Never visit these hosts. Same format as `allow_hosts`.
+deny_hosts = false
+
+# Download files from these hosts without asking first. Same format
+# as `allow_hosts`, plus an optional :port, ie files.phkt.io:7070
+trusted_hosts = false
+
+# Selectors that want a password after a tab, as host/selector. `*`
+# works in both. You're asked for it, hidden, before they're fetched.
+ask_password = false
+
+# Save URLs with the password you typed in history, so you don't have
+# to type it again. They're saved without it otherwise, and never
+# kept anywhere else, like bookmarks or the links `mark_new` remembers.
+save_passwords = false
+
+# Local address to connect from, if not the default.
+# bind = \"192.168.1.2\"
+
+# Where to find bookmarks: files in ~/.config/phetch/ or URLs of
+# Gopher menus, in the order to show them. Separate them with spaces.
+# bookmarks = \"bookmarks.gph gopher://phkt.io/1/links\"
+
+# Bookmark collections, as letter=name pairs. Press `s` then a letter
+# to save to ~/.config/phetch/NAME.gph. `s s` saves to bookmarks.gph.
+# collections = \"r=reading w=work\"
+
+# Path to theme file, if any
+# theme = \"~/.config/phetch/pink.theme\"
+
+# Built-in theme to start from: default, light, high-contrast, or
+# mono. `C` cycles through them. Colors set below still apply.
+# theme_preset = \"light\"
+
+# More keys for back, forward, open, up, down, quit, reload, or
+# bookmark, as a letter, ctrl-x, or a name like left, enter, space,
+# pgdown, or esc. The usual keys still work.
+# [key]
+# back = \"h\"
+# forward = \"l\"
+
+# Inline Theme
+[ui]
+cursor = \"white bold\"
+number = \"magenta\"
+menu = \"yellow\"
+text = \"white\"
+new = \"green bold\"
+focus = \"cyan bold\"
+selector = \"grey\"
+scrollbar = \"grey\"
+
+[item]
+text = \"cyan\"
+menu = \"blue\"
+error = \"red\"
+search = \"white\"
+telnet = \"grey\"
+external = \"green\"
+download = \"white underline\"
+media = \"green underline\"
+unsupported = \"whitebg red\"
+";
+
 /// Not all the config options are available in the phetch.conf. We
 /// also use this struct to keep track of our session's overall state,
 /// such as the UI mode (Print, Run, Raw, etc).
@@ -96,12 +546,23 @@ pub struct Config {
     pub tor: bool,
     /// Wide mode
     pub wide: bool,
+    /// Does toggling wide mode only affect the current URL?
+    pub wide_per_url: bool,
+    /// Wide mode for URLs it's been toggled on, this session. Can't
+    /// be set in conf file.
+    pub wide_urls: HashMap<String, bool>,
     /// Render connection status as emoji
     pub emoji: bool,
     /// Media player to use.
     pub media: Option<String>,
     /// Whether to automatically play media
     pub autoplay: bool,
+    /// Give the media player the URL instead of a downloaded file?
+    pub stream_media: bool,
+    /// Incognito mode: don't save history to disk
+    pub incognito: bool,
+    /// Most entries to keep in the history file. 0 = no limit
+    pub history_size: usize,
     /// Default encoding
     pub encoding: Encoding,
     /// UI mode. Can't be set in conf file.
@@ -110,8 +571,107 @@ pub struct Config {
     pub wrap: usize,
     /// Scroll by how many lines? 0 = full screen
     pub scroll: usize,
+    /// Draw a scrollbar on the right edge?
+    pub scrollbar: bool,
+    /// Show a "Loading" placeholder instead of the old page while
+    /// fetching?
+    pub placeholder: bool,
+    /// Milliseconds to wait before showing the spinner. 0 = none
+    pub spinner_delay: u64,
+    /// Retry downloads from unreachable hosts after the next page
+    /// loads?
+    pub queue_downloads: bool,
+    /// How many parsed pages to keep in memory. 0 = off
+    pub cache_size: usize,
+    /// Seconds between reloads of a pinned page. At least 1
+    pub pin_interval: u64,
+    /// Biggest page to load, in megabytes. 0 = no limit
+    pub max_response: usize,
+    /// Biggest file to download, in megabytes. 0 = no limit
+    pub max_download: usize,
+    /// Milliseconds to wait between requests to the same host. 0 = off
+    pub request_delay: u64,
+    /// How many servers' addresses to remember this session. 0 = off
+    pub dns_cache: usize,
+    /// Seconds to wait on a connection before giving up. At least 1
+    pub timeout: u64,
+    /// Log every request to a file in the phetchdir?
+    pub log_requests: bool,
+    /// Leave search queries out of the request log?
+    pub log_redact: bool,
+    /// Tab stop width in text pages. 0 = print tabs as-is
+    pub tab_width: usize,
+    /// Columns to use for menus of short links. 0 = auto
+    pub columns: usize,
+    /// Mark links that are new since the last visit?
+    pub mark_new: bool,
+    /// Show the current page in the terminal's window title?
+    pub set_title: bool,
+    /// Ask before opening telnet links?
+    pub confirm_telnet: bool,
+    /// Pass telnet links' logins to the telnet client?
+    pub telnet_login: bool,
+    /// Style *bold* and _underline_ markers in info lines?
+    pub emphasis: bool,
+    /// Key that labels the links on screen for jumping. None = off
+    pub hint_key: Option<char>,
+    /// Letters to label links with. At least two, no repeats
+    pub hint_chars: String,
+    /// Extra keys for actions like back and quit
+    pub keymap: Keymap,
+    /// Do links open in a new view or replace the current one?
+    pub open: OpenPolicy,
+    /// Which selections copying a URL sets
+    pub clipboard: Clipboard,
+    /// Template for what `M` copies, with {title} and {url}
+    pub link_format: String,
+    /// Colors the terminal can show. None = detect
+    pub colors: Option<ColorDepth>,
+    /// Shown after `URL:` links to the web. Empty = off
+    pub web_tag: String,
+    /// Shown after Gopher+ items. Empty = off
+    pub plus_tag: String,
+    /// Shown before the status line of the focused view. Empty = off
+    pub focus_marker: String,
+    /// Type 7 URL to search with when the Go to URL prompt gets
+    /// something that isn't a URL. Empty = off
+    pub search_url: String,
+    /// Template for the web proxy URL of a page. Empty = off
+    pub web_proxy: String,
+    /// Template for the status line when there's no message. Empty =
+    /// just messages
+    pub status_format: String,
+    /// Hosts that echo the selector back as the first line
+    pub strip_echo: Vec<String>,
+    /// If not empty, the only hosts we'll visit. Globs
+    pub allow_hosts: Vec<String>,
+    /// Hosts we won't visit. Globs
+    pub deny_hosts: Vec<String>,
+    /// Hosts to download from without confirming. Globs, with an
+    /// optional :port
+    pub trusted_hosts: Vec<String>,
+    /// Selectors to ask for a password for, as `host/selector` globs
+    pub ask_password: Vec<String>,
+    /// Keep passwords in history URLs?
+    pub save_passwords: bool,
+    /// Bookmark files and menu URLs to show together. Empty = just
+    /// bookmarks.gph
+    pub bookmarks: Vec<String>,
+    /// Bookmark collections `s` can save to, as (letter, name)
+    pub collections: Vec<(char, String)>,
+    /// Local address to make connections from. None = OS default
+    pub bind: Option<String>,
+    /// Directory to serve at gopher://local/. Can't be set in conf
+    /// file.
+    pub serve_local: Option<String>,
     /// Color Scheme
     pub theme: Theme,
+    /// Built-in theme the colors started from. See `theme::PRESETS`
+    pub theme_preset: String,
+    /// Problems found while loading the config, like unknown keys or
+    /// bad values in config.toml. Those keys were left at their
+    /// defaults.
+    pub warnings: Vec<String>,
 }
 
 impl Default for Config {
@@ -121,14 +681,170 @@ impl Default for Config {
             tls: false,
             tor: false,
             wide: false,
+            wide_per_url: false,
+            wide_urls: HashMap::new(),
             emoji: false,
             media: Some(DEFAULT_MEDIA_PLAYER.into()),
             autoplay: false,
+            stream_media: false,
+            incognito: false,
+            history_size: DEFAULT_HISTORY_SIZE,
             encoding: Encoding::default(),
             mode: ui::Mode::default(),
             wrap: 0,
             scroll: 0,
+            scrollbar: false,
+            placeholder: false,
+            spinner_delay: DEFAULT_SPINNER_DELAY,
+            queue_downloads: false,
+            cache_size: DEFAULT_CACHE_SIZE,
+            pin_interval: DEFAULT_PIN_INTERVAL,
+            max_response: gopher::DEFAULT_MAX_RESPONSE / gopher::MEGABYTE,
+            max_download: 0,
+            request_delay: 0,
+            dns_cache: gopher::DEFAULT_DNS_CACHE,
+            timeout: TCP_TIMEOUT_IN_SECS,
+            log_requests: false,
+            log_redact: true,
+            tab_width: DEFAULT_TAB_WIDTH,
+            columns: 1,
+            mark_new: false,
+            set_title: false,
+            confirm_telnet: true,
+            telnet_login: false,
+            emphasis: false,
+            hint_key: Some(DEFAULT_HINT_KEY),
+            hint_chars: String::from(DEFAULT_HINT_CHARS),
+            keymap: Keymap::default(),
+            open: OpenPolicy::default(),
+            clipboard: Clipboard::default(),
+            link_format: String::from(DEFAULT_LINK_FORMAT),
+            colors: None,
+            web_tag: String::from(DEFAULT_WEB_TAG),
+            plus_tag: String::new(),
+            focus_marker: String::new(),
+            search_url: String::from(DEFAULT_SEARCH_URL),
+            web_proxy: String::from(DEFAULT_WEB_PROXY),
+            status_format: String::new(),
+            strip_echo: vec![],
+            allow_hosts: vec![],
+            deny_hosts: vec![],
+            trusted_hosts: vec![],
+            ask_password: vec![],
+            save_passwords: false,
+            bookmarks: vec![],
+            collections: vec![],
+            bind: None,
+            serve_local: None,
             theme: Theme::default(),
+            theme_preset: "default".into(),
+            warnings: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// Are we allowed to visit `host`? Denied hosts never are. If
+    /// there's an allow list, only the hosts on it are.
+    pub fn host_allowed(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        if self.deny_hosts.iter().any(|glob| glob_match(glob, &host)) {
+            return false;
+        }
+        self.allow_hosts.is_empty() || self.allow_hosts.iter().any(|glob| glob_match(glob, &host))
+    }
+
+    /// Can we download from `host` on `port` without asking? Entries
+    /// without a port match any port.
+    pub fn trusts(&self, host: &str, port: &str) -> bool {
+        let host = host.to_lowercase();
+        self.trusted_hosts.iter().any(|entry| {
+            let (glob, want) = match entry.rsplit_once(':') {
+                Some((glob, p)) if p.bytes().all(|b| b.is_ascii_digit()) => (glob, Some(p)),
+                _ => (entry.as_str(), None),
+            };
+            glob_match(glob, &host) && want.is_none_or(|p| p == port)
+        })
+    }
+
+    /// Should we ask for a password to send along with `sel` on
+    /// `host`? Entries without a selector match all of them.
+    pub fn asks_password(&self, host: &str, sel: &str) -> bool {
+        let host = host.to_lowercase();
+        self.ask_password.iter().any(|entry| {
+            let (glob, want) = match entry.find('/') {
+                Some(i) => (&entry[..i], Some(&entry[i..])),
+                None => (entry.as_str(), None),
+            };
+            glob_match(&glob.to_lowercase(), &host) && want.is_none_or(|g| glob_match(g, sel))
+        })
+    }
+
+    /// Does `host` echo the selector back at the top of responses?
+    pub fn strips_echo(&self, host: &str) -> bool {
+        self.strip_echo.iter().any(|h| h.eq_ignore_ascii_case(host))
+    }
+
+    /// Should the view for `url` be in wide mode? What you picked for
+    /// that URL wins over the session-wide setting.
+    pub fn wide_for(&self, url: &str) -> bool {
+        *self.wide_urls.get(url).unwrap_or(&self.wide)
+    }
+}
+
+/// What opening a link does with the view you're looking at.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum OpenPolicy {
+    /// Stack a new view on top, so Back returns to the current one.
+    #[default]
+    Push,
+    /// Swap out the current view, keeping history shallow.
+    Replace,
+    /// Replace the current view with menus from the same host, and
+    /// push everything else, so browsing around one server doesn't
+    /// pile up history.
+    SameHost,
+}
+
+impl std::str::FromStr for OpenPolicy {
+    type Err = std::io::Error;
+
+    /// Accepts "push", "replace", or "same_host" and returns the
+    /// `OpenPolicy`, or an `Err`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "push" => Ok(OpenPolicy::Push),
+            "replace" => Ok(OpenPolicy::Replace),
+            "same_host" => Ok(OpenPolicy::SameHost),
+            _ => Err(error!("Expected push, replace, or same_host").into()),
+        }
+    }
+}
+
+/// Where copied URLs go. On X11 and Wayland the primary selection is
+/// what middle-click pastes. macOS only has the clipboard.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Clipboard {
+    /// The regular clipboard.
+    #[default]
+    Clipboard,
+    /// The primary selection.
+    Primary,
+    /// Both of them.
+    Both,
+}
+
+impl std::str::FromStr for Clipboard {
+    type Err = std::io::Error;
+
+    /// Accepts "clipboard", "primary", or "both" and returns the
+    /// `Clipboard`, or an `Err`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "clipboard" => Ok(Clipboard::Clipboard),
+            "primary" => Ok(Clipboard::Primary),
+            "both" => Ok(Clipboard::Both),
+            _ => Err(error!("Expected clipboard, primary, or both").into()),
         }
     }
 }
@@ -139,31 +855,42 @@ pub fn default() -> Config {
     Default::default()
 }
 
-/// Attempt to load ~/.config/phetch/phetch.conf from disk.
+/// Attempt to load ~/.config/phetch/config.toml or
+/// ~/.config/phetch/phetch.conf from disk.
 pub fn load() -> Result<Config> {
-    let mut reader = phetchdir::load(CONFIG_FILE)?;
+    let toml = phetchdir::exists(TOML_FILE);
+    let mut reader = phetchdir::load(if toml { TOML_FILE } else { CONFIG_FILE })?;
     let mut file = String::new();
     reader.read_to_string(&mut file)?;
-    parse(&file)
+    if toml {
+        Ok(parse_toml(&file))
+    } else {
+        parse(&file)
+    }
 }
 
-/// Attempt to load a config from disk.
+/// Attempt to load a config from disk. Files ending in `.toml` are
+/// parsed as TOML.
 pub fn load_file(path: &str) -> Result<Config> {
     let mut reader = OpenOptions::new().read(true).open(path)?;
     let mut file = String::new();
     reader.read_to_string(&mut file)?;
-    parse(&file)
+    if path.ends_with(".toml") {
+        Ok(parse_toml(&file))
+    } else {
+        parse(&file)
+    }
 }
 
 /// Does the config file exist?
 pub fn exists() -> bool {
-    phetchdir::exists(CONFIG_FILE)
+    phetchdir::exists(TOML_FILE) || phetchdir::exists(CONFIG_FILE)
 }
 
 /// Parses a phetch config file into a Config struct.
 fn parse(text: &str) -> Result<Config> {
     let mut cfg = Config::default();
-    let mut keys: HashMap<&str, bool> = HashMap::new();
+    let mut keys = HashMap::new();
 
     for (mut linenum, line) in text.split_terminator('\n').enumerate() {
         linenum += 1;
@@ -177,98 +904,549 @@ fn parse(text: &str) -> Result<Config> {
             continue;
         }
 
-        // line format: "KEY VALUE"
-        let parts: Vec<&str> = line.splitn(2, ' ').collect();
-        if parts.len() != 2 {
-            return Err(error!(
-                r#"Expected "key value" format on line {}: {:?}"#,
-                linenum, line
-            ));
-        }
-        let (key, val) = (parts[0], parts[1]);
-        if keys.contains_key(key) {
-            return Err(error!("Duplicate key on line {}: {}", linenum, key));
-        }
-        match key {
-            "start" => cfg.start = val.into(),
-            "emoji" => cfg.emoji = to_bool(val)?,
-            "tls" => cfg.tls = to_bool(val)?,
-            "tor" => cfg.tor = to_bool(val)?,
-            "wide" => cfg.wide = to_bool(val)?,
-            "wrap" => {
-                if let Ok(num) = val.parse() {
-                    cfg.wrap = num;
-                } else {
-                    return Err(error!(
-                        "`wrap` expects a number value on line {}: {}",
-                        linenum, val
-                    ));
-                }
+        parse_line(&mut cfg, &mut keys, linenum, line)?;
+    }
+
+    downsample_theme(&mut cfg);
+    Ok(cfg)
+}
+
+/// Make the theme fit the colors the terminal can show.
+pub(crate) fn downsample_theme(cfg: &mut Config) {
+    let depth = cfg.colors.unwrap_or_else(ColorDepth::detect);
+    cfg.theme.downsample(depth);
+}
+
+/// Parses a single "KEY VALUE" line of config and applies it to `cfg`.
+/// `keys` tracks which keys we've already seen.
+fn parse_line(
+    cfg: &mut Config,
+    keys: &mut HashMap<String, bool>,
+    linenum: usize,
+    line: &str,
+) -> Result<()> {
+    // line format: "KEY VALUE"
+    let parts: Vec<&str> = line.splitn(2, ' ').collect();
+    if parts.len() != 2 {
+        return Err(error!(
+            r#"Expected "key value" format on line {}: {:?}"#,
+            linenum, line
+        )
+        .into());
+    }
+    let (key, val) = (parts[0], parts[1]);
+    if keys.contains_key(key) {
+        return Err(error!("Duplicate key on line {}: {}", linenum, key).into());
+    }
+    match key {
+        "start" => cfg.start = val.into(),
+        "emoji" => cfg.emoji = to_bool(val)?,
+        "tls" => cfg.tls = to_bool(val)?,
+        "tor" => cfg.tor = to_bool(val)?,
+        "wide" => cfg.wide = to_bool(val)?,
+        "wide_per_url" => cfg.wide_per_url = to_bool(val)?,
+        "wrap" => {
+            if let Ok(num) = val.parse() {
+                cfg.wrap = num;
+            } else {
+                return Err(
+                    error!("`wrap` expects a number value on line {}: {}", linenum, val).into(),
+                );
+            }
+        }
+        "scroll" => {
+            if let Ok(num) = val.parse() {
+                cfg.scroll = num;
+            } else {
+                return Err(error!(
+                    "`scroll` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+        }
+        "history_size" => {
+            if let Ok(num) = val.parse() {
+                cfg.history_size = num;
+            } else {
+                return Err(error!(
+                    "`history_size` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+        }
+        "cache_size" => {
+            if let Ok(num) = val.parse() {
+                cfg.cache_size = num;
+            } else {
+                return Err(error!(
+                    "`cache_size` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+        }
+        "pin_interval" => match val.parse() {
+            Ok(num) if num > 0 => cfg.pin_interval = num,
+            _ => {
+                return Err(error!(
+                    "`pin_interval` expects a number of seconds on line {}: {}",
+                    linenum, val
+                )
+                .into())
+            }
+        },
+        "timeout" => match val.parse() {
+            Ok(num) if num > 0 => cfg.timeout = num,
+            _ => {
+                return Err(error!(
+                    "`timeout` expects a number of seconds on line {}: {}",
+                    linenum, val
+                )
+                .into())
+            }
+        },
+        "request_delay" => {
+            if let Ok(num) = val.parse() {
+                cfg.request_delay = num;
+            } else {
+                return Err(error!(
+                    "`request_delay` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+        }
+        "dns_cache" => {
+            if let Ok(num) = val.parse() {
+                cfg.dns_cache = num;
+            } else {
+                return Err(error!(
+                    "`dns_cache` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+        }
+        "spinner_delay" => {
+            if let Ok(num) = val.parse() {
+                cfg.spinner_delay = num;
+            } else {
+                return Err(error!(
+                    "`spinner_delay` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+        }
+        "tab_width" => {
+            if let Ok(num) = val.parse() {
+                cfg.tab_width = num;
+            } else {
+                return Err(error!(
+                    "`tab_width` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
             }
-            "scroll" => {
-                if let Ok(num) = val.parse() {
-                    cfg.scroll = num;
-                } else {
+        }
+        "max_response" => {
+            cfg.max_response = to_megabytes(val)
+                .map_err(|e| error!("`max_response` {} on line {}", e, linenum))?;
+        }
+        "max_download" => {
+            cfg.max_download = to_megabytes(val)
+                .map_err(|e| error!("`max_download` {} on line {}", e, linenum))?;
+        }
+        "columns" => {
+            if let Ok(num) = val.parse() {
+                cfg.columns = num;
+            } else {
+                return Err(error!(
+                    "`columns` expects a number value on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+        }
+        "media" => {
+            cfg.media = match val.to_lowercase().as_ref() {
+                "no" | "false" | "none" => None,
+                _ => Some(val.into()),
+            }
+        }
+        "autoplay" => cfg.autoplay = to_bool(val)?,
+        "stream_media" => cfg.stream_media = to_bool(val)?,
+        "scrollbar" => cfg.scrollbar = to_bool(val)?,
+        "placeholder" => cfg.placeholder = to_bool(val)?,
+        "queue_downloads" => cfg.queue_downloads = to_bool(val)?,
+        "log_requests" => cfg.log_requests = to_bool(val)?,
+        "log_redact" => cfg.log_redact = to_bool(val)?,
+        "incognito" => cfg.incognito = to_bool(val)?,
+        "mark_new" => cfg.mark_new = to_bool(val)?,
+        "set_title" => cfg.set_title = to_bool(val)?,
+        "confirm_telnet" => cfg.confirm_telnet = to_bool(val)?,
+        "telnet_login" => cfg.telnet_login = to_bool(val)?,
+        "emphasis" => cfg.emphasis = to_bool(val)?,
+        "hint_key" => {
+            let mut chars = val.chars();
+            cfg.hint_key = match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ if !to_bool(val)? => None,
+                _ => {
                     return Err(error!(
-                        "`scroll` expects a number value on line {}: {}",
+                        "`hint_key` expects a single key on line {}: {}",
                         linenum, val
-                    ));
+                    )
+                    .into())
                 }
             }
-            "media" => {
-                cfg.media = match val.to_lowercase().as_ref() {
-                    "false" | "none" => None,
-                    _ => Some(val.into()),
+        }
+        "hint_chars" => {
+            let mut chars = String::new();
+            for c in val.chars().filter(|c| !c.is_whitespace()) {
+                if !chars.contains(c) {
+                    chars.push(c);
                 }
             }
-            "autoplay" => cfg.autoplay = to_bool(val)?,
-            "encoding" => {
-                cfg.encoding = Encoding::from_str(val)
-                    .map_err(|e| error!("{} on line {}: {:?}", e, linenum, line))?;
+            if chars.chars().count() < 2 {
+                return Err(error!(
+                    "`hint_chars` needs at least two letters on line {}: {}",
+                    linenum, val
+                )
+                .into());
+            }
+            cfg.hint_chars = chars;
+        }
+        "web_tag" => {
+            cfg.web_tag = match val.trim() {
+                "no" | "false" => String::new(),
+                tag => tag.into(),
+            }
+        }
+        "plus_tag" => {
+            cfg.plus_tag = match val.trim() {
+                "no" | "false" => String::new(),
+                tag => tag.into(),
+            }
+        }
+        "focus_marker" => {
+            cfg.focus_marker = match val.trim() {
+                "no" | "false" => String::new(),
+                marker => marker.into(),
+            }
+        }
+        "status_format" => {
+            cfg.status_format = match val.trim() {
+                "no" | "false" => String::new(),
+                format => format.into(),
+            }
+        }
+        "web_proxy" => {
+            cfg.web_proxy = match val.trim() {
+                "no" | "false" => String::new(),
+                url => url.into(),
+            }
+        }
+        "bookmarks" => cfg.bookmarks = val.split_whitespace().map(String::from).collect(),
+        "collections" => {
+            cfg.collections = to_collections(val).map_err(|e| {
+                error!(
+                    "`collections` expects letter=name pairs on line {}: {}",
+                    linenum, e
+                )
+            })?
+        }
+        "bind" => {
+            cfg.bind = match val.trim() {
+                "no" | "false" => None,
+                addr => Some(addr.into()),
+            }
+        }
+        "search_url" => {
+            cfg.search_url = match val.trim() {
+                "no" | "false" => String::new(),
+                url => url.into(),
+            }
+        }
+        "strip_echo" => cfg.strip_echo = to_hosts(val),
+        "allow_hosts" => cfg.allow_hosts = to_hosts(val),
+        "deny_hosts" => cfg.deny_hosts = to_hosts(val),
+        "trusted_hosts" => cfg.trusted_hosts = to_hosts(val),
+        "ask_password" => {
+            cfg.ask_password = match val.trim() {
+                "no" | "false" => vec![],
+                globs => globs.split_whitespace().map(String::from).collect(),
             }
+        }
+        "save_passwords" => cfg.save_passwords = to_bool(val)?,
+        "encoding" => {
+            cfg.encoding = val
+                .parse()
+                .map_err(|e| error!("{} on line {}: {:?}", e, linenum, line))?;
+        }
 
-            "theme" => {
-                let homevar = std::env::var("HOME");
-                if homevar.is_err() && val.contains('~') {
-                    return Err(error!("$HOME not set, can't decode `~`"));
-                }
-                cfg.theme = match load_file(&val.replace('~', &homevar.unwrap())) {
-                    Ok(cfg) => cfg.theme,
-                    Err(e) => {
-                        if matches!(e.kind(), io::ErrorKind::NotFound) {
-                            return Err(error!(
-                                "error loading theme: File not found on line {}: {}",
-                                linenum, val
-                            ));
-                        } else {
-                            return Err(error!("error loading theme: {:?}", e));
-                        }
+        "open" => {
+            cfg.open = val
+                .parse()
+                .map_err(|e| error!("{} on line {}: {:?}", e, linenum, line))?;
+        }
+
+        "clipboard" => {
+            cfg.clipboard = val
+                .parse()
+                .map_err(|e| error!("{} on line {}: {:?}", e, linenum, line))?;
+        }
+        "link_format" => cfg.link_format = val.trim().into(),
+
+        "colors" => {
+            cfg.colors = match val.to_lowercase().as_ref() {
+                "auto" => None,
+                depth => Some(
+                    depth
+                        .parse()
+                        .map_err(|e| error!("{} on line {}: {:?}", e, linenum, line))?,
+                ),
+            };
+        }
+
+        "theme" => {
+            let homevar = std::env::var("HOME");
+            if homevar.is_err() && val.contains('~') {
+                return Err(error!("$HOME not set, can't decode `~`").into());
+            }
+            cfg.theme = match load_file(&val.replace('~', &homevar.unwrap())) {
+                Ok(cfg) => cfg.theme,
+                Err(e) => {
+                    if matches!(e.kind(), io::ErrorKind::NotFound) {
+                        return Err(error!(
+                            "error loading theme: File not found on line {}: {}",
+                            linenum, val
+                        )
+                        .into());
+                    } else {
+                        return Err(error!("error loading theme: {:?}", e).into());
                     }
-                };
+                }
+            };
+        }
+
+        "theme_preset" => {
+            cfg.theme = Theme::preset(val.trim()).ok_or_else(|| {
+                error!(
+                    "Unknown theme preset on line {}: {} (try one of: {})",
+                    linenum,
+                    val,
+                    PRESETS.join(", ")
+                )
+            })?;
+            cfg.theme_preset = val.trim().into();
+        }
+
+        // color scheme
+        "ui.cursor" => cfg.theme.ui_cursor = to_color(val),
+        "ui.number" => cfg.theme.ui_number = to_color(val),
+        "ui.menu" => cfg.theme.ui_menu = to_color(val),
+        "ui.text" => cfg.theme.ui_text = to_color(val),
+        "ui.new" => cfg.theme.ui_new = to_color(val),
+        "ui.focus" => cfg.theme.ui_focus = to_color(val),
+        "ui.selector" => cfg.theme.ui_selector = to_color(val),
+        "ui.scrollbar" => cfg.theme.ui_scrollbar = to_color(val),
+
+        "item.text" => cfg.theme.item_text = to_color(val),
+        "item.menu" => cfg.theme.item_menu = to_color(val),
+        "item.error" => cfg.theme.item_error = to_color(val),
+        "item.search" => cfg.theme.item_search = to_color(val),
+        "item.telnet" => cfg.theme.item_telnet = to_color(val),
+        "item.external" => cfg.theme.item_external = to_color(val),
+        "item.download" => cfg.theme.item_download = to_color(val),
+        "item.media" => cfg.theme.item_media = to_color(val),
+        "item.unsupported" => cfg.theme.item_unsupported = to_color(val),
+
+        // extra keys, like `key.back h`
+        _ if key.strip_prefix("key.").is_some_and(Keymap::is_action) => {
+            if let Err(e) = cfg.keymap.bind(&key[4..], val) {
+                return Err(error!("{} on line {}", e, linenum).into());
             }
+        }
 
-            // color scheme
-            "ui.cursor" => cfg.theme.ui_cursor = to_color(val),
-            "ui.number" => cfg.theme.ui_number = to_color(val),
-            "ui.menu" => cfg.theme.ui_menu = to_color(val),
-            "ui.text" => cfg.theme.ui_text = to_color(val),
+        // probably from a newer or older phetch, so don't stop the
+        // whole config from loading
+        _ => {
+            cfg.warnings
+                .push(format!("Unknown key on line {}: {}", linenum, key));
+            return Ok(());
+        }
+    }
+    keys.insert(key.to_string(), true);
+    Ok(())
+}
 
-            "item.text" => cfg.theme.item_text = to_color(val),
-            "item.menu" => cfg.theme.item_menu = to_color(val),
-            "item.error" => cfg.theme.item_error = to_color(val),
-            "item.search" => cfg.theme.item_search = to_color(val),
-            "item.telnet" => cfg.theme.item_telnet = to_color(val),
-            "item.external" => cfg.theme.item_external = to_color(val),
-            "item.download" => cfg.theme.item_download = to_color(val),
-            "item.media" => cfg.theme.item_media = to_color(val),
-            "item.unsupported" => cfg.theme.item_unsupported = to_color(val),
+/// Parses a config.toml file into a Config struct. Only the bits of
+/// TOML phetch needs are supported: `[tables]`, `key = value` pairs,
+/// strings, integers, and booleans. A table's keys are prefixed with
+/// its name, so `cursor` under `[ui]` sets `ui.cursor`.
+///
+/// Rather than aborting on a bad line, the key keeps its default and
+/// the problem is recorded in `cfg.warnings`.
+fn parse_toml(text: &str) -> Config {
+    let mut cfg = Config::default();
+    let mut keys = HashMap::new();
+    let mut table = String::new();
 
-            _ => return Err(error!("Unknown key on line {}: {}", linenum, key)),
+    for (mut linenum, line) in text.lines().enumerate() {
+        linenum += 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            match header
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .strip_suffix(']')
+            {
+                Some(name) if !name.starts_with('[') => table = name.trim().to_string(),
+                _ => cfg
+                    .warnings
+                    .push(format!("Unsupported table on line {}: {}", linenum, line)),
+            }
+            continue;
+        }
+
+        let (key, val) = match parse_toml_pair(line) {
+            Ok(pair) => pair,
+            Err(e) => {
+                cfg.warnings.push(format!("{} on line {}", e, linenum));
+                continue;
+            }
+        };
+        let key = if table.is_empty() {
+            key
+        } else {
+            format!("{}.{}", table, key)
+        };
+        if let Err(e) = parse_line(&mut cfg, &mut keys, linenum, &format!("{} {}", key, val)) {
+            cfg.warnings.push(format!("`{}`: {}", key, e));
         }
-        keys.insert(key, true);
     }
 
-    Ok(cfg)
+    downsample_theme(&mut cfg);
+    cfg
+}
+
+/// Splits a TOML `key = value` line into a key and the value the
+/// phetch.conf parser expects, ie `true` becomes `yes`.
+fn parse_toml_pair(line: &str) -> Result<(String, String)> {
+    let (key, val) = line
+        .split_once('=')
+        .ok_or_else(|| error!("Expected `key = value`"))?;
+    let key = key.trim();
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c))
+    {
+        return Err(error!("Unsupported key {:?}", key).into());
+    }
+
+    let val = val.trim();
+    let (val, rest) = if let Some(val) = val.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = val.chars();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some(c @ '"') | Some(c @ '\\') => out.push(c),
+                    c => return Err(error!("Unsupported escape in `{}`: {:?}", key, c).into()),
+                },
+                Some(c) => out.push(c),
+                None => return Err(error!("Unterminated string for `{}`", key).into()),
+            }
+        }
+        (out, chars.as_str().to_string())
+    } else if let Some(val) = val.strip_prefix('\'') {
+        let end = val
+            .find('\'')
+            .ok_or_else(|| error!("Unterminated string for `{}`", key))?;
+        (val[..end].to_string(), val[end + 1..].to_string())
+    } else {
+        let (val, rest) = val.split_at(val.find('#').unwrap_or(val.len()));
+        let val = match val.trim() {
+            "true" => "yes".to_string(),
+            "false" => "no".to_string(),
+            num if !num.is_empty() && num.chars().all(|c| c.is_ascii_digit() || c == '_') => {
+                num.replace('_', "")
+            }
+            other => return Err(error!("Unsupported value for `{}`: {}", key, other).into()),
+        };
+        (val, rest.to_string())
+    };
+
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(error!("Unexpected `{}` after value for `{}`", rest, key).into());
+    }
+    Ok((key.to_string(), val))
+}
+
+/// Converts a space separated list of hosts into lowercase hosts.
+/// "no" or "false" means no hosts.
+/// Parse "r=reading w=work" into bookmark collections.
+fn to_collections(val: &str) -> Result<Vec<(char, String)>> {
+    if matches!(val.trim(), "no" | "false") {
+        return Ok(vec![]);
+    }
+    val.split_whitespace()
+        .map(|pair| {
+            let (key, name) = pair.split_once('=').unwrap_or((pair, ""));
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if !name.is_empty() => Ok((c, name.to_string())),
+                _ => Err(error!("{}", pair).into()),
+            }
+        })
+        .collect()
+}
+
+fn to_hosts(val: &str) -> Vec<String> {
+    match val.trim() {
+        "no" | "false" => vec![],
+        hosts => hosts.split_whitespace().map(|h| h.to_lowercase()).collect(),
+    }
+}
+
+/// Does `text` match `glob`? `*` matches any run of characters,
+/// including none. Everything else has to match exactly.
+fn glob_match(glob: &str, text: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or("");
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    let last = match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(i) => rest = &rest[i + part.len()..],
+                    None => return false,
+                }
+            }
+            last
+        }
+        // no `*` at all
+        None => return rest.is_empty(),
+    };
+    rest.ends_with(last)
 }
 
 /// Converts a config file's boolean value like "yes" or "false" to a
@@ -277,29 +1455,407 @@ fn to_bool(val: &str) -> Result<bool> {
     match val.to_lowercase().as_ref() {
         "yes" | "true" | "y" => Ok(true),
         "no" | "false" | "n" => Ok(false),
-        _ => Err(error!("Not a boolean: {}", val)),
+        _ => Err(error!("Not a boolean: {}", val).into()),
+    }
+}
+
+/// Converts a size in megabytes, making sure it fits in bytes.
+fn to_megabytes(val: &str) -> Result<usize> {
+    let num: usize = val
+        .parse()
+        .map_err(|_| error!("expects a number value: {}", val))?;
+    match num.checked_mul(gopher::MEGABYTE) {
+        Some(_) => Ok(num),
+        None => Err(error!("is too big: {}", val).into()),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ui::Key;
 
     #[test]
     fn test_parse_default() {
         let config = parse(DEFAULT_CONFIG).expect("Couldn't parse config");
-        assert_eq!(config.tls, false);
-        assert_eq!(config.tor, false);
-        assert_eq!(config.wide, false);
-        assert_eq!(config.emoji, false);
+        assert!(!config.tls);
+        assert!(!config.tor);
+        assert!(!config.wide);
+        assert!(!config.emoji);
         assert_eq!(config.start, "gopher://phetch/1/home");
         assert_eq!(config.media, Some("mpv".to_string()));
+        assert!(!config.incognito);
+    }
+
+    #[test]
+    fn test_toml_default() {
+        let cfg = parse_toml("");
+        assert!(cfg.warnings.is_empty());
+        assert_eq!(format!("{:?}", cfg), format!("{:?}", Config::default()));
+
+        // the example config means the same thing in either format
+        let toml = parse_toml(DEFAULT_TOML);
+        assert_eq!(toml.warnings, Vec::<String>::new());
+        let conf = parse(DEFAULT_CONFIG).unwrap();
+        assert_eq!(format!("{:?}", toml), format!("{:?}", conf));
+    }
+
+    #[test]
+    fn test_toml() {
+        let cfg = parse_toml(
+            r#"
+start = "gopher://phkt.io/1/"  # home
+tls = true
+wrap = 80
+web_tag = 'www'
+
+[ui]
+cursor = "red bold"
+"#,
+        );
+        assert!(cfg.warnings.is_empty());
+        assert_eq!(cfg.start, "gopher://phkt.io/1/");
+        assert!(cfg.tls);
+        assert_eq!(cfg.wrap, 80);
+        assert_eq!(cfg.web_tag, "www");
+        assert_eq!(cfg.theme.ui_cursor, to_color("red bold"));
+    }
+
+    #[test]
+    fn test_toml_warnings() {
+        let cfg = parse_toml(
+            r#"
+tls = true
+wrap = "wide"
+nonsense = 1
+tor = [true]
+wide = "unterminated
+[[tabs]]
+emoji = yes
+"#,
+        );
+        assert!(cfg.tls);
+        assert_eq!(cfg.wrap, 0);
+        assert_eq!(cfg.warnings.len(), 6);
+        assert!(cfg.warnings[0].starts_with("`wrap`"));
+        assert!(cfg.warnings[1].contains("Unknown key on line 4: nonsense"));
+        assert!(cfg.warnings[2].contains("on line 5"));
+        assert!(cfg.warnings[3].contains("Unterminated string for `wide`"));
+        assert!(cfg.warnings[4].contains("Unsupported table on line 7"));
+        assert!(cfg.warnings[5].contains("Unsupported value for `emoji`: yes"));
+    }
+
+    #[test]
+    fn test_max_response() {
+        let cfg = parse("").unwrap();
+        assert_eq!((cfg.max_response, cfg.max_download), (8, 0));
+        let cfg = parse("max_response 1\nmax_download 500").unwrap();
+        assert_eq!((cfg.max_response, cfg.max_download), (1, 500));
+        assert_eq!(parse("").unwrap().request_delay, 0);
+        assert_eq!(parse("request_delay 500").unwrap().request_delay, 500);
+        assert_eq!(parse("").unwrap().dns_cache, 100);
+        assert_eq!(parse("dns_cache 0").unwrap().dns_cache, 0);
+        assert!(parse("dns_cache lots").is_err());
+        assert!(parse("max_response big").is_err());
+        let err = parse("max_download 99999999999999999").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`max_download` is too big: 99999999999999999 on line 1"
+        );
+    }
+
+    #[test]
+    fn test_hints() {
+        let cfg = parse("").unwrap();
+        assert_eq!(cfg.hint_key, Some('\''));
+        assert_eq!(cfg.hint_chars, "asdfghjkl");
+        let cfg = parse("hint_key ;\nhint_chars aa s d").unwrap();
+        assert_eq!(cfg.hint_key, Some(';'));
+        assert_eq!(cfg.hint_chars, "asd");
+        assert_eq!(parse("hint_key no").unwrap().hint_key, None);
+        assert!(parse("hint_key ab").is_err());
+        assert!(parse("hint_chars a").is_err());
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(parse("").unwrap().columns, 1);
+        assert_eq!(parse("columns 3").unwrap().columns, 3);
+        assert_eq!(parse("columns 0").unwrap().columns, 0);
+        assert!(parse("columns lots").is_err());
+    }
+
+    #[test]
+    fn test_spinner_delay() {
+        assert_eq!(parse("").unwrap().spinner_delay, 150);
+        assert_eq!(parse("spinner_delay 0").unwrap().spinner_delay, 0);
+        assert!(parse("spinner_delay soon").is_err());
+    }
+
+    #[test]
+    fn test_cache_size() {
+        assert_eq!(parse("").unwrap().cache_size, 20);
+        assert_eq!(parse("cache_size 0").unwrap().cache_size, 0);
+        assert!(parse("cache_size big").is_err());
+    }
+
+    #[test]
+    fn test_timeout() {
+        assert_eq!(parse("").unwrap().timeout, 10);
+        assert_eq!(parse("timeout 60").unwrap().timeout, 60);
+        assert!(parse("timeout 0").is_err());
+        assert!(parse("timeout -1").is_err());
+    }
+
+    #[test]
+    fn test_pin_interval() {
+        assert_eq!(parse("").unwrap().pin_interval, 60);
+        assert_eq!(parse("pin_interval 5").unwrap().pin_interval, 5);
+        assert!(parse("pin_interval 0").is_err());
+        assert!(parse("pin_interval soon").is_err());
+    }
+
+    #[test]
+    fn test_tab_width() {
+        assert_eq!(parse("").unwrap().tab_width, 8);
+        assert_eq!(parse("tab_width 4").unwrap().tab_width, 4);
+        assert!(parse("tab_width wide").is_err());
+    }
+
+    #[test]
+    fn test_open_policy() {
+        assert_eq!(parse("").unwrap().open, OpenPolicy::Push);
+        assert_eq!(parse("open replace").unwrap().open, OpenPolicy::Replace);
+        assert_eq!(parse("open PUSH").unwrap().open, OpenPolicy::Push);
+        assert_eq!(parse("open same_host").unwrap().open, OpenPolicy::SameHost);
+        assert!(parse("open tabs").is_err());
+    }
+
+    #[test]
+    fn test_colors() {
+        assert_eq!(parse("colors auto").unwrap().colors, None);
+        assert_eq!(
+            parse("colors 256").unwrap().colors,
+            Some(ColorDepth::Ansi256)
+        );
+        assert!(parse("colors 88").is_err());
+
+        let cfg = parse("colors 16\nui.menu #ff0000\nitem.menu color33").unwrap();
+        assert_eq!(cfg.theme.ui_menu, "\x1b[91m");
+        assert_eq!(cfg.theme.item_menu, "\x1b[36m");
+        let cfg = parse("colors truecolor\nui.menu #ff0000").unwrap();
+        assert_eq!(cfg.theme.ui_menu, "\x1b[38;2;255;0;0m");
+    }
+
+    #[test]
+    fn test_clipboard() {
+        assert_eq!(parse("").unwrap().clipboard, Clipboard::Clipboard);
+        assert_eq!(
+            parse("clipboard primary").unwrap().clipboard,
+            Clipboard::Primary
+        );
+        assert_eq!(parse("clipboard Both").unwrap().clipboard, Clipboard::Both);
+        assert!(parse("clipboard secondary").is_err());
+    }
+
+    #[test]
+    fn test_link_format() {
+        assert_eq!(parse("").unwrap().link_format, "[{title}]({url})");
+        assert_eq!(
+            parse("link_format {title} — {url}").unwrap().link_format,
+            "{title} — {url}"
+        );
+    }
+
+    #[test]
+    fn test_web_tag() {
+        assert_eq!(parse("").unwrap().web_tag, "[www]");
+        assert_eq!(parse("web_tag 🌐").unwrap().web_tag, "🌐");
+        assert_eq!(parse("web_tag no").unwrap().web_tag, "");
+        assert_eq!(parse("").unwrap().plus_tag, "");
+        assert_eq!(parse("plus_tag [+]").unwrap().plus_tag, "[+]");
+        assert_eq!(parse("plus_tag no").unwrap().plus_tag, "");
+        assert_eq!(parse("").unwrap().focus_marker, "");
+        assert_eq!(parse("focus_marker >").unwrap().focus_marker, ">");
+        assert_eq!(parse("focus_marker no").unwrap().focus_marker, "");
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        assert!(parse("").unwrap().bookmarks.is_empty());
+        assert_eq!(
+            parse("bookmarks bookmarks.gph  gopher://phkt.io/1/links")
+                .unwrap()
+                .bookmarks,
+            vec!["bookmarks.gph", "gopher://phkt.io/1/links"]
+        );
+    }
+
+    #[test]
+    fn test_bind() {
+        assert_eq!(parse("").unwrap().bind, None);
+        assert_eq!(
+            parse("bind 192.168.1.2").unwrap().bind,
+            Some("192.168.1.2".into())
+        );
+        assert_eq!(parse("bind no").unwrap().bind, None);
+    }
+
+    #[test]
+    fn test_search_url() {
+        assert_eq!(parse("").unwrap().search_url, DEFAULT_SEARCH_URL);
+        assert_eq!(
+            parse("search_url gopher://phkt.io/7/search")
+                .unwrap()
+                .search_url,
+            "gopher://phkt.io/7/search"
+        );
+        assert_eq!(parse("search_url no").unwrap().search_url, "");
+    }
+
+    #[test]
+    fn test_status_format() {
+        assert_eq!(parse("").unwrap().status_format, "");
+        assert_eq!(
+            parse("status_format {title} - {url}")
+                .unwrap()
+                .status_format,
+            "{title} - {url}"
+        );
+        assert_eq!(parse("status_format no").unwrap().status_format, "");
+    }
+
+    #[test]
+    fn test_collections() {
+        assert!(parse("").unwrap().collections.is_empty());
+        assert_eq!(
+            parse("collections r=reading w=work").unwrap().collections,
+            vec![('r', "reading".into()), ('w', "work".into())]
+        );
+        assert!(parse("collections reading").is_err());
+        assert!(parse("collections rw=reading").is_err());
+        assert!(parse("collections r=").is_err());
+    }
+
+    #[test]
+    fn test_strip_echo() {
+        assert!(parse("").unwrap().strip_echo.is_empty());
+        assert!(parse("strip_echo no").unwrap().strip_echo.is_empty());
+        assert_eq!(
+            parse("strip_echo Phkt.io  sdf.org").unwrap().strip_echo,
+            vec!["phkt.io", "sdf.org"]
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("sdf.org", "sdf.org"));
+        assert!(!glob_match("sdf.org", "sdf.org.evil"));
+        assert!(glob_match("*.sdf.org", "gopher.sdf.org"));
+        assert!(!glob_match("*.sdf.org", "sdf.org"));
+        assert!(glob_match("*sdf.org", "sdf.org"));
+        assert!(glob_match("gopher.*", "gopher.floodgap.com"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("a*b*c", "abc"));
+        assert!(glob_match("a*b*c", "a-b-b-c"));
+        assert!(!glob_match("a*b*c", "a-c-b"));
+        assert!(!glob_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_deny_hosts() {
+        let cfg = parse("deny_hosts *.evil.com Spam.org").unwrap();
+        assert!(!cfg.host_allowed("www.evil.com"));
+        assert!(!cfg.host_allowed("spam.org"));
+        assert!(!cfg.host_allowed("SPAM.ORG"));
+        assert!(cfg.host_allowed("evil.com"));
+        assert!(cfg.host_allowed("sdf.org"));
+        assert!(Config::default().host_allowed("spam.org"));
+    }
+
+    #[test]
+    fn test_trusted_hosts() {
+        let cfg = parse("trusted_hosts Files.phkt.io *.sdf.org:7070").unwrap();
+        assert!(cfg.trusts("files.phkt.io", "70"));
+        assert!(cfg.trusts("files.phkt.io", "7070"));
+        assert!(cfg.trusts("gopher.sdf.org", "7070"));
+        assert!(!cfg.trusts("gopher.sdf.org", "70"));
+        assert!(!cfg.trusts("phkt.io", "70"));
+        assert!(!Config::default().trusts("files.phkt.io", "70"));
+    }
+
+    #[test]
+    fn test_ask_password() {
+        let cfg = parse("ask_password Vault.phkt.io/Secret/* sdf.org").unwrap();
+        assert!(cfg.asks_password("vault.phkt.io", "/Secret/diary.txt"));
+        assert!(!cfg.asks_password("vault.phkt.io", "/secret/diary.txt"));
+        assert!(!cfg.asks_password("vault.phkt.io", "/"));
+        assert!(cfg.asks_password("SDF.org", "/users"));
+        assert!(!Config::default().asks_password("sdf.org", "/"));
+        assert!(!parse("ask_password no").unwrap().asks_password("no", ""));
+    }
+
+    #[test]
+    fn test_allow_hosts() {
+        let cfg = parse("allow_hosts sdf.org *.floodgap.com\ndeny_hosts bad.floodgap.com").unwrap();
+        assert!(cfg.host_allowed("sdf.org"));
+        assert!(cfg.host_allowed("gopher.floodgap.com"));
+        assert!(!cfg.host_allowed("bad.floodgap.com"));
+        assert!(!cfg.host_allowed("gopher.sdf.org"));
+        assert!(!cfg.host_allowed("phkt.io"));
+        assert!(parse("allow_hosts no").unwrap().allow_hosts.is_empty());
+    }
+
+    #[test]
+    fn test_wide_for() {
+        let mut cfg = parse("wide yes\nwide_per_url yes").unwrap();
+        assert!(cfg.wide_per_url);
+        assert!(cfg.wide_for("gopher://phkt.io"));
+        cfg.wide_urls.insert("gopher://phkt.io".into(), false);
+        assert!(!cfg.wide_for("gopher://phkt.io"));
+        assert!(cfg.wide_for("gopher://sdf.org"));
+    }
+
+    #[test]
+    fn test_keymap() {
+        let cfg = parse("key.back h\nkey.quit ctrl-x Q").unwrap();
+        assert_eq!(cfg.keymap.translate(Key::Char('h')), Key::Left);
+        assert_eq!(cfg.keymap.translate(Key::Ctrl('x')), Key::Char('q'));
+        assert_eq!(cfg.keymap.translate(Key::Char('Q')), Key::Char('q'));
+        assert!(parse("key.back hyper-h").is_err());
+        // unknown actions are only a warning
+        let cfg = parse("key.dance d").unwrap();
+        assert_eq!(cfg.keymap, Keymap::default());
+        assert_eq!(cfg.warnings, vec!["Unknown key on line 1: key.dance"]);
+        let cfg = parse_toml("[key]\nforward = \"l\"\n");
+        assert_eq!(cfg.keymap.translate(Key::Char('l')), Key::Right);
+    }
+
+    #[test]
+    fn test_history_size() {
+        assert_eq!(parse("").unwrap().history_size, 1000);
+        assert_eq!(parse("history_size 0").unwrap().history_size, 0);
+        assert!(parse("history_size lots").is_err());
+    }
+
+    #[test]
+    fn test_incognito() {
+        let cfg = parse("incognito yes").unwrap();
+        assert!(cfg.incognito);
     }
 
     #[test]
     fn test_bad_key() {
-        let res = parse("random-key yes");
-        assert_eq!(res.is_err(), true);
+        let cfg = parse("random-key yes\ntls yes\nrandom-key no").unwrap();
+        assert!(cfg.tls);
+        assert_eq!(
+            cfg.warnings,
+            vec![
+                "Unknown key on line 1: random-key",
+                "Unknown key on line 3: random-key"
+            ]
+        );
+        assert!(parse("tls maybe").is_err());
     }
 
     #[test]
@@ -311,15 +1867,15 @@ mod tests {
     #[test]
     fn test_comments_ignored() {
         let cfg = parse("# wide yes\ntls yes").unwrap();
-        assert_eq!(cfg.wide, false);
-        assert_eq!(cfg.tls, true);
+        assert!(!cfg.wide);
+        assert!(cfg.tls);
     }
 
     #[test]
     fn test_yes_or_true() {
         let cfg = parse("tls yes\nwide true").unwrap();
-        assert_eq!(cfg.tls, true);
-        assert_eq!(cfg.wide, true);
+        assert!(cfg.tls);
+        assert!(cfg.wide);
     }
 
     #[test]
@@ -335,19 +1891,29 @@ mod tests {
 
         let cfg = parse("media vlc").unwrap();
         assert_eq!(cfg.media, Some("vlc".to_string()));
+
+        let cfg = parse("media no").unwrap();
+        assert_eq!(cfg.media, None);
+
+        // TOML's false turns into "no"
+        let cfg = parse_toml("media = false");
+        assert_eq!(cfg.media, None);
+        assert!(cfg.warnings.is_empty());
+        let cfg = parse_toml("media = \"vlc\"");
+        assert_eq!(cfg.media, Some("vlc".to_string()));
     }
 
     #[test]
     fn test_no_or_false() {
         let cfg = parse("tls false\nwide no\ntor n").unwrap();
-        assert_eq!(cfg.tls, false);
-        assert_eq!(cfg.tor, false);
-        assert_eq!(cfg.wide, false);
+        assert!(!cfg.tls);
+        assert!(!cfg.tor);
+        assert!(!cfg.wide);
     }
     #[test]
     fn test_no_dupe_keys() {
         let res = parse("tls false\nwide no\nemoji yes\ntls yes");
-        assert_eq!(res.is_err(), true);
+        assert!(res.is_err());
         let e = res.unwrap_err();
         assert_eq!(format!("{}", e), "Duplicate key on line 4: tls");
     }
@@ -355,15 +1921,15 @@ mod tests {
     #[test]
     fn test_encoding() {
         let cfg = parse("tls true\nwide no\nemoji yes").unwrap();
-        assert_eq!(cfg.tls, true);
+        assert!(cfg.tls);
         assert_eq!(cfg.encoding, Encoding::default());
 
         let cfg = parse("tls true\nencoding utf8\n").unwrap();
-        assert_eq!(cfg.tls, true);
+        assert!(cfg.tls);
         assert_eq!(cfg.encoding, Encoding::UTF8);
 
         let cfg = parse("tls true\nencoding CP437\n").unwrap();
-        assert_eq!(cfg.tls, true);
+        assert!(cfg.tls);
         assert_eq!(cfg.encoding, Encoding::CP437);
 
         let res = parse("tls true\nencoding what\n");
@@ -398,6 +1964,20 @@ mod tests {
         let cfg = parse("item.text green\nitem.download red underline").unwrap();
         assert_eq!(to_words(cfg.theme.item_text), "green");
         assert_eq!(to_words(cfg.theme.item_download), "red underline");
+        let cfg = parse("ui.selector red\nui.scrollbar blue").unwrap();
+        assert_eq!(to_words(cfg.theme.ui_selector), "red");
+        assert_eq!(to_words(cfg.theme.ui_scrollbar), "blue");
+    }
+
+    #[test]
+    fn test_theme_preset() {
+        use crate::theme::to_words;
+
+        let cfg = parse("theme_preset light\nitem.text green").unwrap();
+        assert_eq!(cfg.theme_preset, "light");
+        assert_eq!(to_words(cfg.theme.item_menu), "darkblue");
+        assert_eq!(to_words(cfg.theme.item_text), "green");
+        assert!(parse("theme_preset neon").is_err());
     }
 
     #[test]
@@ -1,33 +1,35 @@
+//! Gopher doesn't say what character set a response is in. Most are
+//! UTF8 these days, but older servers often send CP437 art.
+
 use std::{borrow::Cow, io::Result};
 
 /// Encoding of Gopher response. Only UTF8 and CP437 are supported.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
 pub enum Encoding {
     /// Unicode
+    #[default]
     UTF8,
     /// https://en.wikipedia.org/wiki/Code_page_437
     CP437,
 }
 
-impl Default for Encoding {
-    fn default() -> Self {
-        Encoding::UTF8
-    }
-}
+impl std::str::FromStr for Encoding {
+    type Err = std::io::Error;
 
-impl Encoding {
     /// Accepts a string like "UTF8" or "CP437" and returns the
     /// appropriate `Encoding`, or an `Err`.
-    pub fn from_str(s: &str) -> Result<Self> {
+    fn from_str(s: &str) -> Result<Self> {
         match s.to_lowercase().as_ref() {
             "utf8" | "utf-8" | "utf 8" => Ok(Encoding::UTF8),
             "cp437" | "cp-437" | "cp 437" | "pc8" | "pc-8" | "oem us" | "oem-us" => {
                 Ok(Encoding::CP437)
             }
-            _ => Err(error!("Expected CP437 or UTF8 encoding")),
+            _ => Err(error!("Expected CP437 or UTF8 encoding").into()),
         }
     }
+}
 
+impl Encoding {
     /// Convert `response` into a String according to `encoding`.
     pub fn encode<'res>(&self, response: &'res [u8]) -> Cow<'res, str> {
         if matches!(self, Encoding::CP437) {
@@ -37,7 +39,40 @@ impl Encoding {
             }
             Cow::from(converted)
         } else {
-            String::from_utf8_lossy(response)
+            utf8_lossy(response)
+        }
+    }
+}
+
+/// Shown where a response was cut off in the middle of a character.
+pub const TRUNCATED: &str = " (truncated)";
+
+/// Like `String::from_utf8_lossy()`, but a multibyte character cut
+/// off at the very end, like after a short read, is dropped and
+/// replaced with `TRUNCATED` instead of a replacement character.
+pub fn utf8_lossy(bytes: &[u8]) -> Cow<'_, str> {
+    let cut = incomplete_tail(bytes);
+    if cut == 0 {
+        return String::from_utf8_lossy(bytes);
+    }
+    let mut s = String::from_utf8_lossy(&bytes[..bytes.len() - cut]).into_owned();
+    s.push_str(TRUNCATED);
+    Cow::from(s)
+}
+
+/// How many bytes at the end of `bytes` are the start of a multibyte
+/// character that never finished.
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    let len = bytes.len();
+    for i in (len.saturating_sub(3)..len).rev() {
+        // skip continuation bytes back to the start of the character
+        if bytes[i] & 0xC0 == 0x80 {
+            continue;
         }
+        return match std::str::from_utf8(&bytes[i..]) {
+            Err(e) if e.error_len().is_none() => len - i,
+            _ => 0,
+        };
     }
+    0
 }
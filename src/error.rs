@@ -0,0 +1,139 @@
+//! phetch's Error type. Most of phetch just wants to show a failure
+//! in the status line, but embedders and tests can match on the kind
+//! of failure instead of picking apart a message - for example, to
+//! retry a request only when the network timed out.
+
+use crate::{gopher::Type, utils::human_bytes};
+use std::{fmt, io};
+
+/// Shorthand for a `Result` with a phetch `Error`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong while fetching and showing Gopher
+/// content.
+#[derive(Debug)]
+pub enum Error {
+    /// Network, disk, or terminal trouble.
+    Io(io::Error),
+    /// The server sent a Gopher type we can't display.
+    Unsupported(Type),
+    /// There's nothing at this URL, like an unknown phetch:// page.
+    NotFound(String),
+    /// The server's hostname couldn't be looked up.
+    Unresolved(String),
+    /// Connecting to or reading from a server took too long.
+    Timeout(String),
+    /// The server ("host:port") couldn't be reached because there's
+    /// no route to it, like when the network is down.
+    Unreachable(String),
+    /// A URL or response didn't make sense.
+    Parse(String),
+    /// The host is off limits according to `allow_hosts` or
+    /// `deny_hosts`.
+    Blocked(String),
+    /// The response was bigger than the limit, in bytes, set with
+    /// `max_response` or `max_download`.
+    TooLarge(usize),
+    /// Anything else, with a message for the user.
+    Other(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Unsupported(typ) => write!(f, "Unsupported Gopher Response: {:?}", typ),
+            Error::NotFound(url) => write!(f, "Not found: {}", url),
+            Error::Unresolved(host) => write!(f, "Could not resolve host: {}", host),
+            Error::Timeout(msg) => write!(f, "Timed out: {}", msg),
+            Error::Unreachable(addr) => write!(f, "Host unreachable: {}", addr),
+            Error::Parse(msg) => write!(f, "{}", msg),
+            Error::Blocked(host) => write!(f, "Blocked by policy: {}", host),
+            Error::TooLarge(max) => write!(f, "Response too large (> {})", human_bytes(*max)),
+            Error::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error {
+    /// Did we fail to get through to the server at all, as opposed to
+    /// it answering badly? Worth trying again later.
+    pub fn is_host_down(&self) -> bool {
+        match self {
+            Error::Unreachable(_) | Error::Unresolved(_) => true,
+            Error::Io(e) => e.kind() == io::ErrorKind::ConnectionRefused,
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            // read timeouts show up as WouldBlock on unix
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Error::Timeout(e.to_string()),
+            _ => Error::Io(e),
+        }
+    }
+}
+
+/// Lets code that deals in `io::Result`, like `main()`, keep using `?`.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match &e {
+            Error::Io(e) => return io::Error::new(e.kind(), e.to_string()),
+            Error::Unsupported(_) => io::ErrorKind::Unsupported,
+            Error::NotFound(_) | Error::Unresolved(_) => io::ErrorKind::NotFound,
+            Error::Timeout(_) => io::ErrorKind::TimedOut,
+            Error::Unreachable(_) => io::ErrorKind::HostUnreachable,
+            Error::Parse(_) => io::ErrorKind::InvalidData,
+            Error::Blocked(_) => io::ErrorKind::PermissionDenied,
+            Error::TooLarge(_) => io::ErrorKind::FileTooLarge,
+            Error::Other(_) => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_io_error() {
+        let e = Error::from(io::Error::new(io::ErrorKind::TimedOut, "slow"));
+        assert!(matches!(e, Error::Timeout(_)));
+        let e = Error::from(io::Error::new(io::ErrorKind::WouldBlock, "slow"));
+        assert!(matches!(e, Error::Timeout(_)));
+        let e = Error::from(io::Error::new(io::ErrorKind::ConnectionRefused, "nope"));
+        assert!(matches!(e, Error::Io(_)));
+        assert_eq!(e.to_string(), "nope");
+    }
+
+    #[test]
+    fn test_into_io_error() {
+        let e: io::Error = Error::Unsupported(Type::Telnet).into();
+        assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(e.to_string(), "Unsupported Gopher Response: Telnet");
+        let e: io::Error = Error::NotFound("gopher://phetch/1/nope".into()).into();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        let e: io::Error = error!("Save failed: {}", "disk full").into();
+        assert_eq!(e.kind(), io::ErrorKind::Other);
+        assert_eq!(e.to_string(), "Save failed: disk full");
+    }
+
+    #[test]
+    fn test_error_macro() {
+        assert!(matches!(error!("nope"), Error::Other(_)));
+        assert_eq!(error!("{} {}", 1, 2).to_string(), "1 2");
+    }
+}
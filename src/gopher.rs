@@ -2,15 +2,27 @@
 //! the ability to make requests or downloads over TLS or Tor,
 //! cleaning Unicode control characters from Gopher responses, and
 //! URL parsing that recognizes different protocols like telnet and
-//! IPv6 addresses.
-
-use crate::ui::{self, Key};
+//! IPv6 addresses. `gophers://` URLs are always fetched over TLS,
+//! for servers that don't take plain connections.
+
+use crate::{
+    clock, encoding,
+    error::{Error, Result},
+    local, netlog,
+    ui::{self, Key},
+};
 use std::{
-    fs,
-    io::{Read, Result, Write},
-    net::TcpStream,
-    net::ToSocketAddrs,
-    os::unix::fs::OpenOptionsExt,
+    borrow::Cow,
+    collections::HashMap,
+    fs, io,
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs},
+    os::unix::{fs::OpenOptionsExt, io::FromRawFd},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
     time::Duration,
 };
 
@@ -23,11 +35,126 @@ use native_tls::TlsConnector;
 mod r#type;
 pub use self::r#type::Type;
 
-/// Some Gopher servers can be kind of slow, we may want to up this or
-/// make it configurable eventually.
-pub const TCP_TIMEOUT_IN_SECS: u64 = 8;
-/// Based on `TCP_TIMEOUT_IN_SECS` but a `Duration` type.
-pub const TCP_TIMEOUT_DURATION: Duration = Duration::from_secs(TCP_TIMEOUT_IN_SECS);
+/// Some Gopher servers can be kind of slow, so this is only the
+/// default. It can be changed with `timeout` in the config.
+pub const TCP_TIMEOUT_IN_SECS: u64 = 10;
+
+/// Seconds to wait on connecting to or reading from a server.
+static TIMEOUT: AtomicU64 = AtomicU64::new(TCP_TIMEOUT_IN_SECS);
+
+lazy_static! {
+    /// Addresses we've already resolved this session, keyed by
+    /// "host:port". Gopher opens a new connection for every request,
+    /// so this saves us a DNS lookup every time we navigate around
+    /// the same server. The address that last worked is kept first in
+    /// line. Holds at most `DNS_CACHE` servers.
+    static ref RESOLVED: Mutex<HashMap<String, Vec<SocketAddr>>> = Mutex::new(HashMap::new());
+
+    /// Local address to connect from, set with `bind` in the config.
+    /// None = let the OS pick.
+    static ref BIND: Mutex<Option<String>> = Mutex::new(None);
+
+    /// When we can next make a request to each host, according to
+    /// `REQUEST_DELAY`, as `clock::now()` times.
+    static ref NEXT_REQUEST: Mutex<HashMap<String, Duration>> = Mutex::new(HashMap::new());
+
+    /// Keys to watch for Ctrl-c while a request waits its turn.
+    static ref INTERRUPT: Mutex<Option<ui::KeyReceiver>> = Mutex::new(None);
+}
+
+/// How long to sleep at a time while waiting our turn, between
+/// checks for Ctrl-c.
+const WAIT_SLICE: Duration = Duration::from_millis(50);
+
+/// How many servers' addresses to remember by default.
+pub const DEFAULT_DNS_CACHE: usize = 100;
+
+/// How many servers' addresses `resolve()` remembers. 0 = off
+static DNS_CACHE: AtomicUsize = AtomicUsize::new(DEFAULT_DNS_CACHE);
+
+/// Milliseconds to wait between requests to the same host. 0 = off
+static REQUEST_DELAY: AtomicU64 = AtomicU64::new(0);
+
+/// `max_response` and `max_download` are set in megabytes of this
+/// many bytes.
+pub const MEGABYTE: usize = 1_000_000;
+
+/// Biggest page `fetch()` will read by default, in bytes. Anything
+/// bigger has to be downloaded.
+pub const DEFAULT_MAX_RESPONSE: usize = 8 * MEGABYTE;
+
+/// Biggest page `fetch()` will read, in bytes. 0 = no limit
+static MAX_RESPONSE: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_RESPONSE);
+
+/// Biggest file `download_url()` will save, in bytes. 0 = no limit
+static MAX_DOWNLOAD: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the most bytes to read for a page and for a download, so a
+/// broken or malicious server can't fill up memory or disk. 0 = no
+/// limit.
+pub fn set_limits(response: usize, download: usize) {
+    MAX_RESPONSE.store(response, Ordering::Relaxed);
+    MAX_DOWNLOAD.store(download, Ordering::Relaxed);
+}
+
+/// Give up on connecting to or hearing from a server after `secs`
+/// seconds. 0 is ignored.
+pub fn set_timeout(secs: u64) {
+    if secs > 0 {
+        TIMEOUT.store(secs, Ordering::Relaxed);
+    }
+}
+
+/// How long to wait on a server before giving up.
+fn timeout() -> Duration {
+    Duration::from_secs(TIMEOUT.load(Ordering::Relaxed))
+}
+
+/// Remember the addresses of up to `hosts` servers for the rest of
+/// the session. 0 = look them up on every request.
+pub fn set_dns_cache(hosts: usize) {
+    DNS_CACHE.store(hosts, Ordering::Relaxed);
+    if hosts == 0 {
+        RESOLVED.lock().unwrap().clear();
+    }
+}
+
+/// Wait at least `ms` milliseconds between requests to the same
+/// host, to be gentle on small servers. 0 = don't wait.
+pub fn set_request_delay(ms: u64) {
+    REQUEST_DELAY.store(ms, Ordering::Relaxed);
+}
+
+/// How long a request to `host` would have to wait right now to
+/// respect `set_request_delay()`.
+pub fn polite_wait(host: &str) -> Duration {
+    let next = NEXT_REQUEST.lock().unwrap().get(host).copied();
+    next.map_or(Duration::ZERO, clock::until)
+}
+
+/// Let Ctrl-c on `keys` cancel a request that's waiting its turn
+/// because of `set_request_delay()`.
+pub fn set_interrupt(keys: Option<ui::KeyReceiver>) {
+    *INTERRUPT.lock().unwrap() = keys;
+}
+
+/// Claim the next turn to make a request to `host`, `delay` after
+/// the last one, and return how long to wait for it. Hosts whose
+/// turn has already come are forgotten, so `next` only holds the
+/// ones we've talked to in the last `delay`.
+fn reserve_turn(next: &mut HashMap<String, Duration>, host: &str, delay: Duration) -> Duration {
+    let now = clock::now();
+    next.retain(|_, at| *at > now);
+    let turn = next.get(host).map_or(now, |&at| at.max(now));
+    next.insert(host.to_string(), turn + delay);
+    turn - now
+}
+
+/// Make all our connections from the local address `addr`, ie
+/// "192.168.1.2" or "[::1]:7070". It's checked when connecting.
+pub fn set_bind(addr: Option<&str>) {
+    *BIND.lock().unwrap() = addr.map(String::from);
+}
 
 trait ReadWrite: Read + Write {}
 impl<T: Read + Write> ReadWrite for T {}
@@ -45,21 +172,24 @@ impl Stream {
 }
 
 impl Read for Stream {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.io.read(buf)
     }
 }
 
 impl Write for Stream {
-    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.io.write(buf)
     }
 
-    fn flush(&mut self) -> Result<()> {
+    fn flush(&mut self) -> io::Result<()> {
         self.io.flush()
     }
 }
 
+/// Scheme for Gopher URLs that have to be fetched over TLS.
+pub const TLS_SCHEME: &str = "gophers://";
+
 /// Gopher URL. Returned by `parse_url()`.
 pub struct Url<'a> {
     /// Gopher Type
@@ -68,19 +198,21 @@ pub struct Url<'a> {
     pub host: &'a str,
     /// Port. Defaults to 70
     pub port: &'a str,
-    /// Selector
-    pub sel: &'a str,
+    /// Selector, percent-decoded
+    pub sel: Cow<'a, str>,
+    /// Must be fetched over TLS? Set by the `gophers://` scheme.
+    pub tls: bool,
 }
 
 /// Fetches a gopher URL and returns a tuple of:
 ///   (did tls work?, raw Gopher response)
 pub fn fetch_url(url: &str, tls: bool, tor: bool) -> Result<(bool, Vec<u8>)> {
-    let u = parse_url(url);
-    fetch(u.host, u.port, u.sel, tls, tor)
+    fetch_parsed(&parse_url(url), tls, tor)
 }
 
 /// Fetches a gopher URL by its component parts and returns a tuple of:
 ///   (did tls work?, raw Gopher response)
+/// A `?` in `selector` is sent as a tab, for search queries.
 pub fn fetch(
     host: &str,
     port: &str,
@@ -88,16 +220,123 @@ pub fn fetch(
     tls: bool,
     tor: bool,
 ) -> Result<(bool, Vec<u8>)> {
-    let mut stream = request(host, port, selector, tls, tor)?;
+    let selector = selector.replace('?', "\t"); // search queries
+    fetch_parsed(&Url::new(Type::Menu, host, port, &selector), tls, tor)
+}
+
+/// Fetches a parsed gopher URL. See `fetch()`.
+fn fetch_parsed(u: &Url, tls: bool, tor: bool) -> Result<(bool, Vec<u8>)> {
+    let (host, port, selector) = (u.host, u.port, &*u.sel);
+    let start = clock::now();
+    let res = open(u, tls, tor).and_then(|mut stream| {
+        let body = read_limited(&mut stream, MAX_RESPONSE.load(Ordering::Relaxed))
+            .map_err(|e| waiting_on(e, host, port))?;
+        Ok((stream.is_tls(), body))
+    });
+    log_request("fetch", host, port, selector, start, &res, |(_, body)| {
+        body.len()
+    });
+    res
+}
+
+/// Say which server a read timed out waiting on, rather than passing
+/// along the OS's "Resource temporarily unavailable".
+fn waiting_on(e: Error, host: &str, port: &str) -> Error {
+    match e {
+        Error::Timeout(_) => Error::Timeout(format!("waiting for {}:{}", host, port)),
+        e => e,
+    }
+}
+
+/// Add a request to the log, if it's on. See `netlog`.
+fn log_request<T>(
+    kind: &str,
+    host: &str,
+    port: &str,
+    selector: &str,
+    start: Duration,
+    res: &Result<T>,
+    size: impl Fn(&T) -> usize,
+) {
+    let sent = selector.len() + 2;
+    netlog::request(
+        kind,
+        host,
+        port,
+        selector,
+        sent,
+        res.as_ref().map(size),
+        clock::elapsed(start),
+    );
+}
+
+/// Read all of `stream`, giving up with `Error::TooLarge` as soon as
+/// there's more than `max` bytes. 0 = no limit.
+fn read_limited(stream: &mut impl Read, max: usize) -> Result<Vec<u8>> {
     let mut body = Vec::new();
-    stream.read_to_end(&mut body)?;
-    Ok((stream.is_tls(), body))
+    if max == 0 {
+        stream.read_to_end(&mut body)?;
+        return Ok(body);
+    }
+    stream.take(max as u64 + 1).read_to_end(&mut body)?;
+    if body.len() > max {
+        return Err(Error::TooLarge(max));
+    }
+    Ok(body)
+}
+
+/// Removes the first line of a response if it's an exact copy of
+/// `selector`, which some servers send before the real content.
+pub fn strip_echo(res: &mut Vec<u8>, selector: &str) {
+    if selector.is_empty() {
+        return;
+    }
+    let end = res.iter().position(|&b| b == b'\n').unwrap_or(res.len());
+    let line = res[..end].strip_suffix(b"\r").unwrap_or(&res[..end]);
+    if line == selector.as_bytes() {
+        res.drain(..(end + 1).min(res.len()));
+    }
+}
+
+/// How many bytes of a response `looks_binary()` checks.
+const SNIFF_LEN: usize = 1024;
+
+/// Does this response look like a binary file rather than text? Some
+/// servers send binaries as type `0`, and dumping them into a Text
+/// view garbles the terminal. Any NUL byte, or more than 10% control
+/// characters in the first `SNIFF_LEN` bytes, counts as binary. A
+/// stray NUL at the very start doesn't, since `strip_bom()` drops it.
+pub fn looks_binary(res: &[u8]) -> bool {
+    let res = strip_bom(res);
+    let head = &res[..res.len().min(SNIFF_LEN)];
+    if head.contains(&0) {
+        return true;
+    }
+    let controls = head
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    controls * 10 > head.len()
+}
+
+/// UTF-8 byte order mark.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Skip a UTF-8 byte order mark and any stray NULs at the start of a
+/// response, which would otherwise show up as junk at the top of the
+/// page. Only for decoding text and menus - downloads keep every byte.
+pub fn strip_bom(mut res: &[u8]) -> &[u8] {
+    while let Some(rest) = res.strip_prefix(BOM).or_else(|| res.strip_prefix(b"\0")) {
+        res = rest;
+    }
+    res
 }
 
 /// Turn a Gopher response from `fetch` into a UTF8 String, cleaning
-/// up unprintable characters along the way.
+/// up unprintable characters along the way. A character cut off at
+/// the end is marked as truncated.
 pub fn response_to_string(res: &[u8]) -> String {
-    let mut s = String::from_utf8_lossy(res).to_string();
+    let mut s = encoding::utf8_lossy(strip_bom(res)).to_string();
     clean_response(&mut s);
     s
 }
@@ -128,37 +367,25 @@ pub fn download_url_with_filename(
     let mut path = std::path::PathBuf::from(".");
     path.push(filename);
 
-    let mut stream = request(u.host, u.port, u.sel, tls, tor)?;
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .append(true)
-        .open(&path)
-        .map_err(|e| error!("{}", e))?;
-
-    let mut buf = [0; 1024];
-    let mut bytes = 0;
-    while let Ok(count) = stream.read(&mut buf) {
-        if count == 0 {
-            break;
-        }
-        bytes += count;
-        file.write_all(&buf[..count])?;
-        if let Ok(chan) = chan.lock() {
-            if let Ok(Key::Ctrl('c')) = chan.try_recv() {
-                if path.exists() {
-                    fs::remove_file(path)?;
-                }
-                return Err(error!("Download cancelled"));
-            }
-        }
-    }
-
-    Ok((filename.to_string(), bytes))
+    let start = clock::now();
+    let res = open(&u, tls, tor)
+        .and_then(|stream| {
+            let file = fs::OpenOptions::new()
+                .create_new(true)
+                .append(true)
+                .open(&path)?;
+            save(stream, file, &path, chan)
+        })
+        .map_err(|e| waiting_on(e, u.host, u.port));
+    log_request("download", u.host, u.port, &u.sel, start, &res, |&bytes| {
+        bytes
+    });
+
+    Ok((filename.to_string(), res?))
 }
 
-/// Downloads a binary to disk. Allows canceling with Ctrl-c, but it's
-/// kind of hacky - needs the UI receiver passed in.
+/// Downloads a binary to disk, in `dir`. Allows canceling with
+/// Ctrl-c, but it's kind of hacky - needs the UI receiver passed in.
 /// Returns a tuple of:
 ///   (path it was saved to, the size in bytes)
 pub fn download_url(
@@ -166,32 +393,59 @@ pub fn download_url(
     tls: bool,
     tor: bool,
     chan: ui::KeyReceiver,
+    dir: &Path,
 ) -> Result<(String, usize)> {
     let u = parse_url(url);
-    let filename = u
-        .sel
-        .split_terminator('/')
-        .rev()
-        .next()
-        .ok_or_else(|| error!("Bad download filename: {}", u.sel))?;
-    let mut path = std::path::PathBuf::from(".");
-    path.push(filename);
-
-    let mut stream = request(u.host, u.port, u.sel, tls, tor)?;
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .mode(0o770)
-        .open(&path)?;
+    let path = dir.join(download_filename(url)?);
+
+    let start = clock::now();
+    let res = open(&u, tls, tor)
+        .and_then(|stream| {
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o770)
+                .open(&path)?;
+            save(stream, file, &path, chan)
+        })
+        .map_err(|e| waiting_on(e, u.host, u.port));
+    log_request("download", u.host, u.port, &u.sel, start, &res, |&bytes| {
+        bytes
+    });
+
+    Ok((path.display().to_string(), res?))
+}
 
+/// Copy `stream` into `file` at `path` and return how many bytes that
+/// was. Removes the file if it gets too big, the server stops
+/// sending, or Ctrl-c is pressed.
+fn save(
+    mut stream: Stream,
+    mut file: fs::File,
+    path: &Path,
+    chan: ui::KeyReceiver,
+) -> Result<usize> {
+    let max = MAX_DOWNLOAD.load(Ordering::Relaxed);
     let mut buf = [0; 1024];
     let mut bytes = 0;
-    while let Ok(count) = stream.read(&mut buf) {
-        if count == 0 {
-            break;
-        }
+    loop {
+        let count = match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(e) => match Error::from(e) {
+                e @ Error::Timeout(_) => {
+                    fs::remove_file(path)?;
+                    return Err(e);
+                }
+                _ => break,
+            },
+        };
         bytes += count;
+        if max > 0 && bytes > max {
+            fs::remove_file(path)?;
+            return Err(Error::TooLarge(max));
+        }
         file.write_all(&buf[..count])?;
         if let Ok(chan) = chan.lock() {
             if let Ok(Key::Ctrl('c')) = chan.try_recv() {
@@ -203,15 +457,161 @@ pub fn download_url(
         }
     }
 
-    Ok((filename.to_string(), bytes))
+    Ok(bytes)
+}
+
+/// The filename to save a download of `url` as, based on its selector.
+pub fn download_filename(url: &str) -> Result<String> {
+    let u = parse_url(url);
+    let mut filename = u
+        .sel
+        .split_terminator('/')
+        .next_back()
+        .ok_or_else(|| Error::Parse(format!("Bad download filename: {}", u.sel)))?
+        .to_string();
+    if let Some(ext) = type_for_url(url).extension() {
+        if !filename.contains('.') {
+            filename.push('.');
+            filename.push_str(ext);
+        }
+    }
+    Ok(filename)
+}
+
+/// Make a request for a parsed URL: over TLS and nothing else for
+/// `gophers://` URLs, otherwise like `request()`.
+fn open(u: &Url, tls: bool, tor: bool) -> Result<Stream> {
+    // --serve-local reads from its directory, never the network
+    if let Some(root) = local::root_for(u.host) {
+        return Ok(Stream {
+            io: Box::new(io::Cursor::new(local::fetch(&root, &u.sel)?)),
+            tls: false,
+        });
+    }
+    if u.tls {
+        send_request_tls(u.host, u.port, &u.sel, tor)
+    } else {
+        send_request(u.host, u.port, &u.sel, tls, tor)
+    }
+}
+
+/// Wait our turn to make a request to `host`, if `REQUEST_DELAY`
+/// is set. Ctrl-c gives up on it.
+fn wait_turn(host: &str) -> Result<()> {
+    let delay = REQUEST_DELAY.load(Ordering::Relaxed);
+    if delay == 0 {
+        return Ok(());
+    }
+    let wait = reserve_turn(
+        &mut NEXT_REQUEST.lock().unwrap(),
+        host,
+        Duration::from_millis(delay),
+    );
+    let keys = INTERRUPT.lock().unwrap().clone();
+    sleep_unless_cancelled(wait, keys.as_ref())
+}
+
+/// Sleep for `wait` a little at a time, stopping early with an error
+/// if Ctrl-c comes in on `keys`.
+fn sleep_unless_cancelled(wait: Duration, keys: Option<&ui::KeyReceiver>) -> Result<()> {
+    let until = clock::now() + wait;
+    loop {
+        let left = clock::until(until);
+        if left.is_zero() {
+            return Ok(());
+        }
+        if let Some(Ok(Key::Ctrl('c'))) = keys.map(|keys| keys.lock().unwrap().try_recv()) {
+            return Err(error!("Request cancelled"));
+        }
+        std::thread::sleep(left.min(WAIT_SLICE));
+    }
+}
+
+/// Make a Gopher request over TLS and return a stream ready to be
+/// read()'d. Unlike `request()`, there's no falling back to a plain
+/// connection: a failed handshake is an error. A `?` in `selector` is
+/// sent as a tab, for search queries.
+pub fn request_tls(host: &str, port: &str, selector: &str, tor: bool) -> Result<Stream> {
+    let selector = selector.replace('?', "\t"); // search queries
+    send_request_tls(host, port, &selector, tor)
+}
+
+/// `request_tls()`, sending `selector` exactly as it is.
+fn send_request_tls(host: &str, port: &str, selector: &str, tor: bool) -> Result<Stream> {
+    wait_turn(host)?;
+    let addr = format!("{}:{}", bracket_ipv6(host), port);
+    let mut stream = tls_connect(host, &addr, tor)?;
+    stream.write_all(selector.as_ref())?;
+    stream.write_all("\r\n".as_ref())?;
+    Ok(Stream {
+        io: stream,
+        tls: true,
+    })
+}
+
+/// Open a TLS session with `host` at `addr`, through Tor if `tor` is
+/// set.
+#[cfg(feature = "tls")]
+fn tls_connect(host: &str, addr: &str, tor: bool) -> Result<Box<dyn ReadWrite>> {
+    let connector = TlsConnector::new().map_err(|e| error!("TLS error: {}", e))?;
+    if tor {
+        #[cfg(feature = "tor")]
+        {
+            let stream = tor_connect(addr)?;
+            return match connector.connect(host, stream) {
+                Ok(stream) => Ok(Box::new(stream)),
+                Err(e) => Err(handshake_error(addr, e)),
+            };
+        }
+    }
+    match connector.connect(host, connect(addr)?) {
+        Ok(stream) => Ok(Box::new(stream)),
+        Err(e) => Err(handshake_error(addr, e)),
+    }
+}
+
+/// Why the TLS handshake with `addr` didn't work out.
+#[cfg(feature = "tls")]
+fn handshake_error<S>(addr: &str, e: native_tls::HandshakeError<S>) -> Error {
+    let reason = match e {
+        native_tls::HandshakeError::Failure(e) => e.to_string(),
+        native_tls::HandshakeError::WouldBlock(_) => "timed out".into(),
+    };
+    error!("TLS handshake with {} failed: {}", addr, reason)
+}
+
+/// Without the `tls` feature, `gophers://` URLs can't be fetched.
+#[cfg(not(feature = "tls"))]
+fn tls_connect(_host: &str, _addr: &str, _tor: bool) -> Result<Box<dyn ReadWrite>> {
+    Err(error!("phetch was built without TLS support").into())
+}
+
+/// Connect to `addr` through the Tor proxy in `TOR_PROXY`, or the
+/// usual local one.
+#[cfg(feature = "tor")]
+fn tor_connect(addr: &str) -> Result<TorStream> {
+    let stream = std::env::var("TOR_PROXY")
+        .unwrap_or_else(|_| "127.0.0.1:9050".into())
+        .to_socket_addrs()?
+        .find_map(|s| TorStream::connect_with_address(s, addr).ok())
+        .ok_or_else(|| error!("Can't create socket"))?;
+    stream.get_ref().set_read_timeout(Some(timeout()))?;
+    Ok(stream)
 }
 
 /// Make a Gopher request and return a TcpStream ready to be read()'d.
 /// Will attempt a TLS connection first, then retry a regular
-/// connection if it fails.
+/// connection if it fails. A `?` in `selector` is sent as a tab, for
+/// search queries.
 pub fn request(host: &str, port: &str, selector: &str, tls: bool, tor: bool) -> Result<Stream> {
     let selector = selector.replace('?', "\t"); // search queries
-    let addr = format!("{}:{}", host, port);
+    send_request(host, port, &selector, tls, tor)
+}
+
+/// `request()`, sending `selector` exactly as it is.
+fn send_request(host: &str, port: &str, selector: &str, tls: bool, tor: bool) -> Result<Stream> {
+    wait_turn(host)?;
+    let addr = format!("{}:{}", bracket_ipv6(host), port);
 
     // attempt tls connection
     if tls {
@@ -219,11 +619,7 @@ pub fn request(host: &str, port: &str, selector: &str, tls: bool, tor: bool) ->
         {
             {
                 if let Ok(connector) = TlsConnector::new() {
-                    let stream = addr
-                        .to_socket_addrs()?
-                        .find_map(|s| TcpStream::connect_timeout(&s, TCP_TIMEOUT_DURATION).ok())
-                        .ok_or_else(|| error!("Can't create socket"))?;
-                    stream.set_read_timeout(Some(TCP_TIMEOUT_DURATION))?;
+                    let stream = connect(&addr)?;
                     if let Ok(mut stream) = connector.connect(host, stream) {
                         stream.write_all(selector.as_ref())?;
                         stream.write_all("\r\n".as_ref())?;
@@ -241,11 +637,7 @@ pub fn request(host: &str, port: &str, selector: &str, tls: bool, tor: bool) ->
     if tor {
         #[cfg(feature = "tor")]
         {
-            let mut stream = std::env::var("TOR_PROXY")
-                .unwrap_or_else(|_| "127.0.0.1:9050".into())
-                .to_socket_addrs()?
-                .find_map(|s| TorStream::connect_with_address(s, addr.as_ref()).ok())
-                .ok_or_else(|| error!("Can't create socket"))?;
+            let mut stream = tor_connect(&addr)?;
             stream.write_all(selector.as_ref())?;
             stream.write_all("\r\n".as_ref())?;
             return Ok(Stream {
@@ -256,11 +648,7 @@ pub fn request(host: &str, port: &str, selector: &str, tls: bool, tor: bool) ->
     }
 
     // no tls or tor, try regular connection
-    let mut stream = addr
-        .to_socket_addrs()?
-        .find_map(|s| TcpStream::connect_timeout(&s, TCP_TIMEOUT_DURATION).ok())
-        .ok_or_else(|| error!("Can't create socket"))?;
-    stream.set_read_timeout(Some(TCP_TIMEOUT_DURATION))?;
+    let mut stream = connect(&addr)?;
     stream.write_all(selector.as_ref())?;
     stream.write_all("\r\n".as_ref())?;
     Ok(Stream {
@@ -269,6 +657,291 @@ pub fn request(host: &str, port: &str, selector: &str, tls: bool, tor: bool) ->
     })
 }
 
+/// Open a TCP connection to `addr` ("host:port"), trying each of
+/// its addresses in turn.
+fn connect(addr: &str) -> Result<TcpStream> {
+    let bind = BIND.lock().unwrap().clone();
+    connect_with(addr, bind.as_deref(), timeout())
+}
+
+/// Like `connect()`, but from the local address `bind`, if given,
+/// giving up on the server after `timeout`.
+fn connect_with(addr: &str, bind: Option<&str>, timeout: Duration) -> Result<TcpStream> {
+    let local = bind.map(parse_bind).transpose()?;
+    let addrs = resolve(addr)?;
+    let mut last_err = None;
+    let (idx, stream) = addrs
+        .iter()
+        .enumerate()
+        .find_map(|(i, s)| {
+            let res = match local {
+                Some(local) => connect_from(local, s, timeout),
+                None => TcpStream::connect_timeout(s, timeout),
+            };
+            match res {
+                Ok(stream) => Some((i, stream)),
+                Err(e) => {
+                    last_err = Some(e);
+                    None
+                }
+            }
+        })
+        .ok_or_else(|| {
+            // the cached addresses might be stale, so look them up
+            // again next time
+            RESOLVED.lock().unwrap().remove(addr);
+            match last_err.take() {
+                // keep timeouts around so callers can tell them apart
+                Some(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    Error::Timeout(format!("connecting to {}", addr))
+                }
+                Some(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::NetworkUnreachable | io::ErrorKind::HostUnreachable
+                    ) =>
+                {
+                    Error::Unreachable(addr.to_string())
+                }
+                // anything else, like a refused connection or a bad
+                // `bind`, keeps its kind
+                Some(e) => Error::Io(io::Error::new(
+                    e.kind(),
+                    format!("Can't connect to {}: {}", addr, e),
+                )),
+                None => Error::Unreachable(addr.to_string()),
+            }
+        })?;
+
+    if idx > 0 {
+        if let Some(cached) = RESOLVED.lock().unwrap().get_mut(addr) {
+            cached.swap(0, idx);
+        }
+    }
+
+    stream.set_read_timeout(Some(timeout))?;
+    // we write the whole selector at once, don't wait around to
+    // batch it with anything else
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+/// Parses the `bind` setting: an IP address, with or without a port.
+fn parse_bind(bind: &str) -> Result<SocketAddr> {
+    if let Ok(ip) = bind.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, 0));
+    }
+    bind.parse()
+        .map_err(|_| Error::Parse(format!("Invalid bind address: {}", bind)))
+}
+
+/// Open a TCP connection to `remote` from the local address `local`.
+/// std can't bind a socket before connecting it, so we do it by hand
+/// with libc, which phetch already uses for the terminal, instead of
+/// pulling in another crate for it.
+fn connect_from(
+    local: SocketAddr,
+    remote: &SocketAddr,
+    timeout: Duration,
+) -> io::Result<TcpStream> {
+    if local.is_ipv4() != remote.is_ipv4() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            format!("Can't reach {} from {}", remote, local),
+        ));
+    }
+
+    let domain = if local.is_ipv4() {
+        libc::AF_INET
+    } else {
+        libc::AF_INET6
+    };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_STREAM, 0) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // the stream owns the socket now, and closes it if we bail
+    let stream = unsafe { TcpStream::from_raw_fd(fd) };
+
+    let (addr, len) = to_sockaddr(&local);
+    if unsafe { libc::bind(fd, &addr as *const _ as *const libc::sockaddr, len) } == -1 {
+        let e = io::Error::last_os_error();
+        return Err(io::Error::new(
+            e.kind(),
+            format!("Can't bind to {}: {}", local, e),
+        ));
+    }
+
+    // connect in the background so we can give up after `timeout`
+    stream.set_nonblocking(true)?;
+    let (addr, len) = to_sockaddr(remote);
+    if unsafe { libc::connect(fd, &addr as *const _ as *const libc::sockaddr, len) } == -1 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::EINPROGRESS) {
+            return Err(e);
+        }
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        // a signal, like SIGWINCH when the terminal is resized, can
+        // wake poll() early. Keep waiting for whatever time is left.
+        let until = clock::now() + timeout;
+        loop {
+            let left = clock::until(until).as_millis() as libc::c_int;
+            match unsafe { libc::poll(&mut pfd, 1, left) } {
+                -1 => {
+                    let e = io::Error::last_os_error();
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(e);
+                    }
+                }
+                0 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "connection timed out",
+                    ))
+                }
+                _ => break,
+            }
+        }
+        if let Some(e) = stream.take_error()? {
+            return Err(e);
+        }
+    }
+    stream.set_nonblocking(false)?;
+    Ok(stream)
+}
+
+/// Convert a `SocketAddr` into something libc's `bind()` and
+/// `connect()` understand.
+fn to_sockaddr(addr: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let len = match addr {
+        SocketAddr::V4(addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            #[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd"))]
+            {
+                sin.sin_len = std::mem::size_of::<libc::sockaddr_in>() as u8;
+            }
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr = libc::in_addr {
+                s_addr: u32::from_ne_bytes(addr.ip().octets()),
+            };
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            #[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd"))]
+            {
+                sin6.sin6_len = std::mem::size_of::<libc::sockaddr_in6>() as u8;
+            }
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_addr = libc::in6_addr {
+                s6_addr: addr.ip().octets(),
+            };
+            sin6.sin6_scope_id = addr.scope_id();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (storage, len as libc::socklen_t)
+}
+
+/// Serve `body` to every request on a local port and return the port.
+#[cfg(test)]
+pub(crate) fn serve(body: &'static str) -> u16 {
+    use std::io::{BufRead, BufReader};
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = server.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut client in server.incoming().flatten() {
+            BufRead::read_line(&mut BufReader::new(&client), &mut String::new()).unwrap();
+            client.write_all(body.as_bytes()).unwrap();
+        }
+    });
+    port
+}
+
+/// A local port that's bound but not listening, so connecting to it
+/// is refused until `listen()` is called. Unlike a port that's been
+/// closed, nothing else can take it in the meantime.
+#[cfg(test)]
+pub(crate) struct RefusingPort {
+    socket: std::net::TcpListener,
+}
+
+#[cfg(test)]
+impl RefusingPort {
+    /// Bind a fresh port on 127.0.0.1.
+    pub(crate) fn bind() -> RefusingPort {
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+        assert_ne!(fd, -1, "{}", io::Error::last_os_error());
+        let socket = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+        let (addr, len) = to_sockaddr(&"127.0.0.1:0".parse().unwrap());
+        let res = unsafe { libc::bind(fd, &addr as *const _ as *const libc::sockaddr, len) };
+        assert_ne!(res, -1, "{}", io::Error::last_os_error());
+        RefusingPort { socket }
+    }
+
+    /// The address connections are refused at.
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.socket.local_addr().unwrap()
+    }
+
+    /// Start taking connections on the port after all.
+    pub(crate) fn listen(self) -> std::net::TcpListener {
+        use std::os::unix::io::AsRawFd;
+        let res = unsafe { libc::listen(self.socket.as_raw_fd(), 128) };
+        assert_ne!(res, -1, "{}", io::Error::last_os_error());
+        self.socket
+    }
+}
+
+/// Resolve "host:port" to a list of socket addresses, using the
+/// ones we found earlier this session if possible.
+fn resolve(addr: &str) -> Result<Vec<SocketAddr>> {
+    let limit = DNS_CACHE.load(Ordering::Relaxed);
+    if let Some(addrs) = RESOLVED.lock().unwrap().get(addr).filter(|_| limit > 0) {
+        return Ok(addrs.clone());
+    }
+
+    // a good port means any failure is down to the host not resolving
+    let (host, port) = addr.rsplit_once(':').unwrap_or((addr, ""));
+    let addrs = match addr.to_socket_addrs() {
+        Ok(addrs) => addrs.collect::<Vec<_>>(),
+        Err(e) if port.parse::<u16>().is_err() => return Err(e.into()),
+        Err(_) => vec![],
+    };
+    if addrs.is_empty() {
+        return Err(Error::Unresolved(host.to_string()));
+    }
+    remember(&mut RESOLVED.lock().unwrap(), addr, &addrs, limit);
+    Ok(addrs)
+}
+
+/// Add the addresses `addr` resolved to to `resolved`, making room
+/// by forgetting another server's if it already holds `limit`.
+fn remember(
+    resolved: &mut HashMap<String, Vec<SocketAddr>>,
+    addr: &str,
+    addrs: &[SocketAddr],
+    limit: usize,
+) {
+    if limit == 0 {
+        return;
+    }
+    if !resolved.contains_key(addr) && resolved.len() >= limit {
+        if let Some(old) = resolved.keys().next().cloned() {
+            resolved.remove(&old);
+        }
+    }
+    resolved.insert(addr.to_string(), addrs.to_vec());
+}
+
 impl<'a> Url<'a> {
     /// Creates a new Gopher Url quickly from a tuple of Url fields.
     pub fn new(typ: Type, host: &'a str, port: &'a str, sel: &'a str) -> Url<'a> {
@@ -276,11 +949,26 @@ impl<'a> Url<'a> {
             typ,
             host,
             port,
-            sel,
+            sel: Cow::Borrowed(sel),
+            tls: false,
         }
     }
 }
 
+/// Splits a `text:` or `menu:` prefix off of a URL, for forcing how
+/// it's displayed no matter its type. Returns the forced type, if
+/// any, and the rest of the URL.
+///   text:gopher://phkt.io/1/ => (Some(Type::Text), "gopher://phkt.io/1/")
+pub fn view_prefix(url: &str) -> (Option<Type>, &str) {
+    if let Some(rest) = url.strip_prefix("text:") {
+        (Some(Type::Text), rest)
+    } else if let Some(rest) = url.strip_prefix("menu:") {
+        (Some(Type::Menu), rest)
+    } else {
+        (None, url)
+    }
+}
+
 /// Given a Gopher URL, returns a gopher::Type.
 pub fn type_for_url(url: &str) -> Type {
     if url.starts_with("telnet://") {
@@ -291,19 +979,301 @@ pub fn type_for_url(url: &str) -> Type {
         return Type::HTML;
     }
 
-    let url = url.trim_start_matches("gopher://");
+    let url = strip_scheme(url, TLS_SCHEME).unwrap_or_else(|| url.trim_start_matches("gopher://"));
     if let Some(idx) = url.find('/') {
         if let Some(t) = url.chars().nth(idx + 1) {
-            return Type::from(t).unwrap_or(Type::Menu);
+            return sniff_type(Type::from(t).unwrap_or(Type::Menu), &url[idx..]);
         }
     }
 
     Type::Menu
 }
 
+/// Some servers send images as generic binaries, so for those we
+/// peek at the selector's file extension to find out what they
+/// really are. Every other declared type is trusted as-is.
+pub fn sniff_type(typ: Type, sel: &str) -> Type {
+    if typ != Type::Binary {
+        return typ;
+    }
+
+    sel.split('?')
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .and_then(|file| file.rsplit_once('.'))
+        .and_then(|(_, ext)| Type::from_extension(ext))
+        .unwrap_or(typ)
+}
+
+/// The host part of any URL, like `sdf.org` in `gopher://sdf.org/1/`
+/// or `example.com` in `https://me@example.com:8080/`.
+pub fn url_host(url: &str) -> &str {
+    let url = view_prefix(url).1;
+    if url.starts_with("gopher://") || url.starts_with("telnet://") || !url.contains("://") {
+        return parse_url(url).host;
+    }
+    let rest = &url[url.find("://").unwrap_or(0) + 3..];
+    let rest = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let rest = rest.rsplit_once('@').map_or(rest, |(_, host)| host);
+    if let Some(ipv6) = rest.strip_prefix('[') {
+        return &ipv6[..ipv6.find(']').unwrap_or(ipv6.len())];
+    }
+    rest.split(':').next().unwrap_or(rest)
+}
+
+/// Does something typed at the Go to URL prompt look like a search
+/// phrase rather than a URL? Spaces give it away, and so does a lone
+/// word with no dots, colons, or slashes in it.
+pub fn looks_like_search(input: &str) -> bool {
+    let input = input.trim();
+    if input.is_empty() {
+        return false;
+    }
+    input.contains(char::is_whitespace) || !input.contains(['.', ':', '/'])
+}
+
+/// The web proxy URL for a Gopher URL, filled in from `template`.
+/// `{url}` is the whole Gopher URL, percent-encoded, and `{host}`,
+/// `{port}`, `{type}`, and `{selector}` are its parts.
+///   https://gopher.floodgap.com/gopher/gw?a={url}
+pub fn web_proxy_url(template: &str, url: &str) -> String {
+    let u = parse_url(url);
+    let typ = u.typ.to_char().to_string();
+    let canonical = build_url(u.typ, u.host, u.port, &u.sel, u.tls);
+    template
+        .replace("{url}", &percent_encode(&canonical, ""))
+        .replace("{host}", u.host)
+        .replace("{port}", u.port)
+        .replace("{type}", &typ)
+        .replace("{selector}", &percent_encode(&u.sel, "/"))
+}
+
+/// `host` in brackets if it's an IPv6 address, so it can be followed
+/// by a port in a URL or "host:port" address.
+pub fn bracket_ipv6(host: &str) -> Cow<'_, str> {
+    if host.contains(':') {
+        format!("[{}]", host).into()
+    } else {
+        host.into()
+    }
+}
+
+/// A Gopher URL from its parts, leaving out the default port. `tls`
+/// makes it a `gophers://` URL.
+fn build_url(typ: Type, host: &str, port: &str, sel: &str, tls: bool) -> String {
+    let scheme = if tls { TLS_SCHEME } else { "gopher://" };
+    let host = bracket_ipv6(host);
+    let sel = encode_selector(sel);
+    if port == "70" {
+        format!("{}{}/{}{}", scheme, host, typ.to_char(), sel)
+    } else {
+        format!("{}{}:{}/{}{}", scheme, host, port, typ.to_char(), sel)
+    }
+}
+
+/// Guess the menu a page lives in by dropping the last part of its
+/// selector, so `gopher://host/0/phlog/post.txt` gives
+/// `gopher://host/1/phlog/`. It only looks at the URL, so it works
+/// without any history, like on a page opened from the command line.
+/// Search queries are ignored and `//` and `..` in the selector are
+/// tidied up. None if it's already at the root.
+pub fn containing_menu_url(url: &str) -> Option<String> {
+    let u = parse_url(view_prefix(url).1);
+    if u.host.is_empty() {
+        return None;
+    }
+    let sel = u.sel.split('\t').next().unwrap_or_default();
+    let mut parts = vec![];
+    for part in sel.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.pop()?;
+    let mut dir = if sel.starts_with('/') { "/" } else { "" }.to_string();
+    for part in parts {
+        dir.push_str(part);
+        dir.push('/');
+    }
+    Some(build_url(Type::Menu, u.host, u.port, &dir, u.tls))
+}
+
+/// Percent-encode everything but unreserved URL characters and the
+/// ones in `keep`.
+fn percent_encode(s: &str, keep: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if url_safe(b, keep) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Can `b` appear in a URL as it is? Unreserved characters can, and
+/// so can the ones in `keep`.
+fn url_safe(b: u8, keep: &str) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~".contains(&b) || keep.as_bytes().contains(&b)
+}
+
+/// Tidy up a URL someone pasted or typed: no surrounding whitespace,
+/// and a lowercase scheme, so `GOPHER://Host/1/` works like
+/// `gopher://Host/1/`. Everything after the scheme is left alone.
+pub fn clean_url(url: &str) -> String {
+    let url = url.trim();
+    match url.find("://") {
+        Some(idx)
+            if url[..idx]
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+') =>
+        {
+            format!("{}{}", url[..idx].to_ascii_lowercase(), &url[idx..])
+        }
+        _ => url.to_string(),
+    }
+}
+
+/// Turn a Gopher URL the way it's often shared on the web into a
+/// real one: `host/0/path` with no scheme gets `gopher://`, and a link
+/// through the Floodgap web proxy, like
+/// `https://gopher.floodgap.com/gopher/gw?a=gopher%3A%2F%2Fhost%2F1%2F`,
+/// becomes the Gopher URL it points to. Anything else comes back
+/// as `clean_url()` would leave it.
+pub fn normalize_url(input: &str) -> String {
+    let url = clean_url(input);
+    if let Some(target) = floodgap_target(&url) {
+        return normalize_url(&target);
+    }
+    if !url.contains("://") {
+        if let Some((host, _)) = url.split_once('/') {
+            if !host.is_empty() && host.contains(['.', ':']) && !host.contains(char::is_whitespace)
+            {
+                return format!("gopher://{}", url);
+            }
+        }
+    }
+    url
+}
+
+/// The Gopher URL a link through Floodgap's web proxy points to.
+fn floodgap_target(url: &str) -> Option<String> {
+    let rest = strip_scheme(url, "https://")
+        .or_else(|| strip_scheme(url, "http://"))
+        .unwrap_or(url);
+    let rest = rest
+        .strip_prefix("gopher.floodgap.com/gopher/gw")
+        .or_else(|| rest.strip_prefix("www.floodgap.com/gopher/gw"))?;
+    let query = rest.trim_start_matches(".lite").strip_prefix('?')?;
+    let target = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("a="))
+        .unwrap_or(query);
+    let target = percent_decode(target);
+    if target.is_empty() {
+        None
+    } else {
+        Some(target)
+    }
+}
+
+/// Punctuation that can stay as it is in the selector part of a URL.
+/// `%` and `?` can't, since they'd be read back as an escape or the
+/// start of a search query.
+const SELECTOR_SAFE: &str = "!\"#$&'()*+,/:;<=>@[\\]^`{|}";
+
+/// Write a selector the way it goes in a URL: percent-encoded, with a
+/// search query after a tab turned into `?query`. The query is left
+/// as it is.
+pub fn encode_selector(sel: &str) -> Cow<'_, str> {
+    let (path, query) = match sel.split_once('\t') {
+        Some((path, query)) => (path, Some(query)),
+        None => (sel, None),
+    };
+    if query.is_none() && path.bytes().all(|b| url_safe(b, SELECTOR_SAFE)) {
+        return Cow::Borrowed(sel);
+    }
+    let mut out = percent_encode(path, SELECTOR_SAFE);
+    if let Some(query) = query {
+        out.push('?');
+        out.push_str(query);
+    }
+    Cow::Owned(out)
+}
+
+/// `sel` the way phetch writes it in its own Gophermaps, like
+/// bookmarks.gph: a search query goes after a `?`, since a tab would
+/// end the field. Menus read it back as a query.
+pub fn menu_selector(sel: &str) -> Cow<'_, str> {
+    match sel.split_once('\t') {
+        Some((path, query)) => Cow::Owned(format!("{}?{}", path, query)),
+        None => Cow::Borrowed(sel),
+    }
+}
+
+/// Undo `encode_selector()`: everything up to the first `?` or tab
+/// is decoded, and the search query after it is kept as it is, after
+/// a tab. Splitting off the query first means an encoded `%3F` stays
+/// part of the selector. Bad escapes are left alone.
+fn decode_selector(sel: &str) -> Cow<'_, str> {
+    let (path, query) = match sel.find(['\t', '?']) {
+        Some(idx) => (&sel[..idx], Some(&sel[idx + 1..])),
+        None => (sel, None),
+    };
+    if query.is_none() && !path.contains('%') {
+        return Cow::Borrowed(sel);
+    }
+    let mut out = percent_decode(path);
+    if let Some(query) = query {
+        out.push('\t');
+        out.push_str(query);
+    }
+    Cow::Owned(out)
+}
+
+/// Undo `percent_encode()`. Bad escapes are left as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(b) if bytes[i] == b'%' => {
+                out.push(b);
+                i += 3;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `url` without `scheme`, like "gopher://", ignoring case.
+fn strip_scheme<'a>(url: &'a str, scheme: &str) -> Option<&'a str> {
+    match url.get(..scheme.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(scheme) => Some(&url[scheme.len()..]),
+        _ => None,
+    }
+}
+
 /// Parses gopher URL into parts.
-pub fn parse_url(url: &str) -> Url {
-    let mut url = url.trim_start_matches("gopher://");
+pub fn parse_url(url: &str) -> Url<'_> {
+    let url = url.trim();
+    let (mut url, tls) = match strip_scheme(url, TLS_SCHEME) {
+        Some(rest) => (rest, true),
+        None => (strip_scheme(url, "gopher://").unwrap_or(url), false),
+    };
     let mut typ = Type::Menu;
     let mut host;
     let mut port = "70";
@@ -315,9 +1285,9 @@ pub fn parse_url(url: &str) -> Url {
     }
 
     // telnet urls
-    if url.starts_with("telnet://") {
+    if let Some(rest) = strip_scheme(url, "telnet://") {
         typ = Type::Telnet;
-        url = url.trim_start_matches("telnet://");
+        url = rest;
     } else if url.contains("://") {
         // non-gopher URLs, stick everything in selector
         return Url::new(Type::HTML, "", "", url);
@@ -331,14 +1301,20 @@ pub fn parse_url(url: &str) -> Url {
         host = url;
     }
 
+    // telnet logins, ex: telnet://guest@bbs.impakt.net:6502
+    if typ == Type::Telnet {
+        if let Some((login, rest)) = host.rsplit_once('@') {
+            sel = login;
+            host = rest;
+        }
+    }
+
     // ipv6
     if let Some(idx) = host.find('[') {
-        if let Some(end) = host[idx + 1..].find(']') {
-            host = &host[idx + 1..=end];
-            if host.len() > end {
-                if let Some(idx) = host[end..].find(':') {
-                    port = &host[idx + 1..];
-                }
+        if let Some((ip, rest)) = host[idx + 1..].split_once(']') {
+            host = ip;
+            if let Some(p) = rest.strip_prefix(':').filter(|p| !p.is_empty()) {
+                port = p;
             }
         } else {
             return Url::new(Type::Error, "Unclosed ipv6 bracket", "", url);
@@ -363,12 +1339,21 @@ pub fn parse_url(url: &str) -> Url {
         }
     }
 
-    Url::new(typ, host, port, sel)
+    Url {
+        tls,
+        sel: if typ == Type::Telnet {
+            Cow::Borrowed(sel)
+        } else {
+            decode_selector(sel)
+        },
+        ..Url::new(typ, host, port, "")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::ManualClock;
 
     #[test]
     fn test_simple_parse() {
@@ -431,7 +1416,7 @@ mod tests {
         assert_eq!(url.typ, Type::Search);
         assert_eq!(url.host, "gopherpedia.com");
         assert_eq!(url.port, "70");
-        assert_eq!(url.sel, "/lookup?Gopher");
+        assert_eq!(url.sel, "/lookup\tGopher");
 
         let url = parse_next_url!();
         assert_eq!(url.typ, Type::Menu);
@@ -442,7 +1427,7 @@ mod tests {
         let url = parse_next_url!();
         assert_eq!(url.typ, Type::Menu);
         assert_eq!(url.host, "1234:2345:dead:4567:7890:1234:beef:1111");
-        assert_eq!(url.port, "70");
+        assert_eq!(url.port, "7443");
         assert_eq!(url.sel, "/files");
 
         let url = parse_next_url!();
@@ -533,6 +1518,214 @@ mod tests {
         assert_eq!(urls.next(), None);
     }
 
+    #[test]
+    fn test_parse_pasted_url() {
+        let url = parse_url("GOPHER://Phkt.io:7070/0/Phetch/README.md");
+        assert_eq!(url.typ, Type::Text);
+        assert_eq!(url.host, "Phkt.io");
+        assert_eq!(url.port, "7070");
+        assert_eq!(url.sel, "/Phetch/README.md");
+
+        let url = parse_url("  \tgopher://phkt.io/1/Links\r\n");
+        assert_eq!(url.typ, Type::Menu);
+        assert_eq!(url.host, "phkt.io");
+        assert_eq!(url.port, "70");
+        assert_eq!(url.sel, "/Links");
+
+        let url = parse_url(" Telnet://bbs.impakt.net:6502/\n");
+        assert_eq!(url.typ, Type::Telnet);
+        assert_eq!(url.host, "bbs.impakt.net");
+        assert_eq!(url.port, "6502");
+
+        let url = parse_url("telnet://guest@bbs.impakt.net:6502");
+        assert_eq!(url.typ, Type::Telnet);
+        assert_eq!(url.host, "bbs.impakt.net");
+        assert_eq!(url.port, "6502");
+        assert_eq!(url.sel, "guest");
+
+        assert_eq!(
+            clean_url("\n GOPHER://Phkt.io/1/Links \n"),
+            "gopher://Phkt.io/1/Links"
+        );
+        assert_eq!(
+            clean_url("HTTPS://GitHub.com/XVXX"),
+            "https://GitHub.com/XVXX"
+        );
+        assert_eq!(clean_url(" phkt.io/1/ABC "), "phkt.io/1/ABC");
+        assert_eq!(clean_url("text:GOPHER://x"), "text:GOPHER://x");
+    }
+
+    #[test]
+    fn test_percent_encoded_selectors() {
+        let url = parse_url("gopher://phkt.io/1/foo%20bar");
+        assert_eq!(url.sel, "/foo bar");
+        let url = parse_url("gopher://phkt.io/0/100%25%20done.txt");
+        assert_eq!(url.sel, "/100% done.txt");
+        let url = parse_url("gopher://phkt.io/0/%E6%97%A5%E8%A8%98.txt");
+        assert_eq!(url.sel, "/日記.txt");
+        // bad escapes, search queries, and web links stay as they are
+        assert_eq!(parse_url("gopher://phkt.io/0/50%zz").sel, "/50%zz");
+        assert_eq!(
+            parse_url("gopher://phkt.io/7/a%20b\tc%20d").sel,
+            "/a b\tc%20d"
+        );
+        assert_eq!(
+            parse_url("gopher://phkt.io/h/URL:https://x.io/a%20b").sel,
+            "phkt.io/h/URL:https://x.io/a%20b"
+        );
+
+        for sel in [
+            "/foo bar",
+            "/100% done.txt",
+            "/日記.txt",
+            "/a\tb c",
+            "/plain/path?q",
+        ] {
+            let url = build_url(Type::Text, "phkt.io", "70", sel, false);
+            assert_eq!(parse_url(&url).sel, sel, "{}", url);
+        }
+        assert_eq!(
+            build_url(Type::Text, "phkt.io", "70", "/100% done/日記.txt", false),
+            "gopher://phkt.io/0/100%25%20done/%E6%97%A5%E8%A8%98.txt"
+        );
+        assert!(matches!(encode_selector("/about.txt"), Cow::Borrowed(_)));
+
+        // only the first ? starts a search query, and an encoded one
+        // is part of the selector
+        assert_eq!(parse_url("gopher://phkt.io/7/s?what?").sel, "/s\twhat?");
+        assert_eq!(parse_url("gopher://phkt.io/0/why%3F.txt").sel, "/why?.txt");
+        assert_eq!(
+            build_url(Type::Search, "phkt.io", "70", "/why?\tnot", false),
+            "gopher://phkt.io/7/why%3F?not"
+        );
+    }
+
+    #[test]
+    fn test_request_selector() {
+        use std::io::{BufRead, BufReader};
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for mut client in server.incoming().flatten() {
+                let mut line = String::new();
+                BufRead::read_line(&mut BufReader::new(&client), &mut line).unwrap();
+                client.write_all(line.as_bytes()).unwrap();
+            }
+        });
+        let sent = |url: &str| {
+            let (_, body) =
+                fetch_url(&format!("gopher://127.0.0.1:{}{}", port, url), false, false).unwrap();
+            String::from_utf8(body).unwrap()
+        };
+        assert_eq!(sent("/0/why%3F.txt"), "/why?.txt\r\n");
+        assert_eq!(sent("/7/find?what?"), "/find\twhat?\r\n");
+
+        // the public API still takes `?` for the query
+        let (_, body) = fetch("127.0.0.1", &port.to_string(), "/find?what", false, false).unwrap();
+        assert_eq!(body, b"/find\twhat\r\n");
+    }
+
+    #[test]
+    fn test_parse_ipv6_url() {
+        let url = parse_url("gopher://[2001:db8::1]:7070/1/");
+        assert_eq!(url.typ, Type::Menu);
+        assert_eq!(url.host, "2001:db8::1");
+        assert_eq!(url.port, "7070");
+        assert_eq!(url.sel, "/");
+
+        let url = parse_url("gopher://[2001:db8::1]/0/about.txt");
+        assert_eq!(url.typ, Type::Text);
+        assert_eq!(url.host, "2001:db8::1");
+        assert_eq!(url.port, "70");
+        assert_eq!(url.sel, "/about.txt");
+
+        let url = parse_url("[::1]:");
+        assert_eq!(url.host, "::1");
+        assert_eq!(url.port, "70");
+
+        let url = parse_url("gopher://192.168.1.2:7070/1/");
+        assert_eq!(url.host, "192.168.1.2");
+        assert_eq!(url.port, "7070");
+        let url = parse_url("gopher://192.168.1.2/1/");
+        assert_eq!(url.host, "192.168.1.2");
+        assert_eq!(url.port, "70");
+
+        assert_eq!(bracket_ipv6("2001:db8::1"), "[2001:db8::1]");
+        assert_eq!(bracket_ipv6("192.168.1.2"), "192.168.1.2");
+        assert_eq!(
+            build_url(Type::Menu, "::1", "7070", "/", false),
+            "gopher://[::1]:7070/1/"
+        );
+    }
+
+    #[test]
+    fn test_fetch_ipv6() {
+        use std::net::TcpListener;
+        // not every machine has IPv6
+        let server = match TcpListener::bind("[::1]:0") {
+            Ok(server) => server,
+            Err(_) => return,
+        };
+        let port = server.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut client = server.accept().unwrap().0;
+            let _ = client.read(&mut [0; 1024]);
+            client.write_all(b"ihello over ipv6\r\n").unwrap();
+        });
+        let url = format!("gopher://[::1]:{}/1/", port);
+        let (_, body) = fetch_url(&url, false, false).unwrap();
+        assert_eq!(body, b"ihello over ipv6\r\n");
+    }
+
+    #[test]
+    fn test_parse_tls_url() {
+        let url = parse_url("gophers://phkt.io/0/Phetch/README.md");
+        assert!(url.tls);
+        assert_eq!(url.typ, Type::Text);
+        assert_eq!(url.host, "phkt.io");
+        assert_eq!(url.port, "70");
+        assert_eq!(url.sel, "/Phetch/README.md");
+
+        let url = parse_url("GOPHERS://sdf.org:7443/1/files");
+        assert!(url.tls);
+        assert_eq!(url.host, "sdf.org");
+        assert_eq!(url.port, "7443");
+        assert_eq!(url.sel, "/files");
+
+        assert!(!parse_url("gopher://phkt.io").tls);
+        assert!(!parse_url("phkt.io").tls);
+        assert_eq!(type_for_url("gophers://phkt.io/0/a.txt"), Type::Text);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_tls_required() {
+        use std::net::TcpListener;
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in server.incoming().take(3) {
+                let mut stream = stream.unwrap();
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(b"iplain old gopher\r\n");
+            }
+        });
+
+        // gopher:// with tls on falls back to a plain connection...
+        let url = format!("gopher://{}/1/", addr);
+        let (tls, body) = fetch_url(&url, true, false).unwrap();
+        assert!(!tls);
+        assert_eq!(body, b"iplain old gopher\r\n");
+
+        // ...but gophers:// doesn't
+        let url = format!("gophers://{}/1/", addr);
+        let err = fetch_url(&url, false, false).unwrap_err();
+        assert!(matches!(&err, Error::Other(_)));
+        assert!(err
+            .to_string()
+            .starts_with(&format!("TLS handshake with {} failed", addr)));
+    }
+
     #[test]
     fn test_type_for_url() {
         assert_eq!(type_for_url("phkt.io"), Type::Menu);
@@ -550,6 +1743,448 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sniff_image_types() {
+        assert_eq!(type_for_url("phkt.io/p/cat.png"), Type::PNG);
+        assert_eq!(type_for_url("phkt.io/g/dog.gif"), Type::GIF);
+        assert_eq!(type_for_url("phkt.io/9/pics/cat.png"), Type::PNG);
+        assert_eq!(type_for_url("phkt.io/9/pics/cat.JPG"), Type::Image);
+        assert_eq!(type_for_url("phkt.io/9/pics/cat.webp"), Type::Image);
+        assert_eq!(type_for_url("phkt.io/9/archive.tar.gz"), Type::Binary);
+        assert_eq!(type_for_url("phkt.io/9/no-extension"), Type::Binary);
+
+        // declared text and menus are never sniffed
+        assert_eq!(type_for_url("phkt.io/0/cat.png"), Type::Text);
+        assert_eq!(type_for_url("phkt.io/1/pics.png/"), Type::Menu);
+        assert_eq!(type_for_url("phkt.io/1/pics/cat.png"), Type::Menu);
+
+        assert!(type_for_url("phkt.io/p/cat").is_download());
+        assert!(type_for_url("phkt.io/9/cat.png").is_download());
+        assert!(!type_for_url("phkt.io/0/cat.png").is_download());
+        assert!(!type_for_url("phkt.io/1/cat.png").is_download());
+
+        assert_eq!(Type::PNG.extension(), Some("png"));
+        assert_eq!(Type::GIF.extension(), Some("gif"));
+        assert_eq!(Type::Binary.extension(), None);
+        assert!(Type::PNG.is_image());
+        assert!(!Type::Binary.is_image());
+    }
+
+    #[test]
+    fn test_resolve_is_cached() {
+        let addrs = resolve("127.0.0.1:7070").unwrap();
+        assert_eq!(addrs, vec!["127.0.0.1:7070".parse().unwrap()]);
+        assert_eq!(RESOLVED.lock().unwrap().get("127.0.0.1:7070"), Some(&addrs));
+        assert_eq!(resolve("127.0.0.1:7070").unwrap(), addrs);
+    }
+
+    #[test]
+    fn test_remember_limit() {
+        let mut resolved = HashMap::new();
+        let addrs = vec!["127.0.0.1:70".parse().unwrap()];
+        remember(&mut resolved, "a:70", &addrs, 0);
+        assert!(resolved.is_empty());
+        remember(&mut resolved, "a:70", &addrs, 2);
+        remember(&mut resolved, "b:70", &addrs, 2);
+        remember(&mut resolved, "a:70", &addrs, 2);
+        assert_eq!(resolved.len(), 2);
+        remember(&mut resolved, "c:70", &addrs, 2);
+        assert_eq!(resolved.len(), 2);
+        assert!(resolved.contains_key("c:70"));
+    }
+
+    #[test]
+    fn test_unresolved_host() {
+        let res = fetch_url("gopher://no-such-host.invalid/1/", false, false);
+        assert!(matches!(res, Err(Error::Unresolved(host)) if host == "no-such-host.invalid"));
+        let err = resolve("no-such-host.invalid:70").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Could not resolve host: no-such-host.invalid"
+        );
+        assert!(!matches!(
+            resolve("no-such-host.invalid:seventy"),
+            Err(Error::Unresolved(_))
+        ));
+    }
+
+    #[test]
+    fn test_connect_sets_nodelay() {
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let stream = connect(&addr).unwrap();
+        assert!(stream.nodelay().unwrap());
+        assert!(RESOLVED.lock().unwrap().contains_key(&addr));
+
+        // failed connections forget what they resolved
+        drop(server);
+        assert!(connect(&addr).is_err());
+        assert!(!RESOLVED.lock().unwrap().contains_key(&addr));
+    }
+
+    #[test]
+    fn test_connect_with_bind() {
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+
+        let stream = connect_with(&addr, Some("127.0.0.1"), timeout()).unwrap();
+        assert_eq!(
+            stream.local_addr().unwrap().ip(),
+            "127.0.0.1".parse::<IpAddr>().unwrap()
+        );
+        assert!(stream.nodelay().unwrap());
+        let (_, peer) = server.accept().unwrap();
+        assert_eq!(peer, stream.local_addr().unwrap());
+
+        let res = connect_with(&addr, Some("localhost-ish"), timeout());
+        assert!(matches!(res, Err(Error::Parse(msg)) if msg.contains("localhost-ish")));
+        let res = connect_with(&addr, Some("::1"), timeout());
+        assert!(matches!(res, Err(e) if e.to_string().contains("from [::1]:0")));
+
+        drop(server);
+        assert!(connect_with(&addr, Some("127.0.0.1"), timeout()).is_err());
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        assert!(matches!(
+            download_filename("gopher://phkt.io"),
+            Err(Error::Parse(_))
+        ));
+
+        let port = RefusingPort::bind();
+        let url = format!("gopher://{}/1/", port.addr());
+        let res = fetch_url(&url, false, false);
+        assert!(
+            matches!(&res, Err(Error::Io(e)) if e.kind() == io::ErrorKind::ConnectionRefused),
+            "{:?}",
+            res
+        );
+        let err = res.unwrap_err();
+        assert!(err.to_string().contains(&port.addr().to_string()));
+        assert!(err.is_host_down());
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        // accepts the connection, then never says anything
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let port = addr.port().to_string();
+        let start = std::time::Instant::now();
+        let mut stream = connect_with(&addr.to_string(), None, Duration::from_secs(1)).unwrap();
+        let res = read_limited(&mut stream, 0).map_err(|e| waiting_on(e, "127.0.0.1", &port));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        match res {
+            Err(e @ Error::Timeout(_)) => {
+                assert_eq!(e.to_string(), format!("Timed out: waiting for {}", addr))
+            }
+            _ => panic!("expected a timeout"),
+        }
+        drop(server);
+
+        set_timeout(0); // ignored
+        assert_eq!(timeout(), Duration::from_secs(TCP_TIMEOUT_IN_SECS));
+    }
+
+    #[test]
+    fn test_view_prefix() {
+        assert_eq!(
+            view_prefix("text:gopher://phkt.io/1/"),
+            (Some(Type::Text), "gopher://phkt.io/1/")
+        );
+        assert_eq!(
+            view_prefix("menu:phkt.io/0/gophermap"),
+            (Some(Type::Menu), "phkt.io/0/gophermap")
+        );
+        assert_eq!(
+            view_prefix("gopher://phkt.io/1/"),
+            (None, "gopher://phkt.io/1/")
+        );
+        assert_eq!(view_prefix("phkt.io/1/text:"), (None, "phkt.io/1/text:"));
+    }
+
+    #[test]
+    fn test_containing_menu_url() {
+        assert_eq!(
+            containing_menu_url("gopher://phkt.io/0/phlog/2020/post.txt").unwrap(),
+            "gopher://phkt.io/1/phlog/2020/"
+        );
+        assert_eq!(
+            containing_menu_url("gopher://phkt.io:7070/1/phlog/2020/").unwrap(),
+            "gopher://phkt.io:7070/1/phlog/"
+        );
+        assert_eq!(
+            containing_menu_url("phkt.io/0/about.txt").unwrap(),
+            "gopher://phkt.io/1/"
+        );
+        assert_eq!(
+            containing_menu_url("gopher://phkt.io/0about.txt").unwrap(),
+            "gopher://phkt.io/1"
+        );
+        assert_eq!(
+            containing_menu_url("text:gopher://phkt.io/0//phlog//./post.txt").unwrap(),
+            "gopher://phkt.io/1/phlog/"
+        );
+        assert_eq!(
+            containing_menu_url("gopher://phkt.io/0/phlog/../about.txt").unwrap(),
+            "gopher://phkt.io/1/"
+        );
+        assert_eq!(
+            containing_menu_url("gopher://phkt.io/7/v2/vs?gopher/phlog").unwrap(),
+            "gopher://phkt.io/1/v2/"
+        );
+        assert_eq!(
+            containing_menu_url("gophers://phkt.io/0/phlog/post.txt").unwrap(),
+            "gophers://phkt.io/1/phlog/"
+        );
+        assert_eq!(containing_menu_url("gopher://phkt.io/1/"), None);
+        assert_eq!(containing_menu_url("gopher://phkt.io/0/a/../"), None);
+        assert_eq!(containing_menu_url("gopher://phkt.io"), None);
+        assert_eq!(containing_menu_url("https://phkt.io/a/b"), None);
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        let cases = [
+            ("gopher.example.org/0/path", "gopher://gopher.example.org/0/path"),
+            ("  phkt.io:7070/1/  ", "gopher://phkt.io:7070/1/"),
+            (
+                "https://gopher.floodgap.com/gopher/gw?a=gopher%3A%2F%2Fphkt.io%2F1%2Fphlog",
+                "gopher://phkt.io/1/phlog",
+            ),
+            (
+                "http://gopher.floodgap.com/gopher/gw.lite?a=gopher%3A%2F%2Fsdf.org%3A70%2F0%2Fabout%20me.txt",
+                "gopher://sdf.org:70/0/about me.txt",
+            ),
+            (
+                "gopher.floodgap.com/gopher/gw?gopher://phkt.io/1/",
+                "gopher://phkt.io/1/",
+            ),
+            (
+                "https://gopher.floodgap.com/gopher/gw?a=phkt.io%2F0%2Fabout.txt",
+                "gopher://phkt.io/0/about.txt",
+            ),
+            // left alone
+            ("GOPHER://phkt.io/1/", "gopher://phkt.io/1/"),
+            ("phkt.io", "phkt.io"),
+            ("https://gopher.floodgap.com/", "https://gopher.floodgap.com/"),
+            ("https://example.com/gw?a=gopher%3A%2F%2Fx", "https://example.com/gw?a=gopher%3A%2F%2Fx"),
+            ("some words/here", "some words/here"),
+            ("/just/a/path", "/just/a/path"),
+        ];
+        for (input, expected) in &cases {
+            assert_eq!(normalize_url(input), *expected, "{}", input);
+        }
+        assert_eq!(percent_decode("100%25 %zz%4"), "100% %zz%4");
+    }
+
+    #[test]
+    fn test_reserve_turn() {
+        let clock = ManualClock::install(Duration::from_secs(1000));
+        let delay = Duration::from_millis(500);
+        let mut next = HashMap::new();
+        assert_eq!(reserve_turn(&mut next, "tiny.host", delay), Duration::ZERO);
+        // right away again: wait out the delay
+        assert_eq!(reserve_turn(&mut next, "tiny.host", delay), delay);
+        // and the one after that waits behind it
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(
+            reserve_turn(&mut next, "tiny.host", delay),
+            Duration::from_millis(900)
+        );
+        // other hosts don't wait
+        assert_eq!(reserve_turn(&mut next, "big.host", delay), Duration::ZERO);
+        // once the time has passed, no waiting
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(reserve_turn(&mut next, "tiny.host", delay), Duration::ZERO);
+        // and hosts whose turn has come are forgotten
+        assert_eq!(next.len(), 1);
+    }
+
+    #[test]
+    fn test_sleep_unless_cancelled() {
+        use std::{
+            sync::{mpsc::channel, Arc},
+            time::Instant,
+        };
+        let start = Instant::now();
+        sleep_unless_cancelled(Duration::from_millis(120), None).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(120));
+
+        let (keys, receiver) = channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        keys.send(Key::Ctrl('c')).unwrap();
+        let start = Instant::now();
+        let err = sleep_unless_cancelled(Duration::from_secs(30), Some(&receiver)).unwrap_err();
+        assert_eq!(err.to_string(), "Request cancelled");
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_web_proxy_url() {
+        let floodgap = "https://gopher.floodgap.com/gopher/gw?a={url}";
+        assert_eq!(
+            web_proxy_url(floodgap, "gopher://phkt.io/1/phetch"),
+            "https://gopher.floodgap.com/gopher/gw?a=gopher%3A%2F%2Fphkt.io%2F1%2Fphetch"
+        );
+        assert_eq!(
+            web_proxy_url(floodgap, "sdf.org:7070"),
+            "https://gopher.floodgap.com/gopher/gw?a=gopher%3A%2F%2Fsdf.org%3A7070%2F1"
+        );
+        assert_eq!(
+            web_proxy_url(
+                "https://proxy.example/{host}/{port}/{type}{selector}",
+                "gopher://phkt.io/0/my notes%3F.txt"
+            ),
+            "https://proxy.example/phkt.io/70/0/my%20notes%3F.txt"
+        );
+        assert_eq!(
+            web_proxy_url(
+                "https://proxy.example/{type}{selector}",
+                "gopher://phkt.io/7/find?gopher"
+            ),
+            "https://proxy.example/7/find%09gopher"
+        );
+    }
+
+    #[test]
+    fn test_looks_like_search() {
+        for url in &[
+            "sdf.org",
+            "gopher://phkt.io/1/",
+            "  bitreich.org/1/lawn ",
+            "[::1]:7070",
+            "localhost:70",
+            "text:gopher://phetch/1/help",
+            "",
+        ] {
+            assert!(!looks_like_search(url), "{:?} is a url", url);
+        }
+        for phrase in &["gopher clients", "phetch", "  rust ", "what is sdf.org"] {
+            assert!(looks_like_search(phrase), "{:?} is a search", phrase);
+        }
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(url_host("gopher://sdf.org:7070/1/maps"), "sdf.org");
+        assert_eq!(url_host("text:gopher://sdf.org/1/"), "sdf.org");
+        assert_eq!(url_host("phkt.io"), "phkt.io");
+        assert_eq!(url_host("telnet://bbs.impakt.net:6502"), "bbs.impakt.net");
+        assert_eq!(url_host("https://me@example.com:8080/a?b"), "example.com");
+        assert_eq!(url_host("http://[::1]:80/"), "::1");
+        assert_eq!(url_host("https://github.com"), "github.com");
+    }
+
+    #[test]
+    fn test_read_limited() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut sel = [0; 64];
+            let _ = stream.read(&mut sel);
+            // stream forever, until the client hangs up
+            let chunk = [b'x'; 1024];
+            while stream.write_all(&chunk).is_ok() {}
+        });
+        let mut stream = request("127.0.0.1", &port, "/huge", false, false).unwrap();
+        match read_limited(&mut stream, 10_000) {
+            Err(Error::TooLarge(max)) => assert_eq!(max, 10_000),
+            other => panic!("expected TooLarge, got {:?}", other.map(|b| b.len())),
+        }
+        drop(stream);
+        server.join().unwrap();
+
+        let mut small = &b"hello"[..];
+        assert_eq!(read_limited(&mut small, 5).unwrap(), b"hello");
+        let mut small = &b"hello"[..];
+        assert!(read_limited(&mut small, 4).is_err());
+        let mut small = &b"hello"[..];
+        assert_eq!(read_limited(&mut small, 0).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_netlog() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut sel = [0; 64];
+            let _ = stream.read(&mut sel);
+            stream.write_all(b"hello world").unwrap();
+        });
+        let dir = crate::phetchdir::TestDir::install("netlog");
+        let path = dir.path.join("requests.log");
+        netlog::enable(path.clone(), true);
+        fetch("127.0.0.1", &port, "/search\tsecret", false, false).unwrap();
+        server.join().unwrap();
+        netlog::error("Connection refused");
+        netlog::disable();
+        fetch("127.0.0.1", &port, "/after", false, false).unwrap_err();
+
+        let log = fs::read_to_string(&path).unwrap();
+        let expected = format!(
+            "fetch 127.0.0.1:{} /search?[redacted]: sent 16 bytes, got 11 bytes in",
+            port
+        );
+        assert!(log.contains(&expected), "{}", log);
+        assert!(log.contains(" error: Connection refused\n"));
+        assert!(!log.contains("secret"));
+        assert!(!log.contains("/after"));
+        assert_eq!(log.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_strip_echo() {
+        let mut res = b"/phlog\r\n1post\t/post\r\n".to_vec();
+        strip_echo(&mut res, "/phlog");
+        assert_eq!(res, b"1post\t/post\r\n");
+
+        // only an exact copy of the selector
+        for sel in &["/phlog/", "/phl", "", "1post\t/post"] {
+            let mut res = b"/phlog\r\n1post\t/post\r\n".to_vec();
+            strip_echo(&mut res, sel);
+            assert_eq!(res, b"/phlog\r\n1post\t/post\r\n");
+        }
+
+        let mut res = b"/about.txt".to_vec();
+        strip_echo(&mut res, "/about.txt");
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn test_looks_binary() {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            net::TcpListener,
+        };
+
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut client, _) = server.accept().unwrap();
+            BufReader::new(&client)
+                .read_line(&mut String::new())
+                .unwrap();
+            let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+            png.extend_from_slice(&[0; 64]);
+            client.write_all(&png).unwrap();
+        });
+        let url = format!("gopher://127.0.0.1:{}/0/not-really-text.txt", port);
+        let (_, res) = fetch_url(&url, false, false).unwrap();
+        assert!(looks_binary(&res));
+
+        assert!(!looks_binary(b""));
+        assert!(!looks_binary(b"Plain old text.\r\n\tIndented.\r\n"));
+        assert!(!looks_binary("* \x1b[92mTitle\x1b[0m\n".as_bytes()));
+        assert!(!looks_binary("Ünïcödé is fine too\n".as_bytes()));
+        assert!(looks_binary(b"\x01\x02\x03\x04 garbage \x05\x06"));
+        assert!(!looks_binary(b"\0Stray NUL up front.\n"));
+        assert!(looks_binary(b"\0Stray NUL\0 and another.\n"));
+    }
+
     #[test]
     fn test_clean_response() {
         let mut test = "Hi".to_string();
@@ -563,4 +2198,26 @@ mod tests {
         clean_response(&mut test);
         assert_eq!(test, "* \x1b[92mTitle\x1b[0m".to_string());
     }
+
+    #[test]
+    fn test_truncated_utf8() {
+        // "héllo wörld" cut off halfway through the ö
+        let res = "héllo wörld".as_bytes();
+        let cut = &res[..res.len() - 4];
+        assert_eq!(response_to_string(cut), "héllo w (truncated)");
+        assert_eq!(
+            crate::encoding::Encoding::UTF8.encode(cut),
+            "héllo w (truncated)"
+        );
+        // a 4 byte character missing its last byte
+        assert_eq!(
+            response_to_string(&"ok 🦀".as_bytes()[..6]),
+            "ok  (truncated)"
+        );
+        // complete, or broken somewhere other than the end
+        assert_eq!(response_to_string(res), "héllo wörld");
+        assert_eq!(response_to_string(b"a\xffb"), "a\u{FFFD}b");
+        assert_eq!(response_to_string(b"ab\xff"), "ab\u{FFFD}");
+        assert_eq!(response_to_string(b"ab\x80"), "ab\u{FFFD}");
+    }
 }
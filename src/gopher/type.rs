@@ -73,6 +73,21 @@ impl Type {
         )
     }
 
+    /// Image file? Downloaded like any other binary.
+    pub fn is_image(self) -> bool {
+        matches!(self, Type::GIF | Type::Image | Type::PNG)
+    }
+
+    /// File extension to use when saving this type to disk, if the
+    /// selector doesn't already have one.
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Type::GIF => Some("gif"),
+            Type::PNG => Some("png"),
+            _ => None,
+        }
+    }
+
     /// Check if media to open in player
     pub fn is_media(self) -> bool {
         matches!(self, Type::Sound | Type::Video)
@@ -144,6 +159,17 @@ impl Type {
             _ => return None,
         })
     }
+
+    /// Guess a Gopher Item Type from a file extension like "png".
+    /// Only images are recognized.
+    pub fn from_extension(ext: &str) -> Option<Type> {
+        Some(match ext.to_ascii_lowercase().as_ref() {
+            "png" => Type::PNG,
+            "gif" => Type::GIF,
+            "jpg" | "jpeg" | "webp" | "bmp" => Type::Image,
+            _ => return None,
+        })
+    }
 }
 
 impl fmt::Display for Type {
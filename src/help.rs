@@ -1,17 +1,42 @@
 //! The `help` module manages all internal Gopher pages, from the help
 //! system itself to the Start and "About Phetch" pages.
 
-use crate::{bookmarks, history};
+use crate::{bookmarks, broken, config::Config, history, notes};
 
-/// Find a help file/page. If found, gives the raw Gophermap.
-pub fn lookup(name: &str) -> Option<String> {
+/// The keyboard shortcuts as a Gophermap, as set up in `cfg`: with
+/// its `hint_key` as the key that labels links, or without that line
+/// if it's turned off, and any keys bound with `key.NAME`.
+pub fn keys(cfg: &Config) -> String {
+    let mut out: String = match cfg.hint_key {
+        Some(key) => KEYS.replace("{hint-key}", &format!("{:<11}", key)),
+        None => KEYS
+            .split_inclusive('\n')
+            .filter(|line| !line.contains("{hint-key}"))
+            .collect(),
+    };
+    let bindings = cfg.keymap.bindings();
+    if !bindings.is_empty() {
+        out.push_str("i    ** your keys **\ni\n");
+        for (key, action) in bindings {
+            out.push_str(&format!("i{:<11}{}\n", key, action));
+        }
+        out.push_str("i\n");
+    }
+    out
+}
+
+/// Find a help file/page. If found, gives the raw Gophermap. `cfg`
+/// is the config in use, for pages that depend on it.
+pub fn lookup(name: &str, cfg: &Config) -> Option<String> {
     Some(match name {
         "" | "/" | "home" | "home/" => format!("{}{}", HEADER, START),
         "history" => history::as_raw_menu(),
         "bookmarks" => bookmarks::as_raw_menu(),
+        "notes" => notes::as_raw_menu(),
+        "broken" => broken::as_raw_menu(),
         "help/config" => format!("{}{}", HEADER, CONFIG),
         "help/themes" => format!("{}{}", HEADER, THEMES),
-        "help/keys" => format!("{}{}", HEADER, KEYS),
+        "help/keys" => format!("{}{}", HEADER, keys(cfg)),
         "help/nav" => format!("{}{}", HEADER, NAV),
         "help/types" => format!("{}{}", HEADER, TYPES),
         "help/bookmarks" => format!("{}{}", HEADER, BOOKMARKS),
@@ -73,6 +98,8 @@ i
 1show help          (ctrl-h)	/help	phetch
 1show history       (ctrl-a)	/history	phetch
 1show bookmarks     (ctrl-b)	/bookmarks	phetch
+1show notes	/notes	phetch
+1show broken pages	/broken	phetch
 i
 ";
 
@@ -100,32 +127,59 @@ i   ** keyboard shortcuts **
 i
 ileft       back in history
 iright      next in history
+iT          reopen closed page
+ictrl-^     flip to last page
+i] or [     next/prev post in phlog
+iD          open containing menu
+iS          save page to a file
+iR          reload page
+iL          pin page to auto-reload
 iup         select prev link
 idown       select next link
 ipg up/down scroll by many lines
 i- or space same as pg up/down
+ihome       jump to the top
+iend or G   jump to the bottom
 i
 inum key    open/select link
 ienter      open current link
 iescape     cancel
 ictrl-c     cancel
+ictrl-l     redraw screen
 i
 if or /     find link in page
 ip or k     select prev link
 in or j     select next link
+i/          find in page
+in or N     next/prev match
 i
 ig          go to gopher url
 iu          edit url
 iy          copy url
+iY          copy web proxy url
+iM          copy markdown link
 i
 ib          show bookmarks
 is          save bookmark
 ia          show history
-i
+iN          add a note to page
+i!          flag page as broken
+iP          toggle incognito mode
+i
+iU          show link selectors
+iI          show link details
+ix          expand/collapse submenu
+iF          filter links
+i{hint-key}label links to jump to
 ir          view raw source
+iX          hex dump of raw bytes
+iE          open raw source in $EDITOR
 id          download raw source
 iw          toggle wide mode
+iC          cycle color themes
 ie          toggle encoding
+iz          toggle reader mode (text)
+i< or >     narrower/wider (text)
 iq          quit phetch
 ih          show help
 i
@@ -168,6 +222,11 @@ icase-insensitive match and try
 ito select it. use arrow keys
 ior ctrl-p/n to cycle matches.
 i
+iif no link matches, enter finds
+ithe text anywhere in the menu.
+in and N go through the matches
+iuntil you press escape.
+i
 ";
 
 const BOOKMARKS: &str = "
@@ -179,16 +238,27 @@ i
 iy      copy url
 is      save bookmark
 i
-iif ~/.config/phetch/ exists,
-ibookmarks will be saved to
+ibookmarks are saved to
 i~/.config/phetch/bookmarks.gph
+iwith the title of the link
+ithat took you to the page.
 i
-ipress b to view them.
+iwith `collections` set, press s
+ithen a letter to file the page
+iin a collection instead, like
+ir for ~/.config/phetch/reading.gph
+i
+ipress b to view them, or go to
+igopher://bookmarks/
 i
 ithe clipboard function uses:
 i
 i- `pbcopy` on macos
-i- `xclip -sel clip` on linux
+i- `wl-copy` on wayland
+i- `xclip -sel clip` on x11
+i
+iset `clipboard both` to copy to
+ithe primary selection too.
 i";
 
 const HISTORY: &str = "
@@ -208,6 +278,17 @@ i
 ifeel free to edit your history
 ifile directly, or share it
 iwith your friends!
+i
+ito find a phrase in the pages
+iyou've visited this session,
+iuse the search link at the top
+iof the history page, or go to
+igopher://phetch/1/search?phrase
+i
+ito browse without saving any
+ihistory, launch phetch with
+i--incognito or press P to
+itoggle incognito mode.
 ";
 
 const CONFIG: &str = "
@@ -239,21 +320,224 @@ i
 i# start in wide mode
 iwide no
 i
+i# media player, and whether to
+i# stream gopher urls to it
+i# instead of downloading first
+imedia mpv
+istream_media no
+i
+i# remember wide mode per page
+iwide_per_url no
+i
 i# show emoji status indicators
 iemoji no
 i
 i# cp437 or utf8 encoding
 iencoding utf8
 i
+i# don't save history
+iincognito no
+i
+i# most entries to keep in
+i# history. 0 = no limit
+ihistory_size 1000
+i
 i# wrap text at N cols. 0 = off
 iwrap 0
 i
 i# page up/down by N lines.
-i# 0 = full screen
+i# 0 = a screenful, less two
 iscroll 0
 i
+i# keep the last N pages in
+i# memory. 0 = off
+icache_size 20
+i
+i# seconds between reloads of a
+i# page pinned with L
+ipin_interval 60
+i
+i# biggest page to load, in MB.
+i# 0 = no limit
+imax_response 8
+i
+i# biggest download, in MB.
+i# 0 = no limit
+imax_download 0
+i
+i# ms to wait between requests
+i# to the same server. 0 = off
+irequest_delay 0
+i
+i# servers whose addresses to
+i# remember. 0 = look up every time
+idns_cache 100
+i
+i# seconds to wait for a server
+i# before giving up
+itimeout 10
+i
+i# log requests to requests.log
+i# in the phetchdir, for bug
+i# reports. or set PHETCH_LOG
+ilog_requests no
+i
+i# hide search queries in the log
+ilog_redact yes
+i
+i# scrollbar on long pages
+iscrollbar no
+i
+i# show what's loading instead
+i# of the old page while fetching
+iplaceholder no
+i
+i# ms to wait before showing the
+i# spinner. 0 = show it right away
+ispinner_delay 150
+i
+i# retry downloads from hosts
+i# that can't be reached after
+i# the next page loads
+iqueue_downloads no
+i
+i# expand tabs in text to N cols.
+i# 0 = print them as-is
+itab_width 8
+i
+i# columns for menus of short
+i# links. 0 = auto
+icolumns 1
+i
+i# mark links that are new since
+i# you last visited a menu
+imark_new no
+i
+i# show the current page in the
+i# terminal's window title
+iset_title no
+i
+i# ask before opening telnet links
+iconfirm_telnet yes
+i
+i# pass telnet logins to telnet -l
+itelnet_login no
+i
+i# show *bold* and _underline_
+i# in info lines
+iemphasis no
+i
+i# label links on screen with
+i# letters to jump to them. no = off
+ihint_key '
+i
+i# letters to label links with
+ihint_chars asdfghjkl
+i
+i# open links in a new view (push)
+i# or in place of this one (replace)
+i# or replace only for menus on
+i# the same server (same_host)
+iopen push
+i
+i# copy urls to the clipboard,
+i# primary selection, or both
+iclipboard clipboard
+i
+i# what M copies
+ilink_format [{title}]({url})
+i
+i# 16, 256, or truecolor colors.
+i# auto = check $COLORTERM/$TERM
+icolors auto
+i
+i# tag for links that leave
+i# gopherspace. `no` hides it.
+iweb_tag [www]
+i
+i# tag for Gopher+ items. off
+i# by default.
+i# plus_tag [+]
+i
+i# marker before the status line
+i# of the focused view, in the
+i# ui.focus color. off by default.
+i# focus_marker >
+i
+i# search server to offer when
+i# Go to URL gets a search phrase
+isearch_url gopher://gopher.floodgap.com/7/v2/vs
+i
+i# web proxy url Y copies
+iweb_proxy https://gopher.floodgap.com/gopher/gw?a={url}
+i
+i# status line when there's no
+i# message: {title} {url} {type}
+i# {pos} {links}
+i# status_format {title} - {url}
+i
+i# hosts that send the selector
+i# back as the first line. the
+i# copy is removed.
+istrip_echo no
+i
+i# only visit these hosts. `*`
+i# matches anything: *.sdf.org
+iallow_hosts no
+i
+i# never visit these hosts
+ideny_hosts no
+i
+i# download from these hosts
+i# without asking. host or host:port
+itrusted_hosts no
+i
+i# ask for a hidden password and
+i# send it with these selectors.
+i# host/selector, `*` works too
+iask_password no
+i
+i# keep those passwords in history
+isave_passwords no
+i
+i# local address to connect from
+ibind 192.168.1.2
+i
+i# bookmark files and menu urls
+i# to show together, in order
+ibookmarks bookmarks.gph gopher://phkt.io/1/links
+i
+i# bookmark collections for s,
+i# as letter=name pairs
+icollections r=reading w=work
+i
 i# path to theme file, if any
 itheme ~/.config/phetch/fun.theme
+i
+i# built-in theme to start from:
+i# default, light, high-contrast,
+i# or mono. C cycles them.
+i# theme_preset light
+i
+i# more keys for back, forward,
+i# open, up, down, quit, reload,
+i# or bookmark. the usual keys
+i# still work.
+i# key.back h
+i# key.forward l
+i
+iprefer TOML? put the same keys
+iin ~/.config/phetch/config.toml
+iinstead, as `key = value` pairs.
+icolors go in [ui] and [item]
+itables:
+i
+i[ui]
+icursor = \"white bold\"
+i
+iif config.toml has a bad key,
+iphetch warns you and uses the
+idefault for it.
 ";
 
 const THEMES: &str = "
@@ -268,6 +552,13 @@ i
 iyou can also set colors directly
 iin your phetch.conf.
 i
+ipress C to cycle through the
+ibuilt-in themes: default, light,
+ihigh-contrast, and mono. to
+istart with one, set
+i`theme_preset NAME` in your
+iphetch.conf.
+i
 iview the current theme with:
 i
 i$ phetch --print-theme
@@ -281,6 +572,10 @@ iui.cursor white bold
 iui.number magenta
 iui.menu yellow
 iui.text white
+iui.new green bold
+iui.focus cyan bold
+iui.selector grey
+iui.scrollbar grey
 iitem.text cyan
 iitem.menu blue
 iitem.error red
@@ -319,6 +614,17 @@ ibluebg
 imagentabg
 icyanbg
 iwhitebg
+i
+ior an rgb color like #ff8800, or
+icolor0 to color255 from the 256
+icolor palette. add bg to either
+ifor the background: #000080bg
+i
+iif your terminal can't show
+ithem, phetch picks the closest
+icolors it can. set `colors` in
+iyour phetch.conf to override
+iwhat phetch detects.
 ";
 
 const TYPES: &str = "
@@ -340,6 +646,7 @@ i
 6uuencoded files	/help/types	phetch
 9binaries	/help/types	phetch
 gGIFs	/help/types	phetch
+pPNGs	/help/types	phetch
 Iimages downloads	/help/types	phetch
 ddocuments	/help/types	phetch
 i
@@ -387,3 +694,30 @@ igit ref: {git-ref}
 ibuilt on: {build-date}
 i
 ";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        let mut cfg = Config::default();
+        let keys = keys(&cfg);
+        assert!(keys.contains("\nig          go to gopher url\n"));
+        assert!(keys.contains("\ni'          label links to jump to\n"));
+        assert!(!keys.contains('\x1b'));
+        assert!(!keys.contains("your keys"));
+        cfg.hint_key = Some(';');
+        assert!(super::keys(&cfg).contains("\ni;          label links"));
+        cfg.hint_key = None;
+        assert!(!super::keys(&cfg).contains("label links"));
+
+        cfg.keymap.bind("back", "h ctrl-h").unwrap();
+        let keys = super::keys(&cfg);
+        assert!(keys.contains("\nih          back\nictrl-h     back\n"));
+        assert_eq!(
+            lookup("help/keys", &cfg),
+            Some(format!("{}{}", HEADER, keys))
+        );
+    }
+}
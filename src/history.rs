@@ -1,9 +1,18 @@
 //! phetch will append every URL visited to a Gophermap if a
 //! `HISTORY_FILE` in `~/.config/phetch/` exists. This file must be
 //! manually created by the user for history tracking to engage.
+//!
+//! Visiting the same page twice in a row only records it once, and
+//! the oldest entries are dropped once there are more than the
+//! `history_size` config option allows.
 
 use crate::phetchdir;
-use std::io::{BufRead, Result};
+use std::{
+    fs,
+    io::{BufRead, Result},
+    path::Path,
+    sync::Mutex,
+};
 
 /// History only works if you've created ~/.config/phetch/history.gph manually.
 const HISTORY_FILE: &str = "history.gph";
@@ -41,13 +50,14 @@ pub fn as_raw_menu() -> String {
         );
     }
 
-    let mut out = vec![format!("i{}:\r\ni", homepath)];
-    match phetchdir::load(HISTORY_FILE) {
-        Ok(reader) => {
-            let mut lines = reader.lines();
-            while let Some(Ok(line)) = lines.next() {
-                out.insert(1, line);
-            }
+    let mut out = vec![format!(
+        "7search pages from this session\t/search\tphetch\t70\r\ni\r\ni{}:\r\ni",
+        homepath
+    )];
+    match load() {
+        Ok(mut lines) => {
+            lines.dedup_by(|a, b| same_url(a, b));
+            out.extend(lines.into_iter().rev());
         }
         Err(e) => out.push(format!("3{}", e)),
     }
@@ -58,11 +68,180 @@ pub fn as_raw_menu() -> String {
     out.join("\r\n")
 }
 
-/// Save a single history entry if the history file exists.
-pub fn save(label: &str, url: &str) -> Result<()> {
-    if let Err(e) = phetchdir::path() {
-        return Err(error!("History file doesn't exist: {}", e));
+/// Every line in the history file, oldest first.
+pub fn load() -> Result<Vec<String>> {
+    phetchdir::load(HISTORY_FILE)?.lines().collect()
+}
+
+/// Do these two history lines point to the same place? Their labels
+/// don't matter, only the type, selector, host, and port.
+fn same_url(a: &str, b: &str) -> bool {
+    match (a.split_once('\t'), b.split_once('\t')) {
+        (Some((label_a, url_a)), Some((label_b, url_b))) => {
+            label_a.chars().next() == label_b.chars().next() && url_a == url_b
+        }
+        _ => false,
+    }
+}
+
+/// A host from history that's only a typo or two away from `host`,
+/// for suggesting when `host` can't be found.
+pub fn similar_host(host: &str) -> Option<String> {
+    let reader = phetchdir::load(HISTORY_FILE).ok()?;
+    let hosts = reader
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| line.split('\t').nth(2).map(str::to_string));
+    closest_host(host, hosts)
+}
+
+/// The host in `hosts` closest to `host`, if it's close enough to
+/// be a typo: one or two letters off, but not the same.
+fn closest_host(host: &str, hosts: impl Iterator<Item = String>) -> Option<String> {
+    let host = host.to_lowercase();
+    hosts
+        .map(|known| (edit_distance(&host, &known.to_lowercase()), known))
+        .filter(|(dist, _)| (1..=2).contains(dist))
+        .min_by_key(|(dist, _)| *dist)
+        .map(|(_, known)| known)
+}
+
+/// How many single character insertions, deletions, or substitutions
+/// it takes to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(ca != *cb);
+            row.push(sub.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+/// Save a single history entry if the history file exists, keeping
+/// no more than `limit` entries. 0 means no limit. The phetchdir
+/// existing isn't enough, since saving a bookmark creates it.
+pub fn save(label: &str, url: &str, limit: usize) -> Result<()> {
+    let path = phetchdir::path()
+        .map_err(|e| error!("History file doesn't exist: {}", e))?
+        .join(HISTORY_FILE);
+    if !path.exists() {
+        return Err(error!("History file doesn't exist: {:?}", path).into());
+    }
+    save_in(&path, label, url, limit)
+}
+
+lazy_static! {
+    /// Held while saving, since pages load on their own threads and
+    /// two saves at once could each drop the other's entry.
+    static ref SAVING: Mutex<()> = Mutex::new(());
+}
+
+/// Add an entry to the history file at `path`, then drop it again if
+/// it's the same page as the last one, and drop the oldest entries
+/// past `limit`.
+fn save_in(path: &Path, label: &str, url: &str, limit: usize) -> Result<()> {
+    let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+    phetchdir::append_to(path, label, url)?;
+    let lines = fs::read_to_string(path)?
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let mut kept = lines.clone();
+    kept.dedup_by(|a, b| same_url(a, b));
+    if limit > 0 && kept.len() > limit {
+        kept.drain(..kept.len() - limit);
+    }
+    if kept.len() != lines.len() {
+        let mut out = kept.join("\r\n");
+        out.push_str("\r\n");
+        phetchdir::replace(path, &out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_host() {
+        let hosts = || {
+            ["phkt.io", "sdf.org", "gopher.floodgap.com"]
+                .iter()
+                .map(|h| h.to_string())
+        };
+        assert_eq!(closest_host("phkt.oi", hosts()), Some("phkt.io".into()));
+        assert_eq!(closest_host("SDF.ORG.", hosts()), Some("sdf.org".into()));
+        assert_eq!(closest_host("gopher.floodgap.com", hosts()), None);
+        assert_eq!(closest_host("example.com", hosts()), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
     }
 
-    phetchdir::append(HISTORY_FILE, label, url)
+    #[test]
+    fn test_save_needs_history_file() {
+        let dir = phetchdir::TestDir::install("history");
+        assert!(save("phkt.io", "gopher://phkt.io", 0).is_err());
+        assert_eq!(dir.read(HISTORY_FILE), "");
+
+        fs::write(phetchdir::path().unwrap().join(HISTORY_FILE), "").unwrap();
+        save("phkt.io", "gopher://phkt.io", 0).unwrap();
+        assert_eq!(dir.read(HISTORY_FILE), "1phkt.io\t\tphkt.io\t70\r\n");
+    }
+
+    #[test]
+    fn test_save_in() {
+        let dir = phetchdir::TestDir::install("history-save-in");
+        let path = dir.path.join(HISTORY_FILE);
+        let visit = |label| save_in(&path, label, &format!("gopher://{}", label), 3).unwrap();
+        visit("phkt.io");
+        visit("phkt.io");
+        visit("sdf.org");
+        visit("phkt.io");
+        visit("floodgap.com");
+        let log = fs::read_to_string(&path).unwrap();
+        let labels: Vec<_> = log.lines().filter_map(|l| l.split('\t').next()).collect();
+        assert_eq!(labels, vec!["1sdf.org", "1phkt.io", "1floodgap.com"]);
+
+        save_in(&path, "Floodgap", "gopher://floodgap.com", 0).unwrap();
+        save_in(&path, "phetch", "gopher://phkt.io/1/phetch", 0).unwrap();
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 4);
+        assert!(same_url(
+            "1phkt.io\t/\tphkt.io\t70",
+            "1home\t/\tphkt.io\t70"
+        ));
+        assert!(!same_url(
+            "0phkt.io\t/\tphkt.io\t70",
+            "1phkt.io\t/\tphkt.io\t70"
+        ));
+    }
+
+    #[test]
+    fn test_save_in_from_threads() {
+        let dir = phetchdir::TestDir::install("history-threads");
+        let path = dir.path.join(HISTORY_FILE);
+        let threads = (0..4)
+            .map(|t| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for i in 0..20 {
+                        let host = format!("host{}-{}", t, i);
+                        save_in(&path, &host, &format!("gopher://{}", host), 50).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 50);
+        assert!(log.lines().all(|l| l.split('\t').count() == 4));
+    }
 }
@@ -42,17 +42,26 @@ pub mod utils;
 pub mod theme;
 pub mod args;
 pub mod bookmarks;
+pub mod broken;
+pub mod clock;
 pub mod config;
-mod encoding;
+pub mod encoding;
+pub mod error;
 pub mod gopher;
 pub mod help;
 pub mod history;
+pub mod local;
 pub mod menu;
+pub mod netlog;
+pub mod notes;
 pub mod phetchdir;
+pub mod seen;
 pub mod terminal;
 pub mod text;
 pub mod ui;
 
+pub use error::Error;
+
 /// Current version of phetch.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Current OS. Used to check for updates.
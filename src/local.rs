@@ -0,0 +1,235 @@
+//! Serve a directory of gophermaps as if it were a Gopher server, so
+//! gopherhole authors can preview their site before publishing it.
+//! Started with `--serve-local DIR`, the directory shows up at
+//! gopher://local/ and its selectors map straight to files and
+//! subdirectories. A directory with a `gophermap` file in it is shown
+//! as that menu, otherwise phetch lists what's in it. Links to other
+//! hosts still go over the network.
+
+use crate::{
+    gopher::{self, Type},
+    Error,
+};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The host local files are served from.
+pub const HOST: &str = "local";
+
+/// The file in a directory that's shown as its menu.
+const GOPHERMAP: &str = "gophermap";
+
+lazy_static! {
+    /// The directory being served, if any.
+    static ref ROOT: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+/// Serve `dir` at gopher://local/, or stop serving if it's `None`.
+/// A `file://` prefix is ignored.
+pub fn set_root(dir: Option<&str>) {
+    *ROOT.lock().unwrap() = dir.map(|d| PathBuf::from(d.trim_start_matches("file://")));
+}
+
+/// The directory `host` is served from, if it's our local host.
+pub fn root_for(host: &str) -> Option<PathBuf> {
+    if host == HOST {
+        ROOT.lock().unwrap().clone()
+    } else {
+        None
+    }
+}
+
+/// Fetch `selector` from the directory at `root`, like a Gopher
+/// server would.
+pub fn fetch(root: &Path, selector: &str) -> Result<Vec<u8>, Error> {
+    let sel = selector.split('\t').next().unwrap_or_default();
+    let path = resolve(root, sel).ok_or_else(|| Error::NotFound(sel.to_string()))?;
+    if path.is_dir() {
+        let dir = sel.trim_end_matches('/');
+        let map = path.join(GOPHERMAP);
+        let menu = if map.is_file() {
+            render_gophermap(&fs::read_to_string(map)?, dir)
+        } else {
+            list_dir(&path, dir)?
+        };
+        Ok(menu.into_bytes())
+    } else {
+        Ok(fs::read(path)?)
+    }
+}
+
+/// The file `sel` points to under `root`, if it exists and doesn't
+/// try to climb out of it, with `..` or a symlink.
+fn resolve(root: &Path, sel: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    for part in Path::new(sel.trim_start_matches('/')).components() {
+        match part {
+            Component::Normal(name) => path.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    let root = root.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    path.starts_with(root).then_some(path)
+}
+
+/// Turn a gophermap into a Gopher menu. Lines without a tab are info
+/// text, missing fields default to this server, and selectors that
+/// don't start with `/` are relative to `dir`.
+fn render_gophermap(source: &str, dir: &str) -> String {
+    let mut out = String::new();
+    for line in source.lines() {
+        let line = line.trim_end_matches('\r');
+        if line == "." {
+            break;
+        }
+        if !line.contains('\t') {
+            out.push_str(&format!("i{}\t\t{}\t70\r\n", line, HOST));
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let name = fields.next().unwrap_or_default();
+        let sel = fields.next().filter(|s| !s.is_empty());
+        let host = fields.next().filter(|s| !s.is_empty());
+        let port = fields.next().filter(|s| !s.is_empty()).unwrap_or("70");
+        let sel = sel.unwrap_or_else(|| name.get(1..).unwrap_or_default());
+        let sel = if host.is_none() && !sel.starts_with('/') && !sel.starts_with("URL:") {
+            format!("{}/{}", dir, sel)
+        } else {
+            sel.to_string()
+        };
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\r\n",
+            name,
+            sel,
+            host.unwrap_or(HOST),
+            port
+        ));
+    }
+    out
+}
+
+/// A menu of what's in the directory at `path`, for directories
+/// without a gophermap.
+fn list_dir(path: &Path, dir: &str) -> Result<String, Error> {
+    let mut entries = fs::read_dir(path)?
+        .filter_map(|e| e.ok())
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+        .filter(|(name, _)| !name.starts_with('.'))
+        .collect::<Vec<_>>();
+    entries.sort();
+    let mut out = String::new();
+    for (name, path) in entries {
+        let typ = if path.is_dir() {
+            Type::Menu
+        } else {
+            file_type(&path)
+        };
+        out.push_str(&format!(
+            "{}{}\t{}/{}\t{}\t70\r\n",
+            typ.to_char(),
+            name,
+            dir,
+            name,
+            HOST
+        ));
+    }
+    Ok(out)
+}
+
+/// Guess the Gopher type of the file at `path`.
+fn file_type(path: &Path) -> Type {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if ext == "gph" {
+        return Type::Menu;
+    }
+    if let Some(typ) = Type::from_extension(ext) {
+        return typ;
+    }
+    match fs::read(path) {
+        Ok(body) if !gopher::looks_binary(&body) => Type::Text,
+        _ => Type::Binary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/local")
+    }
+
+    fn fetch_str(sel: &str) -> String {
+        String::from_utf8(fetch(&site(), sel).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_fetch_gophermap() {
+        assert_eq!(
+            fetch_str("/"),
+            "iWelcome to my gopherhole\t\tlocal\t70\r\n\
+             i\t\tlocal\t70\r\n\
+             0About me\t/about.txt\tlocal\t70\r\n\
+             1Phlog\t/phlog\tlocal\t70\r\n\
+             1Notes\t/notes\tlocal\t70\r\n\
+             1Floodgap\t/\tgopher.floodgap.com\t70\r\n"
+        );
+        assert_eq!(fetch_str(""), fetch_str("/"));
+        assert_eq!(fetch_str("/about.txt"), "Hi, I write a phlog.\n");
+    }
+
+    #[test]
+    fn test_fetch_subdirs() {
+        // relative selectors and missing selectors
+        assert_eq!(
+            fetch_str("/phlog/"),
+            "iPhlog\t\tlocal\t70\r\n\
+             0First post\t/phlog/first.txt\tlocal\t70\r\n\
+             0second.txt\t/phlog/second.txt\tlocal\t70\r\n\
+             1Home\t/\tlocal\t70\r\n"
+        );
+        assert_eq!(fetch_str("/phlog/first.txt"), "Hello, gopherspace.\n");
+        // no gophermap
+        assert_eq!(
+            fetch_str("/notes"),
+            "1drafts\t/notes/drafts\tlocal\t70\r\n\
+             0todo.txt\t/notes/todo.txt\tlocal\t70\r\n"
+        );
+    }
+
+    #[test]
+    fn test_fetch_missing() {
+        assert!(matches!(fetch(&site(), "/nope.txt"), Err(Error::NotFound(s)) if s == "/nope.txt"));
+        assert!(matches!(
+            fetch(&site(), "/../Cargo.toml"),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            fetch(&site(), "/phlog/../../src"),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_fetch_symlink_out() {
+        let dir = crate::phetchdir::TestDir::install("local-symlink");
+        let root = &dir.path;
+        fs::create_dir_all(root.join("phlog")).unwrap();
+        fs::write(root.join("phlog/post.txt"), "hi\n").unwrap();
+        std::os::unix::fs::symlink(root.join("phlog"), root.join("posts")).unwrap();
+        std::os::unix::fs::symlink(site(), root.join("out")).unwrap();
+
+        // links inside the root are fine...
+        assert_eq!(fetch(root, "/posts/post.txt").unwrap(), b"hi\n");
+        // ...but not ones that lead out of it
+        assert!(matches!(
+            fetch(root, "/out/about.txt"),
+            Err(Error::NotFound(_))
+        ));
+    }
+}
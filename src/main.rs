@@ -1,7 +1,7 @@
 use phetch::{
     args,
     config::{Config, SharedConfig},
-    gopher, menu, terminal, theme,
+    gopher, help, local, menu, netlog, terminal, theme,
     ui::{Mode, UI},
 };
 use std::{
@@ -9,6 +9,7 @@ use std::{
     error::Error,
     io::{self, stdout, Write},
     panic, process, str,
+    sync::RwLock,
 };
 
 fn main() {
@@ -22,6 +23,16 @@ fn main() {
 fn run() -> Result<(), Box<dyn Error>> {
     let str_args = env::args().skip(1).collect::<Vec<String>>();
     let mut cfg = args::parse(&str_args)?;
+    gopher::set_bind(cfg.bind.as_deref());
+    gopher::set_limits(
+        cfg.max_response.saturating_mul(gopher::MEGABYTE),
+        cfg.max_download.saturating_mul(gopher::MEGABYTE),
+    );
+    gopher::set_request_delay(cfg.request_delay);
+    gopher::set_dns_cache(cfg.dns_cache);
+    gopher::set_timeout(cfg.timeout);
+    local::set_root(cfg.serve_local.as_deref());
+    netlog::init(cfg.log_requests, cfg.log_redact);
 
     // check for simple modes
     match cfg.mode {
@@ -29,6 +40,13 @@ fn run() -> Result<(), Box<dyn Error>> {
         Mode::Version => return print_version(),
         Mode::Help => return print_usage(),
         Mode::PrintTheme => return print_theme(cfg),
+        Mode::PrintKeys => return print_keys(&cfg),
+        Mode::DumpLinks(format) => {
+            let start = cfg.start.clone();
+            let cfg = SharedConfig::new(RwLock::new(cfg));
+            print!("{}", menu::dump_links(&start, format, cfg)?);
+            return Ok(());
+        }
         Mode::NoTTY => return print_plain(&cfg.start, cfg.tls, cfg.tor),
         Mode::Print => cfg.wide = true,
         Mode::Run => {}
@@ -87,15 +105,22 @@ Options:
     -a, --autoplay         Autoplay media without prompting.
     -A, --no-autoplay      Prompt before playing media.
 
+    -i, --incognito        Don't save visited pages to history.
+        --no-history       Same as --incognito.
+
     -r, --raw              Print raw Gopher response only
     -p, --print            Print rendered Gopher response only
+        --dump-links       Print the links in a menu as TSV, then exit
+        --json             Make --dump-links print JSON instead
     -l, --local            Connect to 127.0.0.1:7070
+        --serve-local DIR  Browse a directory of gophermaps at gopher://local/
     -e, --encoding         Render text documents in CP437 or UTF8.
 
     -c, --config FILE      Use instead of ~/.config/phetch/phetch.conf
     -C, --no-config        Don't use any config file
     -t, --theme FILE       Use FILE for color theme or print current theme.
     --print-theme          Print current theme.
+    --print-keys           Print keyboard shortcuts as a gophermap.
 
     -h, --help             Show this screen
     -v, --version          Show phetch version
@@ -131,10 +156,10 @@ fn print_plain(url: &str, tls: bool, tor: bool) -> Result<(), Box<dyn Error>> {
         }
         gopher::Type::Text => println!("{}", response.trim_end_matches(".\r\n")),
         _ => {
-            return Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                format!("can't print gopher type: {:?}", typ),
-            )));
+            return Err(Box::new(io::Error::other(format!(
+                "can't print gopher type: {:?}",
+                typ
+            ))));
         }
     };
     print!("{}", out);
@@ -147,6 +172,12 @@ fn print_theme(cfg: Config) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Print the keyboard shortcuts as a Gophermap
+fn print_keys(cfg: &Config) -> Result<(), Box<dyn Error>> {
+    print!("{}", help::keys(cfg));
+    Ok(())
+}
+
 /// Put the terminal into raw mode, enter the alternate screen, and
 /// setup the panic handler.
 fn setup_terminal() {
@@ -8,11 +8,31 @@
 
 use crate::{
     config::SharedConfig as Config,
+    encoding::Encoding,
+    error::Result,
     gopher::{self, Type},
     terminal,
     ui::{self, Action, Key, View, MAX_COLS},
+    utils,
 };
-use std::fmt;
+use std::{borrow::Cow, cell::OnceCell, fmt};
+
+/// In auto grid mode (`columns 0`), only menus whose links are all
+/// at most this long get packed into columns.
+const GRID_AUTO_MAX_LEN: usize = 24;
+
+/// Most columns auto grid mode will use.
+const GRID_AUTO_MAX_COLUMNS: usize = 3;
+
+/// Space between columns in grid mode.
+const GRID_GUTTER: usize = 2;
+
+/// How many lines of a menu to parse up front. The rest is parsed
+/// as you scroll, so huge menus show up right away.
+const PARSE_CHUNK: usize = 1000;
+
+/// How many levels of submenus `x` will expand inline.
+const MAX_EXPAND_DEPTH: usize = 3;
 
 /// The Menu holds our Gopher Lines, a list of links, and maintains
 /// both where the cursor is on screen and which lines need to be
@@ -20,6 +40,7 @@ use std::fmt;
 /// for input, the Menu maintains its own `input` for the "quick
 /// navigation" feature using number entry and the "incremental search"
 /// (over menu links) feature using text entry.
+#[derive(Clone)]
 pub struct Menu {
     /// Gopher URL
     pub url: String,
@@ -34,6 +55,13 @@ pub struct Menu {
     pub longest: usize,
     /// Actual Gopher response
     pub raw: String,
+    /// How much of `raw` has been parsed into `spans`, in bytes.
+    parsed: usize,
+    /// How many links there are in all of `raw`, parsed or not.
+    total_links: usize,
+    /// Every link's (name, URL), worked out once if they're wanted
+    /// before the menu is fully parsed.
+    all_links: OnceCell<Vec<(String, String)>>,
     /// User input on a prompt() line
     pub input: String,
     /// UI mode. Interactive (Run), Printing, Raw mode...
@@ -42,6 +70,20 @@ pub struct Menu {
     pub offset: usize,
     /// Incremental search mode?
     pub searching: bool,
+    /// Typing a filter with `F`?
+    pub filtering: bool,
+    /// Only links whose names match this are shown. Empty = all
+    pub filter: String,
+    /// All the lines and links, while `filter` hides some of them.
+    unfiltered: Option<(Vec<LineSpan>, Vec<usize>)>,
+    /// The real link number, in the unfiltered menu, of each link
+    /// shown while filtering.
+    filtered: Vec<usize>,
+    /// Labels for the links on screen while picking one with
+    /// `hint_key`, as (label, link). Empty = not picking
+    hints: Vec<(String, usize)>,
+    /// The start of a label typed so far.
+    hint: String,
     /// Was this menu retrieved via TLS?
     tls: bool,
     /// Retrieved via Tor?
@@ -52,6 +94,28 @@ pub struct Menu {
     wide: bool,
     /// Scroll by how many lines?
     scroll: usize,
+    /// Links that are new since the last visit. Indexes of the `links` vec.
+    pub new_links: Vec<usize>,
+    /// Show each link's type, host, port, and selector after it?
+    pub show_selectors: bool,
+    /// Link counts by type, ie "3 links: 2 menus, 1 text"
+    pub summary: String,
+    /// Showing the details of the selected link in a popup?
+    pub show_info: bool,
+    /// The menu as it was fetched, if any submenus are expanded into
+    /// `raw`.
+    source: Option<String>,
+    /// The bytes the server sent, if decoding them changed anything.
+    raw_response: Option<Vec<u8>>,
+    /// Submenus expanded inline with `x`, as (URL, Gopher response).
+    expanded: Vec<(String, String)>,
+    /// How deeply each line is nested in expanded submenus.
+    depths: Vec<usize>,
+    /// What `/` is looking for in every line, info lines and all,
+    /// once no link matched. Empty when not searching.
+    find: String,
+    /// Line of the current match for `find`, if any.
+    found: Option<usize>,
     /// Global config
     config: Config,
 }
@@ -61,18 +125,28 @@ pub struct Menu {
 pub struct Line<'line, 'txt: 'line> {
     span: &'line LineSpan,
     text: &'txt str,
+    /// The host of the menu, if it came over `gophers://`.
+    tls_host: Option<&'txt str>,
 }
 
 impl<'line, 'txt> Line<'line, 'txt> {
-    fn new(span: &'line LineSpan, text: &'txt str) -> Line<'line, 'txt> {
-        Line { span, text }
+    fn new(
+        span: &'line LineSpan,
+        text: &'txt str,
+        tls_host: Option<&'txt str>,
+    ) -> Line<'line, 'txt> {
+        Line {
+            span,
+            text,
+            tls_host,
+        }
     }
 
     /// Visible line as text. What appeared in the raw Gopher
     /// response.
     pub fn text(&self) -> &str {
-        if self.start < self.text_end {
-            &self.text[self.start + 1..self.text_end]
+        if self.text_start < self.text_end {
+            &self.text[self.text_start..self.text_end]
         } else {
             ""
         }
@@ -84,41 +158,74 @@ impl<'line, 'txt> Line<'line, 'txt> {
         self.text().chars().take(self.truncated_len).collect()
     }
 
+    /// The (selector, host, port) fields of this line, with the same
+    /// defaults `url()` uses for missing ones.
+    pub fn fields(&self) -> (&str, &str, &str) {
+        let mut sel = "(null)";
+        let mut host = "localhost";
+        let mut port = "70";
+        if self.text_end < self.end {
+            let line = &self.text[self.text_end..self.end].trim_end_matches('\r');
+            for (i, chunk) in line.split('\t').enumerate() {
+                match i {
+                    0 => {}
+                    1 => sel = chunk,
+                    2 => host = chunk,
+                    3 => port = chunk,
+                    _ => break,
+                }
+            }
+        }
+        (sel, host, port)
+    }
+
+    /// Does the server say this item has Gopher+ attributes? They
+    /// mark it with a `+`, or a `?` for Gopher+ forms, in a fifth
+    /// field after the port.
+    pub fn is_gopher_plus(&self) -> bool {
+        self.text_end < self.end
+            && self.text[self.text_end..self.end]
+                .trim_end_matches('\r')
+                .split('\t')
+                .nth(4)
+                .is_some_and(|plus| plus.starts_with(['+', '?']))
+    }
+
     /// URL for this line, if it's a link.
     pub fn url(&self) -> String {
         if !self.typ.is_link() || self.text_end >= self.end {
             return String::from("");
         }
 
-        let line = &self.text[self.text_end..self.end].trim_end_matches('\r');
-        let mut sel = "(null)";
-        let mut host = "localhost";
-        let mut port = "70";
-        for (i, chunk) in line.split('\t').enumerate() {
-            match i {
-                0 => {}
-                1 => sel = chunk,
-                2 => host = chunk,
-                3 => port = chunk,
-                _ => break,
-            }
-        }
+        let (sel, host, port) = self.fields();
 
         if self.typ.is_html() {
             sel.trim_start_matches('/')
                 .trim_start_matches("URL:")
                 .to_string()
         } else if self.typ.is_telnet() {
-            format!("telnet://{}:{}", host, port)
+            let host = gopher::bracket_ipv6(host);
+            match sel.trim_start_matches('/') {
+                "" => format!("telnet://{}:{}", host, port),
+                login => format!("telnet://{}@{}:{}", login, host, port),
+            }
         } else {
-            let mut path = format!("/{}{}", self.typ, sel);
+            // a ? starts a search query, like in bookmarks.gph
+            let sel = sel.replacen('?', "\t", 1);
+            let mut path = format!("/{}{}", self.typ, gopher::encode_selector(&sel));
             if sel.is_empty() || sel == "/" {
                 path.clear();
             }
+            // links back to a gophers:// menu's own host stay on TLS
+            let scheme = match self.tls_host {
+                Some(tls) if tls.eq_ignore_ascii_case(host) => gopher::TLS_SCHEME,
+                _ => "gopher://",
+            };
+            let host = gopher::bracket_ipv6(host);
             if port == "70" {
-                format!("gopher://{}{}", host, path)
+                format!("{}{}{}", scheme, host, path)
             } else {
-                format!("gopher://{}:{}{}", host, port, path)
+                format!("{}{}:{}{}", scheme, host, port, path)
             }
         }
     }
@@ -140,11 +247,13 @@ impl<'line, 'txt: 'line> std::ops::Deref for Line<'line, 'txt> {
 /// You won't really interact with this directly, instead call
 /// `menu.lines()` get an iter over `Line` or `menu.line(idx)` to get
 /// a single Line.
+#[derive(Clone)]
 pub struct LineSpan {
     /// Gopher Item Type.
     pub typ: Type,
-    /// Where this line starts in its Menu's `raw` Gopher response.
-    start: usize,
+    /// Where the text/label of this line starts in its Menu's `raw`
+    /// Gopher response, after the type.
+    text_start: usize,
     /// Where this line ends in Menu.raw.
     end: usize,
     /// Where the text/label of this line ends. Might be the same as
@@ -172,14 +281,20 @@ impl LineSpan {
 pub struct LinesIter<'menu> {
     spans: &'menu [LineSpan],
     text: &'menu str,
+    tls_host: Option<&'menu str>,
     curr: usize,
 }
 
 impl<'menu> LinesIter<'menu> {
-    fn new(spans: &'menu [LineSpan], text: &'menu str) -> LinesIter<'menu> {
+    fn new(
+        spans: &'menu [LineSpan],
+        text: &'menu str,
+        tls_host: Option<&'menu str>,
+    ) -> LinesIter<'menu> {
         LinesIter {
             spans,
             text,
+            tls_host,
             curr: 0,
         }
     }
@@ -191,7 +306,7 @@ impl<'menu> Iterator for LinesIter<'menu> {
         if self.curr >= self.spans.len() {
             None
         } else {
-            let line_with = Line::new(&self.spans[self.curr], self.text);
+            let line_with = Line::new(&self.spans[self.curr], self.text, self.tls_host);
             self.curr += 1;
             Some(line_with)
         }
@@ -222,11 +337,27 @@ impl View for Menu {
     }
 
     fn raw(&self) -> &str {
-        self.raw.as_ref()
+        self.source.as_ref().unwrap_or(&self.raw)
+    }
+
+    fn raw_bytes(&self) -> &[u8] {
+        match &self.raw_response {
+            Some(bytes) => bytes,
+            None => self.raw().as_bytes(),
+        }
+    }
+
+    fn clone_view(&self) -> Box<dyn View> {
+        Box::new(self.clone())
     }
 
     fn render(&mut self) -> String {
-        self.render_lines()
+        self.parse_ahead();
+        let mut out = self.render_lines();
+        if self.show_info {
+            out.push_str(&self.render_info());
+        }
+        out
     }
 
     fn respond(&mut self, key: Key) -> Action {
@@ -248,38 +379,336 @@ impl View for Menu {
     fn url(&self) -> &str {
         self.url.as_ref()
     }
+
+    fn summary(&self) -> Option<String> {
+        Some(self.summary.clone())
+    }
+
+    fn links(&self) -> Vec<(String, String)> {
+        self.all_links()
+    }
+
+    fn link_count(&self) -> usize {
+        if self.fully_parsed() {
+            self.links.len()
+        } else {
+            self.total_links
+        }
+    }
+
+    fn scroll_position(&self) -> Option<(usize, usize)> {
+        Some((self.offset, self.row_count()))
+    }
+
+    fn plain_text(&mut self) -> String {
+        self.parse_all();
+        let mut out = String::new();
+        for line in self.lines() {
+            out.push_str(line.text());
+            out.push('\n');
+        }
+        out
+    }
+
+    fn taking_input(&self) -> bool {
+        self.filtering || self.searching || !self.hints.is_empty()
+    }
+
+    fn find(&mut self, query: &str) -> Action {
+        self.find = query.trim().to_string();
+        self.found = None;
+        if self.find.is_empty() {
+            return Action::Redraw;
+        }
+        self.find_next(true)
+    }
+
+    fn place(&self) -> (usize, Option<String>) {
+        (self.offset, self.link(self.link).map(|line| line.url()))
+    }
+
+    fn restore_place(&mut self, offset: usize, link: Option<&str>) {
+        if let Some(url) = link {
+            if let Some(idx) = self.link_urls().iter().position(|u| u == url) {
+                self.link = idx;
+            }
+        }
+        self.offset = offset.min(self.row_count().saturating_sub(1));
+    }
+
+    fn expand(&mut self, url: &str, raw: String) {
+        self.expanded.retain(|(u, _)| u != url);
+        self.expanded.push((url.to_string(), raw));
+        self.rebuild();
+    }
 }
 
 impl Menu {
     /// Create a representation of a Gopher Menu from a raw Gopher
-    /// response and a few options.
+    /// response and a few options. Nothing is fetched, so `response`
+    /// can come from anywhere. `tls` is whether it came over TLS.
+    ///
+    /// ```
+    /// use phetch::{config::SharedConfig, menu::Menu, ui::View};
+    ///
+    /// let gophermap = "iWelcome!\r\n1Phlog\t/phlog\tphkt.io\t70\r\n.\r\n";
+    /// let mut menu = Menu::from("gopher://phkt.io/1/", gophermap.into(), SharedConfig::default(), false);
+    /// menu.term_size(80, 24);
+    /// let out = menu.render();
+    /// assert!(out.contains("Welcome!"));
+    /// assert!(out.contains("Phlog"));
+    /// assert_eq!(menu.link(0).unwrap().url(), "gopher://phkt.io/1/phlog");
+    /// ```
     pub fn from(url: &str, response: String, config: Config, tls: bool) -> Menu {
         Menu {
             tls,
             tor: config.read().unwrap().tor,
-            wide: config.read().unwrap().wide,
+            wide: config.read().unwrap().wide_for(url),
             scroll: config.read().unwrap().scroll,
             mode: config.read().unwrap().mode,
-            ..parse(url, response, config.clone())
+            ..parse_lazily(url, response, config.clone())
         }
     }
 
     /// Lines in this menu. Main iterator for getting Line with text.
-    pub fn lines(&self) -> LinesIter {
-        LinesIter::new(&self.spans, &self.raw)
+    pub fn lines(&self) -> LinesIter<'_> {
+        LinesIter::new(&self.spans, &self.raw, self.tls_host())
+    }
+
+    /// Like `from()`, but for a response that hasn't been decoded yet.
+    /// `encoding` says which character set it's in.
+    ///
+    /// ```
+    /// use phetch::{config::SharedConfig, encoding::Encoding, menu::Menu, ui::View};
+    ///
+    /// let gophermap = b"i\xb0\xb1\xb2 BBS \xb2\xb1\xb0\r\n0About\t/about.txt\tbbs.example\t70\r\n";
+    /// let mut menu = Menu::from_bytes(
+    ///     "gopher://bbs.example/1/",
+    ///     gophermap,
+    ///     Encoding::CP437,
+    ///     SharedConfig::default(),
+    ///     false,
+    /// );
+    /// menu.term_size(80, 24);
+    /// assert!(menu.render().contains("░▒▓ BBS ▓▒░"));
+    /// ```
+    pub fn from_bytes(
+        url: &str,
+        response: &[u8],
+        encoding: Encoding,
+        config: Config,
+        tls: bool,
+    ) -> Menu {
+        let decoded = match encoding {
+            Encoding::UTF8 => gopher::response_to_string(response),
+            _ => encoding.encode(gopher::strip_bom(response)).into_owned(),
+        };
+        let raw_response = if decoded.as_bytes() == response {
+            None
+        } else {
+            Some(response.to_vec())
+        };
+        Menu {
+            raw_response,
+            ..Menu::from(url, decoded, config, tls)
+        }
+    }
+
+    /// This menu's host, if it came over `gophers://`. Its links to
+    /// the same host are `gophers://` too.
+    fn tls_host(&self) -> Option<&str> {
+        let url = gopher::view_prefix(&self.url).1;
+        if url.starts_with(gopher::TLS_SCHEME) {
+            Some(gopher::url_host(url))
+        } else {
+            None
+        }
     }
 
     /// Get a single Line in this menu by index.
-    pub fn line(&self, idx: usize) -> Option<Line> {
+    pub fn line(&self, idx: usize) -> Option<Line<'_, '_>> {
         if idx >= self.spans.len() {
             None
         } else {
-            Some(Line::new(&self.spans[idx], &self.raw))
+            Some(Line::new(&self.spans[idx], &self.raw, self.tls_host()))
+        }
+    }
+
+    /// Replace the menu's content with `raw`, like when a submenu is
+    /// expanded or collapsed. The selected link stays selected if
+    /// it's still there, even if it moved. Otherwise the first link
+    /// is.
+    fn reparse(&mut self, raw: String) {
+        let selected = self.link(self.link).map(|line| line.url());
+        self.unfiltered = None;
+        self.filtered.clear();
+        self.filter.clear();
+        self.filtering = false;
+        self.spans.clear();
+        self.links.clear();
+        self.longest = 0;
+        self.parsed = 0;
+        self.raw = raw;
+        self.all_links = OnceCell::new();
+        self.found = None;
+        self.parse_all();
+        self.new_links.clear();
+        self.show_info = false;
+        self.summary = self.link_summary();
+        let urls = self.link_urls();
+        self.link = selected
+            .and_then(|url| urls.iter().position(|u| *u == url))
+            .unwrap_or(0);
+        self.offset = self.offset.min(self.row_count().saturating_sub(1));
+    }
+
+    /// URLs of all the links in this menu, in order.
+    pub fn link_urls(&self) -> Vec<String> {
+        self.all_links().into_iter().map(|(_, url)| url).collect()
+    }
+
+    /// (name, URL) of every link, even the ones that haven't been
+    /// parsed yet.
+    fn all_links(&self) -> Vec<(String, String)> {
+        if !self.fully_parsed() {
+            return self
+                .all_links
+                .get_or_init(|| {
+                    let mut links = vec![];
+                    let mut start = 0;
+                    while let Some((span, len)) = next_line(&self.raw, start) {
+                        if let Some(span) = span.filter(|span| span.typ.is_link()) {
+                            let line = Line::new(&span, &self.raw, self.tls_host());
+                            links.push((line.text().to_string(), line.url()));
+                        }
+                        start += len;
+                    }
+                    links
+                })
+                .clone();
+        }
+        (0..self.links.len())
+            .filter_map(|i| self.link(i))
+            .map(|line| (line.text().to_string(), line.url()))
+            .collect()
+    }
+
+    /// Has all of `raw` been parsed?
+    pub fn fully_parsed(&self) -> bool {
+        self.parsed >= self.raw.len()
+    }
+
+    /// Parse everything that's left.
+    pub fn parse_all(&mut self) {
+        self.parse_until(usize::MAX, usize::MAX);
+    }
+
+    /// Parse more of `raw`, until there are at least `rows` lines and
+    /// `links` links or it's all been parsed.
+    fn parse_until(&mut self, rows: usize, links: usize) {
+        // filtering parses everything first
+        if self.unfiltered.is_some() {
+            return;
+        }
+        while !self.fully_parsed() && (self.spans.len() < rows || self.links.len() < links) {
+            let (span, len) = match next_line(&self.raw, self.parsed) {
+                Some(line) => line,
+                None => {
+                    self.parsed = self.raw.len();
+                    break;
+                }
+            };
+            if let Some(mut span) = span {
+                self.longest = self.longest.max(span.text_len());
+                if span.typ.is_link() {
+                    span.link = self.links.len();
+                    self.links.push(self.spans.len());
+                }
+                self.spans.push(span);
+            }
+            self.parsed += len;
+        }
+    }
+
+    /// Count the links and find the longest line in what hasn't been
+    /// parsed yet, so link numbers and margins don't shift as the
+    /// rest gets parsed.
+    fn measure_rest(&mut self) {
+        let mut start = self.parsed;
+        self.total_links = self.links.len();
+        while let Some((span, len)) = next_line(&self.raw, start) {
+            if let Some(span) = span {
+                self.longest = self.longest.max(span.text_len());
+                if span.typ.is_link() {
+                    self.total_links += 1;
+                }
+            }
+            start += len;
+        }
+    }
+
+    /// Parse enough to show the screen we're on and the next couple,
+    /// and the link after the selected one.
+    fn parse_ahead(&mut self) {
+        if self.mode != ui::Mode::Run {
+            return self.parse_all();
+        }
+        let rows = self.offset + self.page_rows() * 3;
+        let rows = rows.max(self.link_row(self.link).unwrap_or(0) + self.page_rows());
+        self.parse_until(rows, self.link + 2);
+    }
+
+    /// Count the links by kind, most common first, like
+    /// "24 links: 18 menus, 4 text, 2 web".
+    fn link_summary(&self) -> String {
+        let types = line_types(&self.raw)
+            .filter(|typ| typ.is_link())
+            .collect::<Vec<_>>();
+        if types.is_empty() {
+            return "No links".into();
+        }
+        let mut counts: Vec<(&str, &str, usize)> = vec![
+            ("menu", "menus", 0),
+            ("text", "text", 0),
+            ("search", "searches", 0),
+            ("web", "web", 0),
+            ("telnet", "telnet", 0),
+            ("media", "media", 0),
+            ("download", "downloads", 0),
+            ("other", "other", 0),
+        ];
+        for typ in &types {
+            let kind = match *typ {
+                Type::Menu => 0,
+                typ if typ.is_text() => 1,
+                Type::Search => 2,
+                Type::HTML => 3,
+                Type::Telnet | Type::Telnet3270 => 4,
+                typ if typ.is_media() => 5,
+                typ if typ.is_download() => 6,
+                _ => 7,
+            };
+            counts[kind].2 += 1;
         }
+        // stable, so ties keep the order above
+        counts.sort_by_key(|&(_, _, n)| std::cmp::Reverse(n));
+
+        let total = types.len();
+        format!(
+            "{} link{}: {}",
+            total,
+            if total == 1 { "" } else { "s" },
+            counts
+                .iter()
+                .filter(|(_, _, n)| *n > 0)
+                .map(|(one, many, n)| format!("{} {}", n, if *n == 1 { one } else { many }))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
     }
 
     /// Find a link by its link index.
-    pub fn link(&self, idx: usize) -> Option<Line> {
+    pub fn link(&self, idx: usize) -> Option<Line<'_, '_>> {
         let line = self.links.get(idx)?;
         self.line(*line)
     }
@@ -292,9 +721,15 @@ impl Menu {
         self.size.1
     }
 
+    /// Rows left for content once the status line is drawn. Tiny
+    /// terminals still get one.
+    fn page_rows(&self) -> usize {
+        self.rows().saturating_sub(1).max(1)
+    }
+
     fn scroll_by(&self) -> usize {
         if self.scroll == 0 {
-            self.rows() - 1
+            ui::page_scroll(self.page_rows())
         } else {
             self.scroll
         }
@@ -306,6 +741,10 @@ impl Menu {
             return 0;
         }
         let cols = self.cols();
+        let columns = self.columns();
+        if columns > 1 {
+            return cols.saturating_sub(columns * self.cell_width()) / 2;
+        }
         let longest = if self.longest > MAX_COLS {
             MAX_COLS
         } else {
@@ -315,11 +754,68 @@ impl Menu {
             0
         } else {
             let left = (cols - longest) / 2;
-            if left > 6 {
-                left - 6
+            left.saturating_sub(6)
+        }
+    }
+
+    /// How many columns to lay the links out in. Menus made up
+    /// entirely of short links, like big phone-book style indexes,
+    /// can be packed into a grid if the `columns` setting allows it.
+    /// 1 = always a single column, 0 = pick automatically.
+    fn columns(&self) -> usize {
+        let setting = self.config.read().unwrap().columns;
+        if setting == 1
+            || self.show_selectors
+            || self.links.is_empty()
+            || self.links.len() != self.spans.len()
+        {
+            return 1;
+        }
+
+        let want = if setting > 1 {
+            setting
+        } else if self.longest <= GRID_AUTO_MAX_LEN && self.links.len() > self.rows() {
+            GRID_AUTO_MAX_COLUMNS
+        } else {
+            1
+        };
+
+        want.min(self.cols() / self.cell_width()).max(1)
+    }
+
+    /// Width of a single link, number and all, in grid mode.
+    fn cell_width(&self) -> usize {
+        // "* " + number + ". " + text + gutter
+        2 + self.number_width() + 2 + self.longest.min(MAX_COLS) + GRID_GUTTER
+    }
+
+    /// How many digits the link numbers need. At least two.
+    fn number_width(&self) -> usize {
+        self.link_count().to_string().len().max(2)
+    }
+
+    /// The row a link appears in, ignoring scrolling. In single column
+    /// mode this is the link's line number.
+    fn link_row(&self, link: usize) -> Option<usize> {
+        let columns = self.columns();
+        if columns > 1 {
+            if link < self.links.len() {
+                Some(link / columns)
             } else {
-                0
+                None
             }
+        } else {
+            self.links.get(link).copied()
+        }
+    }
+
+    /// Total number of rows this menu takes up when rendered.
+    fn row_count(&self) -> usize {
+        let columns = self.columns();
+        if columns > 1 {
+            self.links.len().div_ceil(columns)
+        } else {
+            self.spans.len()
         }
     }
 
@@ -330,10 +826,10 @@ impl Menu {
 
     /// Where is the given link relative to the screen?
     fn link_visibility(&self, i: usize) -> Option<LinkPos> {
-        let &pos = self.links.get(i)?;
+        let pos = self.link_row(i)?;
         Some(if pos < self.offset {
             LinkPos::Above
-        } else if pos >= self.offset + self.rows() - 1 {
+        } else if pos >= self.offset + self.page_rows() {
             LinkPos::Below
         } else {
             LinkPos::Visible
@@ -345,8 +841,8 @@ impl Menu {
         if !self.is_visible(link) {
             return None;
         }
-        let &pos = self.links.get(link)?;
-        let x = self.indent() + 1;
+        let pos = self.link_row(link)?;
+        let x = self.indent() + 1 + (link % self.columns()) * self.cell_width();
         let y = if self.offset > pos {
             pos + 1
         } else {
@@ -357,11 +853,15 @@ impl Menu {
     }
 
     fn render_lines(&mut self) -> String {
+        if self.columns() > 1 {
+            return self.render_grid();
+        }
+
         let mut out = String::new();
         let limit = if self.mode == ui::Mode::Run {
             // only show as many lines as screen rows minus one
             // (status bar is always last line)
-            self.rows() - 1
+            self.page_rows()
         } else {
             self.spans.len()
         };
@@ -371,65 +871,261 @@ impl Menu {
 
         for line in iter {
             out.push_str(&left_margin);
-            let config = self.config.read().unwrap();
+            self.render_item(&mut out, &line, 2);
 
-            if line.typ == Type::Info {
-                out.push_str("      ");
-            } else {
-                if line.link == self.link && self.show_cursor() {
-                    out.push_str(&config.theme.ui_cursor);
-                    out.push('*');
-                    out.push_str(reset_color!());
-                } else {
-                    out.push(' ');
+            // clear rest of line
+            out.push_str(terminal::ClearUntilNewline.as_ref());
+            out.push_str("\r\n");
+        }
+
+        // clear remainder of screen
+        out.push_str(terminal::ClearAfterCursor.as_ref());
+
+        out
+    }
+
+    /// Render the menu's links in rows of `columns()` links each.
+    fn render_grid(&self) -> String {
+        let mut out = String::new();
+        let columns = self.columns();
+        let limit = if self.mode == ui::Mode::Run {
+            self.page_rows()
+        } else {
+            self.row_count()
+        };
+        let left_margin = " ".repeat(self.indent());
+        let num_width = self.number_width();
+        let cell_width = self.cell_width();
+
+        for row in (self.offset..self.row_count()).take(limit) {
+            out.push_str(&left_margin);
+            for col in 0..columns {
+                if let Some(line) = self.link(row * columns + col) {
+                    self.render_item(&mut out, &line, num_width);
+                    if col < columns - 1 {
+                        let tag = self.tag(&line).map_or(0, |t| t.chars().count() + 1);
+                        let used = 2 + num_width + 2 + line.text_len().min(MAX_COLS) + tag;
+                        out.push_str(&" ".repeat(cell_width.saturating_sub(used)));
+                    }
                 }
+            }
+            out.push_str(terminal::ClearUntilNewline.as_ref());
+            out.push_str("\r\n");
+        }
+
+        out.push_str(terminal::ClearAfterCursor.as_ref());
+        out
+    }
+
+    /// Render a single menu item: the cursor, the link number padded
+    /// to `num_width` digits, and the colored text.
+    fn render_item(&self, out: &mut String, line: &Line, num_width: usize) {
+        let config = self.config.read().unwrap();
+
+        if line.typ == Type::Info {
+            out.push_str(&" ".repeat(num_width + 4));
+        } else {
+            if line.link == self.link && self.show_cursor() {
+                out.push_str(&config.theme.ui_cursor);
+                out.push('*');
+                out.push_str(reset_color!());
+            } else {
+                out.push(' ');
+            }
+            if self.new_links.contains(&self.real_link(line.link)) {
+                out.push_str(&config.theme.ui_new);
+                out.push('+');
+                out.push_str(reset_color!());
+            } else {
                 out.push(' ');
+            }
+            if self.hints.is_empty() {
                 out.push_str(&config.theme.ui_number);
-                if line.link < 9 {
-                    out.push(' ');
-                }
                 let num = (line.link + 1).to_string();
+                out.push_str(&" ".repeat(num_width.saturating_sub(num.len())));
                 out.push_str(&num);
-                out.push_str(". ");
-                out.push_str(reset_color!());
+            } else {
+                // only labels that start with what's been typed
+                let label = self
+                    .hints
+                    .iter()
+                    .find(|(label, link)| *link == line.link && label.starts_with(&self.hint))
+                    .map_or("", |(label, _)| label.as_str());
+                out.push_str(&config.theme.ui_cursor);
+                out.push_str(&" ".repeat(num_width.saturating_sub(label.len())));
+                out.push_str(label);
             }
+            out.push_str(". ");
+            out.push_str(reset_color!());
+        }
 
-            // truncate long lines, instead of wrapping
-            let text = line.text_truncated();
+        // truncate long lines to what's left of the screen, instead
+        // of wrapping
+        let tag = self.tag(line);
+        let room = if self.columns() > 1 || self.cols() == 0 {
+            MAX_COLS
+        } else {
+            let tag_width = tag.as_ref().map_or(0, |t| t.chars().count() + 1);
+            self.cols()
+                .saturating_sub(self.indent() + num_width + 4 + tag_width)
+                .min(MAX_COLS)
+        };
+        let (text, text_width) = fit_width(line.text(), room);
+
+        // color the line
+        if line.typ.is_media() {
+            out.push_str(&config.theme.item_media);
+        } else if line.typ.is_download() {
+            out.push_str(&config.theme.item_download);
+        } else if !line.typ.is_supported() {
+            out.push_str(&config.theme.item_unsupported);
+        } else {
+            out.push_str(match line.typ {
+                Type::Text => &config.theme.item_text,
+                Type::Menu => &config.theme.item_menu,
+                Type::Info => &config.theme.ui_menu,
+                Type::HTML => &config.theme.item_external,
+                Type::Error => &config.theme.item_error,
+                Type::Telnet => &config.theme.item_telnet,
+                Type::Search => &config.theme.item_search,
+                _ => &config.theme.item_error,
+            });
+        }
+        let text = if line.typ == Type::Info && config.emphasis {
+            emphasize(&text)
+        } else {
+            text
+        };
+        out.push_str(&utils::highlight(&text, &self.find));
+        if let Some(tag) = tag {
+            out.push(' ');
+            out.push_str(&tag);
+        }
+        out.push_str(reset_color!());
 
-            // color the line
-            if line.typ.is_media() {
-                out.push_str(&config.theme.item_media);
-            } else if line.typ.is_download() {
-                out.push_str(&config.theme.item_download);
-            } else if !line.typ.is_supported() {
-                out.push_str(&self.config.read().unwrap().theme.item_unsupported);
-            } else {
-                out.push_str(match line.typ {
-                    Type::Text => &config.theme.item_text,
-                    Type::Menu => &config.theme.item_menu,
-                    Type::Info => &config.theme.ui_menu,
-                    Type::HTML => &config.theme.item_external,
-                    Type::Error => &config.theme.item_error,
-                    Type::Telnet => &config.theme.item_telnet,
-                    Type::Search => &config.theme.item_search,
-                    _ => &config.theme.item_error,
-                });
-            }
-            out.push_str(&text);
-            out.push_str(reset_color!());
+        if self.show_selectors && line.typ.is_link() {
+            let used = self.indent() + num_width + 4 + text_width;
+            if let Some(selector) = self.selector_annotation(line, used) {
+                out.push_str(&config.theme.ui_selector);
+                out.push_str(&selector);
+                out.push_str(reset_color!());
+            }
+        }
+    }
 
-            // clear rest of line
-            out.push_str(terminal::ClearUntilNewline.as_ref());
-            out.push_str("\r\n");
+    /// A box in the middle of the screen with everything we know
+    /// about the selected link, drawn over the menu. Long values wrap
+    /// instead of being cut off.
+    fn render_info(&self) -> String {
+        let line = match self.link(self.link) {
+            Some(line) => line,
+            None => return String::new(),
+        };
+        let (sel, host, port) = line.fields();
+        let fields = [
+            ("Name", line.text().to_string()),
+            ("Type", format!("{:?} ({})", line.typ, line.typ)),
+            ("Host", host.to_string()),
+            ("Port", port.to_string()),
+            ("Selector", sel.to_string()),
+            ("URL", line.url()),
+        ];
+
+        let cols = if self.cols() == 0 { 80 } else { self.cols() };
+        let inner = cols.saturating_sub(6).clamp(20, MAX_COLS);
+        let value_width = inner - 10;
+        let mut rows = vec![];
+        for (label, value) in &fields {
+            let chars = value.chars().collect::<Vec<_>>();
+            let mut chunks = chars.chunks(value_width);
+            let first: String = chunks
+                .next()
+                .map(|c| c.iter().collect())
+                .unwrap_or_default();
+            rows.push(format!("{:<10}{}", format!("{}:", label), first));
+            for chunk in chunks {
+                rows.push(format!("{:10}{}", "", chunk.iter().collect::<String>()));
+            }
         }
 
-        // clear remainder of screen
-        out.push_str(terminal::ClearAfterCursor.as_ref());
+        let width = rows
+            .iter()
+            .map(|r| r.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(20);
+        let height = rows.len() + 2;
+        let x = (cols.saturating_sub(width + 4) / 2 + 1) as u16;
+        let y = (self.rows().saturating_sub(height) / 2 + 1) as u16;
 
+        let mut out = String::new();
+        let config = self.config.read().unwrap();
+        out.push_str(&config.theme.ui_menu);
+        out.push_str(&format!(
+            "{}┌{}┐",
+            terminal::Goto(x, y),
+            "─".repeat(width + 2)
+        ));
+        for (i, row) in rows.iter().enumerate() {
+            out.push_str(&format!(
+                "{}│ {:<width$} │",
+                terminal::Goto(x, y + 1 + i as u16),
+                row,
+                width = width
+            ));
+        }
+        out.push_str(&format!(
+            "{}└{}┘",
+            terminal::Goto(x, y + height as u16 - 1),
+            "─".repeat(width + 2)
+        ));
+        out.push_str(reset_color!());
         out
     }
 
+    /// The "  TYPE host:port selector" shown after links when
+    /// `show_selectors` is on, truncated to fit after `used` columns.
+    fn selector_annotation(&self, line: &Line, used: usize) -> Option<String> {
+        let (sel, host, port) = line.fields();
+        let annotation = format!("  {} {}:{} {}", line.typ.to_char(), host, port, sel);
+        if self.cols() == 0 {
+            return Some(annotation);
+        }
+        let room = self.cols().saturating_sub(used + 1);
+        if room < 8 {
+            None
+        } else if annotation.chars().count() > room {
+            let mut short = annotation.chars().take(room - 1).collect::<String>();
+            short.push('…');
+            Some(short)
+        } else {
+            Some(annotation)
+        }
+    }
+
+    /// The tag to show after a link: `web_tag` for links that leave
+    /// gopherspace, like `URL:https://...` items, or `plus_tag` for
+    /// Gopher+ items.
+    fn tag(&self, line: &Line) -> Option<String> {
+        let config = self.config.read().unwrap();
+        let url = line.url();
+        let tag = if line.typ.is_html()
+            && !url.starts_with("gopher://")
+            && !url.starts_with(gopher::TLS_SCHEME)
+        {
+            &config.web_tag
+        } else if line.is_gopher_plus() {
+            &config.plus_tag
+        } else {
+            return None;
+        };
+        if tag.is_empty() {
+            None
+        } else {
+            Some(tag.clone())
+        }
+    }
+
     /// Clear and re-draw the cursor.
     fn reset_cursor(&mut self, old_link: usize) -> Action {
         if self.links.is_empty() {
@@ -476,11 +1172,18 @@ impl Menu {
 
     /// User input field.
     fn render_input(&self) -> String {
+        if self.filtering {
+            let count = match &self.unfiltered {
+                Some((_, links)) => format!(" ({}/{})", self.links.len(), links.len()),
+                None => String::new(),
+            };
+            return format!("Filter{}: {}{}", count, self.filter, terminal::ShowCursor);
+        }
         format!("Find: {}{}", self.input, terminal::ShowCursor)
     }
 
     fn redraw_input(&self) -> Action {
-        if self.searching {
+        if self.searching || self.filtering {
             Action::Status(self.render_input())
         } else {
             Action::Status(terminal::HideCursor.to_string())
@@ -554,7 +1257,7 @@ impl Menu {
                             .iter()
                             .take(self.link)
                             .rev()
-                            .find(|&&i| i < (self.rows() + scroll - 1))
+                            .find(|&&i| i < (self.page_rows() + scroll))
                         {
                             self.link = self.line(pos).unwrap().link;
                         }
@@ -639,12 +1342,8 @@ impl Menu {
 
     /// Final `self.offset` value.
     fn final_offset(&self) -> usize {
-        let padding = (self.rows() as f64 * 0.9) as usize;
-        if self.spans.len() > padding {
-            self.spans.len() - padding
-        } else {
-            0
-        }
+        let padding = ((self.rows() as f64 * 0.9) as usize).max(1);
+        self.row_count().saturating_sub(padding)
     }
 
     /// Search through links to find a match based on the pattern,
@@ -672,6 +1371,54 @@ impl Menu {
         None
     }
 
+    /// Go to the next line matching `find`, or the previous one if
+    /// `forward` isn't set, going around the end of the menu. A link
+    /// that matches gets selected.
+    fn find_next(&mut self, forward: bool) -> Action {
+        self.parse_all();
+        let lines = self
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !utils::find_matches(line.text(), &self.find).is_empty())
+            .map(|(i, line)| (i, line.typ.is_link().then_some(line.link)))
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            let find = std::mem::take(&mut self.find);
+            self.found = None;
+            return Action::Error(format!("Not found: {}", find));
+        }
+
+        // start from the last match, or the top of the screen
+        let idx = if forward {
+            lines
+                .iter()
+                .position(|&(i, _)| self.found.map_or(i >= self.offset, |f| i > f))
+                .unwrap_or(0)
+        } else {
+            lines
+                .iter()
+                .rposition(|&(i, _)| self.found.map_or(i < self.offset, |f| i < f))
+                .unwrap_or(lines.len() - 1)
+        };
+        let (line, link) = lines[idx];
+        self.found = Some(line);
+        // only menus of nothing but links are laid out in a grid
+        let row = match link {
+            Some(link) => {
+                self.link = link;
+                self.link_row(link).unwrap_or(line)
+            }
+            None => line,
+        };
+        if row < self.offset || row >= self.offset + self.page_rows() {
+            self.offset = row.min(self.final_offset());
+        }
+        Action::List(vec![
+            Action::Redraw,
+            Action::Status(format!("Match {} of {}", idx + 1, lines.len())),
+        ])
+    }
+
     fn action_down(&mut self) -> Action {
         let new_link = self.link + 1;
 
@@ -724,7 +1471,7 @@ impl Menu {
 
                             // scroll if we are within 5 lines of the end
                             if self.spans.len() >= self.rows() // dont scroll if content too small
-                                && pos >= self.offset + self.rows() - 6
+                                && pos >= (self.offset + self.rows()).saturating_sub(6)
                             {
                                 self.offset += 1;
                             } else {
@@ -745,7 +1492,7 @@ impl Menu {
 
     /// Select and optionally scroll to a link.
     fn action_select_link(&mut self, link: usize) -> Action {
-        if let Some(&pos) = self.links.get(link) {
+        if let Some(pos) = self.link_row(link) {
             let old_link = self.link;
             self.link = link;
             if self.is_visible(link) {
@@ -771,28 +1518,296 @@ impl Menu {
         }
     }
 
-    /// Select and open link.
-    fn action_follow_link(&mut self, link: usize) -> Action {
-        self.action_select_link(link);
-        self.action_open()
-    }
-
-    /// Scroll to a link if it's not visible.
-    fn scroll_to(&mut self, link: usize) -> Action {
-        if !self.is_visible(link) {
-            if let Some(&pos) = self.links.get(link) {
-                if pos > 5 {
-                    self.offset = pos - 5;
-                } else {
-                    self.offset = 0;
-                }
-                if self.offset > self.final_offset() {
-                    self.offset = self.final_offset();
-                }
-                return Action::Redraw;
+    /// Move the cursor `by` links in grid mode. Moving down from the
+    /// second-to-last row lands on the final link if the last row is
+    /// short.
+    fn action_grid_move(&mut self, by: isize) -> Action {
+        let last = self.links.len() - 1;
+        let mut new_link = self.link as isize + by;
+        if new_link < 0 {
+            return Action::None;
+        }
+        if new_link as usize > last {
+            if by > 1 && self.link_row(self.link) < self.link_row(last) {
+                new_link = last as isize;
+            } else {
+                return Action::None;
             }
         }
-        Action::None
+
+        let old_link = self.link;
+        self.link = new_link as usize;
+        if self.is_visible(self.link) {
+            self.reset_cursor(old_link)
+        } else {
+            self.scroll_to(self.link)
+        }
+    }
+
+    /// Scroll by a page in grid mode, dragging the cursor along if it
+    /// would otherwise go off screen.
+    fn action_grid_page(&mut self, down: bool) -> Action {
+        let columns = self.columns();
+        let old_offset = self.offset;
+        if down {
+            self.offset = (self.offset + self.scroll_by()).min(self.final_offset());
+        } else {
+            self.offset = self.offset.saturating_sub(self.scroll_by());
+        }
+
+        match self.link_visibility(self.link) {
+            Some(LinkPos::Above) => self.link = self.offset * columns,
+            Some(LinkPos::Below) => {
+                let bottom = self.offset + self.rows().saturating_sub(2);
+                self.link = (bottom * columns).min(self.links.len() - 1);
+            }
+            _ if self.offset == old_offset => {
+                let old_link = self.link;
+                self.link = if down { self.links.len() - 1 } else { 0 };
+                return self.reset_cursor(old_link);
+            }
+            _ => {}
+        }
+        Action::Redraw
+    }
+
+    /// Respond to the arrow keys and paging in grid mode. Returns
+    /// None for keys that work the same as in a regular menu.
+    fn process_grid_key(&mut self, key: Key) -> Option<Action> {
+        let columns = self.columns() as isize;
+        let last = self.links.len() - 1;
+        Some(match key {
+            Key::Up | Key::Ctrl('p') | Key::Char('p') | Key::Ctrl('k') | Key::Char('k') => {
+                self.action_grid_move(-columns)
+            }
+            Key::Down | Key::Ctrl('n') | Key::Char('n') | Key::Ctrl('j') | Key::Char('j') => {
+                self.action_grid_move(columns)
+            }
+            // Left and Right still go back and forward at either end.
+            Key::Left if self.link > 0 => self.action_grid_move(-1),
+            Key::Right if self.link < last => self.action_grid_move(1),
+            Key::PageUp | Key::Ctrl('-') | Key::Char('-') => self.action_grid_page(false),
+            Key::PageDown | Key::Ctrl(' ') | Key::Char(' ') => self.action_grid_page(true),
+            _ => return None,
+        })
+    }
+
+    /// Select and open link.
+    fn action_follow_link(&mut self, link: usize) -> Action {
+        self.action_select_link(link);
+        self.action_open()
+    }
+
+    /// Scroll to a link if it's not visible.
+    fn scroll_to(&mut self, link: usize) -> Action {
+        if !self.is_visible(link) {
+            if let Some(pos) = self.link_row(link) {
+                if pos > 5 {
+                    self.offset = pos - 5;
+                } else {
+                    self.offset = 0;
+                }
+                if self.offset > self.final_offset() {
+                    self.offset = self.final_offset();
+                }
+                return Action::Redraw;
+            }
+        }
+        Action::None
+    }
+
+    /// Expand the selected menu link inline, or collapse it if it's
+    /// already expanded. The UI fetches it and hands it to `expand()`.
+    fn action_expand(&mut self) -> Action {
+        let (url, typ) = match self.link(self.link) {
+            Some(line) => (line.url(), line.typ),
+            None => return Action::None,
+        };
+        if typ != Type::Menu {
+            return Action::Error("Only menus can be expanded.".into());
+        }
+        if let Some(i) = self.expanded.iter().position(|(u, _)| *u == url) {
+            self.expanded.remove(i);
+            self.rebuild();
+            return Action::Redraw;
+        }
+        let links = self
+            .unfiltered
+            .as_ref()
+            .map_or(&self.links, |(_, links)| links);
+        let pos = links[self.real_link(self.link)];
+        let depth = self.depths.get(pos).copied().unwrap_or(0);
+        if depth >= MAX_EXPAND_DEPTH {
+            return Action::Error(format!(
+                "Can't expand more than {} menus deep.",
+                MAX_EXPAND_DEPTH
+            ));
+        }
+        Action::Expand(url)
+    }
+
+    /// Rebuild `raw` from `source` with every expanded submenu
+    /// nested under its link.
+    fn rebuild(&mut self) {
+        let source = self.source.take().unwrap_or_else(|| self.raw.clone());
+        let mut raw = String::new();
+        let mut depths = vec![];
+        nest(
+            &source,
+            self.tls_host(),
+            0,
+            &self.expanded,
+            &mut vec![],
+            &mut raw,
+            &mut depths,
+        );
+        self.reparse(raw);
+        self.depths = depths;
+        if !self.expanded.is_empty() {
+            self.source = Some(source);
+        }
+    }
+
+    /// The number of link `link` in the full menu, even while a
+    /// filter is hiding some of them.
+    fn real_link(&self, link: usize) -> usize {
+        self.filtered.get(link).copied().unwrap_or(link)
+    }
+
+    /// Show only the links matching `filter`, or everything if it's
+    /// empty. The selected link stays selected if it still matches.
+    fn apply_filter(&mut self) {
+        let selected = self.real_link(self.link);
+        let (spans, links) = match self.unfiltered.take() {
+            Some(all) => all,
+            None => (
+                std::mem::take(&mut self.spans),
+                std::mem::take(&mut self.links),
+            ),
+        };
+        self.filtered.clear();
+        self.offset = 0;
+        if self.filter.is_empty() {
+            self.spans = spans;
+            self.links = links;
+            self.link = selected;
+            self.scroll_to(selected);
+            return;
+        }
+
+        let pattern = self.filter.to_lowercase();
+        self.spans.clear();
+        self.links.clear();
+        for &pos in &links {
+            let span = &spans[pos];
+            if fuzzy_match(
+                &Line::new(span, &self.raw, None).text().to_lowercase(),
+                &pattern,
+            ) {
+                let mut span = span.clone();
+                span.link = self.links.len();
+                self.filtered.push(spans[pos].link);
+                self.links.push(self.spans.len());
+                self.spans.push(span);
+            }
+        }
+        self.link = self
+            .filtered
+            .iter()
+            .position(|&link| link == selected)
+            .unwrap_or(0);
+        self.unfiltered = Some((spans, links));
+        self.scroll_to(self.link);
+    }
+
+    /// Redraw the menu after the filter changed.
+    fn action_filter(&mut self) -> Action {
+        self.apply_filter();
+        Action::List(vec![Action::Redraw, self.redraw_input()])
+    }
+
+    /// self.filtering == true
+    fn process_filter_mode_key(&mut self, key: Key) -> Option<Action> {
+        Some(match key {
+            Key::Char('\n') => {
+                self.filtering = false;
+                self.redraw_input()
+            }
+            Key::Char(c) => {
+                self.filter.push(c);
+                self.action_filter()
+            }
+            Key::Backspace | Key::Delete => {
+                if self.filter.pop().is_none() {
+                    self.filtering = false;
+                }
+                self.action_filter()
+            }
+            Key::Esc | Key::Ctrl('c') => {
+                self.filtering = false;
+                self.filter.clear();
+                self.action_filter()
+            }
+            _ => return None,
+        })
+    }
+
+    /// Label each link on screen with letters from `hint_chars`.
+    fn action_hint(&mut self) -> Action {
+        let visible = (0..self.links.len())
+            .filter(|&link| self.is_visible(link))
+            .collect::<Vec<_>>();
+        if visible.is_empty() {
+            return Action::None;
+        }
+        let chars = self.config.read().unwrap().hint_chars.clone();
+        self.hint.clear();
+        self.hints = hint_labels(visible.len(), &chars)
+            .into_iter()
+            .zip(visible)
+            .collect();
+        Action::List(vec![Action::Redraw, self.redraw_hint()])
+    }
+
+    fn redraw_hint(&self) -> Action {
+        if self.hints.is_empty() {
+            Action::Status(terminal::HideCursor.to_string())
+        } else {
+            Action::Status(format!("Hint: {}{}", self.hint, terminal::ShowCursor))
+        }
+    }
+
+    /// !self.hints.is_empty()
+    fn process_hint_mode_key(&mut self, key: Key) -> Action {
+        match key {
+            Key::Char(c) if !c.is_whitespace() => self.hint.push(c),
+            Key::Backspace | Key::Delete if !self.hint.is_empty() => {
+                self.hint.pop();
+            }
+            _ => self.hints.clear(),
+        }
+        let matches = self
+            .hints
+            .iter()
+            .filter(|(label, _)| label.starts_with(&self.hint))
+            .collect::<Vec<_>>();
+        if let [(label, link)] = matches[..] {
+            if *label == self.hint {
+                let link = *link;
+                self.hints.clear();
+                self.hint.clear();
+                return Action::List(vec![Action::Redraw, self.action_follow_link(link)]);
+            }
+        }
+        if matches.is_empty() && !self.hints.is_empty() {
+            let hint = std::mem::take(&mut self.hint);
+            self.hints.clear();
+            return Action::List(vec![
+                Action::Redraw,
+                Action::Error(format!("No link labeled {}", hint)),
+            ]);
+        }
+        Action::List(vec![Action::Redraw, self.redraw_hint()])
     }
 
     /// Open the currently selected link.
@@ -814,6 +1829,10 @@ impl Menu {
                     Action::Prompt(
                         prompt.clone(),
                         Box::new(move |query| {
+                            let query = query.trim();
+                            if query.is_empty() {
+                                return Action::None;
+                            }
                             Action::Open(
                                 format!("{}{}", prompt, query),
                                 format!("{}?{}", url, query),
@@ -836,10 +1855,10 @@ impl Menu {
             if self.link_matching(0, &self.input).is_some() {
                 return self.action_open();
             } else {
-                let input = self.input.clone();
+                // look through the rest of the menu instead
+                let input = std::mem::take(&mut self.input);
                 self.searching = false;
-                self.input.clear();
-                return Action::Error(format!("No links match: {}", input));
+                return self.find(&input);
             }
         }
 
@@ -853,10 +1872,57 @@ impl Menu {
 
     /// Respond to user input.
     fn process_key(&mut self, key: Key) -> Action {
-        if self.searching {
+        self.parse_ahead();
+        if matches!(
+            key,
+            Key::End | Key::Char('f' | 'F' | 'G' | '/' | 'i' | '0'..='9') | Key::Ctrl('f' | 'i')
+        ) {
+            // these need to know about every link
+            self.parse_all();
+        }
+
+        // any key closes the info popup
+        if self.show_info {
+            self.show_info = false;
+            return Action::Redraw;
+        }
+
+        if !self.hints.is_empty() {
+            return self.process_hint_mode_key(key);
+        }
+
+        if self.filtering {
+            if let Some(action) = self.process_filter_mode_key(key) {
+                return action;
+            }
+        } else if self.searching {
             if let Key::Char(c) = key {
                 return self.process_search_mode_char(c);
             }
+        } else if !matches!(key, Key::Char('0'..='9') | Key::Backspace | Key::Delete) {
+            // typed link numbers only stick around until you do
+            // something else
+            self.input.clear();
+        }
+
+        // n and N go through matches until escape ends the search
+        if !self.find.is_empty() && !self.searching && !self.filtering {
+            match key {
+                Key::Char('n') => return self.find_next(true),
+                Key::Char('N') => return self.find_next(false),
+                Key::Esc => {
+                    self.find.clear();
+                    self.found = None;
+                    return Action::Redraw;
+                }
+                _ => {}
+            }
+        }
+
+        if self.columns() > 1 && (!self.searching || self.input.is_empty()) {
+            if let Some(action) = self.process_grid_key(key) {
+                return action;
+            }
         }
 
         match key {
@@ -874,13 +1940,30 @@ impl Menu {
                 self.link = 0;
                 Action::Redraw
             }
-            Key::End => {
+            Key::End | Key::Char('G') => {
                 self.offset = self.final_offset();
                 if !self.links.is_empty() {
                     self.link = self.links.len() - 1;
                 }
                 Action::Redraw
             }
+            Key::Char('U') => {
+                self.show_selectors = !self.show_selectors;
+                Action::Redraw
+            }
+            Key::Char('I') if !self.links.is_empty() => {
+                self.show_info = true;
+                Action::Redraw
+            }
+            Key::Char(c) if !self.searching && Some(c) == self.config.read().unwrap().hint_key => {
+                self.action_hint()
+            }
+            Key::Char('x') => self.action_expand(),
+            Key::Char('F') => {
+                self.searching = false;
+                self.filtering = true;
+                self.action_filter()
+            }
             Key::Char('f') | Key::Ctrl('f') | Key::Char('/') | Key::Char('i') | Key::Ctrl('i') => {
                 self.searching = true;
                 self.input.clear();
@@ -890,6 +1973,14 @@ impl Menu {
                 if self.searching {
                     self.input.pop();
                     self.redraw_input()
+                } else if self.input.pop().is_some() {
+                    // edit the link number being typed, not history
+                    match self.input.parse::<usize>() {
+                        Ok(num) if num > 0 && num <= self.links.len() => {
+                            self.action_select_link(num - 1)
+                        }
+                        _ => Action::None,
+                    }
                 } else {
                     Action::Keypress(key)
                 }
@@ -902,6 +1993,9 @@ impl Menu {
                         self.input.clear();
                     }
                     self.redraw_input()
+                } else if !self.filter.is_empty() {
+                    self.filter.clear();
+                    self.action_filter()
                 } else {
                     Action::Keypress(key)
                 }
@@ -935,56 +2029,248 @@ impl Menu {
     }
 }
 
-/// Parse gopher response into a Menu object.
-pub fn parse(url: &str, raw: String, config: Config) -> Menu {
-    let mut spans = vec![];
-    let mut links = vec![];
-    let mut longest = 0;
-    let mut start = 0;
-
-    for line in raw.split_terminator('\n') {
-        // Check for Gopher's weird "end of response" message.
-        if line == ".\r" || line == "." {
+/// `count` labels made of `chars`, all the same length and as short
+/// as possible, like "a", "s", "d" or "aa", "as", "ad".
+fn hint_labels(count: usize, chars: &str) -> Vec<String> {
+    let chars = chars.chars().collect::<Vec<_>>();
+    if chars.len() < 2 {
+        return vec![];
+    }
+    let mut len = 1;
+    while chars.len().pow(len) < count {
+        len += 1;
+    }
+    (0..count)
+        .map(|mut n| {
+            let mut label = vec![chars[0]; len as usize];
+            for slot in label.iter_mut().rev() {
+                *slot = chars[n % chars.len()];
+                n /= chars.len();
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
+
+/// Does `text` contain all the chars of `pattern`, in order?
+fn fuzzy_match(text: &str, pattern: &str) -> bool {
+    let mut chars = text.chars();
+    pattern.chars().all(|p| chars.any(|c| c == p))
+}
+
+/// Append the lines of Gopher menu `text` to `out`, indented
+/// `depth` levels, with any `expanded` submenus nested under their
+/// links. `visiting` guards against menus that contain themselves.
+/// The depth of each line goes in `depths`. `tls_host` is the menu's
+/// host if it came over `gophers://`.
+fn nest(
+    text: &str,
+    tls_host: Option<&str>,
+    depth: usize,
+    expanded: &[(String, String)],
+    visiting: &mut Vec<String>,
+    out: &mut String,
+    depths: &mut Vec<usize>,
+) {
+    for line in text.split_terminator('\n') {
+        let line = line.trim_end_matches('\r');
+        if line == "." {
             break;
         }
-
         if line.is_empty() {
-            start += 1;
             continue;
         }
+        let mut chars = line.chars();
+        let typ = chars.next().unwrap_or('i');
+        let indent = "  ".repeat(depth);
+        if Type::from(typ).is_none() && !line.contains('\t') {
+            // keep banners whole
+            out.push_str(&format!("i{}{}\r\n", indent, line));
+        } else {
+            out.push_str(&format!("{}{}{}\r\n", typ, indent, chars.as_str()));
+        }
+        depths.push(depth);
+
+        let url = match parse_line(0, line) {
+            Some(span) if span.typ == Type::Menu => Line::new(&span, line, tls_host).url(),
+            _ => continue,
+        };
+        if depth >= MAX_EXPAND_DEPTH || visiting.contains(&url) {
+            continue;
+        }
+        if let Some((_, child)) = expanded.iter().find(|(u, _)| *u == url) {
+            visiting.push(url);
+            nest(child, tls_host, depth + 1, expanded, visiting, out, depths);
+            visiting.pop();
+        }
+    }
+}
 
-        if let Some(mut span) = parse_line(start, &raw) {
-            if span.text_len() > longest {
-                longest = span.text_len();
+/// How `dump_links()` writes out each link.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum LinkFormat {
+    /// Tab separated type, name, host, port, selector, and URL.
+    #[default]
+    Tsv,
+    /// One JSON object per line, with the same fields.
+    Json,
+}
+
+/// Fetch the menu at `url` and list its links in `format`, one per
+/// line, for scripts and other programs. It's fetched and parsed
+/// according to `config`, like it would be in the UI.
+pub fn dump_links(url: &str, format: LinkFormat, config: Config) -> Result<String> {
+    if gopher::type_for_url(url) != Type::Menu {
+        return Err(error!("Not a Gopher menu: {}", url));
+    }
+    let (tls, tor) = {
+        let config = config.read().unwrap();
+        (config.tls, config.tor)
+    };
+    let (_, response) = gopher::fetch_url(url, tls, tor)?;
+    let menu = parse(url, gopher::response_to_string(&response), config);
+    let mut out = String::new();
+    for line in (0..menu.links.len()).filter_map(|i| menu.link(i)) {
+        let (sel, host, port) = line.fields();
+        let typ = line.typ.to_char().to_string();
+        let fields = [
+            ("type", typ.as_str()),
+            ("name", line.text()),
+            ("host", host),
+            ("port", port),
+            ("selector", sel),
+            ("url", &line.url()),
+        ];
+        match format {
+            LinkFormat::Tsv => {
+                let values: Vec<_> = fields.iter().map(|(_, v)| *v).collect();
+                out.push_str(&values.join("\t"));
             }
-            if span.typ.is_link() {
-                span.link = links.len();
-                links.push(spans.len());
+            LinkFormat::Json => {
+                let pairs: Vec<_> = fields
+                    .iter()
+                    .map(|(k, v)| format!("\"{}\":{}", k, json_string(v)))
+                    .collect();
+                out.push_str(&format!("{{{}}}", pairs.join(",")));
             }
-            spans.push(span);
         }
+        out.push('\n');
+    }
+    Ok(out)
+}
 
-        start += line.len() + 1;
+/// `s` as a quoted JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
     }
+    out.push('"');
+    out
+}
 
-    Menu {
+/// Parse gopher response into a Menu object.
+pub fn parse(url: &str, raw: String, config: Config) -> Menu {
+    let mut menu = parse_lazily(url, raw, config);
+    menu.parse_all();
+    menu
+}
+
+/// Like `parse()`, but only the first `PARSE_CHUNK` lines are parsed.
+/// The rest is parsed as it's needed.
+fn parse_lazily(url: &str, raw: String, config: Config) -> Menu {
+    let mut menu = Menu {
         url: url.into(),
-        spans,
-        links,
-        longest,
+        spans: vec![],
+        links: vec![],
+        longest: 0,
         raw,
+        parsed: 0,
+        total_links: 0,
+        all_links: OnceCell::new(),
         input: String::new(),
         link: 0,
         mode: Default::default(),
         offset: 0,
         searching: false,
+        filtering: false,
+        filter: String::new(),
+        unfiltered: None,
+        filtered: vec![],
+        hints: vec![],
+        hint: String::new(),
         size: (0, 0),
         tls: false,
         tor: false,
         wide: false,
         scroll: 0,
+        new_links: vec![],
+        show_selectors: false,
+        summary: String::new(),
+        show_info: false,
+        source: None,
+        raw_response: None,
+        expanded: vec![],
+        depths: vec![],
+        find: String::new(),
+        found: None,
         config,
+    };
+    // grids are laid out based on every link
+    if menu.config.read().unwrap().columns == 1 {
+        menu.parse_until(PARSE_CHUNK, 0);
+        menu.measure_rest();
+    } else {
+        menu.parse_all();
     }
+    menu.summary = menu.link_summary();
+    menu
+}
+
+/// The type of each line in a Gopher menu, without parsing the rest
+/// of it.
+fn line_types(raw: &str) -> impl Iterator<Item = Type> + '_ {
+    raw.split_terminator('\n')
+        .take_while(|line| *line != "." && *line != ".\r")
+        .filter(|line| !line.is_empty())
+        .filter_map(line_type)
+}
+
+/// The type of a line in a Gopher menu. Unknown types are treated as
+/// binaries, unless there's no tab, since some servers send a banner
+/// of plain text before the menu.
+fn line_type(line: &str) -> Option<Type> {
+    let first = line.chars().next()?;
+    Some(match Type::from(first) {
+        Some(typ) => typ,
+        None if !line.contains('\t') => Type::Info,
+        None => Type::Binary,
+    })
+}
+
+/// The line of a Gopher menu starting at byte `start` of `raw`, if
+/// it's not blank, and how many bytes it takes up. None once the
+/// response is over.
+fn next_line(raw: &str, start: usize) -> Option<(Option<LineSpan>, usize)> {
+    let rest = raw.get(start..).filter(|rest| !rest.is_empty())?;
+    let len = rest.find('\n').map_or(rest.len(), |i| i + 1);
+    let line = rest[..len].trim_end_matches('\n');
+
+    // Check for Gopher's weird "end of response" message.
+    if line == ".\r" || line == "." {
+        return None;
+    }
+    let span = if line.is_empty() {
+        None
+    } else {
+        parse_line(start, raw)
+    };
+    Some((span, len))
 }
 
 /// Parses a single line from a Gopher menu into a `LineSpan` struct.
@@ -1003,7 +2289,13 @@ pub fn parse_line(start: usize, raw: &str) -> Option<LineSpan> {
     } else {
         end
     };
-    let typ = Type::from(line.chars().next()?).unwrap_or(Type::Binary);
+    let first = line.chars().next()?;
+    let typ = line_type(line)?;
+    let text_start = if typ == Type::Info && first != 'i' {
+        start
+    } else {
+        start + first.len_utf8()
+    };
 
     let mut truncated_len = if text_end - start > MAX_COLS {
         MAX_COLS + 1
@@ -1040,7 +2332,7 @@ pub fn parse_line(start: usize, raw: &str) -> Option<LineSpan> {
     }
 
     Some(LineSpan {
-        start,
+        text_start,
         end,
         text_end,
         truncated_len,
@@ -1050,9 +2342,83 @@ pub fn parse_line(start: usize, raw: &str) -> Option<LineSpan> {
     })
 }
 
+/// Cut `text` down to `width` columns on screen, ending it with `…`
+/// if anything was cut, and say how many columns it takes up. Color
+/// codes don't take up any room and wide characters take up two.
+fn fit_width(text: &str, width: usize) -> (Cow<'_, str>, usize) {
+    let mut used = 0;
+    let mut cut = None;
+    let mut is_color = false;
+    for (i, c) in text.char_indices() {
+        if is_color {
+            is_color = c != 'm';
+            continue;
+        } else if c == '\x1b' && text[i + 1..].starts_with('[') {
+            is_color = true;
+            continue;
+        }
+        let w = utils::char_width(c);
+        if cut.is_none() && used + w >= width {
+            cut = Some((i, used));
+        }
+        if used + w > width {
+            return match cut {
+                Some((i, used)) if width > 0 => (format!("{}…", &text[..i]).into(), used + 1),
+                _ => ("".into(), 0),
+            };
+        }
+        used += w;
+    }
+    (text.into(), used)
+}
+
+/// Turn `*bold*` and `_underline_` markers in an info line into
+/// the real thing. Only markers hugging a word at each end count, so
+/// ASCII art, `snake_case`, and `2*3*4` are left alone, as is any
+/// line that already has its own escape codes.
+fn emphasize(text: &str) -> Cow<'_, str> {
+    if text.contains('\x1b') || !text.contains(['*', '_']) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev = None;
+    while let Some(c) = rest.chars().next() {
+        let style = match c {
+            '*' => Some(("\x1b[1m", "\x1b[22m")),
+            '_' => Some(("\x1b[4m", "\x1b[24m")),
+            _ => None,
+        };
+        if let Some((on, off)) = style.filter(|_| prev.is_none_or(char::is_whitespace)) {
+            if let Some(end) = rest[1..].find(c) {
+                let inner = &rest[1..end + 1];
+                let after = rest[end + 2..].chars().next();
+                let hugs = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+                if hugs(inner.chars().next())
+                    && hugs(inner.chars().last())
+                    && inner.chars().any(char::is_alphabetic)
+                    && after.is_none_or(|a| a.is_whitespace() || ".,;:!?)".contains(a))
+                {
+                    out.push_str(on);
+                    out.push_str(inner);
+                    out.push_str(off);
+                    rest = &rest[end + 2..];
+                    prev = Some(c);
+                    continue;
+                }
+            }
+        }
+        out.push(c);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    Cow::Owned(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ui::ColorMode;
 
     macro_rules! parse {
         ($s:expr) => {
@@ -1060,6 +2426,244 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_reparse_clamps_selection() {
+        let mut menu = parse!(
+            "1one\t/1\thost\t70\r\n1two\t/2\thost\t70\r\n1three\t/3\thost\t70\r\n1four\t/4\thost\t70\r\n"
+        );
+        menu.term_size(80, 40);
+        menu.action_select_link(3);
+        assert_eq!(menu.link, 3);
+
+        // fewer links, and the selected one is gone
+        menu.reparse("iNews\r\n1one\t/1\thost\t70\r\n1two\t/2\thost\t70\r\n".into());
+        assert_eq!(menu.links.len(), 2);
+        assert_eq!(menu.link, 0);
+        assert!(menu.link(menu.link).is_some());
+        assert!(menu.render().contains("one"));
+
+        // the selected link moved
+        menu.action_select_link(1);
+        menu.reparse("1zero\t/0\thost\t70\r\n1one\t/1\thost\t70\r\n1two\t/2\thost\t70\r\n".into());
+        assert_eq!(menu.link, 2);
+        assert_eq!(menu.link(menu.link).unwrap().text(), "two");
+
+        menu.reparse("iNothing here\r\n".into());
+        assert_eq!(menu.link, 0);
+        assert!(menu.link(menu.link).is_none());
+        assert_eq!(menu.offset, 0);
+        menu.render();
+    }
+
+    #[test]
+    fn test_hint_labels() {
+        assert_eq!(hint_labels(3, "asdf"), vec!["a", "s", "d"]);
+        assert_eq!(hint_labels(4, "asd"), vec!["aa", "as", "ad", "sa"]);
+        assert_eq!(hint_labels(10, "as").len(), 10);
+        assert_eq!(hint_labels(10, "as")[9], "saas");
+        assert!(hint_labels(3, "a").is_empty());
+    }
+
+    #[test]
+    fn test_hints() {
+        let mut raw = String::from("iLinks\r\n");
+        for i in 0..12 {
+            raw.push_str(&format!("1Link {}\t/{}\thost\t70\r\n", i, i));
+        }
+        let mut menu = parse!(&raw);
+        menu.term_size(80, 40);
+        assert!(matches!(menu.respond(Key::Char('\'')), Action::List(_)));
+        // 12 links need two letters from "asdfghjkl"
+        assert!(menu.render().contains("sd. "));
+        assert!(matches!(menu.respond(Key::Char('s')), Action::List(_)));
+        let out = menu.render();
+        assert!(!out.contains("aa. "));
+        assert!(out.contains("sa. "));
+        // "sd" is the 12th label
+        match menu.respond(Key::Char('d')) {
+            Action::List(actions) => match &actions[1] {
+                Action::Open(title, url) => {
+                    assert_eq!(title, "Link 11");
+                    assert_eq!(url, "gopher://host/1/11");
+                }
+                _ => panic!("expected Open"),
+            },
+            _ => panic!("expected List"),
+        }
+        assert_eq!(menu.link, 11);
+        assert!(menu.hints.is_empty());
+
+        // anything else cancels
+        menu.respond(Key::Char('\''));
+        menu.respond(Key::Esc);
+        assert!(menu.hints.is_empty());
+        menu.respond(Key::Char('\''));
+        assert!(
+            matches!(menu.respond(Key::Char('q')), Action::List(a) if matches!(a[1], Action::Error(_)))
+        );
+        assert!(menu.hints.is_empty());
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut menu = parse!(
+            "iLinks\r\n1Apples\t/a\thost\t70\r\n1Bananas\t/b\thost\t70\r\n0About apps\t/c\thost\t70\r\n1Cherries\t/d\thost\t70\r\n"
+        );
+        menu.term_size(80, 40);
+        menu.action_select_link(2);
+        menu.respond(Key::Char('F'));
+        assert!(menu.filtering);
+        menu.respond(Key::Char('a'));
+        menu.respond(Key::Char('p'));
+        assert_eq!(menu.links.len(), 2);
+        let out = menu.render();
+        assert!(out.contains("Apples"));
+        assert!(out.contains("About apps"));
+        assert!(!out.contains("Bananas"));
+        assert!(!out.contains("Links"));
+        assert!(menu.render_input().contains("(2/4)"));
+
+        // the selection stays on "About apps", now link 2 of 2
+        assert_eq!(menu.link, 1);
+        assert_eq!(menu.real_link(menu.link), 2);
+
+        // navigate and open within the filtered links
+        menu.respond(Key::Char('\n'));
+        assert!(!menu.filtering);
+        menu.respond(Key::Up);
+        match menu.respond(Key::Char('\n')) {
+            Action::Open(title, url) => {
+                assert_eq!(title, "Apples");
+                assert_eq!(url, "gopher://host/1/a");
+            }
+            _ => panic!("expected Open"),
+        }
+        assert!(
+            matches!(menu.respond(Key::Char('1')), Action::Open(_, url) if url == "gopher://host/1/a")
+        );
+
+        // Esc brings everything back, keeping the selection
+        menu.respond(Key::Esc);
+        assert!(menu.filter.is_empty());
+        assert_eq!(menu.links.len(), 4);
+        assert_eq!(menu.link, 0);
+        assert!(menu.render().contains("Bananas"));
+
+        // fuzzy
+        menu.respond(Key::Char('F'));
+        menu.respond(Key::Char('c'));
+        menu.respond(Key::Char('h'));
+        menu.respond(Key::Char('s'));
+        assert_eq!(menu.links.len(), 1);
+        assert_eq!(menu.link(menu.link).unwrap().text(), "Cherries");
+        menu.respond(Key::Esc);
+        assert_eq!(menu.links.len(), 4);
+        assert!(!menu.filtering);
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut menu = parse!("1one\t/1\thost\t70\r\n1two\t/2\thost\t70\r\n");
+        menu.term_size(80, 40);
+        assert!(
+            matches!(menu.respond(Key::Char('x')), Action::Expand(url) if url == "gopher://host/1/1")
+        );
+
+        menu.expand(
+            "gopher://host/1/1",
+            "iInside\r\n1deeper\t/1/a\thost\t70\r\n.\r\n0ignored\t/\thost\t70\r\n".into(),
+        );
+        let out = menu.render();
+        assert!(out.contains("  Inside"));
+        assert!(out.contains("  deeper"));
+        assert!(!out.contains("ignored"));
+        assert_eq!(menu.links.len(), 3);
+        assert_eq!(menu.raw(), "1one\t/1\thost\t70\r\n1two\t/2\thost\t70\r\n");
+
+        // move into the submenu
+        menu.action_down();
+        assert_eq!(menu.link(menu.link).unwrap().url(), "gopher://host/1/1/a");
+        assert!(matches!(menu.respond(Key::Char('x')), Action::Expand(_)));
+
+        // collapse it again
+        menu.action_up();
+        assert!(matches!(menu.respond(Key::Char('x')), Action::Redraw));
+        assert_eq!(menu.links.len(), 2);
+        assert!(!menu.render().contains("Inside"));
+    }
+
+    #[test]
+    fn test_gophers_links() {
+        let mut menu = Menu::from(
+            "gophers://host/1/",
+            "1Phlog\t/phlog\thost\t70\r\n1Elsewhere\t/\tother\t70\r\n0Ported\t/a.txt\tHOST\t7070\r\n"
+                .into(),
+            Config::default(),
+            true,
+        );
+        menu.term_size(80, 40);
+        assert_eq!(
+            menu.link_urls(),
+            vec![
+                "gophers://host/1/phlog",
+                "gopher://other",
+                "gophers://HOST:7070/0/a.txt"
+            ]
+        );
+        let url = match menu.respond(Key::Char('\n')) {
+            Action::Open(_, url) => url,
+            _ => panic!("expected Open"),
+        };
+        assert_eq!(url, "gophers://host/1/phlog");
+
+        // one level deep, its links to the same host are still TLS
+        let mut phlog = Menu::from(
+            &url,
+            "0Post\t/phlog/post.txt\thost\t70\r\n".into(),
+            Config::default(),
+            true,
+        );
+        phlog.term_size(80, 40);
+        assert!(
+            matches!(phlog.respond(Key::Char('\n')), Action::Open(_, url) if url == "gophers://host/0/phlog/post.txt")
+        );
+
+        // and so are submenus expanded inline
+        menu.expand(&url, "1Older\t/phlog/old\thost\t70\r\n".into());
+        assert_eq!(menu.link(1).unwrap().url(), "gophers://host/1/phlog/old");
+    }
+
+    #[test]
+    fn test_dump_links() {
+        let port = gopher::serve(include_str!("../tests/dump-links.gph"));
+        let url = format!("gopher://127.0.0.1:{}/1/", port);
+
+        let tsv = dump_links(&url, LinkFormat::Tsv, Config::default()).unwrap();
+        let lines: Vec<_> = tsv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "1\tPhlog\tphkt.io\t70\t/phlog\tgopher://phkt.io/1/phlog"
+        );
+        assert_eq!(
+            lines[1],
+            "0\t\"Quoted\" notes\tsdf.org\t7070\t/notes.txt\tgopher://sdf.org:7070/0/notes.txt"
+        );
+        assert_eq!(
+            lines[3],
+            "h\tWebsite\tphkt.io\t70\tURL:https://phkt.io\thttps://phkt.io"
+        );
+
+        let json = dump_links(&url, LinkFormat::Json, Config::default()).unwrap();
+        assert_eq!(
+            json.lines().nth(1).unwrap(),
+            r#"{"type":"0","name":"\"Quoted\" notes","host":"sdf.org","port":"7070","selector":"/notes.txt","url":"gopher://sdf.org:7070/0/notes.txt"}"#
+        );
+
+        let text_url = format!("gopher://127.0.0.1:{}/0/", port);
+        assert!(dump_links(&text_url, LinkFormat::Tsv, Config::default()).is_err());
+    }
+
     #[test]
     fn test_simple_menu() {
         let menu = parse!(
@@ -1094,7 +2698,7 @@ i---------------------------------------------------------
         assert_eq!(menu.lines().nth(5).unwrap().text(), "-----------");
         assert_eq!(
             menu.lines().nth(6).unwrap().url(),
-            "telnet://bbs.impakt.net:6502"
+            "telnet://a@bbs.impakt.net:6502"
         );
         assert_eq!(
             menu.lines().nth(7).unwrap().url(),
@@ -1103,6 +2707,29 @@ i---------------------------------------------------------
         assert_eq!(menu.lines().nth(8).unwrap().text(), "-----------");
     }
 
+    #[test]
+    fn test_ipv6_links() {
+        let menu = parse!("1Home\t/\t2001:db8::1\t70\r\n0About\t/about.txt\t::1\t7070\r\n");
+        assert_eq!(menu.link(0).unwrap().url(), "gopher://[2001:db8::1]");
+        assert_eq!(
+            menu.link(1).unwrap().url(),
+            "gopher://[::1]:7070/0/about.txt"
+        );
+        let url = menu.link(1).unwrap().url();
+        let u = gopher::parse_url(&url);
+        assert_eq!((u.host, u.port, &*u.sel), ("::1", "7070", "/about.txt"));
+    }
+
+    #[test]
+    fn test_search_links() {
+        // the way bookmarks.gph saves a search
+        let sel = gopher::menu_selector("/find what\tgopher");
+        let menu = parse!(format!("7Find\t{}\thost\t70\r\n", sel));
+        let url = menu.link(0).unwrap().url();
+        assert_eq!(url, "gopher://host/7/find%20what?gopher");
+        assert_eq!(gopher::parse_url(&url).sel, "/find what\tgopher");
+    }
+
     #[test]
     fn test_no_path() {
         let menu = parse!("1Circumlunar Space		circumlunar.space	70");
@@ -1203,4 +2830,603 @@ i	Err	bitreich.org	70
                 .to_string()
         );
     }
+
+    #[test]
+    fn test_search_prompt() {
+        let mut menu = parse!("7Veronica\t/v2/vs\tgopher.floodgap.com\t70\r\n");
+        menu.term_size(80, 24);
+        let mut search = |query: &str| match menu.action_open() {
+            Action::Prompt(prompt, fun) => {
+                assert_eq!(prompt, "Veronica> ");
+                fun(query.to_string())
+            }
+            _ => panic!("expected a prompt"),
+        };
+        assert!(matches!(search("  "), Action::None));
+        assert!(matches!(
+            search(" phetch "),
+            Action::Open(title, url)
+                if title == "Veronica> phetch"
+                    && url == "gopher://gopher.floodgap.com/7/v2/vs?phetch"
+        ));
+    }
+
+    #[test]
+    fn test_show_selectors() {
+        let mut menu = parse!(
+            "iwelcome\r\n1phlog\t/phlog\tphkt.io\t70\r\n0today's post\t/phlog/a-very-long-selector-for-today.txt\tsdf.org\t7070\r\n"
+        );
+        menu.mode = ui::Mode::Print;
+        menu.term_size(200, 40);
+        assert!(!menu.render().contains("phkt.io"));
+
+        assert!(matches!(menu.respond(Key::Char('U')), Action::Redraw));
+        let out = menu.render();
+        let rows = out.split("\r\n").collect::<Vec<_>>();
+        assert!(!rows[0].contains("localhost")); // info lines stay as-is
+        assert!(rows[1].contains("phlog") && rows[1].contains("1 phkt.io:70 /phlog"));
+        assert!(rows[2].contains("0 sdf.org:7070 /phlog/a-very-long-selector-for-today.txt"));
+
+        // long selectors get cut off at the edge of the screen
+        menu.term_size(70, 40);
+        let out = menu.render();
+        let rows = out.split("\r\n").collect::<Vec<_>>();
+        assert!(rows[2].contains("0 sdf.org:7070 /") && rows[2].contains('…'));
+        assert!(!rows[2].contains("today.txt"));
+
+        menu.respond(Key::Char('U'));
+        assert!(!menu.render().contains("phkt.io"));
+    }
+
+    #[test]
+    fn test_raw_bytes() {
+        let bytes = b"i\xb0\xb1\xb2 BBS\r\n0About\t/about.txt\tbbs.example\t70\r\n";
+        let menu = Menu::from_bytes(
+            "gopher://bbs.example/1/",
+            bytes,
+            Encoding::CP437,
+            Config::default(),
+            false,
+        );
+        assert!(menu.raw().starts_with("i░▒▓ BBS"));
+        assert_eq!(menu.raw_bytes(), bytes);
+
+        // nothing to keep when decoding didn't change anything
+        let bytes = b"iBBS\r\n0About\t/about.txt\tbbs.example\t70\r\n";
+        let menu = Menu::from_bytes("", bytes, Encoding::UTF8, Config::default(), false);
+        assert!(menu.raw_response.is_none());
+        assert_eq!(menu.raw_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_find_info_lines() {
+        let mut src = String::new();
+        for i in 0..60 {
+            src.push_str(&format!("iplain line {}\r\n", i));
+            if i % 20 == 10 {
+                src.push_str("iWelcome to the Ärger hole\r\n");
+                src.push_str(&format!("1link {}\t/{}\tphkt.io\t70\r\n", i, i));
+            }
+        }
+        src.push_str("1Ärgernis\t/last\tphkt.io\t70\r\n");
+        let mut menu = parse!(src);
+        menu.term_size(80, 20);
+        let status = |action: Action| match action {
+            Action::List(list) => format!("{:?}", list[1]),
+            other => format!("{:?}", other),
+        };
+        let search = |menu: &mut Menu, query: &str| {
+            menu.respond(Key::Char('/'));
+            for c in query.chars() {
+                menu.respond(Key::Char(c));
+            }
+            menu.respond(Key::Char('\n'))
+        };
+
+        // no link is called "hole", so look through the info lines
+        assert_eq!(status(search(&mut menu, "HOLE")), "Status: Match 1 of 3");
+        assert!(!menu.searching);
+        assert_eq!(menu.found, Some(11));
+        assert!(menu.render().contains("Ärger \x1b[7mhole\x1b[27m"));
+
+        assert_eq!(status(menu.respond(Key::Char('n'))), "Status: Match 2 of 3");
+        assert_eq!(menu.found, Some(33));
+        assert_eq!(menu.offset, 33);
+        menu.respond(Key::Char('n'));
+        assert_eq!(status(menu.respond(Key::Char('n'))), "Status: Match 1 of 3");
+        assert_eq!(status(menu.respond(Key::Char('N'))), "Status: Match 3 of 3");
+
+        // a matching link gets selected
+        assert_eq!(status(menu.find("ärgernis")), "Status: Match 1 of 1");
+        assert_eq!(menu.link, 3);
+
+        // escape ends the search, and n moves down again
+        menu.respond(Key::Esc);
+        assert!(!menu.render().contains("\x1b[7m"));
+        assert!(menu.find.is_empty());
+        menu.respond(Key::Char('n'));
+
+        assert_eq!(
+            status(search(&mut menu, "nowhere")),
+            "Error: Not found: nowhere"
+        );
+        // links are still opened when they match
+        assert!(matches!(search(&mut menu, "link 30"), Action::Open(..)));
+    }
+
+    #[test]
+    fn test_backspace_edits_input() {
+        let mut src = String::new();
+        for i in 0..300 {
+            src.push_str(&format!("1link{}\t/{}\tphkt.io\t70\r\n", i, i));
+        }
+        let mut menu = parse!(src);
+        menu.term_size(80, 40);
+
+        // searching: backspace edits the query
+        menu.respond(Key::Char('/'));
+        for c in "link2".chars() {
+            menu.respond(Key::Char(c));
+        }
+        assert!(!matches!(menu.respond(Key::Backspace), Action::Keypress(_)));
+        assert_eq!(menu.input, "link");
+        menu.respond(Key::Backspace);
+        menu.respond(Key::Backspace);
+        assert_eq!(menu.input, "li");
+        assert!(menu.searching);
+        menu.respond(Key::Esc);
+        menu.respond(Key::Esc);
+        assert!(!menu.searching);
+
+        // link numbers: backspace edits the number
+        menu.respond(Key::Char('2'));
+        menu.respond(Key::Char('5'));
+        assert_eq!(menu.link, 24);
+        assert!(!matches!(menu.respond(Key::Backspace), Action::Keypress(_)));
+        assert_eq!(menu.input, "2");
+        assert_eq!(menu.link, 1);
+        assert!(!matches!(menu.respond(Key::Backspace), Action::Keypress(_)));
+        assert_eq!(menu.input, "");
+
+        // empty buffer: back to the UI to go back in history
+        assert!(matches!(
+            menu.respond(Key::Backspace),
+            Action::Keypress(Key::Backspace)
+        ));
+
+        // a typed number is forgotten once you move on
+        menu.respond(Key::Char('3'));
+        menu.respond(Key::Down);
+        assert!(matches!(
+            menu.respond(Key::Backspace),
+            Action::Keypress(Key::Backspace)
+        ));
+    }
+
+    #[test]
+    fn test_long_line() {
+        let mut menu = parse!(include_str!("../tests/long-line.gph"));
+        menu.mode = ui::Mode::Print;
+        let clear = terminal::ClearUntilNewline.to_string();
+        for cols in [80, 40] {
+            menu.term_size(cols, 24);
+            let out = menu.render();
+            let rows = out
+                .split("\r\n")
+                .map(utils::strip_colors)
+                .collect::<Vec<_>>();
+            for row in &rows[..5] {
+                let width = row
+                    .replace(&clear, "")
+                    .chars()
+                    .map(utils::char_width)
+                    .sum::<usize>();
+                assert!(width <= cols, "{} columns: {:?}", width, row);
+            }
+            assert!(rows[0].contains("Before the long line"));
+            assert!(rows[1].contains(" 1. A very long menu line that goes"));
+            assert!(rows[1].ends_with(&format!("…{}", clear)));
+            assert!(rows[2].contains(" 2. 日本語"));
+            assert!(rows[2].contains('…'));
+            assert!(rows[3].contains(" 3. After the long line"));
+            assert!(rows[4].contains("The end"));
+        }
+
+        // the cursor is still on the right row, and the popup shows
+        // the whole name
+        menu.action_down();
+        assert_eq!(menu.screen_coords(1), Some((1, 3)));
+        menu.show_info = true;
+        assert!(menu.render().contains("タイトル"));
+        assert_eq!(
+            fit_width("\x1b[1mbold\x1b[0m", 4),
+            ("\x1b[1mbold\x1b[0m".into(), 4)
+        );
+        assert_eq!(fit_width("bolder", 4), ("bol…".into(), 4));
+        assert_eq!(fit_width("日本語", 5), ("日本…".into(), 5));
+        assert_eq!(fit_width("日本語", 4), ("日…".into(), 3));
+    }
+
+    #[test]
+    fn test_web_tag() {
+        let mut menu = parse!(include_str!("../tests/web-links.gph"));
+        menu.mode = ui::Mode::Print;
+        menu.term_size(80, 40);
+        let rows = menu.render();
+        let rows = rows.split("\r\n").collect::<Vec<_>>();
+        assert!(rows[0].contains("phetch's webpage [www]"));
+        assert!(!rows[1].contains("[www]")); // regular gopher link
+        assert!(!rows[2].contains("[www]")); // URL: pointing back to gopher
+        assert!(rows[3].contains("ssh kiosk [www]"));
+        assert_eq!(
+            menu.link(0).unwrap().url(),
+            "https://github.com/xvxx/phetch"
+        );
+
+        menu.config.write().unwrap().web_tag = "🌐".into();
+        assert!(menu.render().contains("phetch's webpage 🌐"));
+        menu.config.write().unwrap().web_tag.clear();
+        assert!(!menu.render().contains("phetch's webpage "));
+    }
+
+    #[test]
+    fn test_gopher_plus() {
+        let mut menu = parse!(include_str!("../tests/gopher-plus.gph"));
+        menu.mode = ui::Mode::Print;
+        menu.term_size(80, 40);
+        assert_eq!(menu.links.len(), 4);
+        let urls: Vec<_> = menu.link_urls();
+        assert_eq!(
+            urls,
+            vec![
+                "gopher://gopher.example/1/phlog",
+                "gopher://gopher.example/0/about.txt",
+                "gopher://gopher.example:7070/7/search",
+                "gopher://gopher.example/9/archive.zip",
+            ]
+        );
+        let plus: Vec<_> = (0..4)
+            .map(|i| menu.link(i).unwrap().is_gopher_plus())
+            .collect();
+        assert_eq!(plus, vec![true, false, true, true]);
+        assert_eq!(
+            menu.link(0).unwrap().fields(),
+            ("/phlog", "gopher.example", "70")
+        );
+        assert!(!menu.render().contains("[+]"));
+
+        menu.config.write().unwrap().plus_tag = "[+]".into();
+        let rows = menu.render();
+        let rows = rows.split("\r\n").collect::<Vec<_>>();
+        assert!(rows[1].contains("Phlog [+]"));
+        assert!(!rows[2].contains("[+]"));
+        assert!(rows[3].contains("Search [+]"));
+    }
+
+    #[test]
+    fn test_lazy_parse_layout() {
+        let mut raw = String::new();
+        for i in 0..PARSE_CHUNK * 2 {
+            raw.push_str(&format!("0File {}\t/{}.txt\thost\t70\r\n", i, i));
+        }
+        raw.push_str("0A much, much longer name than the rest\t/long.txt\thost\t70\r\n");
+        let mut menu = Menu::from("gopher://host/1/", raw, Config::default(), false);
+        menu.term_size(80, 40);
+        assert!(!menu.fully_parsed());
+        // numbers and margins are worked out from the whole menu
+        let (width, indent) = (menu.number_width(), menu.indent());
+        assert_eq!(width, 4);
+        assert_eq!(menu.link_count(), PARSE_CHUNK * 2 + 1);
+        assert_eq!(menu.links().len(), PARSE_CHUNK * 2 + 1);
+        assert!(!menu.fully_parsed());
+        menu.respond(Key::Char('G'));
+        assert!(menu.fully_parsed());
+        assert_eq!((menu.number_width(), menu.indent()), (width, indent));
+    }
+
+    #[test]
+    fn test_lazy_parse() {
+        let mut raw = String::from("iA very big directory\r\n");
+        for i in 0..50_000 {
+            raw.push_str(&format!("0File {}\t/{}.txt\thost\t70\r\n", i, i));
+        }
+        raw.push_str(".\r\n");
+        let mut menu = Menu::from("gopher://host/1/", raw, Config::default(), false);
+        menu.term_size(80, 40);
+        let out = menu.render();
+        assert!(out.contains("File 0"));
+        assert!(!menu.fully_parsed());
+        assert!(menu.spans.len() <= PARSE_CHUNK);
+        assert_eq!(menu.summary, "50000 links: 50000 text");
+
+        // scrolling parses as it goes
+        for _ in 0..50 {
+            menu.respond(Key::PageDown);
+        }
+        assert!(menu.spans.len() > PARSE_CHUNK);
+        assert!(menu.render().contains(&format!("File {}", menu.link)));
+        assert!(!menu.fully_parsed());
+
+        // jumping to the end needs it all
+        menu.respond(Key::End);
+        assert!(menu.fully_parsed());
+        assert_eq!(menu.links.len(), 50_000);
+        assert_eq!(menu.link(menu.link).unwrap().text(), "File 49999");
+        assert_eq!(menu.link_urls().len(), 50_000);
+    }
+
+    #[test]
+    fn test_page_down() {
+        let mut src = String::new();
+        for i in 0..100 {
+            src.push_str(&format!("1link{}\t/{}\tphkt.io\t70\r\n", i, i));
+        }
+        let mut menu = parse!(src);
+        menu.term_size(80, 24);
+        menu.respond(Key::Char(' '));
+        assert_eq!(menu.offset, 23 - ui::PAGE_OVERLAP);
+        // the cursor comes along to the new screen
+        assert!(menu.is_visible(menu.link));
+        assert_eq!(menu.link, 21);
+        menu.respond(Key::PageUp);
+        assert_eq!(menu.offset, 0);
+        assert!(menu.is_visible(menu.link));
+        for _ in 0..10 {
+            menu.respond(Key::PageDown);
+        }
+        assert_eq!(menu.offset, menu.final_offset());
+        assert_eq!(menu.link, 99);
+        assert_eq!(ui::page_scroll(3), 3);
+    }
+
+    #[test]
+    fn test_jump_to_bottom() {
+        let mut menu = parse!("1one\t/1\tphkt.io\t70\r\n1two\t/2\tphkt.io\t70\r\n");
+        menu.term_size(80, 24);
+        menu.respond(Key::Char('G'));
+        assert_eq!(menu.link, 1);
+        assert_eq!(menu.offset, 0);
+        menu.respond(Key::Home);
+        assert_eq!(menu.link, 0);
+
+        let mut src = String::new();
+        for i in 0..100 {
+            src.push_str(&format!("1link{}\t/{}\tphkt.io\t70\r\n", i, i));
+        }
+        let mut menu = parse!(src);
+        menu.term_size(80, 20);
+        menu.respond(Key::Char('G'));
+        assert_eq!(menu.link, 99);
+        assert_eq!(menu.offset, 100 - 18);
+        assert!(menu.render().contains("link99"));
+
+        // G and End go to the same place
+        let (link, offset) = (menu.link, menu.offset);
+        menu.respond(Key::Home);
+        menu.respond(Key::End);
+        assert_eq!((menu.link, menu.offset), (link, offset));
+    }
+
+    #[test]
+    fn test_banner() {
+        let mut menu = parse!(include_str!("../tests/banner.gph"));
+        menu.mode = ui::Mode::Print;
+        menu.term_size(80, 40);
+        let rows = menu.render();
+        let rows = rows.split("\r\n").collect::<Vec<_>>();
+        assert!(rows[0].contains("Welcome to the Example Gopher Server!"));
+        assert!(rows[1].contains("====================================="));
+        assert!(rows[3].contains("Last updated 2020-06-01"));
+        assert!(!rows[0].contains("1."));
+        assert!(rows[5].contains("About this server"));
+        assert_eq!(menu.links.len(), 3);
+        assert_eq!(menu.link(0).unwrap().text(), "About this server");
+        assert_eq!(
+            menu.link(0).unwrap().url(),
+            "gopher://example.com/0/about.txt"
+        );
+        assert_eq!(
+            menu.link(2).unwrap().url(),
+            "gopher://example.com/9/archive.zip"
+        );
+        assert_eq!(menu.line(3).unwrap().typ, Type::Info);
+    }
+
+    #[test]
+    fn test_info_popup() {
+        let mut menu = parse!(
+            "i-- links --\r\n1A link with a very long name that won't fit\t/some/long/selector\tphkt.io\t7070\r\n"
+        );
+        menu.term_size(40, 20);
+        assert!(!menu.render().contains("Selector:"));
+
+        assert!(matches!(menu.respond(Key::Char('I')), Action::Redraw));
+        let out = menu.render();
+        assert!(out.contains("│ Name:     A link with a very long  │"));
+        assert!(out.contains("│           name that won't fit      │"));
+        assert!(out.contains("│ Type:     Menu (1)                 │"));
+        assert!(out.contains("│ Port:     7070                     │"));
+        assert!(out.contains("│ Selector: /some/long/selector      │"));
+        assert!(out.contains("│ URL:      gopher://phkt.io:7070/1/ │"));
+        assert!(out.contains("│           some/long/selector       │"));
+
+        // any key closes it without doing anything else
+        assert!(matches!(menu.respond(Key::Down), Action::Redraw));
+        assert!(!menu.show_info);
+        assert!(!menu.render().contains("Selector:"));
+        assert_eq!(menu.link, 0);
+    }
+
+    #[test]
+    fn test_emphasis() {
+        let mut menu = parse!(include_str!("../tests/emphasis.gph"));
+        menu.mode = ui::Mode::Print;
+        menu.term_size(80, 40);
+        assert!(!menu.render().contains("\x1b[1m"));
+
+        menu.config.write().unwrap().emphasis = true;
+        let rows = menu.render();
+        let rows = rows.split("\r\n").collect::<Vec<_>>();
+        assert!(rows[0].contains("A \x1b[1mreally\x1b[22m \x1b[4mgood\x1b[24m post."));
+        assert!(rows[1].contains("Read \x1b[1mthe whole thing\x1b[22m, twice"));
+        assert!(rows[2].contains("*--*--* _-_-_ *"));
+        assert!(rows[3].contains("snake_case_name and 2*3*4"));
+        assert!(rows[4].contains("*not links*")); // links keep their markers
+        assert!(menu.raw().contains("A *really* _good_ post."));
+    }
+
+    #[test]
+    fn test_tiny_terminal() {
+        let mut src = String::new();
+        for i in 0..20 {
+            src.push_str(&format!("1link{}\t/{}\tphkt.io\t70\r\n", i, i));
+            src.push_str("iinfo\r\n");
+        }
+        for rows in 0..3 {
+            let mut menu = parse!(src);
+            menu.term_size(20, rows);
+            let out = menu.render();
+            assert!(out.contains("link0"));
+            assert!(!out.contains("link1\x1b"));
+            for _ in 0..10 {
+                menu.respond(Key::Down);
+                menu.respond(Key::PageDown);
+                menu.render();
+            }
+            for _ in 0..10 {
+                menu.respond(Key::Up);
+                menu.respond(Key::PageUp);
+            }
+            assert!(menu.render().contains("link0"));
+        }
+
+        // grids degrade the same way
+        let mut menu = parse!(src.replace("iinfo\r\n", ""));
+        menu.config.write().unwrap().columns = 2;
+        menu.term_size(40, 1);
+        let out = menu.render();
+        assert!(out.contains("link0") && out.contains("link1"));
+        assert!(!out.contains("link2"));
+        menu.respond(Key::Right);
+        menu.respond(Key::Down);
+        menu.render();
+    }
+
+    #[test]
+    fn test_render_with() {
+        let mut menu = parse!(
+            "iphetch\t\t\t\r\n1Releases\t/releases\tphkt.io\t70\r\n0README\t/README\tphkt.io\t70\r\n"
+        );
+
+        assert_eq!(
+            menu.render_with(40, 4, ColorMode::Color),
+            "               \x1b[93mphetch\x1b[0m\x1b[K\r\n         \x1b[97;1m*\x1b[0m \x1b[95m 1. \x1b[0m\x1b[94mReleases\x1b[0m\x1b[K\r\n           \x1b[95m 2. \x1b[0m\x1b[96mREADME\x1b[0m\x1b[K\r\n\x1b[J"
+        );
+        assert_eq!(
+            menu.render_with(40, 4, ColorMode::Mono),
+            "               phetch\x1b[K\r\n         *  1. Releases\x1b[K\r\n            2. README\x1b[K\r\n\x1b[J"
+        );
+        assert_eq!(
+            menu.render_with(12, 2, ColorMode::Mono),
+            "      phetch\x1b[K\r\n\x1b[J"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let menu = parse!(
+            "iwelcome\r\n1one\t/1\tphkt.io\r\n1two\t/2\tphkt.io\r\n0readme\t/0\tphkt.io\r\nhweb\tURL:https://phkt.io\tphkt.io\r\n1three\t/3\tphkt.io\r\n"
+        );
+        assert_eq!(menu.summary().unwrap(), "5 links: 3 menus, 1 text, 1 web");
+        let menu = parse!("9file.zip\t/f.zip\tphkt.io\r\n");
+        assert_eq!(menu.summary().unwrap(), "1 link: 1 download");
+        let menu = parse!("ijust info\r\n");
+        assert_eq!(menu.summary().unwrap(), "No links");
+    }
+
+    #[test]
+    fn test_new_links_marker() {
+        let mut menu = parse!("1old\t/old\tphkt.io\t70\r\n1new\t/new\tphkt.io\t70\r\n");
+        menu.mode = ui::Mode::Print;
+        menu.term_size(80, 40);
+        let seen = ["gopher://phkt.io/1/old".to_string()]
+            .iter()
+            .cloned()
+            .collect();
+        menu.new_links = crate::seen::new_links(&seen, &menu.link_urls());
+        assert_eq!(menu.new_links, vec![1]);
+
+        let out = menu.render();
+        let rows = out.split("\r\n").collect::<Vec<_>>();
+        assert!(!rows[0].contains('+'));
+        assert!(rows[1].contains('+'));
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let raw = (1..=11)
+            .map(|i| format!("1item{}\t/{}\tphkt.io\t70\r\n", i, i))
+            .collect::<String>();
+        let config = Config::default();
+        config.write().unwrap().columns = 3;
+        let mut menu = parse("test", raw, config);
+        menu.term_size(80, 40);
+
+        assert_eq!(menu.columns(), 3);
+        assert_eq!(menu.row_count(), 4);
+
+        menu.mode = ui::Mode::Print;
+        let out = menu.render();
+        let rows = out.split("\r\n").collect::<Vec<_>>();
+        assert!(rows[0].contains(" 1. ") && rows[0].contains(" 2. ") && rows[0].contains(" 3. "));
+        assert!(rows[1].contains(" 4. ") && rows[1].contains(" 6. "));
+        assert!(rows[3].contains("10. ") && rows[3].contains("11. "));
+        assert!(!rows[3].contains("12. "));
+        // the first column lines up
+        assert_eq!(rows[0].find("item1"), rows[1].find("item4"));
+
+        menu.process_key(Key::Down);
+        assert_eq!(menu.link, 3);
+        menu.process_key(Key::Right);
+        assert_eq!(menu.link, 4);
+        menu.process_key(Key::Up);
+        assert_eq!(menu.link, 1);
+        menu.process_key(Key::Left);
+        assert_eq!(menu.link, 0);
+        assert!(matches!(
+            menu.process_key(Key::Left),
+            Action::Keypress(Key::Left)
+        ));
+
+        // last row is short, so down lands on the final link
+        menu.action_select_link(8);
+        menu.process_key(Key::Down);
+        assert_eq!(menu.link, 10);
+    }
+
+    #[test]
+    fn test_grid_needs_short_links_only() {
+        let config = Config::default();
+        config.write().unwrap().columns = 3;
+        let mut menu = parse(
+            "test",
+            "iheader\r\n1one\t/\tphkt.io\t70\r\n1two\t/\tphkt.io\t70\r\n".into(),
+            config.clone(),
+        );
+        menu.term_size(80, 40);
+        assert_eq!(menu.columns(), 1);
+
+        // auto mode only kicks in for long lists
+        config.write().unwrap().columns = 0;
+        let mut menu = parse("test", "1one\t/\tphkt.io\t70\r\n".into(), config.clone());
+        menu.term_size(80, 40);
+        assert_eq!(menu.columns(), 1);
+
+        let raw = (1..=100)
+            .map(|i| format!("1item{}\t/{}\tphkt.io\t70\r\n", i, i))
+            .collect::<String>();
+        let mut menu = parse("test", raw, config);
+        menu.term_size(80, 40);
+        assert_eq!(menu.columns(), 3);
+    }
 }
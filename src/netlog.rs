@@ -0,0 +1,193 @@
+//! An optional log of every Gopher request phetch makes: what was
+//! asked for, how many bytes went each way, how long it took, and
+//! what went wrong. Handy for bug reports. It's off unless
+//! `log_requests yes` is set in phetch.conf or `PHETCH_LOG` is set in
+//! the environment, and lives in `LOG_FILE` in the phetchdir. It's
+//! never shown in the UI.
+
+use crate::{clock, phetchdir, Error};
+#[cfg(test)]
+use std::cell::RefCell;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Where requests are logged, in the phetchdir.
+pub const LOG_FILE: &str = "requests.log";
+
+/// Is logging on? Checked before doing any work.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    /// The log file, and whether to hide search queries.
+    static ref LOG: Mutex<Option<(PathBuf, bool)>> = Mutex::new(None);
+    /// Passwords typed this session, which are never logged.
+    static ref SECRETS: Mutex<Vec<String>> = Mutex::new(vec![]);
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Tests log to their own file, so they only see their own
+    /// requests.
+    static TEST_LOG: RefCell<Option<(PathBuf, bool)>> = const { RefCell::new(None) };
+}
+
+/// Turn logging on if the config or `PHETCH_LOG` asks for it and the
+/// phetchdir exists.
+pub fn init(enabled: bool, redact: bool) {
+    let from_env = std::env::var("PHETCH_LOG").is_ok_and(|v| !v.is_empty() && v != "0");
+    if enabled || from_env {
+        if let Ok(dir) = phetchdir::path() {
+            enable(dir.join(LOG_FILE), redact);
+        }
+    }
+}
+
+/// Log to `path`. Search queries are left out if `redact` is set.
+pub fn enable(path: PathBuf, redact: bool) {
+    set_sink(Some((path, redact)));
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stop logging.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+    set_sink(None);
+}
+
+/// Is logging on? In tests, it's up to each thread.
+fn enabled() -> bool {
+    cfg!(test) || ENABLED.load(Ordering::Relaxed)
+}
+
+/// The log file and whether to hide search queries, if logging is on.
+fn sink() -> Option<(PathBuf, bool)> {
+    #[cfg(test)]
+    return TEST_LOG.with(|log| log.borrow().clone());
+    #[cfg(not(test))]
+    LOG.lock().unwrap().clone()
+}
+
+/// Log to `sink`, or nowhere.
+fn set_sink(sink: Option<(PathBuf, bool)>) {
+    #[cfg(test)]
+    TEST_LOG.with(|log| *log.borrow_mut() = sink);
+    #[cfg(not(test))]
+    {
+        *LOG.lock().unwrap() = sink;
+    }
+}
+
+/// Never log `secret` as a search query, even if queries are logged.
+pub fn hide(secret: &str) {
+    let mut secrets = SECRETS.lock().unwrap();
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// Log a request for `selector` on `host:port`: `sent` bytes out,
+/// and either the size of the response or what went wrong.
+pub fn request(
+    kind: &str,
+    host: &str,
+    port: &str,
+    selector: &str,
+    sent: usize,
+    received: Result<usize, &Error>,
+    elapsed: Duration,
+) {
+    if !enabled() {
+        return;
+    }
+    let redact = sink().is_some_and(|(_, r)| r);
+    let selector = logged_selector(selector, redact);
+    let ms = elapsed.as_millis();
+    let result = match received {
+        Ok(bytes) => format!("got {} bytes in {}ms", bytes, ms),
+        Err(e) => format!("failed after {}ms: {}", ms, e),
+    };
+    write(&format!(
+        "{} {}:{} {}: sent {} bytes, {}",
+        kind, host, port, selector, sent, result
+    ));
+}
+
+/// Log an error shown in the status line.
+pub fn error(msg: &str) {
+    if enabled() {
+        write(&format!("error: {}", msg));
+    }
+}
+
+/// Append `line` to the log, stamped with the time. Failing to log
+/// isn't worth bothering anyone about.
+fn write(line: &str) {
+    let path = match sink() {
+        Some((path, _)) => path,
+        None => return,
+    };
+    let secs = clock::unix_secs();
+    if let Ok(mut file) = OpenOptions::new().append(true).create(true).open(path) {
+        let _ = writeln!(file, "{} {}", secs, line.replace(['\r', '\n'], " "));
+    }
+}
+
+/// `selector` the way it goes in the log: without its search query
+/// if `redact` is set or the query is a password.
+fn logged_selector(selector: &str, redact: bool) -> String {
+    let secret = selector.find(['\t', '?']).is_some_and(|i| {
+        SECRETS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|s| *s == selector[i + 1..])
+    });
+    if redact || secret {
+        redact_query(selector)
+    } else {
+        selector.to_string()
+    }
+}
+
+/// `selector` without its search query, if it has one.
+fn redact_query(selector: &str) -> String {
+    match selector.find(['\t', '?']) {
+        Some(i) => format!("{}?[redacted]", &selector[..i]),
+        None => selector.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_query() {
+        assert_eq!(redact_query("/about.txt"), "/about.txt");
+        assert_eq!(redact_query("/v2/vs?gopher"), "/v2/vs?[redacted]");
+        assert_eq!(redact_query("/v2/vs\tgopher"), "/v2/vs?[redacted]");
+    }
+
+    #[test]
+    fn test_passwords_never_logged() {
+        hide("correct horse");
+        assert_eq!(
+            logged_selector("/vault\tcorrect horse", false),
+            "/vault?[redacted]"
+        );
+        assert_eq!(
+            logged_selector("/vault?correct horse", false),
+            "/vault?[redacted]"
+        );
+        assert_eq!(logged_selector("/v2/vs?gopher", false), "/v2/vs?gopher");
+        assert_eq!(logged_selector("/v2/vs?gopher", true), "/v2/vs?[redacted]");
+    }
+}
@@ -0,0 +1,137 @@
+//! Notes are short bits of text you can attach to a page, like
+//! "revisit this", which show up in the status line whenever you open
+//! it again. Like bookmarks, they only work if `~/.config/phetch/`
+//! exists. They're kept in `NOTES_FILE` as one "url TAB note" pair
+//! per line, and listed at gopher://phetch/1/notes.
+
+use crate::{gopher, phetchdir};
+use std::{fs, io::Result};
+
+/// Where notes are kept.
+const NOTES_FILE: &str = "notes.txt";
+
+/// The note for the page at `url`, if there is one.
+pub fn get(url: &str) -> Option<String> {
+    let path = phetchdir::path().ok()?.join(NOTES_FILE);
+    let notes = fs::read_to_string(path).ok()?;
+    parse(&notes)
+        .into_iter()
+        .find(|(u, _)| u == url)
+        .map(|(_, note)| note)
+}
+
+/// Set the note for the page at `url`. An empty note deletes it.
+pub fn save(url: &str, note: &str) -> Result<()> {
+    let path = phetchdir::path()?.join(NOTES_FILE);
+    let old = fs::read_to_string(&path).unwrap_or_default();
+    phetchdir::replace(&path, &set(&old, url, note))
+}
+
+/// Delete the note for the page at `url`.
+pub fn delete(url: &str) -> Result<()> {
+    save(url, "")
+}
+
+/// Get all notes in Gophermap format, with a link to each page and a
+/// link to delete each note.
+pub fn as_raw_menu() -> String {
+    let path = match phetchdir::path() {
+        Ok(path) => path.join(NOTES_FILE),
+        Err(e) => {
+            return format!(
+                "i\r\ni\x1b[91m{}\x1b[0m\r\ni\r\niNotes can only be saved if {} exists.\r\n",
+                e,
+                phetchdir::DIR
+            )
+        }
+    };
+    to_menu(&parse(&fs::read_to_string(path).unwrap_or_default()))
+}
+
+/// (url, note) pairs from the contents of `NOTES_FILE`.
+fn parse(notes: &str) -> Vec<(String, String)> {
+    notes
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(url, note)| (url.to_string(), note.to_string()))
+        .collect()
+}
+
+/// `notes` with the note for `url` replaced by `note`, or removed if
+/// `note` is empty. New notes go at the end.
+fn set(notes: &str, url: &str, note: &str) -> String {
+    let note = note.replace(['\t', '\r', '\n'], " ");
+    let note = note.trim();
+    let mut out = String::new();
+    let mut found = false;
+    for (u, old) in parse(notes) {
+        if u != url {
+            out.push_str(&format!("{}\t{}\n", u, old));
+        } else if !note.is_empty() && !found {
+            out.push_str(&format!("{}\t{}\n", url, note));
+            found = true;
+        }
+    }
+    if !note.is_empty() && !found {
+        out.push_str(&format!("{}\t{}\n", url, note));
+    }
+    out
+}
+
+/// Gophermap listing `notes`.
+fn to_menu(notes: &[(String, String)]) -> String {
+    let mut out = format!("i{}{}:\r\ni\r\n", phetchdir::DIR, NOTES_FILE);
+    if notes.is_empty() {
+        out.push_str("iNo notes yet.\r\ni\r\niUse N to add a note to a page.\r\n");
+        return out;
+    }
+    for (url, note) in notes {
+        let u = gopher::parse_url(url);
+        out.push_str(&format!(
+            "{}{}\t{}\t{}\t{}\r\n",
+            u.typ.to_char(),
+            note,
+            gopher::menu_selector(&u.sel),
+            u.host,
+            u.port
+        ));
+        out.push_str(&format!("i  {}\r\n", url));
+        out.push_str(&format!(
+            "1  delete note\t/notes/delete/{}\tphetch\r\n",
+            url
+        ));
+        out.push_str("i\r\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let notes = set("", "gopher://phkt.io/1/", "revisit\tthis\n");
+        assert_eq!(notes, "gopher://phkt.io/1/\trevisit this\n");
+        let notes = set(&notes, "gopher://sdf.org/1/", "later");
+        let notes = set(&notes, "gopher://phkt.io/1/", "done");
+        assert_eq!(
+            parse(&notes),
+            vec![
+                ("gopher://phkt.io/1/".into(), "done".into()),
+                ("gopher://sdf.org/1/".into(), "later".into()),
+            ]
+        );
+        let notes = set(&notes, "gopher://sdf.org/1/", "  ");
+        assert_eq!(notes, "gopher://phkt.io/1/\tdone\n");
+    }
+
+    #[test]
+    fn test_to_menu() {
+        assert!(to_menu(&[]).contains("No notes yet."));
+        let menu = to_menu(&[("gopher://phkt.io/0/phetch.txt".into(), "read me".into())]);
+        assert!(menu.contains("0read me\t/phetch.txt\tphkt.io\t70\r\n"));
+        assert!(menu
+            .contains("1  delete note\t/notes/delete/gopher://phkt.io/0/phetch.txt\tphetch\r\n"));
+    }
+}
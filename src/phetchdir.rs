@@ -1,7 +1,7 @@
-//! The phetchdir is `DIR`, or `~/.config/phetch` by default. There is
-//! currently no way to change it. Bookmarks, user history, and the
-//! `phetch.conf` all live in this directory in a fully loaded
-//! installation of phetch.
+//! The phetchdir is `DIR`, or `~/.config/phetch` by default. It follows
+//! `XDG_CONFIG_HOME` if that's set, to `$XDG_CONFIG_HOME/phetch`.
+//! Bookmarks, user history, and the `phetch.conf` all live in this
+//! directory in a fully loaded installation of phetch.
 //!
 //! This module provides helpers for working with the phetchdir:
 //! checking its existence, saving to files inside it, and the sort.
@@ -10,8 +10,18 @@ use crate::gopher;
 use std::{
     fs::{File, OpenOptions},
     io::{prelude::*, BufReader, Result, Write},
+    path::{Path, PathBuf},
 };
 
+#[cfg(test)]
+use std::cell::RefCell;
+
+#[cfg(test)]
+thread_local! {
+    /// This thread's phetchdir, if a test installed a `TestDir`.
+    static TEST_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
 /// The directory where phetch stores its files. Ex: bookmarks file
 /// If you want the full, expanded path, use `path()`.
 pub const DIR: &str = "~/.config/phetch/";
@@ -32,31 +42,47 @@ pub fn load(filename: &str) -> Result<BufReader<File>> {
         if let Ok(file) = OpenOptions::new().read(true).open(&path) {
             Ok(BufReader::new(file))
         } else {
-            Err(error!("Couldn't open {:?}", path))
+            Err(error!("Couldn't open {:?}", path).into())
         }
     })
 }
 
 /// Append a menu item as a line to a file in the phetchdir.
 pub fn append(filename: &str, label: &str, url: &str) -> Result<()> {
-    path().and_then(|dotdir| {
-        let path = dotdir.join(filename);
-        if let Ok(mut file) = OpenOptions::new().append(true).create(true).open(path) {
-            let u = gopher::parse_url(url);
-            write!(
-                file,
-                "{}{}\t{}\t{}\t{}\r\n",
-                u.typ.to_char(),
-                label,
-                u.sel,
-                u.host,
-                u.port
-            )?;
-            Ok(())
-        } else {
-            Err(error!("Can't open file for writing: {:?}", filename))
+    path().and_then(|dotdir| append_to(&dotdir.join(filename), label, url))
+}
+
+/// Append a menu item as a line to the file at `path`.
+pub fn append_to(path: &Path, label: &str, url: &str) -> Result<()> {
+    if let Ok(mut file) = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)
+    {
+        // a hand-edited file might not end with a newline, and
+        // we don't want to glue our line onto its last one
+        let mut last = [0];
+        if file.seek(std::io::SeekFrom::End(-1)).is_ok()
+            && file.read_exact(&mut last).is_ok()
+            && last[0] != b'\n'
+        {
+            file.write_all(b"\r\n")?;
         }
-    })
+        let u = gopher::parse_url(url);
+        write!(
+            file,
+            "{}{}\t{}\t{}\t{}\r\n",
+            u.typ.to_char(),
+            label,
+            gopher::menu_selector(&u.sel),
+            u.host,
+            u.port
+        )?;
+        Ok(())
+    } else {
+        Err(error!("Can't open file for writing: {:?}", path).into())
+    }
 }
 
 /// Add a menu item as the first line in a file in the phetchdir.
@@ -67,6 +93,7 @@ pub fn prepend(filename: &str, label: &str, url: &str) -> Result<()> {
             .write(true)
             .read(true)
             .create(true)
+            .truncate(false)
             .open(path)
         {
             let url = gopher::parse_url(url);
@@ -78,33 +105,171 @@ pub fn prepend(filename: &str, label: &str, url: &str) -> Result<()> {
                 "{}{}\t{}\t{}\t{}\r\n",
                 url.typ.to_char(),
                 label,
-                url.sel,
+                gopher::menu_selector(&url.sel),
                 url.host,
                 url.port
             )?;
             file.write_all(&buf)?;
             Ok(())
         } else {
-            Err(error!("Can't open file for writing: {:?}", filename))
+            Err(error!("Can't open file for writing: {:?}", filename).into())
         }
     })
 }
 
+/// Replace the file at `path` with `contents` all at once, by writing
+/// them next to it first, so it's never left half written.
+pub fn replace(path: &Path, contents: &str) -> Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    std::fs::write(&tmp, contents)?;
+    let res = std::fs::rename(&tmp, path);
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    res
+}
+
 /// Returns the full, expanded PathBuf of the phetchdir only if it exists.
 /// Returns None otherwise.
 /// If you just want the phetchdir path whether or not it exists, use
 /// the DIR constant directly.
-pub fn path() -> Result<std::path::PathBuf> {
-    let homevar = std::env::var("HOME");
-    if homevar.is_err() {
-        return Err(error!("$HOME not set, can't decode `~`"));
+pub fn path() -> Result<PathBuf> {
+    let xdg = std::env::var("XDG_CONFIG_HOME").ok();
+    let dotdir = location()?;
+    if dotdir.exists() {
+        Ok(dotdir)
+    } else if xdg_dir(xdg.as_deref()).is_some() {
+        Err(error!("Config dir not found: {}", dotdir.display()).into())
+    } else {
+        Err(error!("Config dir not found: {}", DIR).into())
     }
+}
 
-    let dotdir = DIR.replace('~', &homevar.unwrap());
-    let dotdir = std::path::Path::new(&dotdir);
-    if dotdir.exists() {
-        Ok(std::path::PathBuf::from(dotdir))
+/// Create the phetchdir if it doesn't exist yet, and return its
+/// full path.
+pub fn create() -> Result<PathBuf> {
+    let dotdir = location()?;
+    std::fs::create_dir_all(&dotdir)?;
+    Ok(dotdir)
+}
+
+/// Where the phetchdir is, or would be, whether or not it exists.
+fn location() -> Result<PathBuf> {
+    #[cfg(test)]
+    {
+        if let Some(dir) = TEST_DIR.with(|d| d.borrow().clone()) {
+            return Ok(dir);
+        }
+    }
+    let xdg = std::env::var("XDG_CONFIG_HOME").ok();
+    let home = std::env::var("HOME").ok();
+    dir_for(xdg.as_deref(), home.as_deref())
+}
+
+/// Where the phetchdir is, given `$XDG_CONFIG_HOME` and `$HOME`.
+fn dir_for(xdg: Option<&str>, home: Option<&str>) -> Result<PathBuf> {
+    if let Some(dir) = xdg_dir(xdg) {
+        return Ok(dir);
+    }
+    match home {
+        Some(home) => Ok(PathBuf::from(DIR.replace('~', home))),
+        None => Err(error!("$HOME not set, can't decode `~`").into()),
+    }
+}
+
+/// The phetchdir under `$XDG_CONFIG_HOME`, if it's set. The spec says
+/// to ignore it if it's empty or relative.
+fn xdg_dir(xdg: Option<&str>) -> Option<PathBuf> {
+    let xdg = Path::new(xdg?);
+    if xdg.is_absolute() {
+        Some(xdg.join("phetch"))
     } else {
-        Err(error!("Config dir not found: {}", DIR))
+        None
+    }
+}
+
+/// A phetchdir for tests, in a fresh temporary directory. Installing
+/// one points this thread at it until it's dropped, so tests running
+/// side by side don't share files or touch the real one.
+#[cfg(test)]
+pub(crate) struct TestDir {
+    pub(crate) path: PathBuf,
+}
+
+#[cfg(test)]
+impl TestDir {
+    /// Create a phetchdir named after `name` and use it on this thread.
+    pub(crate) fn install(name: &str) -> TestDir {
+        let path = std::env::temp_dir().join(format!("phetch-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TEST_DIR.with(|d| *d.borrow_mut() = Some(path.clone()));
+        TestDir { path }
+    }
+
+    /// The contents of `filename` in this phetchdir, or "" if there's
+    /// no such file.
+    pub(crate) fn read(&self, filename: &str) -> String {
+        std::fs::read_to_string(self.path.join(filename)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        TEST_DIR.with(|d| *d.borrow_mut() = None);
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_for() {
+        let dir = |xdg, home| dir_for(xdg, home).map(|p| p.display().to_string());
+        assert_eq!(
+            dir(None, Some("/home/gopher")).unwrap(),
+            "/home/gopher/.config/phetch/"
+        );
+        assert_eq!(
+            dir(Some("/srv/config"), Some("/home/gopher")).unwrap(),
+            "/srv/config/phetch"
+        );
+        assert_eq!(
+            dir(Some("/srv/config"), None).unwrap(),
+            "/srv/config/phetch"
+        );
+        // empty or relative XDG_CONFIG_HOME is ignored
+        assert_eq!(
+            dir(Some(""), Some("/home/gopher")).unwrap(),
+            "/home/gopher/.config/phetch/"
+        );
+        assert_eq!(
+            dir(Some("config"), Some("/home/gopher")).unwrap(),
+            "/home/gopher/.config/phetch/"
+        );
+        assert!(dir(None, None).is_err());
+    }
+
+    #[test]
+    fn test_append_encoded_url() {
+        let path = std::env::temp_dir().join(format!("phetch-append-{}.gph", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        append_to(
+            &path,
+            "diary",
+            "gopher://phkt.io/0/my%20%E6%97%A5%E8%A8%98.txt",
+        )
+        .unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved, "0diary\t/my 日記.txt\tphkt.io\t70\r\n");
+
+        let menu = crate::menu::parse("gopher://phkt.io/1/", saved, Default::default());
+        let urls: Vec<_> = menu.lines().map(|line| line.url()).collect();
+        assert_eq!(urls, vec!["gopher://phkt.io/0/my%20%E6%97%A5%E8%A8%98.txt"]);
     }
 }
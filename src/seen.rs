@@ -0,0 +1,145 @@
+//! phetch can remember which links were on a menu the last time you
+//! visited it and mark the ones that have shown up since, which is
+//! handy for keeping up with phlogs. It's enabled with `mark_new yes`
+//! in phetch.conf and, like history, only works if `~/.config/phetch/`
+//! exists. Everything is kept in `SEEN_FILE` as one "menu-url TAB
+//! link-url" pair per line, for the last `MAX_MENUS` menus saved.
+
+use crate::phetchdir;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{BufRead, Result},
+    path::Path,
+    sync::Mutex,
+};
+
+/// Where we keep track of the links we've seen.
+const SEEN_FILE: &str = "seen.txt";
+
+/// How many menus we remember links for. The ones saved longest ago
+/// are forgotten first.
+const MAX_MENUS: usize = 500;
+
+lazy_static! {
+    /// Held while saving, since menus load on their own threads and
+    /// two saves at once could each drop the other's links.
+    static ref SAVING: Mutex<()> = Mutex::new(());
+}
+
+/// The links that were on the menu at `url` last time we saved it,
+/// or None if we've never been there.
+pub fn load(url: &str) -> Option<HashSet<String>> {
+    let reader = phetchdir::load(SEEN_FILE).ok()?;
+    let mut seen = None;
+    for line in reader.lines().map_while(|l| l.ok()) {
+        if let Some((menu, link)) = line.split_once('\t') {
+            if menu == url {
+                seen.get_or_insert_with(HashSet::new)
+                    .insert(link.to_string());
+            }
+        }
+    }
+    seen
+}
+
+/// Replace the links we remember for the menu at `url`. `dir` is
+/// the phetchdir, found before this is handed off to another thread.
+pub fn save(dir: &Path, url: &str, links: &[String]) -> Result<()> {
+    let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+    let path = dir.join(SEEN_FILE);
+    let old = fs::read_to_string(&path).unwrap_or_default();
+    let mut kept = old
+        .lines()
+        .filter(|line| !matches!(line.split_once('\t'), Some((menu, _)) if menu == url))
+        .collect::<Vec<_>>();
+    let mut menus = kept
+        .iter()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(menu, _)| menu)
+        .collect::<Vec<_>>();
+    menus.dedup();
+    if menus.len() >= MAX_MENUS {
+        let forget = menus[..=menus.len() - MAX_MENUS].to_vec();
+        kept.retain(
+            |line| !matches!(line.split_once('\t'), Some((menu, _)) if forget.contains(&menu)),
+        );
+    }
+    let mut out = String::new();
+    for line in kept {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for link in links {
+        out.push_str(&format!("{}\t{}\n", url, link));
+    }
+    phetchdir::replace(&path, &out)
+}
+
+/// Which of `links` weren't around last time? Returns their indexes.
+pub fn new_links(seen: &HashSet<String>, links: &[String]) -> Vec<usize> {
+    links
+        .iter()
+        .enumerate()
+        .filter(|(_, link)| !seen.contains(*link))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_links() {
+        let seen = ["gopher://phkt.io/0/one", "gopher://phkt.io/0/two"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let links = [
+            "gopher://phkt.io/0/three".to_string(),
+            "gopher://phkt.io/0/two".to_string(),
+            "gopher://phkt.io/0/one".to_string(),
+            "gopher://phkt.io/0/four".to_string(),
+        ];
+        assert_eq!(new_links(&seen, &links), vec![0, 3]);
+        assert!(new_links(&seen, &links[1..3]).is_empty());
+    }
+
+    #[test]
+    fn test_save_keeps_every_menu() {
+        let dir = phetchdir::TestDir::install("seen-save");
+        let saves = (0..8)
+            .map(|i| {
+                let path = dir.path.clone();
+                std::thread::spawn(move || {
+                    let url = format!("gopher://phkt.io/1/{}", i);
+                    save(&path, &url, &[format!("{}/link", url)]).unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        for save in saves {
+            save.join().unwrap();
+        }
+        assert_eq!(dir.read(SEEN_FILE).lines().count(), 8);
+    }
+
+    #[test]
+    fn test_save_forgets_oldest_menus() {
+        let dir = phetchdir::TestDir::install("seen-cap");
+        for i in 0..=MAX_MENUS {
+            let url = format!("gopher://phkt.io/1/{}", i);
+            save(
+                &dir.path,
+                &url,
+                &[format!("{}/a", url), format!("{}/b", url)],
+            )
+            .unwrap();
+        }
+        let seen = dir.read(SEEN_FILE);
+        assert_eq!(seen.lines().count(), MAX_MENUS * 2);
+        assert!(!seen.contains("gopher://phkt.io/1/0\t"));
+        assert!(seen.contains("gopher://phkt.io/1/1\t"));
+        assert!(seen.ends_with(&format!("gopher://phkt.io/1/{}/b\n", MAX_MENUS)));
+    }
+}
@@ -4,7 +4,7 @@
 
 use lazy_static::lazy_static;
 use libc::{cfmakeraw, tcgetattr, tcsetattr, termios as Termios, STDIN_FILENO, TCSANOW};
-use std::{io, sync::Mutex};
+use std::{fmt, io, sync::Mutex};
 
 pub use termion::cursor::Goto;
 pub use termion::cursor::Hide as HideCursor;
@@ -20,6 +20,24 @@ pub use termion::clear::UntilNewline as ClearUntilNewline;
 
 type Result<T> = std::result::Result<T, io::Error>;
 
+/// Set the terminal window or tab title. Control characters are
+/// dropped so a page can't sneak its own escape sequences in.
+pub struct SetTitle<'a>(pub &'a str);
+
+impl fmt::Display for SetTitle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title: String = self.0.chars().filter(|c| !c.is_control()).collect();
+        write!(f, "\x1b]0;{}\x07", title)
+    }
+}
+
+/// Save the current window title, so `PopTitle` can bring it back.
+/// Terminals that don't keep a title stack ignore this.
+pub const PUSH_TITLE: &str = "\x1b[22;0t";
+
+/// Restore the window title saved with `PUSH_TITLE`.
+pub const POP_TITLE: &str = "\x1b[23;0t";
+
 lazy_static! {
     // Some(Termios) -> we're in the raw mode and this is the previous mode
     // None -> we're not in the raw mode
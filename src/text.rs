@@ -5,13 +5,24 @@
 use crate::{
     config::SharedConfig as Config,
     encoding::Encoding,
-    terminal,
+    gopher, terminal,
     ui::{self, Action, Key, View, MAX_COLS},
+    utils::{find_matches, highlight},
 };
 use std::{borrow::Cow, fmt, str};
 
+/// Text in reader mode wraps at this many columns, at most.
+const READER_WIDTH: usize = 72;
+
+/// `<` won't make text narrower than this many columns.
+const MIN_WIDTH: usize = 20;
+
+/// Columns `<` and `>` change the width by.
+const WIDTH_STEP: usize = 4;
+
 /// The Text View holds the raw Gopher response as well as information
 /// about which lines should currently be displayed on screen.
+#[derive(Clone)]
 pub struct Text {
     /// Ref to our global config
     config: Config,
@@ -41,6 +52,18 @@ pub struct Text {
     pub wide: bool,
     /// How many lines to scroll by. 0 = full screen
     scroll: usize,
+    /// Reader mode: no status line or scrollbar, and text wrapped
+    /// at `READER_WIDTH` in the middle of the screen.
+    pub reader: bool,
+    /// Column to wrap at, picked with `<` and `>`. Wins over the
+    /// `wrap` setting and reader mode.
+    width: Option<usize>,
+    /// Is this a hex dump of another page?
+    hex: bool,
+    /// What `/` is looking for. Empty when not searching.
+    find: String,
+    /// Row of the current match for `find`, if any.
+    found: Option<usize>,
 }
 
 impl fmt::Display for Text {
@@ -62,10 +85,31 @@ impl View for Text {
         self.url.as_ref()
     }
 
+    fn clone_view(&self) -> Box<dyn View> {
+        Box::new(self.clone())
+    }
+
     fn raw(&self) -> &str {
         str::from_utf8(&self.raw_response).unwrap_or_default()
     }
 
+    fn raw_bytes(&self) -> &[u8] {
+        &self.raw_response
+    }
+
+    fn is_hex_dump(&self) -> bool {
+        self.hex
+    }
+
+    fn find(&mut self, query: &str) -> Action {
+        self.find = query.trim().to_string();
+        self.found = None;
+        if self.find.is_empty() {
+            return Action::Redraw;
+        }
+        self.find_next(true)
+    }
+
     fn term_size(&mut self, cols: usize, rows: usize) {
         self.size = (cols, rows);
     }
@@ -82,17 +126,66 @@ impl View for Text {
         self.encoding
     }
 
+    fn scroll_position(&self) -> Option<(usize, usize)> {
+        Some((self.offset, self.lines))
+    }
+
+    fn place(&self) -> (usize, Option<String>) {
+        (self.offset, None)
+    }
+
+    fn restore_place(&mut self, offset: usize, _link: Option<&str>) {
+        self.offset = offset.min(self.lines.saturating_sub(1));
+    }
+
+    fn reader(&self) -> bool {
+        self.reader
+    }
+
+    fn summary(&self) -> Option<String> {
+        let text = self
+            .encoded_response
+            .trim_end_matches(['\r', '\n'])
+            .trim_end_matches("\n.");
+        let lines = text.lines().count();
+        let words = text.split_whitespace().count();
+        Some(format!(
+            "{} line{}, {} word{}",
+            lines,
+            if lines == 1 { "" } else { "s" },
+            words,
+            if words == 1 { "" } else { "s" },
+        ))
+    }
+
     fn respond(&mut self, c: Key) -> Action {
         match c {
             Key::Home => {
                 self.offset = 0;
                 Action::Redraw
             }
-            Key::End => {
+            Key::End | Key::Char('G') => {
                 self.offset = self.final_scroll();
                 Action::Redraw
             }
             Key::Ctrl('e') | Key::Char('e') => self.toggle_encoding(),
+            Key::Char('z') => {
+                self.reader = !self.reader;
+                self.encode_response();
+                self.offset = self.offset.min(self.final_scroll());
+                Action::Redraw
+            }
+            Key::Char('<') => self.change_width(false),
+            Key::Char('>') => self.change_width(true),
+            Key::Char('/') => Action::Prompt("Find: ".into(), Box::new(Action::Find)),
+            // n and N go through matches until escape ends the search
+            Key::Char('n') if !self.find.is_empty() => self.find_next(true),
+            Key::Char('N') if !self.find.is_empty() => self.find_next(false),
+            Key::Esc if !self.find.is_empty() => {
+                self.find.clear();
+                self.found = None;
+                Action::Redraw
+            }
             Key::Down | Key::Ctrl('n') | Key::Char('n') | Key::Ctrl('j') | Key::Char('j') => {
                 if self.offset < self.final_scroll() {
                     self.offset += 1;
@@ -133,12 +226,11 @@ impl View for Text {
     }
 
     fn render(&mut self) -> String {
-        let (_cols, rows) = self.size;
         let mut out = String::new();
-        let wrap = self.config.read().unwrap().wrap;
+        let wrap = self.wrap_width();
         let indent = self.indent_str(wrap);
         let limit = if self.mode == ui::Mode::Run {
-            rows - 1
+            self.page_rows()
         } else {
             self.lines
         };
@@ -156,8 +248,7 @@ impl View for Text {
             if !self.wide {
                 out.push_str(&indent);
             }
-            let line = line.trim_end_matches('\r').replace('\t', "    ");
-            out.push_str(&line);
+            out.push_str(&highlight(line.trim_end_matches('\r'), &self.find));
 
             // clear rest of line
             out.push_str(&format!("{}", terminal::ClearUntilNewline));
@@ -173,12 +264,23 @@ impl View for Text {
 }
 
 impl Text {
-    /// Create a Text View from a raw Gopher response and a few options.
+    /// Create a Text View from a raw Gopher response and a few
+    /// options. Nothing is fetched, so `response` can come from
+    /// anywhere. It's decoded with the `encoding` in `config`.
+    ///
+    /// ```
+    /// use phetch::{config::SharedConfig, text::Text, ui::View};
+    ///
+    /// let body = b"Hello, gopherspace!\r\n".to_vec();
+    /// let mut text = Text::from("gopher://phkt.io/0/hi.txt", body, SharedConfig::default(), false);
+    /// text.term_size(80, 24);
+    /// assert!(text.render().contains("Hello, gopherspace!"));
+    /// ```
     pub fn from(url: &str, response: Vec<u8>, config: Config, tls: bool) -> Text {
         let mode = config.read().unwrap().mode;
         let tor = config.read().unwrap().tor;
         let encoding = config.read().unwrap().encoding;
-        let wide = config.read().unwrap().wide;
+        let wide = config.read().unwrap().wide_for(url);
         let scroll = config.read().unwrap().scroll;
 
         let mut new = Text {
@@ -196,11 +298,95 @@ impl Text {
             encoding,
             wide,
             scroll,
+            reader: false,
+            width: None,
+            hex: false,
+            find: String::new(),
+            found: None,
         };
         new.encode_response();
         new
     }
 
+    /// Like `from()`, but decodes `response` as `encoding` no matter
+    /// what the config says.
+    ///
+    /// ```
+    /// use phetch::{config::SharedConfig, encoding::Encoding, text::Text, ui::View};
+    ///
+    /// let body = b"\xc9\xcd\xbb\r\n".to_vec();
+    /// let config = SharedConfig::default();
+    /// let mut text = Text::from_bytes("gopher://bbs.example/0/art", body, Encoding::CP437, config, false);
+    /// text.term_size(80, 24);
+    /// assert!(text.render().contains("╔═╗"));
+    /// assert_eq!(text.encoding(), Encoding::CP437);
+    /// ```
+    pub fn from_bytes(
+        url: &str,
+        response: Vec<u8>,
+        encoding: Encoding,
+        config: Config,
+        tls: bool,
+    ) -> Text {
+        let mut text = Text::from(url, response, config, tls);
+        if text.encoding != encoding {
+            text.encoding = encoding;
+            text.encode_response();
+        }
+        text
+    }
+
+    /// A hex dump of `bytes`, which were fetched from `url`, with
+    /// offset, hex, and ASCII columns like `hexdump -C`.
+    pub fn hex_dump(url: &str, bytes: &[u8], config: Config, tls: bool) -> Text {
+        let mut text = Text::from_bytes(
+            url,
+            hex_dump(bytes).into_bytes(),
+            Encoding::UTF8,
+            config,
+            tls,
+        );
+        text.wide = true;
+        text.hex = true;
+        text
+    }
+
+    /// Scroll to the next match for `find`, or the previous one if
+    /// `forward` isn't set, going around the end of the page.
+    fn find_next(&mut self, forward: bool) -> Action {
+        let rows = wrap_text(&self.encoded_response, self.wrap_width())
+            .into_iter()
+            .enumerate()
+            .filter(|(_, row)| !find_matches(row, &self.find).is_empty())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            let find = std::mem::take(&mut self.find);
+            self.found = None;
+            return Action::Error(format!("Not found: {}", find));
+        }
+
+        // start from the last match, or the top of the screen
+        let idx = if forward {
+            rows.iter()
+                .position(|&r| self.found.map_or(r >= self.offset, |f| r > f))
+                .unwrap_or(0)
+        } else {
+            rows.iter()
+                .rposition(|&r| self.found.map_or(r < self.offset, |f| r < f))
+                .unwrap_or(rows.len() - 1)
+        };
+        let row = rows[idx];
+        self.found = Some(row);
+        if row < self.offset || row >= self.offset + self.page_rows() {
+            self.offset = row.min(self.final_scroll());
+        }
+        Action::List(vec![
+            Action::Redraw,
+            Action::Status(format!("Match {} of {}", idx + 1, rows.len())),
+        ])
+    }
+
     /// Toggle between our two encodings.
     fn toggle_encoding(&mut self) -> Action {
         if matches!(self.encoding, Encoding::UTF8) {
@@ -213,42 +399,84 @@ impl Text {
         Action::Redraw
     }
 
-    /// Convert the response to a Rust String and cache metadata like
-    /// the number of lines.
+    /// Convert the response to a Rust String, minus any leading BOM,
+    /// expand its tabs, and cache metadata like the number of lines.
     fn encode_response(&mut self) {
-        self.encoded_response = self.encoding.encode(&self.raw_response).into();
-        let wrapped = wrap_text(
-            self.encoded_response.as_ref(),
-            self.config.read().unwrap().wrap,
-        );
+        let tab_width = self.config.read().unwrap().tab_width;
+        let response = gopher::strip_bom(&self.raw_response);
+        self.encoded_response = expand_tabs(&self.encoding.encode(response), tab_width).into();
+        let wrapped = wrap_text(self.encoded_response.as_ref(), self.wrap_width());
         self.lines = wrapped.len();
-        self.longest = wrapped.iter().map(|line| line.len()).max().unwrap_or(0) as usize;
+        self.longest = wrapped.iter().map(|line| line.len()).max().unwrap_or(0);
     }
 
     /// Final `self.scroll` value.
     fn final_scroll(&self) -> usize {
-        let padding = (self.size.1 as f64 * 0.9) as usize;
-        if self.lines > padding {
-            self.lines - padding
+        let padding = ((self.size.1 as f64 * 0.9) as usize).max(1);
+        self.lines.saturating_sub(padding)
+    }
+
+    /// Rows left for content once the status line is drawn. Tiny
+    /// terminals still get one. Reader mode gets them all.
+    fn page_rows(&self) -> usize {
+        if self.reader {
+            self.size.1.max(1)
         } else {
-            0
+            self.size.1.saturating_sub(1).max(1)
+        }
+    }
+
+    /// Make the text `WIDTH_STEP` columns wider or narrower and
+    /// rewrap it, staying between `MIN_WIDTH` and the terminal width.
+    fn change_width(&mut self, wider: bool) -> Action {
+        let current = match self.width {
+            Some(width) => width,
+            None => self.longest_line_with_wrap(self.wrap_width()),
+        };
+        let width = if wider {
+            current + WIDTH_STEP
+        } else {
+            current.saturating_sub(WIDTH_STEP)
+        };
+        let width = width.clamp(MIN_WIDTH, self.size.0.max(MIN_WIDTH));
+        self.width = Some(width);
+        self.encode_response();
+        self.offset = self.offset.min(self.final_scroll());
+        Action::List(vec![
+            Action::Redraw,
+            Action::Status(format!("Width: {} columns", width)),
+        ])
+    }
+
+    /// Column to wrap lines at, 0 = don't. Reader mode never goes
+    /// past `READER_WIDTH`.
+    fn wrap_width(&self) -> usize {
+        if let Some(width) = self.width {
+            return width;
+        }
+        let wrap = self.config.read().unwrap().wrap;
+        if self.reader && (wrap == 0 || wrap > READER_WIDTH) {
+            READER_WIDTH
+        } else {
+            wrap
         }
     }
 
     /// How many lines to scroll by when paging up or down.
     fn scroll_by(&self) -> usize {
         if self.scroll == 0 {
-            self.size.1 - 1
+            ui::page_scroll(self.page_rows())
         } else {
             self.scroll
         }
     }
 
     /// Determine the longest line, considering any line wrapping and
-    /// `MAX_COL`.
+    /// `MAX_COL`, or the width picked with `>` if it's wider.
     fn longest_line_with_wrap(&self, wrap: usize) -> usize {
-        let longest = if self.longest > MAX_COLS {
-            MAX_COLS
+        let max_cols = self.width.unwrap_or(0).max(MAX_COLS);
+        let longest = if self.longest > max_cols {
+            max_cols
         } else {
             self.longest
         };
@@ -262,7 +490,7 @@ impl Text {
 
     /// Produce the string to use for indentation, or the left margin,
     /// for a given text document.
-    fn indent_str(&self, wrap: usize) -> Cow<str> {
+    fn indent_str(&self, wrap: usize) -> Cow<'_, str> {
         let (cols, _) = self.size;
         let longest = self.longest_line_with_wrap(wrap);
 
@@ -276,6 +504,36 @@ impl Text {
     }
 }
 
+/// Replaces each tab with enough spaces to reach the next multiple
+/// of `width` columns, so tab-aligned columns line up. Tabs are left
+/// alone if `width` is 0.
+fn expand_tabs(text: &str, width: usize) -> Cow<'_, str> {
+    if width == 0 || !text.contains('\t') {
+        return Cow::from(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut col = 0;
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = width - col % width;
+                out.push_str(&" ".repeat(spaces));
+                col += spaces;
+            }
+            '\n' => {
+                out.push(c);
+                col = 0;
+            }
+            _ => {
+                out.push(c);
+                col += 1;
+            }
+        }
+    }
+    Cow::from(out)
+}
+
 /// Splits a chunk of text into a vector of strings with at most
 /// `wrap` characters each. Tries to be smart and wrap at punctuation,
 /// otherwise just wraps at `wrap`.
@@ -327,9 +585,56 @@ fn wrap_text(lines: &str, wrap: usize) -> Vec<&str> {
     out
 }
 
+/// Format `bytes` 16 to a line as offset, hex, and printable ASCII.
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x} ", i * 16));
+        for col in 0..16 {
+            if col == 8 {
+                out.push(' ');
+            }
+            match chunk.get(col) {
+                Some(b) => out.push_str(&format!(" {:02x}", b)),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str("  |");
+        for &b in chunk {
+            out.push(if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            });
+        }
+        out.push_str("|\n");
+    }
+    out.push_str(&format!("{:08x}\n", bytes.len()));
+    out
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ui::{ColorMode, PAGE_OVERLAP};
+
+    #[test]
+    fn test_hex_dump() {
+        let bytes = b"iHi\tthere\r\n\x00\xff\xc3\xa9 gopher!";
+        assert_eq!(
+            hex_dump(bytes),
+            "00000000  69 48 69 09 74 68 65 72  65 0d 0a 00 ff c3 a9 20  |iHi.there...... |\n\
+             00000010  67 6f 70 68 65 72 21                              |gopher!|\n\
+             00000017\n"
+        );
+        assert_eq!(hex_dump(b""), "00000000\n");
+
+        let mut text = Text::hex_dump("gopher://host/9/f", bytes, Config::default(), false);
+        assert!(text.is_hex_dump());
+        assert_eq!(text.url(), "gopher://host/9/f");
+        let out = text.render_with(80, 5, ColorMode::Mono);
+        assert!(out.contains("00000010  67 6f 70 68 65 72 21"));
+    }
 
     #[test]
     fn test_cp437() {
@@ -351,6 +656,243 @@ mod test {
         assert!(res.contains("Θ"));
     }
 
+    #[test]
+    fn test_bom() {
+        let body = include_bytes!("../tests/bom.txt");
+        assert!(body.starts_with(b"\xEF\xBB\xBF"));
+        let mut text = Text::from("", body.to_vec(), Config::default(), false);
+        text.mode = ui::Mode::Print;
+        let res = text.render();
+        assert!(!res.contains('\u{feff}'));
+        assert!(res.starts_with("Byte order marks"));
+
+        let body = b"\0Stray NUL.\n".to_vec();
+        let mut text = Text::from("", body, Config::default(), false);
+        text.mode = ui::Mode::Print;
+        assert!(text.render().starts_with("Stray NUL."));
+    }
+
+    #[test]
+    fn test_reader_mode() {
+        let mut body = "word ".repeat(30);
+        for i in 0..30 {
+            body.push_str(&format!("\nline {}", i));
+        }
+        let mut text = Text::from("", body.into(), Config::default(), false);
+        text.term_size(80, 10);
+        let rows = |text: &mut Text| text.render().matches("\r\n").count();
+        assert_eq!(rows(&mut text), 9);
+        assert!(text.render().starts_with("word"));
+
+        assert!(matches!(text.respond(Key::Char('z')), Action::Redraw));
+        assert!(text.reader());
+        assert_eq!(rows(&mut text), 10);
+        let out = text.render();
+        let first = out.split("\r\n").next().unwrap();
+        assert!(first.starts_with(&format!("{}word", " ".repeat(5))));
+        assert!(first.trim_end_matches("\x1b[K").len() <= 5 + READER_WIDTH);
+
+        text.respond(Key::Char('z'));
+        assert!(!text.reader());
+        assert_eq!(rows(&mut text), 9);
+    }
+
+    #[test]
+    fn test_page_down() {
+        let body = (0..100)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>();
+        let mut text = Text::from("", body.into_bytes(), Config::default(), false);
+        text.term_size(80, 24);
+        text.respond(Key::Char(' '));
+        // a screen less the status line, less the overlap
+        assert_eq!(text.offset, 23 - PAGE_OVERLAP);
+        assert!(text
+            .render()
+            .split("\r\n")
+            .next()
+            .unwrap()
+            .contains("line 21"));
+        text.respond(Key::PageUp);
+        assert_eq!(text.offset, 0);
+        assert!(text.respond(Key::PageUp).is_none());
+        for _ in 0..10 {
+            text.respond(Key::PageDown);
+        }
+        assert_eq!(text.offset, text.final_scroll());
+    }
+
+    #[test]
+    fn test_find() {
+        let mut body = String::new();
+        for i in 0..100 {
+            let word = if i % 30 == 10 { "Needle" } else { "hay" };
+            body.push_str(&format!("line {} {}\n", i, word));
+        }
+        let mut text = Text::from("", body.into_bytes(), Config::default(), false);
+        text.term_size(80, 10);
+
+        assert!(matches!(text.respond(Key::Char('/')), Action::Prompt(p, _) if p == "Find: "));
+        let status = |action: Action| match action {
+            Action::List(list) => format!("{:?}", list[1]),
+            other => format!("{:?}", other),
+        };
+        assert_eq!(status(text.find(" needle ")), "Status: Match 1 of 3");
+        assert_eq!(text.found, Some(10));
+        assert_eq!(text.offset, 10);
+        assert!(text.render().contains("line 10 \x1b[7mNeedle\x1b[27m"));
+
+        assert_eq!(status(text.respond(Key::Char('n'))), "Status: Match 2 of 3");
+        assert_eq!(text.offset, 40);
+        text.respond(Key::Char('n'));
+        assert_eq!(text.found, Some(70));
+        assert_eq!(status(text.respond(Key::Char('n'))), "Status: Match 1 of 3");
+        assert_eq!(text.offset, 10);
+        assert_eq!(status(text.respond(Key::Char('N'))), "Status: Match 3 of 3");
+
+        // escape ends the search, and n scrolls again
+        text.respond(Key::Esc);
+        assert!(!text.render().contains("\x1b[7m"));
+        let offset = text.offset;
+        text.respond(Key::Char('n'));
+        assert_eq!(text.offset, offset + 1);
+
+        assert_eq!(status(text.find("pins")), "Error: Not found: pins");
+        assert!(text.find.is_empty());
+        assert_eq!(
+            highlight("a hay HAY", "hay"),
+            "a \x1b[7mhay\x1b[27m \x1b[7mHAY\x1b[27m"
+        );
+    }
+
+    #[test]
+    fn test_find_matches() {
+        // case folding isn't only for ASCII
+        assert_eq!(find_matches("Ärger ÄRGER", "ärger"), vec![0..6, 7..13]);
+        assert_eq!(
+            highlight("straße STRASSE", "STRASSE"),
+            "straße \x1b[7mSTRASSE\x1b[27m"
+        );
+        assert_eq!(highlight("ΣΟΦΊΑ", "σοφία"), "\x1b[7mΣΟΦΊΑ\x1b[27m");
+        // color codes are never matched or split
+        assert!(find_matches("\x1b[91mred\x1b[0m", "91").is_empty());
+        assert!(find_matches("\x1b[1m", "m").is_empty());
+        assert_eq!(
+            highlight("\x1b[92mclient\x1b[0m", "client"),
+            "\x1b[92m\x1b[7mclient\x1b[27m\x1b[0m"
+        );
+        assert_eq!(
+            highlight("gre\x1b[1men", "green"),
+            "\x1b[7mgre\x1b[1men\x1b[27m"
+        );
+        assert!(find_matches("anything", "").is_empty());
+    }
+
+    #[test]
+    fn test_change_width() {
+        let body = "word ".repeat(40);
+        let mut text = Text::from("", body.into(), Config::default(), false);
+        text.term_size(100, 10);
+        let widest = |text: &mut Text| {
+            text.render()
+                .split("\r\n")
+                .map(|line| line.trim_end_matches("\x1b[K").trim().len())
+                .max()
+                .unwrap_or(0)
+        };
+        assert!(widest(&mut text) > 100);
+
+        text.respond(Key::Char('<'));
+        assert_eq!(text.wrap_width(), MAX_COLS - WIDTH_STEP);
+        assert!(widest(&mut text) <= MAX_COLS - WIDTH_STEP);
+        for _ in 0..20 {
+            text.respond(Key::Char('<'));
+        }
+        assert_eq!(text.wrap_width(), MIN_WIDTH);
+        assert!(widest(&mut text) <= MIN_WIDTH);
+
+        for _ in 0..30 {
+            text.respond(Key::Char('>'));
+        }
+        assert_eq!(text.wrap_width(), 100);
+        assert!(widest(&mut text) > MAX_COLS);
+    }
+
+    #[test]
+    fn test_tiny_terminal() {
+        let body = "one\ntwo\nthree\nfour".as_bytes().to_vec();
+        for rows in 0..3 {
+            let mut text = Text::from("", body.clone(), Config::default(), false);
+            text.term_size(20, rows);
+            let out = text.render();
+            assert!(out.contains("one"));
+            assert!(!out.contains("three"));
+            text.respond(Key::PageDown);
+            text.respond(Key::Down);
+            text.render();
+            text.respond(Key::End);
+            assert!(text.render().contains("four"));
+        }
+
+        // shorter than the screen: nowhere to go
+        let mut text = Text::from("", body, Config::default(), false);
+        text.term_size(20, 24);
+        text.respond(Key::Char('G'));
+        assert_eq!(text.offset, 0);
+    }
+
+    #[test]
+    fn test_tabs() {
+        let body = "ab\tc\nphetch.tar.gz\t1M\n".as_bytes().to_vec();
+        let render = |tab_width| {
+            let config = Config::default();
+            config.write().unwrap().tab_width = tab_width;
+            let mut text = Text::from("", body.clone(), config, false);
+            text.mode = ui::Mode::Print;
+            text.render()
+        };
+
+        let out = render(8);
+        assert!(out.contains("ab      c"));
+        assert!(out.contains("phetch.tar.gz   1M"));
+        assert!(!out.contains('\t'));
+
+        let out = render(4);
+        assert!(out.contains("ab  c"));
+        assert!(out.contains("phetch.tar.gz   1M"));
+
+        assert!(render(0).contains("ab\tc"));
+    }
+
+    #[test]
+    fn test_render_with() {
+        let body = "# phetch\n\nquick lil\tgopher \x1b[92mclient\x1b[0m\n.\r\n";
+        let mut text = Text::from("", body.as_bytes().to_vec(), Config::default(), false);
+
+        assert_eq!(
+            text.render_with(60, 4, ColorMode::Color),
+            "           # phetch\x1b[K\r\n           \x1b[K\r\n           quick lil       gopher \x1b[92mclient\x1b[0m\x1b[K\r\n\x1b[J"
+        );
+        assert_eq!(
+            text.render_with(60, 4, ColorMode::Mono),
+            "           # phetch\x1b[K\r\n           \x1b[K\r\n           quick lil       gopher client\x1b[K\r\n\x1b[J"
+        );
+        // one row for text, one for the status line
+        assert_eq!(
+            text.render_with(20, 2, ColorMode::Mono),
+            "# phetch\x1b[K\r\n\x1b[J"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let body = "# phetch\n\nquick lil gopher client\r\n.\r\n";
+        let text = Text::from("", body.as_bytes().to_vec(), Config::default(), false);
+        assert_eq!(text.summary().unwrap(), "3 lines, 6 words");
+        let text = Text::from("", b"hi".to_vec(), Config::default(), false);
+        assert_eq!(text.summary().unwrap(), "1 line, 1 word");
+    }
+
     #[test]
     fn test_wrapping() {
         let text = "regular line
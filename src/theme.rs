@@ -24,7 +24,7 @@ macro_rules! reset_color {
 }
 
 /// Color scheme for UI and menu items.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Theme {
     // UI Colors
     /// The * cursor that appears next to the selected menu item.
@@ -35,6 +35,14 @@ pub struct Theme {
     pub ui_menu: String,
     /// The color of the text content in a document.
     pub ui_text: String,
+    /// The marker next to links that are new since the last visit.
+    pub ui_new: String,
+    /// The marker in the status line showing which view has focus.
+    pub ui_focus: String,
+    /// Selectors shown next to links with `U`.
+    pub ui_selector: String,
+    /// The scrollbar drawn with `scrollbar yes`.
+    pub ui_scrollbar: String,
 
     // Menu Item Colors
     /// Text document.
@@ -64,6 +72,10 @@ impl Default for Theme {
             ui_number: to_color("magenta"),
             ui_menu: to_color("yellow"),
             ui_text: to_color("white"),
+            ui_new: to_color("green bold"),
+            ui_focus: to_color("cyan bold"),
+            ui_selector: to_color("grey"),
+            ui_scrollbar: to_color("grey"),
 
             item_text: to_color("cyan"),
             item_menu: to_color("blue"),
@@ -87,6 +99,10 @@ ui.cursor {ui_cursor}
 ui.number {ui_number}
 ui.menu {ui_menu}
 ui.text {ui_text}
+ui.new {ui_new}
+ui.focus {ui_focus}
+ui.selector {ui_selector}
+ui.scrollbar {ui_scrollbar}
 
 item.text {item_text}
 item.menu {item_menu}
@@ -101,6 +117,10 @@ item.unsupported {item_unsupported}",
             ui_number = to_words(&self.ui_number),
             ui_menu = to_words(&self.ui_menu),
             ui_text = to_words(&self.ui_text),
+            ui_new = to_words(&self.ui_new),
+            ui_focus = to_words(&self.ui_focus),
+            ui_selector = to_words(&self.ui_selector),
+            ui_scrollbar = to_words(&self.ui_scrollbar),
             item_text = to_words(&self.item_text),
             item_menu = to_words(&self.item_menu),
             item_error = to_words(&self.item_error),
@@ -114,7 +134,168 @@ item.unsupported {item_unsupported}",
     }
 }
 
+/// The built-in themes, in the order `C` cycles through them.
+pub const PRESETS: [&str; 4] = ["default", "light", "high-contrast", "mono"];
+
+impl Theme {
+    /// The built-in theme called `name`, if there is one. See `PRESETS`.
+    pub fn preset(name: &str) -> Option<Theme> {
+        let c = color_or_plain;
+        Some(match name {
+            "default" => Theme::default(),
+            "light" => Theme {
+                ui_cursor: c("black bold"),
+                ui_number: c("darkmagenta"),
+                ui_menu: c("darkblue"),
+                ui_text: c("black"),
+                ui_new: c("darkgreen bold"),
+                ui_focus: c("darkcyan bold"),
+                ui_selector: c("grey"),
+                ui_scrollbar: c("grey"),
+
+                item_text: c("darkcyan"),
+                item_menu: c("darkblue"),
+                item_error: c("darkred"),
+                item_search: c("black"),
+                item_telnet: c("grey"),
+                item_external: c("darkgreen"),
+                item_download: c("black underline"),
+                item_media: c("darkgreen underline"),
+                item_unsupported: c("darkred underline"),
+            },
+            "high-contrast" => Theme {
+                ui_cursor: c("yellow bold"),
+                ui_number: c("white bold"),
+                ui_menu: c("white bold"),
+                ui_text: c("white"),
+                ui_new: c("yellow bold"),
+                ui_focus: c("yellow bold"),
+                ui_selector: c("white"),
+                ui_scrollbar: c("white"),
+
+                item_text: c("cyan bold"),
+                item_menu: c("yellow bold"),
+                item_error: c("red bold"),
+                item_search: c("white bold underline"),
+                item_telnet: c("white"),
+                item_external: c("green bold"),
+                item_download: c("white bold underline"),
+                item_media: c("green bold underline"),
+                item_unsupported: c("redbg white bold"),
+            },
+            "mono" => Theme {
+                ui_cursor: c("bold"),
+                ui_number: c(""),
+                ui_menu: c(""),
+                ui_text: c(""),
+                ui_new: c("bold"),
+                ui_focus: c("bold"),
+                ui_selector: c(""),
+                ui_scrollbar: c(""),
+
+                item_text: c(""),
+                item_menu: c("bold"),
+                item_error: c("bold"),
+                item_search: c("underline"),
+                item_telnet: c(""),
+                item_external: c("underline"),
+                item_download: c("underline"),
+                item_media: c("underline"),
+                item_unsupported: c("bold"),
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// `to_color()`, but "" leaves the terminal's own color alone.
+fn color_or_plain(words: &str) -> String {
+    match words {
+        "" => String::new(),
+        words => to_color(words),
+    }
+}
+
+/// How many colors the terminal can show.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ColorDepth {
+    /// The 16 basic ANSI colors.
+    Ansi16,
+    /// The xterm 256 color palette.
+    Ansi256,
+    /// Any RGB color.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guess what the terminal supports from `$COLORTERM` and `$TERM`.
+    pub fn detect() -> ColorDepth {
+        detect_depth(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+}
+
+impl std::str::FromStr for ColorDepth {
+    type Err = std::io::Error;
+
+    /// Accepts "16", "256", or "truecolor" and returns the
+    /// `ColorDepth`, or an `Err`.
+    fn from_str(s: &str) -> std::io::Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "16" => Ok(ColorDepth::Ansi16),
+            "256" => Ok(ColorDepth::Ansi256),
+            "truecolor" | "24bit" => Ok(ColorDepth::TrueColor),
+            _ => Err(error!("Expected auto, 16, 256, or truecolor").into()),
+        }
+    }
+}
+
+fn detect_depth(colorterm: Option<&str>, term: Option<&str>) -> ColorDepth {
+    let colorterm = colorterm.unwrap_or("").to_lowercase();
+    let term = term.unwrap_or("").to_lowercase();
+    if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") {
+        ColorDepth::Ansi256
+    } else {
+        ColorDepth::Ansi16
+    }
+}
+
+impl Theme {
+    /// Swap any colors `depth` can't show for the closest ones it
+    /// can, so a truecolor theme still looks right in a 16 color
+    /// terminal.
+    pub fn downsample(&mut self, depth: ColorDepth) {
+        for code in [
+            &mut self.ui_cursor,
+            &mut self.ui_number,
+            &mut self.ui_menu,
+            &mut self.ui_text,
+            &mut self.ui_new,
+            &mut self.ui_focus,
+            &mut self.ui_selector,
+            &mut self.ui_scrollbar,
+            &mut self.item_text,
+            &mut self.item_menu,
+            &mut self.item_error,
+            &mut self.item_search,
+            &mut self.item_telnet,
+            &mut self.item_external,
+            &mut self.item_download,
+            &mut self.item_media,
+            &mut self.item_unsupported,
+        ] {
+            *code = downsample(code, depth);
+        }
+    }
+}
+
 /// Convert a string like "blue underline" or "red" into a color code.
+/// "#ff8800" is an RGB color and "color208" is one from the 256 color
+/// palette. Add "bg" to either for the background.
 pub fn to_color<S: AsRef<str>>(line: S) -> String {
     if *crate::NO_COLOR {
         return "".into();
@@ -130,7 +311,7 @@ pub fn to_color<S: AsRef<str>>(line: S) -> String {
     let len = parts.len();
 
     for (i, part) in parts.iter().enumerate() {
-        out.push_str(&color_code(part).to_string());
+        out.push_str(&color_code(part));
         if i < len - 1 {
             out.push(';');
         }
@@ -142,16 +323,172 @@ pub fn to_color<S: AsRef<str>>(line: S) -> String {
 
 /// Convert color code like "\x1b[91m" into something like "red"
 pub fn to_words<S: AsRef<str>>(code: S) -> String {
-    code.as_ref()
-        .replace("\x1b[", "")
-        .replace('m', "")
-        .split(';')
-        .map(color_word)
-        .collect::<Vec<_>>()
-        .join(" ")
+    let code = code.as_ref().replace("\x1b[", "").replace('m', "");
+    let mut params = code.split(';');
+    let mut words = vec![];
+    while let Some(param) = params.next() {
+        words.push(match param {
+            "38" | "48" => {
+                let bg = if param == "48" { "bg" } else { "" };
+                match extended_color(&mut params) {
+                    Some(Extended::Rgb(r, g, b)) => format!("#{:02x}{:02x}{:02x}{}", r, g, b, bg),
+                    Some(Extended::Indexed(n)) => format!("color{}{}", n, bg),
+                    None => "white".into(),
+                }
+            }
+            _ => color_word(param).into(),
+        });
+    }
+    words.join(" ")
+}
+
+/// Rewrite the color code `code` using only colors `depth` can show.
+pub fn downsample(code: &str, depth: ColorDepth) -> String {
+    let params = match code.strip_prefix("\x1b[").and_then(|c| c.strip_suffix('m')) {
+        Some(params) if depth != ColorDepth::TrueColor => params,
+        _ => return code.into(),
+    };
+    let mut params = params.split(';');
+    let mut out = vec![];
+    while let Some(param) = params.next() {
+        if param != "38" && param != "48" {
+            out.push(param.to_string());
+            continue;
+        }
+        let bg = param == "48";
+        let rgb = match extended_color(&mut params) {
+            Some(Extended::Rgb(r, g, b)) => (r, g, b),
+            Some(Extended::Indexed(n)) if depth == ColorDepth::Ansi256 => {
+                out.push(format!("{};5;{}", param, n));
+                continue;
+            }
+            Some(Extended::Indexed(n)) if n < 16 => {
+                out.push(ansi16_code(n, bg).to_string());
+                continue;
+            }
+            Some(Extended::Indexed(n)) => index_to_rgb(n),
+            None => continue,
+        };
+        out.push(match depth {
+            ColorDepth::Ansi256 => format!("{};5;{}", param, nearest_256(rgb)),
+            _ => ansi16_code(nearest_16(rgb), bg).to_string(),
+        });
+    }
+    format!("\x1b[{}m", out.join(";"))
+}
+
+/// The color after a 38 or 48 in a color code.
+enum Extended {
+    Rgb(u8, u8, u8),
+    Indexed(u8),
 }
 
-fn color_code(color: &str) -> usize {
+/// Read the rest of a 38 or 48 color: either `2;r;g;b` or `5;n`.
+fn extended_color<'a>(params: &mut impl Iterator<Item = &'a str>) -> Option<Extended> {
+    let mut next = || params.next().and_then(|p| p.parse::<u8>().ok());
+    match next()? {
+        2 => Some(Extended::Rgb(next()?, next()?, next()?)),
+        5 => Some(Extended::Indexed(next()?)),
+        _ => None,
+    }
+}
+
+/// The usual xterm RGB values for the 16 basic colors.
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Levels each channel can take in the 6x6x6 color cube.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The SGR code for basic color `n`, ie 91 for bright red. There are
+/// only dark backgrounds, like in theme files.
+fn ansi16_code(n: u8, bg: bool) -> u8 {
+    match (n < 8, bg) {
+        (true, false) => 30 + n,
+        (false, false) => 90 + n - 8,
+        (_, true) => 40 + n % 8,
+    }
+}
+
+/// RGB value of color `n` in the 256 color palette.
+fn index_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI16[n as usize],
+        16..=231 => {
+            let n = n - 16;
+            (
+                CUBE[(n / 36) as usize],
+                CUBE[(n / 6 % 6) as usize],
+                CUBE[(n % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (n - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Index of the basic color closest to `rgb`.
+fn nearest_16(rgb: (u8, u8, u8)) -> u8 {
+    (0..16u8)
+        .min_by_key(|&n| distance(rgb, ANSI16[n as usize]))
+        .unwrap_or(7)
+}
+
+/// Index of the color in the cube or grey ramp closest to `rgb`.
+/// The basic 16 are skipped since terminals often change them.
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255u8)
+        .min_by_key(|&n| distance(rgb, index_to_rgb(n)))
+        .unwrap_or(16)
+}
+
+fn color_code(color: &str) -> String {
+    let (color, bg) = match color.strip_suffix("bg") {
+        Some(c) if c.starts_with('#') || c.starts_with("color") => (c, true),
+        _ => (color, false),
+    };
+    let prefix = if bg { 48 } else { 38 };
+    if let Some(hex) = color.strip_prefix('#') {
+        if let (6, Ok(rgb)) = (hex.len(), u32::from_str_radix(hex, 16)) {
+            return format!(
+                "{};2;{};{};{}",
+                prefix,
+                rgb >> 16,
+                (rgb >> 8) & 0xff,
+                rgb & 0xff
+            );
+        }
+    }
+    if let Some(Ok(n)) = color.strip_prefix("color").map(str::parse::<u8>) {
+        return format!("{};5;{}", prefix, n);
+    }
+    basic_code(color).to_string()
+}
+
+fn basic_code(color: &str) -> usize {
     match color {
         "bold" => 1,
         "underline" => 4,
@@ -221,13 +558,58 @@ mod tests {
 
     #[test]
     fn test_color_scheme() {
-        let mut theme = Theme::default();
-        theme.ui_cursor = to_color("bold").into();
-        theme.ui_menu = to_color("red").into();
-        theme.item_menu = to_color("blue underline").into();
+        let theme = Theme {
+            ui_cursor: to_color("bold"),
+            ui_menu: to_color("red"),
+            item_menu: to_color("blue underline"),
+            ..Theme::default()
+        };
 
         assert_eq!("\u{1b}[1m", theme.ui_cursor);
         assert_eq!("\u{1b}[91m", theme.ui_menu);
         assert_eq!("\u{1b}[94;4m", theme.item_menu);
     }
+
+    #[test]
+    fn test_extended_colors() {
+        assert_eq!(to_color("#ff8800 bold"), "\x1b[38;2;255;136;0;1m");
+        assert_eq!(to_color("color17bg"), "\x1b[48;5;17m");
+        assert_eq!(to_words("\x1b[38;2;255;136;0;1m"), "#ff8800 bold");
+        assert_eq!(to_words("\x1b[48;5;17m"), "color17bg");
+    }
+
+    #[test]
+    fn test_downsample() {
+        let orange = to_color("#ff8800 underline");
+        assert_eq!(downsample(&orange, ColorDepth::TrueColor), orange);
+        assert_eq!(downsample(&orange, ColorDepth::Ansi256), "\x1b[38;5;208;4m");
+        assert_eq!(downsample(&orange, ColorDepth::Ansi16), "\x1b[33;4m");
+
+        let mut theme = Theme {
+            ui_menu: to_color("#ff0000"),
+            item_menu: to_color("#000080bg white"),
+            item_text: to_color("color21"),
+            ..Theme::default()
+        };
+        theme.downsample(ColorDepth::Ansi16);
+        assert_eq!(theme.ui_menu, "\x1b[91m");
+        assert_eq!(theme.item_menu, "\x1b[44;97m");
+        assert_eq!(theme.item_text, "\x1b[34m");
+        assert_eq!(theme.ui_cursor, to_color("white bold"));
+    }
+
+    #[test]
+    fn test_detect_depth() {
+        assert_eq!(detect_depth(None, None), ColorDepth::Ansi16);
+        assert_eq!(detect_depth(None, Some("xterm")), ColorDepth::Ansi16);
+        assert_eq!(
+            detect_depth(None, Some("xterm-256color")),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            detect_depth(Some("truecolor"), Some("xterm-256color")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(detect_depth(Some("24bit"), None), ColorDepth::TrueColor);
+    }
 }
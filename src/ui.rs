@@ -12,26 +12,40 @@
 //! renders its content to a String. The UI is what draws it.
 
 mod action;
+mod cache;
+mod keymap;
 mod mode;
 mod view;
-pub use self::{action::Action, mode::Mode, view::View};
+pub use self::{
+    action::Action,
+    keymap::Keymap,
+    mode::Mode,
+    view::{ColorMode, View},
+};
 
+use self::cache::Cache;
 use crate::{
-    bookmarks,
-    config::{Config, SharedConfig},
+    bookmarks, broken, clock,
+    config::{self, Config, OpenPolicy, SharedConfig},
     encoding::Encoding,
+    error::{Error, Result},
     gopher::{self, Type},
-    help, history,
+    help, history, local,
     menu::Menu,
-    terminal,
+    netlog, notes, phetchdir, seen, terminal,
     text::Text,
-    theme, utils, BUG_URL,
+    theme::{self, Theme},
+    utils, BUG_URL,
 };
 use std::{
-    io::{stdin, stdout, Result, Write},
+    collections::HashMap,
+    fs,
+    io::{stdin, stdout, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
     process::{self, Stdio},
     sync::{
-        mpsc::{channel, Receiver, Sender},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
         Arc, Mutex, RwLock,
     },
     thread,
@@ -50,6 +64,196 @@ pub type KeyReceiver = Arc<Mutex<Receiver<Key>>>;
 /// wrapping in text views.
 pub const MAX_COLS: usize = 77;
 
+/// Lines from the last screen that stay in view when paging by a
+/// full screen, so it's easy to pick up where you were.
+pub const PAGE_OVERLAP: usize = 2;
+
+/// How far page up and page down go by default: a screen of `rows`,
+/// less `PAGE_OVERLAP` unless the screen is tiny.
+pub fn page_scroll(rows: usize) -> usize {
+    if rows > PAGE_OVERLAP * 2 {
+        rows - PAGE_OVERLAP
+    } else {
+        rows.max(1)
+    }
+}
+
+/// Columns on the right of the status line kept free for the
+/// connection status when showing a page summary.
+const STATUS_RESERVED: usize = 12;
+
+/// How many closed views we remember for reopening with `T`.
+const MAX_CLOSED: usize = 20;
+
+/// What the user said to a `confirm()` question.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Answer {
+    /// ENTER or Y.
+    Yes,
+    /// N, or any other key.
+    No,
+    /// Esc or Ctrl-c: forget the whole thing.
+    Cancel,
+}
+
+/// Which of the `page` rows of a scrollbar the thumb covers, when
+/// scrolled `offset` rows into `total`. None if it all fits on one
+/// page.
+fn scrollbar_thumb(offset: usize, total: usize, page: usize) -> Option<std::ops::Range<usize>> {
+    if total <= page || page == 0 {
+        return None;
+    }
+    let size = (page * page / total).max(1);
+    let start = (offset * page / total).min(page - size);
+    Some(start..start + size)
+}
+
+/// What to ask before opening a telnet session, with everything the
+/// menu asked for spelled out. Control characters are dropped so a
+/// menu can't mess with the terminal.
+fn telnet_question(host: &str, port: &str, login: &str) -> String {
+    let mut question = format!("Telnet to {} port {}", host, port);
+    if !login.is_empty() {
+        question.push_str(&format!(", login \"{}\"", login));
+    }
+    question.push('?');
+    question.retain(|c| !c.is_control());
+    question
+}
+
+/// Fill in the `status_format` placeholders for `view`, which was
+/// opened as `title`. Unknown ones are left as they are.
+fn format_status(format: &str, view: &dyn View, title: &str) -> String {
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        match &rest[1..end] {
+            "url" => out.push_str(view.url()),
+            "title" => out.push_str(title),
+            "type" => out.push_str(&format!("{:?}", gopher::type_for_url(view.url()))),
+            "pos" => {
+                if let Some((offset, total)) = view.scroll_position() {
+                    out.push_str(&format!("{}/{}", (offset + 1).min(total), total));
+                }
+            }
+            "links" => out.push_str(&view.link_count().to_string()),
+            _ => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Refuse URLs whose host `allow_hosts` or `deny_hosts` rules out.
+/// phetch's own pages and `--serve-local` are always allowed.
+fn check_host(config: &Config, url: &str) -> Result<()> {
+    if gopher::view_prefix(url).1.starts_with("gopher://phetch/") {
+        return Ok(());
+    }
+    let host = gopher::url_host(url);
+    // The host rules are about what phetch talks to over the
+    // network. The directory given to --serve-local is read
+    // straight off the disk, so they'd only get in the way of
+    // previewing it. Without --serve-local, "local" is just
+    // another host name and the rules apply.
+    if local::root_for(host).is_some() {
+        return Ok(());
+    }
+    if config.host_allowed(host) {
+        Ok(())
+    } else {
+        Err(Error::Blocked(host.to_string()))
+    }
+}
+
+/// A link to `url` named `title`, written out using `template`, like
+/// "[{title}]({url})" for Markdown. Brackets and parens that would
+/// end a Markdown or org-mode link early are escaped, and a title
+/// with "{url}" in it is left as it is.
+fn format_link(template: &str, title: &str, url: &str) -> String {
+    let (title, url) = if template.contains("[[{url}][{title}]]") {
+        // org-mode can't escape brackets in a description
+        (
+            title.replace('[', "{").replace(']', "}"),
+            url.replace('[', "%5B").replace(']', "%5D"),
+        )
+    } else if template.contains("[{title}]({url})") {
+        (
+            title
+                .replace('\\', "\\\\")
+                .replace('[', "\\[")
+                .replace(']', "\\]"),
+            url.replace('(', "%28")
+                .replace(')', "%29")
+                .replace(' ', "%20"),
+        )
+    } else {
+        (title.to_string(), url.to_string())
+    };
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        match &rest[1..end] {
+            "title" => out.push_str(&title),
+            "url" => out.push_str(&url),
+            _ => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `path` with a leading `~` swapped for the home directory.
+fn expand_home(path: &str) -> Result<PathBuf> {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match std::env::var("HOME") {
+            Ok(home) => Ok(PathBuf::from(format!("{}{}", home, rest))),
+            Err(_) => Err(error!("$HOME not set, can't decode `~`")),
+        },
+        _ => Ok(PathBuf::from(path)),
+    }
+}
+
+/// The phetch page a short URL like gopher://bookmarks/ stands for,
+/// or `url` itself.
+fn expand_alias(url: String) -> String {
+    match url.trim_end_matches('/') {
+        "gopher://bookmarks" => "gopher://phetch/1/bookmarks".into(),
+        _ => url,
+    }
+}
+
+/// The bottom `row` of the screen showing `prompt` and what's been
+/// typed so far. `masked` input is shown as one `*` per character.
+fn prompt_line(row: u16, prompt: &str, input: &str, masked: bool) -> String {
+    let input = if masked {
+        "*".repeat(input.chars().count())
+    } else {
+        input.to_string()
+    };
+    format!(
+        "{}{}{}{}",
+        terminal::Goto(1, row),
+        terminal::ClearCurrentLine,
+        prompt,
+        input
+    )
+}
+
 /// Fatal errors. In general we want to try and catch any errors
 /// (network, parsing gopher response, etc) and just show an error
 /// message in the status bar, but if we can't write to STDOUT or
@@ -57,15 +261,48 @@ pub const MAX_COLS: usize = 77;
 const ERR_SCREEN: &str = "Fatal Error using Alternate Screen.";
 const ERR_STDOUT: &str = "Fatal Error writing to STDOUT.";
 
+/// The key sent down the keyboard channel when the terminal is
+/// resized, to wake up the input loop so it redraws.
+const RESIZE: Key = Key::F(5);
+
 lazy_static! {
     /// Channel to send SIGWINCH (resize) events on, once received.
     static ref RESIZE_SENDER: Arc<Mutex<Option<Sender<Key>>>> = Arc::new(Mutex::new(None));
 }
 
+/// Draw `label` and some dots on row `rows` of `out` until something
+/// comes down `stop`. Nothing's drawn if that happens within `delay`,
+/// so quick requests don't flicker. Returns `out` when it's done.
+fn spin<W: Write>(mut out: W, label: &str, rows: u16, delay: Duration, stop: Receiver<bool>) -> W {
+    let stopped = |wait| !matches!(stop.recv_timeout(wait), Err(RecvTimeoutError::Timeout));
+    if stopped(delay) {
+        return out;
+    }
+    for i in (0..=3).cycle() {
+        write!(
+            out,
+            "{}{}{}{}{}{}{}",
+            terminal::Goto(1, rows),
+            terminal::HideCursor,
+            label,
+            ".".repeat(i),
+            terminal::ClearUntilNewline,
+            theme::color::Reset,
+            terminal::ShowCursor,
+        )
+        .expect(ERR_STDOUT);
+        out.flush().expect(ERR_STDOUT);
+        if stopped(Duration::from_millis(500)) {
+            break;
+        }
+    }
+    out
+}
+
 /// Raw resize handler that is called when SIGWINCH is received.
 fn resize_handler(_: i32) {
     if let Some(sender) = &*RESIZE_SENDER.lock().unwrap() {
-        sender.send(Key::F(5)).unwrap();
+        sender.send(RESIZE).unwrap();
     }
 }
 
@@ -85,6 +322,8 @@ pub struct UI {
     views: Vec<Box<dyn View>>,
     /// Index of currently focused View
     focused: usize,
+    /// Index of the View focused before this one, for Ctrl-^
+    previous: usize,
     /// Does the UI need to be entirely redrawn?
     dirty: bool,
     /// Is the UI running?
@@ -97,6 +336,47 @@ pub struct UI {
     config: SharedConfig,
     /// Channel where UI events are sent.
     keys: KeyReceiver,
+    /// URLs of views dropped from history, most recent last.
+    closed: Vec<String>,
+    /// Pages we've already loaded, ready to show again.
+    cache: Cache,
+    /// Links of the menu we last opened a link from, so `]` and `[`
+    /// can read straight through a phlog's posts.
+    siblings: Vec<(String, String)>,
+    /// Waiting for the letter of a bookmark collection after `s`?
+    filing: bool,
+    /// URL being fetched, while `placeholder` is on.
+    loading: Option<String>,
+    /// The title each URL was opened with, for `status_format`. Only
+    /// pages that are open or cached keep theirs.
+    titles: HashMap<String, String>,
+    /// Downloads to retry after the next successful fetch, when
+    /// `queue_downloads` is on.
+    queued: Vec<String>,
+    /// Window title we last set, when `set_title` is on. Empty until
+    /// the first one, which also saves the terminal's own title.
+    title: String,
+    /// The page pinned with `L` to reload every `pin_interval`.
+    pinned: Option<Pin>,
+    /// The theme from the config and its `theme_preset`, put aside
+    /// while `C` shows the built-in ones.
+    own_theme: Option<(String, Theme)>,
+    /// Where downloads are saved. Empty means the current directory.
+    download_dir: PathBuf,
+}
+
+/// How long to wait after a keypress before reloading a pinned page,
+/// so it doesn't jump around while you're scrolling.
+const PIN_IDLE: Duration = Duration::from_secs(3);
+
+/// A page that reloads itself while it's on screen.
+struct Pin {
+    /// URL of the pinned page.
+    url: String,
+    /// When it was last loaded, from `clock::now()`.
+    refreshed: Duration,
+    /// When a key was last pressed.
+    touched: Duration,
 }
 
 impl UI {
@@ -107,15 +387,43 @@ impl UI {
             size = (cols as usize, rows as usize);
         };
 
-        UI {
+        let mut ui = UI {
             views: vec![],
             focused: 0,
+            previous: 0,
             dirty: true,
             running: true,
             size,
-            config: Arc::new(RwLock::new(config)),
             status: String::new(),
             keys: Self::spawn_keyboard_listener(),
+            cache: Cache::new(config.cache_size),
+            config: Arc::new(RwLock::new(config)),
+            closed: vec![],
+            siblings: vec![],
+            filing: false,
+            loading: None,
+            titles: HashMap::new(),
+            queued: vec![],
+            title: String::new(),
+            pinned: None,
+            own_theme: None,
+            download_dir: PathBuf::new(),
+        };
+        gopher::set_interrupt(Some(ui.keys.clone()));
+        ui.show_config_warnings();
+        ui
+    }
+
+    /// Let the user know about anything in their config we had to
+    /// ignore.
+    fn show_config_warnings(&mut self) {
+        let warnings = self.config.read().unwrap().warnings.clone();
+        if let Some(first) = warnings.first() {
+            let mut status = format!("Config error, using default: {}", first);
+            if warnings.len() > 1 {
+                status.push_str(&format!(" (+{} more)", warnings.len() - 1));
+            }
+            self.set_status(&status);
         }
     }
 
@@ -130,16 +438,24 @@ impl UI {
 
     /// Print the current view to the screen in rendered form.
     pub fn draw(&mut self) -> Result<()> {
-        let status = self.render_status();
+        let status = if self.reader() {
+            String::new()
+        } else {
+            self.render_status()
+        };
         let mut out = stdout();
+        if let Some(title) = self.render_title() {
+            out.write_all(title.as_ref())?;
+        }
         if self.dirty {
             let screen = self.render()?;
             write!(
                 out,
-                "{}{}{}{}",
+                "{}{}{}{}{}",
                 terminal::Goto(1, 1),
                 terminal::HideCursor,
                 screen,
+                self.render_scrollbar().unwrap_or_default(),
                 status,
             )?;
             out.flush()?;
@@ -153,56 +469,184 @@ impl UI {
 
     /// Accept user input and update data.
     pub fn update(&mut self) {
+        if self.pin_wait() == Some(Duration::ZERO) {
+            if let Err(e) = self.refresh_pin() {
+                self.set_status(&format!("Couldn't reload pinned page: {}", e));
+            }
+            return;
+        }
         let action = self.process_view_input();
         if !action.is_none() {
             self.status.clear();
         }
         if let Err(e) = self.process_action(action) {
+            netlog::error(&e.to_string());
+            let hint = match &e {
+                Error::Unresolved(host) => {
+                    history::similar_host(host).map(|similar| format!(" Did you mean {}?", similar))
+                }
+                _ => None,
+            };
             self.set_status(&format!(
-                "{}{}{}",
+                "{}{}{}{}",
                 &self.config.read().unwrap().theme.item_error,
                 e,
+                hint.unwrap_or_default(),
                 terminal::HideCursor
             ));
         }
     }
 
-    /// Reload the currently focused view while preserving history.
+    /// Reload the currently focused view while preserving history,
+    /// keeping the scroll position and selected link where we can.
+    /// The old view stays put until the new one has loaded, so a
+    /// failed reload leaves everything as it was.
     pub fn reload(&mut self, title: &str, url: &str) -> Result<()> {
-        let mut rest = if self.views.len() > self.focused + 1 {
-            self.views.drain(self.focused..).collect()
-        } else {
-            vec![self.views.remove(self.views.len() - 1)]
+        self.check_host(url)?;
+        let cached = self
+            .without_password(gopher::view_prefix(url).1)
+            .to_string();
+        self.cache.remove(&cached);
+        let view = match gopher::view_prefix(url) {
+            (Some(typ), bare) => self.load_as(title, bare, typ)?,
+            (None, _) => self.load(title, url)?,
         };
-        if self.focused > 0 {
-            self.focused -= 1;
-        }
-        self.open(title, url)?;
-        if rest.len() > 1 {
-            rest.remove(0); // drop the view we're reloading
-            self.views.append(&mut rest);
+        let mut view = match view {
+            Some(view) => view,
+            None => return Ok(()),
+        };
+        self.announce(view.as_ref());
+        match self.views.get_mut(self.focused) {
+            Some(old) => {
+                if view.url() == url {
+                    let (offset, link) = old.place();
+                    view.restore_place(offset, link.as_deref());
+                }
+                *old = view;
+                self.dirty = true;
+            }
+            None => self.add_view(view),
         }
         Ok(())
     }
 
     /// Open a URL - Gopher, internal, telnet, or something else.
     pub fn open(&mut self, title: &str, url: &str) -> Result<()> {
+        let policy = self.config.read().unwrap().open;
+        match self.with_password(&expand_alias(gopher::clean_url(url))) {
+            Some(url) => {
+                let policy = self.resolve_policy(policy, &url);
+                self.open_with(title, &url, policy)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Turn `OpenPolicy::SameHost` into Replace if `url` is a menu on
+    /// the focused view's host, or Push if it's not.
+    fn resolve_policy(&self, policy: OpenPolicy, url: &str) -> OpenPolicy {
+        if policy != OpenPolicy::SameHost {
+            return policy;
+        }
+        match self.views.get(self.focused) {
+            Some(view)
+                if gopher::type_for_url(url) == Type::Menu
+                    && gopher::url_host(view.url()) == gopher::url_host(url) =>
+            {
+                OpenPolicy::Replace
+            }
+            _ => OpenPolicy::Push,
+        }
+    }
+
+    /// If `url` wants a password, ask for one and add it to the
+    /// selector after a tab. None if the user cancels.
+    fn with_password(&self, url: &str) -> Option<String> {
+        let u = gopher::parse_url(url);
+        if u.sel.contains('\t') || !self.config.read().unwrap().asks_password(u.host, &u.sel) {
+            return Some(url.to_string());
+        }
+        let password = self.prompt_secret("Password: ")?;
+        netlog::hide(&password);
+        Some(format!("{}?{}", url, password))
+    }
+
+    /// `url` without the password `with_password()` added to it, for
+    /// anything phetch remembers about the page: seen links,
+    /// bookmarks, and titles. Pages behind a password aren't cached.
+    fn without_password<'a>(&self, url: &'a str) -> &'a str {
+        let config = self.config.read().unwrap();
+        let u = gopher::parse_url(url);
+        match u.sel.split_once('\t') {
+            Some((sel, _)) if config.asks_password(u.host, sel) => {
+                &url[..url.rfind('?').unwrap_or(url.len())]
+            }
+            _ => url,
+        }
+    }
+
+    /// `url` the way history should remember it: without a password,
+    /// unless `save_passwords` is on.
+    fn history_url<'a>(&self, url: &'a str) -> &'a str {
+        if self.config.read().unwrap().save_passwords {
+            url
+        } else {
+            self.without_password(url)
+        }
+    }
+
+    /// Forget the titles of pages that are neither open nor cached,
+    /// except `url`'s, so they don't pile up as we browse.
+    fn forget_titles(&mut self, url: &str) {
+        let open = self
+            .views
+            .iter()
+            .map(|view| self.without_password(view.url()).to_string())
+            .collect::<Vec<_>>();
+        let cache = &self.cache;
+        self.titles
+            .retain(|u, _| u == url || open.contains(u) || cache.contains(u));
+    }
+
+    /// What to call the page at `url`: the title it was opened with,
+    /// or its URL, without any password, if it didn't have one.
+    fn title_for<'a>(&'a self, url: &'a str) -> &'a str {
+        let url = self.without_password(url);
+        self.titles.get(url).map_or(url, |t| t.as_str())
+    }
+
+    /// Open a URL, pushing a new view or replacing the focused one
+    /// according to `policy`.
+    fn open_with(&mut self, title: &str, url: &str, policy: OpenPolicy) -> Result<()> {
+        self.check_host(url)?;
+
         if let Some(view) = self.views.get(self.focused) {
             if view.url() == url {
                 return self.reload(title, url);
             }
         }
 
+        // text: and menu: force how a gopher URL is displayed
+        if let (Some(typ), url) = gopher::view_prefix(url) {
+            if let Some(view) = self.load_as(title, url, typ)? {
+                self.place_view(view, policy);
+            }
+            return Ok(());
+        }
+
         // telnet
         if url.starts_with("telnet://") {
             return self.telnet(url);
         }
 
         // non-gopher URL
-        if url.contains("://") && !url.starts_with("gopher://") {
+        if url.contains("://")
+            && !url.starts_with("gopher://")
+            && !url.starts_with(gopher::TLS_SCHEME)
+        {
             self.dirty = true;
-            return if self.confirm(&format!("Open external URL? {}", url)) {
-                utils::open_external(url)
+            return if self.confirm(&format!("Open external URL? {}", url)) == Answer::Yes {
+                Ok(utils::open_external(url)?)
             } else {
                 Ok(())
             };
@@ -214,9 +658,9 @@ impl UI {
         if typ.is_media() && self.config.read().unwrap().media.is_some() {
             self.dirty = true;
             return if self.config.read().unwrap().autoplay
-                || self.confirm(&format!("Open in media player? {}", url))
+                || self.confirm(&format!("Open in media player? {}", url)) == Answer::Yes
             {
-                utils::open_media(self.config.read().unwrap().media.as_ref().unwrap(), url)
+                self.play_media(url)
             } else {
                 Ok(())
             };
@@ -224,16 +668,81 @@ impl UI {
 
         if typ.is_download() {
             self.dirty = true;
-            return if self.confirm(&format!("Download {}?", url)) {
+            let u = gopher::parse_url(url);
+            if self.config.read().unwrap().trusts(u.host, u.port) {
+                self.download(url)?;
+                let status = format!("Auto-downloaded from trusted host. {}", self.status);
+                self.set_status(&status);
+                return Ok(());
+            }
+            return if self.confirm(&format!("Download {}?", url)) == Answer::Yes {
                 self.download(url)
             } else {
                 Ok(())
             };
         }
 
-        self.load(title, url).map(|view| {
-            self.add_view(view);
-        })
+        if let Some(view) = self.load(title, url)? {
+            self.place_view(view, policy);
+        }
+        Ok(())
+    }
+
+    /// Add a freshly loaded view according to `policy`.
+    fn place_view(&mut self, view: Box<dyn View>, policy: OpenPolicy) {
+        self.announce(view.as_ref());
+        match policy {
+            OpenPolicy::Push | OpenPolicy::SameHost => self.add_view(view),
+            OpenPolicy::Replace => self.replace_view(view),
+        }
+    }
+
+    /// Show the note or summary for a freshly loaded view.
+    fn announce(&mut self, view: &dyn View) {
+        // don't cover up something more important, like config errors
+        if self.status.is_empty() {
+            if let Some(note) = notes::get(view.url()) {
+                self.set_status(&format!("Note: {}", note));
+            } else if let Some(summary) = view.summary() {
+                self.set_summary(&summary);
+            }
+        }
+    }
+
+    /// Open what was typed at the Go to URL prompt. If it looks like a
+    /// search phrase, offer to search `search_url` for it instead.
+    fn go_to(&mut self, input: &str) -> Result<()> {
+        let search_url = self.config.read().unwrap().search_url.clone();
+        if !search_url.is_empty() && gopher::looks_like_search(input) {
+            let query = input.trim();
+            self.dirty = true;
+            let question = format!(
+                "Search {} for \"{}\"?",
+                gopher::url_host(&search_url),
+                query
+            );
+            // no means try it as a URL after all
+            match self.confirm(&question) {
+                Answer::Yes => return self.open(query, &format!("{}?{}", search_url, query)),
+                Answer::No => {}
+                Answer::Cancel => return Ok(()),
+            }
+        }
+        let url = gopher::normalize_url(input);
+        if gopher::type_for_url(&url) == Type::Search && !gopher::parse_url(&url).sel.contains('\t')
+        {
+            return match self.prompt("Search: ", "") {
+                Some(query) if !query.trim().is_empty() => {
+                    let url = format!("{}?{}", url, query.trim());
+                    self.open(&url, &url)
+                }
+                _ => {
+                    self.set_status("Search cancelled.");
+                    Ok(())
+                }
+            };
+        }
+        self.open(&url, &url)
     }
 
     /// Used to download content of the current view with a provided filename
@@ -260,6 +769,63 @@ impl UI {
         })
     }
 
+    /// Fetch the submenu at `url` and show it inline in the focused
+    /// menu.
+    fn expand(&mut self, url: &str) -> Result<()> {
+        self.check_host(url)?;
+        let thread_url = url.to_string();
+        let (tls, tor) = (
+            self.config.read().unwrap().tls,
+            self.config.read().unwrap().tor,
+        );
+        let (_, body) = self.spinner(&format!("Loading {}", url), move || {
+            gopher::fetch_url(&thread_url, tls, tor)
+        })??;
+        if let Some(view) = self.views.get_mut(self.focused) {
+            view.expand(url, gopher::response_to_string(&body));
+        }
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Open a sound or video in the media player. Unless
+    /// `stream_media` is on, or it's on the web, it's downloaded to a
+    /// temporary file first, which is removed once the player exits.
+    fn play_media(&mut self, url: &str) -> Result<()> {
+        let (player, stream) = {
+            let config = self.config.read().unwrap();
+            (
+                config.media.clone().unwrap_or_default(),
+                config.stream_media,
+            )
+        };
+        if stream || url.contains("URL:") {
+            return Ok(utils::open_media(&player, url)?);
+        }
+        let path = self.download_media(url)?;
+        let res = utils::open_media(&player, &path.to_string_lossy());
+        let _ = fs::remove_file(&path);
+        Ok(res?)
+    }
+
+    /// Download `url` to a temporary file for the media player.
+    fn download_media(&mut self, url: &str) -> Result<PathBuf> {
+        let filename = gopher::download_filename(url)?;
+        let path = std::env::temp_dir().join(format!("phetch-{}-{}", process::id(), filename));
+        let _ = fs::remove_file(&path);
+        let (url, target) = (url.to_string(), path.to_string_lossy().to_string());
+        let (tls, tor) = (
+            self.config.read().unwrap().tls,
+            self.config.read().unwrap().tor,
+        );
+        let chan = self.keys.clone();
+        self.spinner(&format!("Downloading {}", url), move || {
+            gopher::download_url_with_filename(&url, tls, tor, chan, &target)
+        })
+        .and_then(|res| res)?;
+        Ok(path)
+    }
+
     /// Download a binary file. Used by `open()` internally.
     fn download(&mut self, url: &str) -> Result<()> {
         let url = url.to_string();
@@ -268,63 +834,287 @@ impl UI {
             self.config.read().unwrap().tor,
         );
         let chan = self.keys.clone();
-        self.spinner(&format!("Downloading {}", url), move || {
-            gopher::download_url(&url, tls, tor, chan)
-        })
-        .and_then(|res| res)
-        .map(|(path, bytes)| {
-            self.set_status(
-                format!(
-                    "Download complete! {} saved to {}",
-                    utils::human_bytes(bytes),
-                    path
-                )
-                .as_ref(),
-            );
-        })
+        let thread_url = url.clone();
+        let dir = self.download_dir.clone();
+        let res = self
+            .spinner(&format!("Downloading {}", url), move || {
+                gopher::download_url(&thread_url, tls, tor, chan, &dir)
+            })
+            .and_then(|res| res);
+        match res {
+            Ok((path, bytes)) => {
+                self.set_status(
+                    format!(
+                        "Download complete! {} saved to {}",
+                        utils::human_bytes(bytes),
+                        path
+                    )
+                    .as_ref(),
+                );
+                Ok(())
+            }
+            Err(e) if e.is_host_down() && self.config.read().unwrap().queue_downloads => {
+                if !self.queued.contains(&url) {
+                    self.queued.push(url);
+                }
+                self.set_status(&format!(
+                    "{}. Download queued until the next page loads.",
+                    e
+                ));
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Try the downloads that were queued while their hosts were
+    /// unreachable. Ones that still can't be reached stay queued.
+    fn retry_downloads(&mut self) {
+        for url in std::mem::take(&mut self.queued) {
+            if let Err(e) = self.download(&url) {
+                self.set_status(&format!("Download failed: {}", e));
+            }
+        }
+    }
+
+    /// Refuse URLs whose host `allow_hosts` or `deny_hosts` rules out.
+    fn check_host(&self, url: &str) -> Result<()> {
+        check_host(&self.config.read().unwrap(), url)
     }
 
-    /// Fetches a URL and returns a View for its content.
-    fn load(&mut self, title: &str, url: &str) -> Result<Box<dyn View>> {
+    /// Fetches a URL and returns a View for its content, or None if
+    /// there's nothing to show, like when a "text" file was binary.
+    fn load(&mut self, title: &str, url: &str) -> Result<Option<Box<dyn View>>> {
+        self.load_as(title, url, gopher::type_for_url(url))
+    }
+
+    /// Fetches a URL and displays it as `typ`, whatever the URL says.
+    fn load_as(&mut self, title: &str, url: &str, typ: Type) -> Result<Option<Box<dyn View>>> {
+        let cached = self.without_password(url).to_string();
+        // a page behind a password is only as good as the password
+        // it was fetched with, so those always go to the server
+        let cacheable = cached == url;
+        if !title.is_empty() {
+            self.titles.insert(cached.clone(), title.to_string());
+            self.forget_titles(&cached);
+        }
         // on-line help
         if url.starts_with("gopher://phetch/") {
-            return self.load_internal(url);
+            if typ == Type::Text {
+                let source = self.load_internal(url)?.raw().to_string();
+                return Ok(Some(Box::new(Text::from(
+                    url,
+                    source.into(),
+                    self.config.clone(),
+                    false,
+                ))));
+            }
+            return self.load_internal(url).map(Some);
+        }
+        // record history urls, unless we're incognito
+        if !self.config.read().unwrap().incognito {
+            let hurl = self.history_url(url).to_string();
+            let hname = title.to_string();
+            let limit = self.config.read().unwrap().history_size;
+            thread::spawn(move || history::save(&hname, &hurl, limit));
+        }
+        if let Some(mut view) = self.cache.get(&cached, typ).filter(|_| cacheable) {
+            view.set_wide(self.config.read().unwrap().wide_for(url));
+            return Ok(Some(view));
         }
-        // record history urls
-        let hurl = url.to_string();
-        let hname = title.to_string();
-        thread::spawn(move || history::save(&hname, &hurl));
         // request thread
         let thread_url = url.to_string();
         let (tls, tor) = (
             self.config.read().unwrap().tls,
             self.config.read().unwrap().tor,
         );
+        if self.config.read().unwrap().placeholder {
+            self.loading = Some(cached.clone());
+            self.dirty = true;
+            self.draw()?;
+        }
         // don't spin on first ever request
-        let (tls, res) = if self.views.is_empty() {
-            gopher::fetch_url(&thread_url, tls, tor)?
+        let fetched = if self.views.is_empty() {
+            gopher::fetch_url(&thread_url, tls, tor)
         } else {
-            self.spinner("", move || gopher::fetch_url(&thread_url, tls, tor))??
+            let host = gopher::url_host(url);
+            let label = if gopher::polite_wait(host).is_zero() {
+                String::new()
+            } else {
+                format!("Waiting to be polite to {}", host)
+            };
+            self.spinner(&label, move || gopher::fetch_url(&thread_url, tls, tor))
+                .and_then(|res| res)
         };
-        let typ = gopher::type_for_url(url);
-        match typ {
-            Type::Menu | Type::Search => Ok(Box::new(Menu::from(
-                url,
-                gopher::response_to_string(&res),
-                self.config.clone(),
-                tls,
-            ))),
-            Type::Text | Type::HTML => Ok(Box::new(Text::from(url, res, self.config.clone(), tls))),
-            _ => Err(error!("Unsupported Gopher Response: {:?}", typ)),
+        // back to the old view if this failed, or on to the new one
+        if self.loading.take().is_some() {
+            self.dirty = true;
+        }
+        let (tls, mut res) = match fetched {
+            Err(Error::TooLarge(max)) => {
+                self.dirty = true;
+                let question = format!(
+                    "Response too large (> {}), download instead?",
+                    utils::human_bytes(max)
+                );
+                if self.confirm(&question) == Answer::Yes {
+                    self.download(url)?;
+                }
+                return Ok(None);
+            }
+            fetched => fetched?,
+        };
+        if !self.queued.is_empty() {
+            self.retry_downloads();
+        }
+        let u = gopher::parse_url(url);
+        if self.config.read().unwrap().strips_echo(u.host) {
+            gopher::strip_echo(&mut res, &u.sel);
+        }
+        let view: Box<dyn View> = match typ {
+            Type::Menu | Type::Search => {
+                let mut menu =
+                    Menu::from_bytes(url, &res, Encoding::UTF8, self.config.clone(), tls);
+                if self.config.read().unwrap().mark_new {
+                    self.mark_new_links(&mut menu);
+                }
+                Box::new(menu)
+            }
+            Type::Text | Type::HTML if gopher::looks_binary(&res) => {
+                self.dirty = true;
+                let question = format!("{} looks like a binary file. Download?", url);
+                if self.confirm(&question) == Answer::Yes {
+                    self.save_response(url, &res)?;
+                }
+                return Ok(None);
+            }
+            Type::Text | Type::HTML => Box::new(Text::from(url, res, self.config.clone(), tls)),
+            _ => return Err(Error::Unsupported(typ)),
+        };
+        if cacheable {
+            self.cache.insert(&cached, typ, view.as_ref());
+        }
+        Ok(Some(view))
+    }
+
+    /// Save a response we've already fetched, like `download()` would.
+    fn save_response(&mut self, url: &str, res: &[u8]) -> Result<()> {
+        let path = self.download_dir.join(gopher::download_filename(url)?);
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o770)
+            .open(&path)?
+            .write_all(res)?;
+        self.set_status(&format!(
+            "Download complete! {} saved to {}",
+            utils::human_bytes(res.len()),
+            path.display()
+        ));
+        Ok(())
+    }
+
+    /// Mark the links that have appeared on `menu` since we last saw
+    /// it, then remember its current links for next time.
+    fn mark_new_links(&self, menu: &mut Menu) {
+        let links = menu.link_urls();
+        let url = self.without_password(&menu.url).to_string();
+        if let Some(seen) = seen::load(&url) {
+            menu.new_links = seen::new_links(&seen, &links);
+        }
+        if !self.config.read().unwrap().incognito {
+            if let Ok(dir) = phetchdir::path() {
+                thread::spawn(move || seen::save(&dir, &url, &links));
+            }
+        }
+    }
+
+    /// A Gophermap of the pages in the cache that mention `query`,
+    /// each with the line it's on.
+    fn search_menu(&self, query: &str) -> String {
+        let query = query.trim();
+        let found = self.cache.search(query);
+        if found.is_empty() {
+            return format!(
+                "iNo pages from this session mention \"{}\".\r\ni\r\n\
+                 iOnly pages still in the cache are searched.\r\n",
+                query
+            );
+        }
+        let mut out = format!(
+            "iPages from this session that mention \"{}\":\r\ni\r\n",
+            query
+        );
+        for (url, line) in found {
+            let label = self.title_for(&url).replace('\t', " ");
+            let u = gopher::parse_url(&url);
+            let mut snippet = line.replace('\t', " ");
+            if snippet.chars().count() > 60 {
+                snippet = snippet.chars().take(59).collect::<String>() + "…";
+            }
+            out.push_str(&format!(
+                "{}{}\t{}\t{}\t{}\r\ni  {}\r\n",
+                u.typ.to_char(),
+                label,
+                gopher::menu_selector(&u.sel),
+                u.host,
+                u.port,
+                snippet
+            ));
         }
+        out
     }
 
     /// Get Menu for on-line help, home page, etc, ex: gopher://phetch/1/help/types
     fn load_internal(&mut self, url: &str) -> Result<Box<dyn View>> {
-        if let Some(source) = help::lookup(
-            url.trim_start_matches("gopher://phetch/")
-                .trim_start_matches("1/"),
-        ) {
+        let page = url.trim_start_matches("gopher://phetch/");
+        let page = page
+            .strip_prefix("1/")
+            .or_else(|| page.strip_prefix("7/"))
+            .unwrap_or(page);
+        if let Some(query) = page.strip_prefix("search?") {
+            return Ok(Box::new(Menu::from(
+                url,
+                self.search_menu(query),
+                self.config.clone(),
+                false,
+            )));
+        }
+        // any gophermap can link here, so always ask first
+        if let Some(noted) = page.strip_prefix("notes/delete/") {
+            if self.confirm(&format!("Delete note for {}?", noted)) == Answer::Yes {
+                notes::delete(noted)?;
+                self.set_status(&format!("Deleted note for {}", noted));
+            }
+            return Ok(Box::new(Menu::from(
+                "gopher://phetch/1/notes",
+                notes::as_raw_menu(),
+                self.config.clone(),
+                false,
+            )));
+        }
+        let sources = self.config.read().unwrap().bookmarks.clone();
+        if page == "bookmarks" && !sources.is_empty() {
+            let (tls, tor) = (
+                self.config.read().unwrap().tls,
+                self.config.read().unwrap().tor,
+            );
+            let config = self.config.clone();
+            let source = self.spinner("", move || {
+                bookmarks::as_merged_menu(&sources, tls, tor, |url| {
+                    check_host(&config.read().unwrap(), url)
+                })
+            })?;
+            return Ok(Box::new(Menu::from(
+                url,
+                source,
+                self.config.clone(),
+                false,
+            )));
+        }
+        let source = help::lookup(page, &self.config.read().unwrap());
+        if let Some(source) = source {
             Ok(Box::new(Menu::from(
                 url,
                 source,
@@ -332,7 +1122,7 @@ impl UI {
                 false,
             )))
         } else {
-            Err(error!("phetch URL not found: {}", url))
+            Err(Error::NotFound(url.to_string()))
         }
     }
 
@@ -346,11 +1136,30 @@ impl UI {
         self.size.1 as u16
     }
 
+    /// # of rows the terminal has right now, even if it was resized
+    /// since the last render
+    fn live_rows(&self) -> u16 {
+        terminal_size().map_or(self.rows(), |(_, rows)| rows)
+    }
+
     /// Set the current columns and rows.
     fn term_size(&mut self, cols: usize, rows: usize) {
         self.size = (cols, rows);
     }
 
+    /// Wipe the screen and draw everything again from scratch, in
+    /// case another program wrote over it or the terminal was resized.
+    fn repaint(&mut self) -> Result<()> {
+        if let Ok((cols, rows)) = terminal_size() {
+            self.term_size(cols as usize, rows as usize);
+        }
+        let mut out = stdout();
+        write!(out, "{}", terminal::ClearAll)?;
+        out.flush()?;
+        self.dirty = true;
+        Ok(())
+    }
+
     /// Show a spinner while running a thread. Used to make gopher requests or
     /// download files.
     fn spinner<T: Send + 'static, F: 'static + Send + FnOnce() -> T>(
@@ -362,26 +1171,9 @@ impl UI {
 
         let (tx, rx) = channel();
         let label = label.to_string();
-        let rows = self.rows() as u16;
-        thread::spawn(move || loop {
-            for i in 0..=3 {
-                if rx.try_recv().is_ok() {
-                    return;
-                }
-                print!(
-                    "{}{}{}{}{}{}{}",
-                    terminal::Goto(1, rows),
-                    terminal::HideCursor,
-                    label,
-                    ".".repeat(i),
-                    terminal::ClearUntilNewline,
-                    theme::color::Reset,
-                    terminal::ShowCursor,
-                );
-                stdout().flush().expect(ERR_STDOUT);
-                thread::sleep(Duration::from_millis(500));
-            }
-        });
+        let rows = self.rows();
+        let delay = Duration::from_millis(self.config.read().unwrap().spinner_delay);
+        thread::spawn(move || spin(stdout(), &label, rows, delay, rx));
 
         let result = req.join();
         tx.send(true).expect("Fatal Error in Spinner channel."); // stop spinner
@@ -391,12 +1183,21 @@ impl UI {
 
     /// Create a rendered String for the current View in its current state.
     pub fn render(&mut self) -> Result<String> {
+        if let Some(url) = &self.loading {
+            return Ok(self.render_placeholder(url));
+        }
         // TODO: only get size on SIGWINCH
         if let Ok((cols, rows)) = terminal_size() {
             self.term_size(cols as usize, rows as usize);
             if !self.views.is_empty() && self.focused < self.views.len() {
+                // the scrollbar takes the last column
+                let view_cols = if self.config.read().unwrap().scrollbar && !self.reader() {
+                    (cols as usize).saturating_sub(1)
+                } else {
+                    cols as usize
+                };
                 if let Some(view) = self.views.get_mut(self.focused) {
-                    view.term_size(cols as usize, rows as usize);
+                    view.term_size(view_cols, rows as usize);
                     return Ok(view.render());
                 }
             }
@@ -412,11 +1213,73 @@ impl UI {
         }
     }
 
+    /// A blank screen with "Loading URL…" in the middle, shown
+    /// instead of the focused view while `url` is fetched.
+    fn render_placeholder(&self, url: &str) -> String {
+        let cols = self.cols() as usize;
+        let mut msg = format!("Loading {}…", url);
+        if msg.chars().count() > cols {
+            msg = msg.chars().take(cols.saturating_sub(1)).collect();
+            msg.push('…');
+        }
+        let x = cols.saturating_sub(msg.chars().count()) / 2 + 1;
+        let y = (self.rows() / 2).max(1);
+        format!(
+            "{}{}{}{}{}",
+            terminal::ClearAll,
+            terminal::Goto(x as u16, y),
+            self.config.read().unwrap().theme.ui_text,
+            msg,
+            reset_color!(),
+        )
+    }
+
+    /// Is the focused view in reader mode, with no status line or
+    /// scrollbar?
+    fn reader(&self) -> bool {
+        self.views
+            .get(self.focused)
+            .is_some_and(|view| view.reader())
+    }
+
+    /// Draw the scrollbar in the last column, if it's turned on and
+    /// the focused view doesn't fit on one screen.
+    fn render_scrollbar(&self) -> Option<String> {
+        if self.loading.is_some() || self.reader() || !self.config.read().unwrap().scrollbar {
+            return None;
+        }
+        let (offset, total) = self.views.get(self.focused)?.scroll_position()?;
+        let page = (self.rows() as usize).saturating_sub(1).max(1);
+        let thumb = scrollbar_thumb(offset, total, page)?;
+        let col = self.cols();
+
+        let mut out = self.config.read().unwrap().theme.ui_scrollbar.clone();
+        for row in 0..page {
+            out.push_str(&terminal::Goto(col, row as u16 + 1).to_string());
+            out.push(if thumb.contains(&row) { '█' } else { '│' });
+        }
+        out.push_str(reset_color!());
+        Some(out)
+    }
+
     /// Set the status line's content.
     fn set_status(&mut self, status: &str) {
         self.status = status.replace('\n', "\\n").replace('\r', "\\r");
     }
 
+    /// Show a page summary in the status line, cut short on narrow
+    /// terminals to leave room for the TLS/Tor indicators.
+    fn set_summary(&mut self, summary: &str) {
+        let room = (self.cols() as usize).saturating_sub(STATUS_RESERVED);
+        if self.cols() == 0 || summary.chars().count() <= room {
+            self.set_status(summary);
+        } else if room > 0 {
+            let mut short = summary.chars().take(room - 1).collect::<String>();
+            short.push('…');
+            self.set_status(&short);
+        }
+    }
+
     /// Render the connection status (TLS or Tor).
     fn render_conn_status(&self) -> Option<String> {
         let view = self.views.get(self.focused)?;
@@ -426,6 +1289,26 @@ impl UI {
             status.push("CP439");
         }
 
+        if self
+            .pinned
+            .as_ref()
+            .is_some_and(|pin| pin.url == view.url())
+        {
+            if self.config.read().unwrap().emoji {
+                status.push("📌");
+            } else {
+                status.push("PIN");
+            }
+        }
+
+        if self.config.read().unwrap().incognito {
+            if self.config.read().unwrap().emoji {
+                status.push("🕶");
+            } else {
+                status.push("PRIVATE");
+            }
+        }
+
         if view.is_tls() {
             if self.config.read().unwrap().emoji {
                 status.push("🔐");
@@ -447,7 +1330,7 @@ impl UI {
             let len = len + status.len();
             Some(format!(
                 "{}{}",
-                terminal::Goto(self.cols() - len as u16, self.rows()),
+                terminal::Goto(self.cols().saturating_sub(len as u16).max(1), self.rows()),
                 status
                     .iter()
                     .map(|s| theme::to_color("bold white") + s + reset_color!())
@@ -457,69 +1340,461 @@ impl UI {
         }
     }
 
+    /// The status line when there's no message to show, according to
+    /// `status_format`.
+    fn idle_status(&self) -> Option<String> {
+        let format = self.config.read().unwrap().status_format.clone();
+        if format.is_empty() {
+            return None;
+        }
+        let view = self.views.get(self.focused)?;
+        let title = self.title_for(view.url());
+        Some(format_status(&format, view.as_ref(), title))
+    }
+
+    /// The `focus_marker` for the focused view, in the `ui.focus`
+    /// color, if it's set and there's a view to mark.
+    fn render_focus(&self) -> Option<String> {
+        let cfg = self.config.read().unwrap();
+        if cfg.focus_marker.is_empty() || self.views.get(self.focused).is_none() {
+            return None;
+        }
+        Some(format!(
+            "{}{}{} ",
+            cfg.theme.ui_focus,
+            cfg.focus_marker,
+            theme::color::Reset
+        ))
+    }
+
+    /// The escape sequence to set the window title to the focused
+    /// page, if `set_title` is on and it's changed since last time.
+    fn render_title(&mut self) -> Option<String> {
+        if !self.config.read().unwrap().set_title {
+            return None;
+        }
+        let view = self.views.get(self.focused)?;
+        let page = self.title_for(view.url());
+        let title = format!("phetch: {}", page);
+        if title == self.title {
+            return None;
+        }
+        let push = if self.title.is_empty() {
+            terminal::PUSH_TITLE
+        } else {
+            ""
+        };
+        let out = format!("{}{}", push, terminal::SetTitle(&title));
+        self.title = title;
+        Some(out)
+    }
+
     /// Render the status line.
     fn render_status(&self) -> String {
+        let idle = if self.status.is_empty() {
+            self.idle_status()
+        } else {
+            None
+        };
         format!(
-            "{}{}{}{}{}{}",
+            "{}{}{}{}{}{}{}",
             terminal::HideCursor,
             terminal::Goto(1, self.rows()),
             terminal::ClearCurrentLine,
-            self.status,
+            self.render_focus().unwrap_or_default(),
+            idle.as_deref().unwrap_or(&self.status),
             self.render_conn_status().unwrap_or_else(|| "".into()),
             theme::color::Reset,
         )
     }
 
+    /// Pin the focused page to reload every `pin_interval` seconds,
+    /// or unpin it if it's already pinned.
+    fn toggle_pin(&mut self) {
+        let url = match self.views.get(self.focused) {
+            Some(view) if !view.is_hex_dump() => view.url().to_string(),
+            Some(_) => return self.set_status("Hex dumps can't be pinned."),
+            None => return,
+        };
+        if self.pinned.as_ref().is_some_and(|pin| pin.url == url) {
+            self.pinned = None;
+            self.set_status("Unpinned.");
+            return;
+        }
+        let page = gopher::view_prefix(&url).1;
+        let typ = gopher::type_for_url(page);
+        if page.starts_with("gopher://phetch/")
+            || !matches!(typ, Type::Menu | Type::Search | Type::Text)
+        {
+            return self.set_status("Only menus and text pages can be pinned.");
+        }
+        let now = clock::now();
+        self.pinned = Some(Pin {
+            url,
+            refreshed: now,
+            touched: now,
+        });
+        let secs = self.config.read().unwrap().pin_interval;
+        self.set_status(&format!("Pinned. Reloading every {}s, L to stop.", secs));
+    }
+
+    /// How long until the pinned page should reload, if it's the one
+    /// on screen. Zero means now.
+    fn pin_wait(&self) -> Option<Duration> {
+        let pin = self.pinned.as_ref()?;
+        if self.views.get(self.focused)?.url() != pin.url {
+            return None;
+        }
+        let interval = Duration::from_secs(self.config.read().unwrap().pin_interval);
+        let due = (pin.refreshed + interval).max(pin.touched + PIN_IDLE);
+        Some(clock::until(due))
+    }
+
+    /// Reload the pinned page in place.
+    fn refresh_pin(&mut self) -> Result<()> {
+        let url = match &mut self.pinned {
+            Some(pin) => {
+                pin.refreshed = clock::now();
+                pin.url.clone()
+            }
+            None => return Ok(()),
+        };
+        let title = self.title_for(&url).to_string();
+        self.reload(&title, &url)?;
+        self.set_status(&format!(
+            "Reloaded at {}.",
+            clock::local_time(clock::unix_secs())
+        ));
+        Ok(())
+    }
+
+    /// Show a hex dump of what the server sent for the focused view,
+    /// or go back to the page if it's already a hex dump.
+    fn toggle_hex_dump(&mut self) {
+        let view = match self.views.get(self.focused) {
+            Some(view) => view,
+            None => return,
+        };
+        if view.is_hex_dump() {
+            if self.focused > 0 {
+                self.focus(self.focused - 1);
+            }
+            return;
+        }
+        let hex = Text::hex_dump(
+            view.url(),
+            view.raw_bytes(),
+            self.config.clone(),
+            view.is_tls(),
+        );
+        self.add_view(Box::new(hex));
+    }
+
+    /// Switch to the next built-in theme in `theme::PRESETS`, then
+    /// back to the user's own theme once they've all been shown.
+    fn cycle_theme(&mut self) {
+        let mut cfg = self.config.write().unwrap();
+        let presets = theme::PRESETS.len();
+        let own_preset = self
+            .own_theme
+            .as_ref()
+            .map_or(&cfg.theme_preset, |(p, _)| p);
+        let last = theme::PRESETS
+            .iter()
+            .position(|p| p == own_preset)
+            .unwrap_or(presets - 1);
+        let next = match self.own_theme {
+            Some(_) => theme::PRESETS
+                .iter()
+                .position(|&p| p == cfg.theme_preset)
+                .filter(|&i| i != last)
+                .map(|i| (i + 1) % presets),
+            None => Some((last + 1) % presets),
+        };
+        let name = match next.map(|i| theme::PRESETS[i]) {
+            Some(name) => {
+                let preset = Theme::preset(name).unwrap_or_default();
+                let own = std::mem::replace(&mut cfg.theme, preset);
+                let own_preset = std::mem::replace(&mut cfg.theme_preset, name.to_string());
+                self.own_theme.get_or_insert((own_preset, own));
+                config::downsample_theme(&mut cfg);
+                name
+            }
+            None => {
+                if let Some((preset, theme)) = self.own_theme.take() {
+                    cfg.theme = theme;
+                    cfg.theme_preset = preset;
+                }
+                "your own"
+            }
+        };
+        drop(cfg);
+        self.set_status(&format!("Theme: {}", name));
+        self.dirty = true;
+    }
+
     /// Add a View to the app's currently opened Views.
     fn add_view(&mut self, view: Box<dyn View>) {
         self.dirty = true;
         if !self.views.is_empty() && self.focused < self.views.len() - 1 {
-            self.views.truncate(self.focused + 1);
+            self.close_views(self.focused + 1);
         }
         self.views.push(view);
         if self.views.len() > 1 {
-            self.focused += 1;
+            self.focus(self.focused + 1);
         }
     }
 
-    /// Ask user to confirm action with ENTER or Y.
-    fn confirm(&self, question: &str) -> bool {
-        let rows = self.rows();
+    /// Focus the view at `idx`, remembering the one we're leaving.
+    fn focus(&mut self, idx: usize) {
+        self.dirty = true;
+        self.previous = self.focused;
+        self.focused = idx;
+    }
 
-        let mut out = stdout();
-        write!(
-            out,
-            "{}{}{}{} [Y/n]: {}",
-            theme::color::Reset,
-            terminal::Goto(1, rows),
-            terminal::ClearCurrentLine,
-            question,
-            terminal::ShowCursor,
-        )
-        .expect(ERR_STDOUT);
-        out.flush().expect(ERR_STDOUT);
+    /// Jump back to the view focused before this one. Doing it again
+    /// jumps back here.
+    fn toggle_previous(&mut self) {
+        if self.previous != self.focused && self.previous < self.views.len() {
+            self.focus(self.previous);
+        }
+    }
+
+    /// Replace the focused view, dropping any forward history. Back
+    /// goes to whatever was behind the view being replaced.
+    fn replace_view(&mut self, view: Box<dyn View>) {
+        if self.views.is_empty() {
+            return self.add_view(view);
+        }
+        self.dirty = true;
+        self.close_views(self.focused);
+        self.views.push(view);
+    }
+
+    /// Drop every view from `idx` on, remembering them so they can be
+    /// reopened. The one closest to `idx` gets reopened first.
+    fn close_views(&mut self, idx: usize) {
+        for view in self.views.drain(idx..).rev() {
+            self.closed.push(view.url().to_string());
+        }
+        if self.closed.len() > MAX_CLOSED {
+            self.closed.drain(..self.closed.len() - MAX_CLOSED);
+        }
+    }
+
+    /// Reopen the most recently closed view. Views closed by
+    /// reopening it go to the back of the line, so pressing `T`
+    /// again keeps walking back through what was closed before.
+    fn reopen_closed(&mut self) -> Result<()> {
+        let url = self
+            .closed
+            .pop()
+            .ok_or_else(|| error!("No closed views to reopen."))?;
+        let older = self.closed.len();
+        let res = self.open(&url, &url);
+        let newer = self.closed.split_off(older);
+        self.closed.splice(0..0, newer);
+        if self.closed.len() > MAX_CLOSED {
+            self.closed.truncate(MAX_CLOSED);
+        }
+        res
+    }
+
+    /// If `url` is a link on the focused menu, remember the menu's
+    /// links for `open_sibling()`.
+    fn remember_siblings(&mut self, url: &str) {
+        if let Some(view) = self.views.get(self.focused) {
+            let links = view.links();
+            if links.iter().any(|(_, u)| u == url) {
+                self.siblings = links;
+            }
+        }
+    }
+
+    /// Open the next (or previous) link of the same type as the
+    /// focused page on the menu it was opened from. Handy for reading
+    /// through a phlog without going back to the index each time.
+    fn open_sibling(&mut self, forward: bool) -> Result<()> {
+        let url = match self.views.get(self.focused) {
+            Some(view) => view.url().to_string(),
+            None => return Ok(()),
+        };
+        let pos = self
+            .siblings
+            .iter()
+            .position(|(_, u)| *u == url)
+            .ok_or_else(|| error!("Open a post from a phlog first."))?;
+        let typ = gopher::type_for_url(&url);
+        let same_type = |(_, u): &&(String, String)| gopher::type_for_url(u) == typ;
+        let sibling = if forward {
+            self.siblings[pos + 1..].iter().find(same_type)
+        } else {
+            self.siblings[..pos].iter().rev().find(same_type)
+        };
+        match sibling.cloned() {
+            Some((title, url)) => return self.open(&title, &url),
+            None if forward => self.set_status("End of phlog."),
+            None => self.set_status("Start of phlog."),
+        }
+        Ok(())
+    }
+
+    /// Open the menu the focused page lives in, going by its selector.
+    /// Handy for finding the rest of a phlog after following a link
+    /// straight to one post.
+    fn open_containing_menu(&mut self) -> Result<()> {
+        let url = match self.views.get(self.focused) {
+            Some(view) => view.url().to_string(),
+            None => return Ok(()),
+        };
+        match gopher::containing_menu_url(&url) {
+            Some(menu) => self.open(&menu, &menu),
+            None => {
+                self.set_status("Already at the top of this server.");
+                Ok(())
+            }
+        }
+    }
+
+    /// Bookmark the focused view in the collection mapped to `key`
+    /// after `s` was pressed. `s` again means plain old bookmarks.gph,
+    /// and anything else cancels.
+    fn file_bookmark(&mut self, key: Key) -> Action {
+        let collection = match key {
+            Key::Char('s') | Key::Ctrl('s') => None,
+            Key::Char(c) => {
+                let config = self.config.read().unwrap();
+                match config.collections.iter().find(|(k, _)| *k == c) {
+                    Some((_, name)) => Some(name.clone()),
+                    None => return Action::Status("Bookmark cancelled.".into()),
+                }
+            }
+            _ => return Action::Status("Bookmark cancelled.".into()),
+        };
+        match self.save_bookmark(collection.as_deref()) {
+            Ok(msg) => Action::Status(msg),
+            Err(e) => Action::Error(e.to_string()),
+        }
+    }
+
+    /// Bookmark the focused view, in `collection` if given, and
+    /// return a message saying so. It's labeled with the title of the
+    /// link that opened it, or the URL if there wasn't one.
+    fn save_bookmark(&self, collection: Option<&str>) -> Result<String> {
+        let url = match self.views.get(self.focused) {
+            Some(view) => self.without_password(view.url()),
+            None => return Err(error!("Could not get URL from view")),
+        };
+        let label = match self.title_for(url).trim() {
+            "" => url,
+            title => title,
+        };
+        let res = match collection {
+            Some(name) => bookmarks::save_to_collection(name, label, url),
+            None => bookmarks::save(label, url),
+        };
+        match (res, collection) {
+            (Err(e), _) => Err(error!("Save failed: {}", e)),
+            (Ok(()), Some(name)) => Ok(format!("Saved bookmark to {}: {}", name, url)),
+            (Ok(()), None) => Ok(format!("Saved bookmark: {}", url)),
+        }
+    }
+
+    /// Ask where to save the focused page, then save it there.
+    fn prompt_save_page(&mut self) -> Result<()> {
+        let url = match self.views.get(self.focused) {
+            Some(view) => view.url().to_string(),
+            None => return Err(error!("Could not get URL from view")),
+        };
+        let u = gopher::parse_url(&url);
+        let default = u.sel.split_terminator('/').next_back().unwrap_or("");
+        if let Some(path) = self.prompt("Save page as: ", default) {
+            if path.trim().is_empty() {
+                return Err(error!("Please provide a filename."));
+            }
+            let msg = self.save_page(path.trim())?;
+            self.set_status(&msg);
+        }
+        Ok(())
+    }
 
-        if let Ok(key) = self.keys.lock().unwrap().recv() {
-            matches!(key, Key::Char('\n') | Key::Char('y') | Key::Char('Y'))
+    /// Save the focused page to `path`, as the server sent it. Menus
+    /// saved to a `.txt` file are saved as the text you see instead.
+    /// Existing files are left alone.
+    fn save_page(&mut self, path: &str) -> Result<String> {
+        let target = expand_home(path)?;
+        let view = match self.views.get_mut(self.focused) {
+            Some(view) => view,
+            None => return Err(error!("Could not get URL from view")),
+        };
+        let body = if target.extension().is_some_and(|ext| ext == "txt") {
+            view.plain_text().into_bytes()
         } else {
-            false
+            view.raw_bytes().to_vec()
+        };
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&target)
+            .and_then(|mut file| file.write_all(&body))
+            .map_err(|e| error!("Save failed: {}", e))?;
+        Ok(format!(
+            "Saved {} to {}",
+            utils::human_bytes(body.len()),
+            target.display()
+        ))
+    }
+
+    /// Ask user to confirm action with ENTER or Y. Esc cancels, as
+    /// does closing the keyboard channel.
+    fn confirm(&self, question: &str) -> Answer {
+        let mut out = stdout();
+        let keys = self.keys.lock().unwrap();
+        loop {
+            write!(
+                out,
+                "{}{}{}{} [Y/n]: {}",
+                theme::color::Reset,
+                terminal::Goto(1, self.live_rows()),
+                terminal::ClearCurrentLine,
+                question,
+                terminal::ShowCursor,
+            )
+            .expect(ERR_STDOUT);
+            out.flush().expect(ERR_STDOUT);
+
+            return match keys.recv() {
+                // resizing isn't an answer, so ask again at the bottom
+                Ok(RESIZE) => continue,
+                Ok(Key::Char('\n')) | Ok(Key::Char('y')) | Ok(Key::Char('Y')) => Answer::Yes,
+                Ok(Key::Esc) | Ok(Key::Ctrl('c')) | Err(_) => Answer::Cancel,
+                Ok(_) => Answer::No,
+            };
         }
     }
 
     /// Prompt user for input and return what was entered, if anything.
     fn prompt(&self, prompt: &str, value: &str) -> Option<String> {
-        let rows = self.rows();
+        self.read_input(prompt, value, false)
+    }
+
+    /// Prompt user for a password. What they type shows up as `*`s.
+    fn prompt_secret(&self, prompt: &str) -> Option<String> {
+        self.read_input(prompt, "", true)
+    }
+
+    /// Read a line of input at the bottom of the screen, starting
+    /// with `value`. If `masked`, hide what's typed.
+    fn read_input(&self, prompt: &str, value: &str, masked: bool) -> Option<String> {
+        let mut rows = self.live_rows();
         let mut input = value.to_string();
 
         let mut out = stdout();
         write!(
             out,
-            "{}{}{}{}{}{}",
+            "{}{}{}",
             theme::color::Reset,
-            terminal::Goto(1, rows),
-            terminal::ClearCurrentLine,
-            prompt,
-            input,
+            prompt_line(rows, prompt, &input, masked),
             terminal::ShowCursor,
         )
         .expect(ERR_STDOUT);
@@ -554,18 +1829,11 @@ impl UI {
                 Key::Backspace | Key::Delete => {
                     input.pop();
                 }
+                RESIZE => rows = self.live_rows(),
                 _ => {}
             }
 
-            write!(
-                out,
-                "{}{}{}{}",
-                terminal::Goto(1, rows),
-                terminal::ClearCurrentLine,
-                prompt,
-                input,
-            )
-            .expect(ERR_STDOUT);
+            write!(out, "{}", prompt_line(rows, prompt, &input, masked)).expect(ERR_STDOUT);
             out.flush().expect(ERR_STDOUT);
         }
 
@@ -576,14 +1844,30 @@ impl UI {
         }
     }
 
-    /// Opens an interactive telnet session.
+    /// Opens an interactive telnet session, after asking first if
+    /// `confirm_telnet` is on.
     fn telnet(&mut self, url: &str) -> Result<()> {
-        let gopher::Url { host, port, .. } = gopher::parse_url(url);
+        let gopher::Url {
+            host, port, sel, ..
+        } = gopher::parse_url(url);
+        let login = sel.trim_start_matches('/');
+        let (confirm, send_login) = {
+            let cfg = self.config.read().unwrap();
+            (cfg.confirm_telnet, cfg.telnet_login)
+        };
+        if confirm && self.confirm(&telnet_question(host, port, login)) != Answer::Yes {
+            self.set_status("Telnet cancelled.");
+            return Ok(());
+        }
 
+        let mut args = vec![];
+        if send_login && !login.is_empty() {
+            args.extend(["-l", login]);
+        }
+        args.extend([host, port]);
         terminal::disable_raw_mode()?;
         let mut cmd = process::Command::new("telnet")
-            .arg(host)
-            .arg(port)
+            .args(args)
             .stdin(Stdio::inherit())
             .stdout(Stdio::inherit())
             .spawn()?;
@@ -596,8 +1880,37 @@ impl UI {
 
     /// Asks the current View to process user input and produce an Action.
     fn process_view_input(&mut self) -> Action {
-        if let Some(view) = self.views.get_mut(self.focused) {
-            if let Ok(key) = self.keys.lock().unwrap().recv() {
+        if self.views.get(self.focused).is_some() {
+            let wait = self.pin_wait();
+            let keys = self.keys.clone();
+            let keys = keys.lock().unwrap();
+            let key = match wait {
+                // wake up in time to reload the pinned page
+                Some(wait) => match keys.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => return Action::None,
+                    key => key.ok(),
+                },
+                None => keys.recv().ok(),
+            };
+            if let Some(key) = key {
+                if let Some(pin) = &mut self.pinned {
+                    pin.touched = clock::now();
+                }
+                // resizing only means redrawing, and the view
+                // shouldn't take it as a keypress
+                if key == RESIZE {
+                    return Action::Keypress(key);
+                }
+                if self.filing {
+                    self.filing = false;
+                    return self.file_bookmark(key);
+                }
+                let view = &mut self.views[self.focused];
+                let key = if view.taking_input() {
+                    key
+                } else {
+                    self.config.read().unwrap().keymap.translate(key)
+                };
                 return view.respond(key);
             }
         }
@@ -612,9 +1925,9 @@ impl UI {
         // Give our resize handler a channel to send events on.
         *RESIZE_SENDER.lock().unwrap() = Some(sender.clone());
         unsafe {
-            libc::signal(libc::SIGWINCH, resize_handler as usize);
-            libc::signal(libc::SIGINT, sigint_handler as usize);
-            libc::signal(libc::SIGCONT, sigcont_handler as usize);
+            libc::signal(libc::SIGWINCH, resize_handler as *const () as usize);
+            libc::signal(libc::SIGINT, sigint_handler as *const () as usize);
+            libc::signal(libc::SIGCONT, sigcont_handler as *const () as usize);
         }
 
         thread::spawn(move || {
@@ -660,26 +1973,34 @@ impl UI {
                 out.flush()?;
             }
             Action::Status(s) => self.set_status(&s),
-            Action::Open(title, url) => self.open(&title, &url)?,
+            Action::Open(title, url) => {
+                self.remember_siblings(&url);
+                self.open(&title, &url)?
+            }
+            Action::Expand(url) => self.expand(&url)?,
+            Action::Find(query) => {
+                if let Some(view) = self.views.get_mut(self.focused) {
+                    let action = view.find(&query);
+                    self.process_action(action)?;
+                }
+            }
             Action::Prompt(query, fun) => {
                 if let Some(response) = self.prompt(&query, "") {
                     self.process_action(fun(response))?;
                 }
             }
             // F5 = redraw the display on resize
-            Action::Keypress(Key::F(5)) => self.dirty = true,
-            Action::Keypress(Key::Left) | Action::Keypress(Key::Backspace) => {
-                if self.focused > 0 {
-                    self.dirty = true;
-                    self.focused -= 1;
-                }
+            Action::Keypress(RESIZE) => self.dirty = true,
+            // ctrl-l = clear the screen and redraw everything
+            Action::Keypress(Key::Ctrl('l')) => self.repaint()?,
+            Action::Keypress(Key::Left) | Action::Keypress(Key::Backspace) if self.focused > 0 => {
+                self.focus(self.focused - 1)
             }
-            Action::Keypress(Key::Right) => {
-                if self.focused < self.views.len() - 1 {
-                    self.dirty = true;
-                    self.focused += 1;
-                }
+            Action::Keypress(Key::Right) if self.focused < self.views.len() - 1 => {
+                self.focus(self.focused + 1)
             }
+            // ctrl-^ = flip between this view and the last one
+            Action::Keypress(Key::Ctrl('6')) => self.toggle_previous(),
             Action::Keypress(Key::Char(key)) | Action::Keypress(Key::Ctrl(key)) => match key {
                 'a' => self.open("History", "gopher://phetch/1/history")?,
                 'b' => self.open("Bookmarks", "gopher://phetch/1/bookmarks")?,
@@ -693,14 +2014,15 @@ impl UI {
                     if url.starts_with("gopher://phetch/") {
                         return Err(error!("Can't download internal phetch pages."));
                     }
+                    self.check_host(url)?;
 
-                    let u = gopher::parse_url(&url);
-                    let default_filename = u.sel.split_terminator('/').rev().next().unwrap_or("");
+                    let u = gopher::parse_url(url);
+                    let default_filename = u.sel.split_terminator('/').next_back().unwrap_or("");
                     if let Some(filename) = self.prompt("Save to disk as: ", default_filename) {
                         if filename.trim().is_empty() {
                             return Err(error!("Please provide a filename."));
                         }
-                        match self.download_file_with_filename(url, String::from(filename)) {
+                        match self.download_file_with_filename(url, filename) {
                             Ok(()) => (),
                             Err(e) => return Err(error!("Download failed: {}", e)),
                         }
@@ -708,10 +2030,25 @@ impl UI {
                 }
                 'g' => {
                     if let Some(url) = self.prompt("Go to URL: ", "") {
-                        self.open(&url, &url)?;
+                        self.go_to(&url)?;
+                    }
+                }
+                'E' => {
+                    if let Some(view) = self.views.get(self.focused) {
+                        let res = utils::open_editor(view.raw());
+                        self.dirty = true;
+                        res?;
                     }
                 }
                 'h' => self.open("Help", "gopher://phetch/1/help")?,
+                'T' => self.reopen_closed()?,
+                ']' => self.open_sibling(true)?,
+                'D' => self.open_containing_menu()?,
+                'S' => self.prompt_save_page()?,
+                'X' => self.toggle_hex_dump(),
+                'L' => self.toggle_pin(),
+                'C' => self.cycle_theme(),
+                '[' => self.open_sibling(false)?,
                 'r' => {
                     if let Some(view) = self.views.get(self.focused) {
                         let url = view.url();
@@ -728,15 +2065,18 @@ impl UI {
                     }
                 }
                 's' => {
-                    if let Some(view) = self.views.get(self.focused) {
-                        let url = view.url();
-                        match bookmarks::save(url, url) {
-                            Ok(()) => {
-                                let msg = format!("Saved bookmark: {}", url);
-                                self.set_status(&msg);
-                            }
-                            Err(e) => return Err(error!("Save failed: {}", e)),
-                        }
+                    let collections = self.config.read().unwrap().collections.clone();
+                    if collections.is_empty() {
+                        let msg = self.save_bookmark(None)?;
+                        self.set_status(&msg);
+                    } else {
+                        self.filing = true;
+                        let names = collections
+                            .iter()
+                            .map(|(key, name)| format!("[{}] {}", key, name))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        self.set_status(&format!("Save bookmark to: {}, [s] bookmarks", names));
                     }
                 }
                 'u' => {
@@ -749,21 +2089,99 @@ impl UI {
                 }
                 'y' => {
                     if let Some(view) = self.views.get(self.focused) {
-                        let url = view.url();
-                        utils::copy_to_clipboard(url)?;
+                        let url = self.without_password(view.url());
+                        let target = self.config.read().unwrap().clipboard;
+                        utils::copy_to_clipboard(url, target)?;
                         let msg = format!("Copied {} to clipboard.", url);
                         self.set_status(&msg);
                     }
                 }
+                'Y' => {
+                    if let Some(view) = self.views.get(self.focused) {
+                        let url = self.without_password(view.url());
+                        if url.starts_with("gopher://phetch/") {
+                            return Err(error!("Can't share internal phetch pages."));
+                        }
+                        let template = self.config.read().unwrap().web_proxy.clone();
+                        if template.is_empty() {
+                            return Err(error!("Set `web_proxy` to share pages."));
+                        }
+                        let proxy = gopher::web_proxy_url(&template, url);
+                        let target = self.config.read().unwrap().clipboard;
+                        utils::copy_to_clipboard(&proxy, target)?;
+                        let msg = format!("Copied {} to clipboard.", proxy);
+                        self.set_status(&msg);
+                    }
+                }
+                'M' => {
+                    if let Some(view) = self.views.get(self.focused) {
+                        let url = self.without_password(view.url());
+                        let title = self.title_for(url);
+                        let template = self.config.read().unwrap().link_format.clone();
+                        let link = format_link(&template, title.trim(), url);
+                        let target = self.config.read().unwrap().clipboard;
+                        utils::copy_to_clipboard(&link, target)?;
+                        let msg = format!("Copied {} to clipboard.", link);
+                        self.set_status(&msg);
+                    }
+                }
                 'w' => {
-                    let wide = self.config.read().unwrap().wide;
-                    self.config.write().unwrap().wide = !wide;
+                    let per_url = self.config.read().unwrap().wide_per_url;
+                    if !per_url {
+                        let wide = self.config.read().unwrap().wide;
+                        self.config.write().unwrap().wide = !wide;
+                    }
                     if let Some(view) = self.views.get_mut(self.focused) {
-                        let w = view.wide();
-                        view.set_wide(!w);
+                        let w = !view.wide();
+                        view.set_wide(w);
+                        if per_url {
+                            let url = view.url().to_string();
+                            self.config.write().unwrap().wide_urls.insert(url, w);
+                        }
                         self.dirty = true;
                     }
                 }
+                'N' => {
+                    let url = match self.views.get(self.focused) {
+                        Some(view) => self.without_password(view.url()).to_string(),
+                        None => return Ok(()),
+                    };
+                    if url.starts_with("gopher://phetch/") {
+                        return Err(error!("Can't add notes to internal phetch pages."));
+                    }
+                    let old = notes::get(&url).unwrap_or_default();
+                    if let Some(note) = self.prompt("Note: ", &old) {
+                        notes::save(&url, &note).map_err(|e| error!("Save failed: {}", e))?;
+                        if note.trim().is_empty() {
+                            self.set_status("Deleted note.");
+                        } else {
+                            self.set_status(&format!("Note: {}", note.trim()));
+                        }
+                    }
+                }
+                '!' => {
+                    let url = match self.views.get(self.focused) {
+                        Some(view) => self.without_password(view.url()).to_string(),
+                        None => return Ok(()),
+                    };
+                    if url.starts_with("gopher://phetch/") {
+                        return Err(error!("Can't flag internal phetch pages."));
+                    }
+                    if let Some(reason) = self.prompt("Broken because (optional): ", "") {
+                        broken::flag(&url, &reason).map_err(|e| error!("Save failed: {}", e))?;
+                        self.set_status("Flagged as broken. See gopher://phetch/1/broken");
+                    }
+                }
+                'P' => {
+                    let incognito = !self.config.read().unwrap().incognito;
+                    self.config.write().unwrap().incognito = incognito;
+                    self.dirty = true;
+                    if incognito {
+                        self.set_status("Incognito mode on. History won't be saved.");
+                    } else {
+                        self.set_status("Incognito mode off.");
+                    }
+                }
                 'q' => self.running = false,
                 c => return Err(error!("Unknown keypress: {}", c)),
             },
@@ -772,3 +2190,1428 @@ impl UI {
         Ok(())
     }
 }
+
+impl Drop for UI {
+    /// Put back the window title we replaced, if `set_title` is on.
+    fn drop(&mut self) {
+        if !self.title.is_empty() {
+            print!("{}", terminal::POP_TITLE);
+            stdout().flush().expect(ERR_STDOUT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, gopher::serve};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A UI that doesn't listen to the keyboard or install signal
+    /// handlers, so tests can poke at it directly.
+    fn test_ui(config: Config) -> UI {
+        let (_, receiver) = channel();
+        UI {
+            views: vec![],
+            focused: 0,
+            previous: 0,
+            dirty: true,
+            running: true,
+            size: (80, 24),
+            cache: Cache::new(config.cache_size),
+            config: Arc::new(RwLock::new(config)),
+            status: String::new(),
+            keys: Arc::new(Mutex::new(receiver)),
+            closed: vec![],
+            siblings: vec![],
+            filing: false,
+            loading: None,
+            titles: HashMap::new(),
+            queued: vec![],
+            title: String::new(),
+            pinned: None,
+            own_theme: None,
+            download_dir: PathBuf::new(),
+        }
+    }
+
+    fn text(ui: &UI, url: &str) -> Box<dyn View> {
+        Box::new(Text::from(url, vec![], ui.config.clone(), false))
+    }
+
+    fn urls(ui: &UI) -> Vec<&str> {
+        ui.views.iter().map(|v| v.url()).collect()
+    }
+
+    /// Like `serve()`, but also counts requests.
+    fn serve_counted(body: &'static str) -> (u16, Arc<AtomicUsize>) {
+        use std::{
+            io::{BufRead, BufReader},
+            net::TcpListener,
+        };
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        thread::spawn(move || {
+            for mut client in server.incoming().flatten() {
+                BufRead::read_line(&mut BufReader::new(&client), &mut String::new()).unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                client.write_all(body.as_bytes()).unwrap();
+            }
+        });
+        (port, hits)
+    }
+
+    fn back(ui: &mut UI) {
+        ui.process_action(Action::Keypress(Key::Left)).unwrap();
+    }
+
+    #[test]
+    fn test_view_prefix_forces_view_type() {
+        let gophermap = "1Phlog\t/phlog\tphkt.io\t70\r\n";
+
+        // a menu, shown as text
+        let mut ui = test_ui(Config::default());
+        let url = format!("gopher://127.0.0.1:{}/1/", serve(gophermap));
+        ui.open("test", &format!("text:{}", url)).unwrap();
+        assert_eq!(urls(&ui), vec![url.as_str()]);
+        let out = ui.views[0].render();
+        assert!(out.contains("1Phlog") && out.contains("/phlog"));
+
+        // a text file, parsed as a menu
+        let mut ui = test_ui(Config::default());
+        let url = format!("gopher://127.0.0.1:{}/0/map.txt", serve(gophermap));
+        ui.open("test", &format!("menu:{}", url)).unwrap();
+        let out = ui.views[0].render();
+        assert!(out.contains("Phlog") && !out.contains("/phlog"));
+
+        // internal pages work too
+        let mut ui = test_ui(Config::default());
+        ui.open("test", "text:gopher://phetch/1/help").unwrap();
+        ui.views[0].term_size(80, 40);
+        assert!(ui.views[0].render().contains("/help/keys"));
+    }
+
+    #[test]
+    fn test_strip_echo() {
+        let gophermap = include_str!("../tests/selector-echo.gph");
+        let port = serve(gophermap);
+        let url = format!("gopher://127.0.0.1:{}/1/phlog", port);
+
+        let mut ui = test_ui(Config::default());
+        ui.open("test", &url).unwrap();
+        assert_eq!(ui.views[0].raw(), gophermap);
+
+        let mut ui = test_ui(Config {
+            strip_echo: vec!["127.0.0.1".into()],
+            ..Config::default()
+        });
+        ui.open("test", &url).unwrap();
+        let raw = ui.views[0].raw();
+        assert!(raw.starts_with("iphlog\t"));
+        // later copies are real content
+        assert!(raw.contains("\n/phlog\n"));
+
+        // different selector, nothing stripped
+        let url = format!("gopher://127.0.0.1:{}/1/phlog/", port);
+        ui.open("test", &url).unwrap();
+        assert_eq!(ui.views[1].raw(), gophermap);
+    }
+
+    #[test]
+    fn test_blocked_hosts() {
+        let mut ui = test_ui(Config {
+            allow_hosts: vec!["*.sdf.org".into()],
+            deny_hosts: vec!["127.0.0.1".into()],
+            ..Config::default()
+        });
+        let url = format!("gopher://127.0.0.1:{}/0/hi", serve("hi there"));
+        let res = ui.open("test", &url);
+        assert!(matches!(res, Err(Error::Blocked(host)) if host == "127.0.0.1"));
+        let res = ui.open("test", "https://example.com/");
+        assert!(matches!(res, Err(Error::Blocked(host)) if host == "example.com"));
+        let res = ui.open("test", "text:gopher://phkt.io/1/");
+        assert!(matches!(res, Err(Error::Blocked(_))));
+        assert!(ui.views.is_empty());
+
+        // internal pages still work
+        ui.open("Help", "gopher://phetch/1/help").unwrap();
+        assert_eq!(ui.views.len(), 1);
+    }
+
+    #[test]
+    fn test_serve_local_skips_host_rules() {
+        let mut ui = test_ui(Config {
+            allow_hosts: vec!["*.sdf.org".into()],
+            deny_hosts: vec!["local".into()],
+            ..Config::default()
+        });
+        let res = ui.open("test", "gopher://local/1/");
+        assert!(matches!(res, Err(Error::Blocked(host)) if host == "local"));
+
+        let site = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/local");
+        local::set_root(site.to_str());
+        let res = ui.open("test", "gopher://local/1/");
+        let dir = phetchdir::TestDir::install("local-download");
+        let path = dir.path.join("about.txt");
+        let download = gopher::download_url_with_filename(
+            "gopher://local/0/about.txt",
+            false,
+            false,
+            ui.keys.clone(),
+            path.to_str().unwrap(),
+        );
+        local::set_root(None);
+        res.unwrap();
+        assert!(ui.views[0].raw().contains("Welcome to my gopherhole"));
+        // downloads come from the directory too, not the network
+        assert_eq!(download.unwrap().1, 21);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Hi, I write a phlog.\n");
+    }
+
+    #[test]
+    fn test_summary_status() {
+        let gophermap = "1one\t/1\tphkt.io\r\n1two\t/2\tphkt.io\r\n0readme\t/0\tphkt.io\r\n";
+        let url = format!("gopher://127.0.0.1:{}/1/", serve(gophermap));
+        let mut ui = test_ui(Config::default());
+        ui.open("test", &url).unwrap();
+        assert_eq!(ui.status, "3 links: 2 menus, 1 text");
+
+        // narrow terminal
+        let mut ui = test_ui(Config::default());
+        ui.term_size(30, 10);
+        ui.open("test", &url).unwrap();
+        assert_eq!(ui.status, "3 links: 2 menus,…");
+
+        // config warnings win
+        let mut ui = test_ui(Config::default());
+        ui.set_status("Config error");
+        ui.open("test", &url).unwrap();
+        assert_eq!(ui.status, "Config error");
+    }
+
+    #[test]
+    fn test_cached_pages() {
+        let (port, hits) = serve_counted("1one\t/1\tphkt.io\r\n");
+        let url = format!("gopher://127.0.0.1:{}/1/", port);
+        let mut ui = test_ui(Config::default());
+        ui.open("test", &url).unwrap();
+        ui.open("help", "gopher://phetch/1/help").unwrap();
+        ui.open("test", &url).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            urls(&ui),
+            vec![url.as_str(), "gopher://phetch/1/help", url.as_str()]
+        );
+
+        // the same URL shown differently is its own page
+        ui.open("test", &format!("text:{}", url)).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // reloading skips the cache, and caches the new copy
+        ui.process_action(Action::Keypress(Key::Char('R'))).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        ui.open("help", "gopher://phetch/1/help").unwrap();
+        ui.open("test", &url).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        // no cache
+        let mut ui = test_ui(Config {
+            cache_size: 0,
+            ..Config::default()
+        });
+        ui.open("test", &url).unwrap();
+        ui.open("help", "gopher://phetch/1/help").unwrap();
+        ui.open("test", &url).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_titles_bounded() {
+        let port = serve("iok\r\n");
+        let url = |page: &str| format!("gopher://127.0.0.1:{}/1/{}", port, page);
+        let mut ui = test_ui(Config {
+            cache_size: 1,
+            ..Config::default()
+        });
+        for page in &["a", "b", "c"] {
+            ui.open(page, &url(page)).unwrap();
+        }
+        assert_eq!(ui.titles.len(), 3);
+
+        // b and c are closed and pushed out of the cache
+        back(&mut ui);
+        back(&mut ui);
+        ui.open("d", &url("d")).unwrap();
+        ui.open("e", &url("e")).unwrap();
+        let mut titles = ui.titles.values().cloned().collect::<Vec<_>>();
+        titles.sort();
+        assert_eq!(titles, vec!["a", "d", "e"]);
+        assert_eq!(ui.title_for(&url("a")), "a");
+        assert_eq!(ui.title_for(&url("b")), url("b"));
+    }
+
+    #[test]
+    fn test_open_sibling() {
+        let port = serve("A post.\r\n");
+        let post = |n: usize| format!("gopher://127.0.0.1:{}/0/{}.txt", port, n);
+        let gophermap = format!(
+            "iMy phlog\t\t\t\r\n0First\t/1.txt\t127.0.0.1\t{port}\r\n\
+             1Archive\t/old\t127.0.0.1\t{port}\r\n0Second\t/2.txt\t127.0.0.1\t{port}\r\n\
+             0Third\t/3.txt\t127.0.0.1\t{port}\r\n",
+            port = port
+        );
+        let menu = format!(
+            "gopher://127.0.0.1:{}/1/",
+            serve(Box::leak(gophermap.into()))
+        );
+        let mut ui = test_ui(Config::default());
+
+        // not opened from a menu
+        ui.open("test", &post(1)).unwrap();
+        assert!(ui.process_action(Action::Keypress(Key::Char(']'))).is_err());
+
+        ui.open("phlog", &menu).unwrap();
+        ui.process_action(Action::Open("First".into(), post(1)))
+            .unwrap();
+        ui.process_action(Action::Keypress(Key::Char(']'))).unwrap();
+        assert_eq!(ui.views[ui.focused].url(), post(2));
+        ui.process_action(Action::Keypress(Key::Char(']'))).unwrap();
+        assert_eq!(ui.views[ui.focused].url(), post(3));
+        ui.process_action(Action::Keypress(Key::Char(']'))).unwrap();
+        assert_eq!(ui.views[ui.focused].url(), post(3));
+        assert!(ui.status.contains("End of phlog."));
+
+        // the Archive menu is skipped going back too
+        ui.process_action(Action::Keypress(Key::Char('['))).unwrap();
+        ui.process_action(Action::Keypress(Key::Char('['))).unwrap();
+        assert_eq!(ui.views[ui.focused].url(), post(1));
+        ui.process_action(Action::Keypress(Key::Char('['))).unwrap();
+        assert!(ui.status.contains("Start of phlog."));
+    }
+
+    #[test]
+    fn test_bookmark_collections() {
+        let mut ui = test_ui(Config {
+            collections: vec![('r', "reading".into()), ('w', "work".into())],
+            ..Config::default()
+        });
+        ui.views.push(text(&ui, "gopher://phkt.io/0/post.txt"));
+        ui.process_action(Action::Keypress(Key::Char('s'))).unwrap();
+        assert!(ui.filing);
+        assert!(ui.status.contains("[r] reading, [w] work, [s] bookmarks"));
+
+        // unmapped letters cancel
+        ui.filing = false;
+        let action = ui.file_bookmark(Key::Char('x'));
+        assert!(matches!(action, Action::Status(msg) if msg == "Bookmark cancelled."));
+        let action = ui.file_bookmark(Key::Esc);
+        assert!(matches!(action, Action::Status(msg) if msg == "Bookmark cancelled."));
+    }
+
+    #[test]
+    fn test_trusted_hosts_skip_confirm() {
+        let dir = phetchdir::TestDir::install("trusted-hosts");
+        let url = format!("gopher://127.0.0.1:{}/9/trusted.bin", serve("\x00\x01\x02"));
+
+        // nobody's there to say yes, so untrusted hosts don't download
+        let mut ui = test_ui(Config::default());
+        ui.download_dir = dir.path.clone();
+        ui.open("test", &url).unwrap();
+        assert_eq!(dir.read("trusted.bin"), "");
+
+        let mut ui = test_ui(Config {
+            trusted_hosts: vec!["127.0.0.1".into()],
+            ..Config::default()
+        });
+        ui.download_dir = dir.path.clone();
+        ui.open("test", &url).unwrap();
+        assert_eq!(dir.read("trusted.bin"), "\x00\x01\x02");
+        assert!(ui.status.contains("Auto-downloaded from trusted host."));
+        assert!(ui.status.contains("Download complete!"));
+    }
+
+    #[test]
+    fn test_loading_placeholder() {
+        let mut ui = test_ui(Config::default());
+        ui.views.push(text(&ui, "gopher://phkt.io/0/old.txt"));
+        ui.loading = Some("gopher://phkt.io/1/slow".into());
+        let out = ui.render().unwrap();
+        assert!(out.starts_with("\x1b[2J"));
+        assert!(out.contains("\x1b[12;25H"));
+        assert!(out.contains("Loading gopher://phkt.io/1/slow…"));
+        assert!(ui.render_scrollbar().is_none());
+
+        ui.size = (20, 24);
+        assert!(ui.render().unwrap().contains("Loading gopher://ph…"));
+
+        // a failed fetch goes back to the old page
+        let mut ui = test_ui(Config {
+            placeholder: true,
+            ..Config::default()
+        });
+        ui.views.push(text(&ui, "gopher://phkt.io/0/old.txt"));
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = format!("gopher://127.0.0.1:{}/1/", port);
+        assert!(ui.open("test", &url).is_err());
+        assert!(ui.loading.is_none());
+        assert_eq!(urls(&ui), vec!["gopher://phkt.io/0/old.txt"]);
+    }
+
+    #[test]
+    fn test_confirm_telnet() {
+        assert_eq!(
+            telnet_question("bbs.impakt.net", "6502", ""),
+            "Telnet to bbs.impakt.net port 6502?"
+        );
+        assert_eq!(
+            telnet_question("bbs.impakt.net", "23", "guest\x1b[2J"),
+            "Telnet to bbs.impakt.net port 23, login \"guest[2J\"?"
+        );
+
+        let mut ui = test_ui(Config::default());
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+        keys.send(Key::Char('n')).unwrap();
+        ui.open("BBS", "telnet://guest@bbs.impakt.net:6502")
+            .unwrap();
+        assert_eq!(ui.status, "Telnet cancelled.");
+        assert!(ui.keys.lock().unwrap().try_recv().is_err());
+        assert!(ui.views.is_empty());
+    }
+
+    #[test]
+    fn test_save_page() {
+        let dir = phetchdir::TestDir::install("save-page");
+        let path = |name: &str| dir.path.join(name).display().to_string();
+        let mut ui = test_ui(Config::default());
+        let url = format!(
+            "gopher://127.0.0.1:{}/1/",
+            serve("iWelcome\r\n1Phlog\t/phlog\t127.0.0.1\t70\r\n")
+        );
+        ui.open("Home", &url).unwrap();
+
+        let msg = ui.save_page(&path("home.gph")).unwrap();
+        assert!(msg.starts_with("Saved 38 bytes to "));
+        assert_eq!(
+            fs::read_to_string(dir.path.join("home.gph")).unwrap(),
+            "iWelcome\r\n1Phlog\t/phlog\t127.0.0.1\t70\r\n"
+        );
+        ui.save_page(&path("home.txt")).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path.join("home.txt")).unwrap(),
+            "Welcome\nPhlog\n"
+        );
+
+        // never overwrites
+        let err = ui.save_page(&path("home.gph")).unwrap_err();
+        assert!(err.to_string().contains("Save failed"));
+        assert!(ui.save_page(&path("nope/home.gph")).is_err());
+
+        assert_eq!(
+            expand_home("notes.txt").unwrap(),
+            PathBuf::from("notes.txt")
+        );
+        assert_eq!(expand_home("~user/x").unwrap(), PathBuf::from("~user/x"));
+    }
+
+    #[test]
+    fn test_resize_during_prompts() {
+        let mut ui = test_ui(Config::default());
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+        keys.send(RESIZE).unwrap();
+        keys.send(Key::Char('y')).unwrap();
+        assert_eq!(ui.confirm("Really?"), Answer::Yes);
+
+        for key in [Key::Char('h'), RESIZE, Key::Char('i'), Key::Char('\n')] {
+            keys.send(key).unwrap();
+        }
+        assert_eq!(ui.prompt("Say: ", ""), Some("hi".into()));
+
+        // the view never sees it
+        ui.open("Help", "gopher://phetch/1/help").unwrap();
+        keys.send(RESIZE).unwrap();
+        assert!(matches!(ui.process_view_input(), Action::Keypress(RESIZE)));
+    }
+
+    #[test]
+    fn test_go_to_search() {
+        let (port, hits) = serve_counted("iresults\r\n");
+        let url = format!("gopher://127.0.0.1:{}/7/find", port);
+        let mut ui = test_ui(Config::default());
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+
+        // nothing typed, nothing fetched
+        keys.send(Key::Char('\n')).unwrap();
+        ui.go_to(&url).unwrap();
+        assert_eq!(ui.status, "Search cancelled.");
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+        assert!(ui.views.is_empty());
+
+        for c in " gopher\n".chars() {
+            keys.send(Key::Char(c)).unwrap();
+        }
+        ui.go_to(&url).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(urls(&ui), vec![format!("{}?gopher", url)]);
+
+        // already has a query
+        ui.go_to(&format!("{}?phetch", url)).unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert!(ui.keys.lock().unwrap().try_recv().is_err());
+    }
+
+    #[test]
+    fn test_confirm_answers() {
+        let mut ui = test_ui(Config::default());
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+        let answers = [
+            (Key::Char('\n'), Answer::Yes),
+            (Key::Char('y'), Answer::Yes),
+            (Key::Char('Y'), Answer::Yes),
+            (Key::Char('n'), Answer::No),
+            (Key::Char('x'), Answer::No),
+            (Key::Down, Answer::No),
+            (Key::Esc, Answer::Cancel),
+            (Key::Ctrl('c'), Answer::Cancel),
+        ];
+        for (key, answer) in answers {
+            keys.send(key).unwrap();
+            assert_eq!(ui.confirm("Well?"), answer, "{:?}", key);
+        }
+        drop(keys);
+        assert_eq!(ui.confirm("Anyone there?"), Answer::Cancel);
+    }
+
+    #[test]
+    fn test_download_media() {
+        let port = serve("RIFF....WAVEfmt ");
+        let mut ui = test_ui(Config::default());
+        let url = format!("gopher://127.0.0.1:{}/s/sounds/chime", port);
+        let path = ui.download_media(&url).unwrap();
+        assert!(path.starts_with(std::env::temp_dir()));
+        assert!(path.to_string_lossy().ends_with("-chime"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "RIFF....WAVEfmt ");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_download_unreachable() {
+        let dir = phetchdir::TestDir::install("download-unreachable");
+        let port = gopher::RefusingPort::bind();
+        let addr = port.addr();
+        let url = format!("gopher://{}/9/queued.bin", addr);
+
+        // fails right away with a clear error, instead of a hang
+        let mut ui = test_ui(Config::default());
+        ui.download_dir = dir.path.clone();
+        let start = std::time::Instant::now();
+        let res = ui.download(&url);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(matches!(&res, Err(e) if e.is_host_down()));
+        assert!(res.unwrap_err().to_string().contains(&addr.to_string()));
+        assert!(ui.status.is_empty());
+        assert!(ui.queued.is_empty());
+        assert_eq!(dir.read("queued.bin"), "");
+
+        let mut ui = test_ui(Config {
+            queue_downloads: true,
+            ..Config::default()
+        });
+        ui.download_dir = dir.path.clone();
+        ui.download(&url).unwrap();
+        assert!(ui.status.contains("Can't connect to"));
+        assert!(ui.status.contains("queued"));
+        assert_eq!(ui.queued, vec![url.clone()]);
+
+        // retried once a page loads, and done once the host is back
+        ui.open(
+            "test",
+            &format!("gopher://127.0.0.1:{}/1/", serve("iok\r\n")),
+        )
+        .unwrap();
+        assert_eq!(ui.queued, vec![url]);
+        let server = port.listen();
+        thread::spawn(move || {
+            let (mut client, _) = server.accept().unwrap();
+            let _ = std::io::Read::read(&mut client, &mut [0; 64]);
+            client.write_all(b"\x00\x01\x02").unwrap();
+        });
+        ui.open(
+            "test",
+            &format!("gopher://127.0.0.1:{}/1/", serve("iok\r\n")),
+        )
+        .unwrap();
+        assert!(ui.queued.is_empty());
+        assert_eq!(dir.read("queued.bin"), "\x00\x01\x02");
+        assert!(ui.status.contains("Download complete!"));
+    }
+
+    #[test]
+    fn test_spinner_delay() {
+        // quick work finishes before the delay, so nothing's drawn
+        let (tx, rx) = channel();
+        let spinner =
+            thread::spawn(move || spin(vec![], "Loading", 24, Duration::from_secs(5), rx));
+        assert_eq!(thread::spawn(|| 2 + 2).join().unwrap(), 4);
+        tx.send(true).unwrap();
+        assert!(spinner.join().unwrap().is_empty());
+
+        // slow work gets a spinner
+        let (tx, rx) = channel();
+        let spinner = thread::spawn(move || spin(vec![], "Loading", 24, Duration::ZERO, rx));
+        thread::sleep(Duration::from_millis(50));
+        tx.send(true).unwrap();
+        let out = String::from_utf8(spinner.join().unwrap()).unwrap();
+        assert!(out.contains("Loading"));
+    }
+
+    #[test]
+    fn test_masked_prompt() {
+        let line = prompt_line(24, "Password: ", "hunter2", true);
+        assert!(line.ends_with("Password: *******"));
+        assert!(!line.contains("hunter2"));
+        assert!(prompt_line(24, "Note: ", "hunter2", false).ends_with("Note: hunter2"));
+    }
+
+    #[test]
+    fn test_delete_note_asks_first() {
+        let dir = phetchdir::TestDir::install("delete-note");
+        notes::save("gopher://phkt.io/1/", "revisit").unwrap();
+        let mut ui = test_ui(Config::default());
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+
+        let url = "gopher://phetch/1/notes/delete/gopher://phkt.io/1/";
+        keys.send(Key::Char('n')).unwrap();
+        ui.open("Notes", url).unwrap();
+        assert_eq!(notes::get("gopher://phkt.io/1/").unwrap(), "revisit");
+
+        keys.send(Key::Char('y')).unwrap();
+        ui.open("Notes", url).unwrap();
+        assert!(notes::get("gopher://phkt.io/1/").is_none());
+        assert_eq!(dir.read("notes.txt"), "");
+    }
+
+    #[test]
+    fn test_ask_password() {
+        let dir = phetchdir::TestDir::install("ask-password");
+        let port = serve("iWelcome back\r\n1Entries\t/vault/entries\t127.0.0.1\t70\r\n");
+        let mut ui = test_ui(Config {
+            ask_password: vec!["127.0.0.1/vault/*".into()],
+            mark_new: true,
+            ..Config::default()
+        });
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+
+        let url = format!("gopher://127.0.0.1:{}/1/vault/diary", port);
+        keys.send(Key::Esc).unwrap();
+        ui.open("Diary", &url).unwrap();
+        assert!(ui.views.is_empty());
+
+        for c in "hunter2\n".chars() {
+            keys.send(Key::Char(c)).unwrap();
+        }
+        ui.open("Diary", &url).unwrap();
+        let opened = format!("{}?hunter2", url);
+        assert_eq!(urls(&ui), vec![opened.as_str()]);
+        assert_eq!(ui.history_url(&opened), url);
+        assert_eq!(ui.title_for(&opened), "Diary");
+        assert_eq!(
+            ui.save_bookmark(None).unwrap(),
+            format!("Saved bookmark: {}", url)
+        );
+        for c in "my diary\n".chars() {
+            keys.send(Key::Char(c)).unwrap();
+        }
+        ui.process_action(Action::Keypress(Key::Char('N'))).unwrap();
+        keys.send(Key::Char('\n')).unwrap();
+        ui.process_action(Action::Keypress(Key::Char('!'))).unwrap();
+
+        // nothing phetch keeps has the password in it
+        let mut seen = String::new();
+        for _ in 0..100 {
+            seen = dir.read("seen.txt");
+            if !seen.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(seen.starts_with(&format!("{}\t", url)), "{}", seen);
+        assert!(!seen.contains("hunter2"));
+        let bookmarks = dir.read("bookmarks.gph");
+        assert!(bookmarks.contains("Diary"));
+        assert!(!bookmarks.contains("hunter2"));
+        let notes = dir.read("notes.txt");
+        assert!(notes.contains("my diary"));
+        assert!(!notes.contains("hunter2"));
+        let broken = dir.read("broken.gph");
+        assert!(broken.contains("/vault/diary"));
+        assert!(!broken.contains("hunter2"));
+        assert!(!ui.titles.keys().any(|url| url.contains("hunter2")));
+
+        ui.config.write().unwrap().save_passwords = true;
+        assert_eq!(ui.history_url(&opened), opened);
+    }
+
+    #[test]
+    fn test_password_pages_arent_cached() {
+        let _dir = phetchdir::TestDir::install("password-cache");
+        let (port, hits) = serve_counted("iWelcome back\r\n");
+        let mut ui = test_ui(Config {
+            ask_password: vec!["127.0.0.1/vault/*".into()],
+            ..Config::default()
+        });
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+
+        // a wrong password, then the right one: both go to the server
+        let url = format!("gopher://127.0.0.1:{}/1/vault/diary", port);
+        for password in &["wrong\n", "hunter2\n"] {
+            for c in password.chars() {
+                keys.send(Key::Char(c)).unwrap();
+            }
+            ui.open("Diary", &url).unwrap();
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert!(!ui.cache.contains(&url));
+    }
+
+    #[test]
+    fn test_go_to_search_answers() {
+        let (port, searches) = serve_counted("iFound it\r\n");
+        let search_url = format!("gopher://127.0.0.1:{}/7/search", port);
+        let mut ui = test_ui(Config {
+            search_url,
+            ..Config::default()
+        });
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+
+        // esc does nothing at all
+        keys.send(Key::Esc).unwrap();
+        ui.go_to("localhost").unwrap();
+        assert!(ui.views.is_empty());
+        assert_eq!(searches.load(Ordering::SeqCst), 0);
+
+        // no tries it as a URL instead, which nothing's serving
+        keys.send(Key::Char('n')).unwrap();
+        assert!(ui.go_to("localhost").is_err());
+        assert!(ui.views.is_empty());
+        assert_eq!(searches.load(Ordering::SeqCst), 0);
+
+        keys.send(Key::Char('y')).unwrap();
+        ui.go_to("localhost").unwrap();
+        assert!(ui.views[0].url().ends_with("/7/search?localhost"));
+        assert_eq!(searches.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_scrollbar_thumb() {
+        assert_eq!(scrollbar_thumb(0, 10, 10), None);
+        assert_eq!(scrollbar_thumb(0, 20, 10), Some(0..5));
+        assert_eq!(scrollbar_thumb(5, 20, 10), Some(2..7));
+        assert_eq!(scrollbar_thumb(10, 20, 10), Some(5..10));
+        // scrolled as far as it goes, which can be past the last page
+        assert_eq!(scrollbar_thumb(18, 20, 10), Some(5..10));
+        assert_eq!(scrollbar_thumb(0, 1000, 10), Some(0..1));
+        assert_eq!(scrollbar_thumb(999, 1000, 10), Some(9..10));
+    }
+
+    #[test]
+    fn test_format_link() {
+        let (title, url) = ("phetch", "gopher://phkt.io/1/phetch");
+        assert_eq!(
+            format_link("[{title}]({url})", title, url),
+            "[phetch](gopher://phkt.io/1/phetch)"
+        );
+        assert_eq!(
+            format_link("[[{url}][{title}]]", title, url),
+            "[[gopher://phkt.io/1/phetch][phetch]]"
+        );
+        assert_eq!(
+            format_link("{title} — {url}", title, url),
+            "phetch — gopher://phkt.io/1/phetch"
+        );
+
+        // placeholders in the title aren't filled in
+        assert_eq!(
+            format_link("{title} — {url}", "{url}", url),
+            "{url} — gopher://phkt.io/1/phetch"
+        );
+        assert_eq!(format_link("{nope} {title", title, url), "{nope} {title");
+
+        // nor do they break the link
+        let (title, url) = ("[draft] a\\b", "gopher://phkt.io/0/a (1).txt");
+        assert_eq!(
+            format_link("[{title}]({url})", title, url),
+            "[\\[draft\\] a\\\\b](gopher://phkt.io/0/a%20%281%29.txt)"
+        );
+        assert_eq!(
+            format_link("[[{url}][{title}]]", title, "gopher://phkt.io/0/[x]"),
+            "[[gopher://phkt.io/0/%5Bx%5D][{draft} a\\b]]"
+        );
+        assert_eq!(
+            format_link("{title}: {url}", title, url),
+            "[draft] a\\b: gopher://phkt.io/0/a (1).txt"
+        );
+    }
+
+    #[test]
+    fn test_containing_menu_from_launch() {
+        let port = serve("1Post\t/phlog/post.txt\t127.0.0.1\t70\r\n");
+        let start = format!("127.0.0.1:{}/0/phlog//post.txt", port);
+        let cfg = crate::args::parse(&[start]).unwrap();
+        let mut ui = test_ui(cfg);
+        let start = ui.config.read().unwrap().start.clone();
+        ui.open(&start, &start).unwrap();
+        assert_eq!(ui.views.len(), 1);
+
+        ui.process_action(Action::Keypress(Key::Char('D'))).unwrap();
+        let menu = format!("gopher://127.0.0.1:{}/1/phlog/", port);
+        assert_eq!(urls(&ui), vec![start.as_str(), menu.as_str()]);
+        assert!(ui.views[1].raw().starts_with("1Post"));
+
+        ui.process_action(Action::Keypress(Key::Char('D'))).unwrap();
+        let root = format!("gopher://127.0.0.1:{}/1/", port);
+        assert_eq!(ui.views[2].url(), root);
+        ui.process_action(Action::Keypress(Key::Char('D'))).unwrap();
+        assert_eq!(ui.views.len(), 3);
+        assert_eq!(ui.status, "Already at the top of this server.");
+    }
+
+    #[test]
+    fn test_search_cache() {
+        let mut ui = test_ui(Config::default());
+        let text = format!(
+            "gopher://127.0.0.1:{}/0/about.txt",
+            serve("Gophers\r\ndig long TUNNELS\r\n")
+        );
+        let menu = format!(
+            "gopher://127.0.0.1:{}/1/",
+            serve("1Phlog\t/tunnels\tphkt.io\t70\r\niNo match here\r\n")
+        );
+        ui.open("About", &text).unwrap();
+        ui.open(&menu, &menu).unwrap();
+
+        ui.open("Search", "gopher://phetch/7/search?tunnels")
+            .unwrap();
+        let raw = ui.views[2].raw().to_string();
+        assert!(raw.starts_with("iPages from this session that mention \"tunnels\":"));
+        assert!(raw.contains("0About\t/about.txt\t127.0.0.1\t"));
+        assert!(raw.contains("\r\ni  dig long TUNNELS\r\n"));
+        // selectors don't count
+        assert!(!raw.contains(&menu[..menu.len() - 2]));
+
+        ui.open("Search", "gopher://phetch/1/search?moles").unwrap();
+        assert!(ui.views[3]
+            .raw()
+            .starts_with("iNo pages from this session mention \"moles\"."));
+    }
+
+    #[test]
+    fn test_toggle_hex_dump() {
+        let mut ui = test_ui(Config::default());
+        let url = format!("gopher://127.0.0.1:{}/0/odd.txt", serve("ok, then\x07\r\n"));
+        ui.open("odd", &url).unwrap();
+        ui.process_action(Action::Keypress(Key::Char('X'))).unwrap();
+        assert_eq!(ui.views.len(), 2);
+        assert!(ui.views[1].is_hex_dump());
+        assert_eq!(ui.views[1].url(), url);
+        assert!(ui.views[1]
+            .raw()
+            .starts_with("00000000  6f 6b 2c 20 74 68 65 6e  07 0d 0a "));
+
+        ui.process_action(Action::Keypress(Key::Char('X'))).unwrap();
+        assert_eq!(ui.focused, 0);
+        assert!(!ui.views[0].is_hex_dump());
+    }
+
+    #[test]
+    fn test_pinned_page_reloads() {
+        let clock = ManualClock::install(Duration::from_secs(1_000));
+        let (port, hits) = serve_counted("iStatus: up\r\n");
+        let url = format!("gopher://127.0.0.1:{}/1/", port);
+        let mut ui = test_ui(Config {
+            pin_interval: 30,
+            ..Config::default()
+        });
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+        ui.open("Status", &url).unwrap();
+        assert_eq!(ui.pin_wait(), None);
+        ui.process_action(Action::Keypress(Key::Char('L'))).unwrap();
+        assert_eq!(ui.status, "Pinned. Reloading every 30s, L to stop.");
+        assert!(ui.render_conn_status().unwrap().contains("PIN"));
+        assert_eq!(ui.pin_wait(), Some(Duration::from_secs(30)));
+
+        // pressing keys holds off the reload for a bit
+        clock.advance(Duration::from_secs(29));
+        keys.send(Key::Down).unwrap();
+        ui.update();
+        clock.advance(Duration::from_secs(1));
+        let idle = PIN_IDLE - Duration::from_secs(1);
+        assert_eq!(ui.pin_wait(), Some(idle));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        clock.advance(idle);
+        assert_eq!(ui.pin_wait(), Some(Duration::ZERO));
+        ui.update();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert!(ui.status.starts_with("Reloaded at "));
+        assert_eq!(urls(&ui), vec![url.as_str()]);
+        assert_eq!(ui.pin_wait(), Some(Duration::from_secs(30)));
+
+        // only while it's on screen
+        ui.open("Help", "gopher://phetch/1/help").unwrap();
+        assert_eq!(ui.pin_wait(), None);
+        ui.process_action(Action::Keypress(Key::Char('L'))).unwrap();
+        assert_eq!(ui.status, "Only menus and text pages can be pinned.");
+        back(&mut ui);
+        ui.process_action(Action::Keypress(Key::Char('L'))).unwrap();
+        assert_eq!(ui.status, "Unpinned.");
+        assert_eq!(ui.pin_wait(), None);
+    }
+
+    #[test]
+    fn test_pinned_page_survives_failed_reload() {
+        use std::{
+            io::{BufRead, BufReader},
+            net::TcpListener,
+        };
+        let clock = ManualClock::install(Duration::from_secs(2_000));
+        // answers once, then goes away
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let mut client = server.incoming().next().unwrap().unwrap();
+            BufRead::read_line(&mut BufReader::new(&client), &mut String::new()).unwrap();
+            client.write_all(b"iStatus: up\r\n").unwrap();
+        });
+        let url = format!("gopher://127.0.0.1:{}/1/", port);
+        let mut ui = test_ui(Config {
+            pin_interval: 30,
+            ..Config::default()
+        });
+        ui.open("Status", &url).unwrap();
+        handle.join().unwrap();
+        ui.process_action(Action::Keypress(Key::Char('L'))).unwrap();
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(ui.pin_wait(), Some(Duration::ZERO));
+        ui.update();
+        assert!(
+            ui.status.starts_with("Couldn't reload pinned page: "),
+            "{}",
+            ui.status
+        );
+        assert_eq!(urls(&ui), vec![url.as_str()]);
+        assert!(ui.views[0].render().contains("Status: up"));
+        assert_eq!(ui.pin_wait(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_cycle_theme() {
+        let mut ui = test_ui(Config::default());
+        let url = format!(
+            "gopher://127.0.0.1:{}/1/",
+            serve("1Phlog\t/phlog\tphkt.io\t70\r\n")
+        );
+        ui.open("Home", &url).unwrap();
+        let render = |ui: &mut UI| {
+            ui.views[0].term_size(80, 24);
+            ui.views[0].render()
+        };
+        assert!(render(&mut ui).contains("\x1b[94mPhlog"));
+
+        ui.process_action(Action::Keypress(Key::Char('C'))).unwrap();
+        assert_eq!(ui.status, "Theme: light");
+        assert!(render(&mut ui).contains("\x1b[34mPhlog"));
+
+        for _ in 0..3 {
+            ui.process_action(Action::Keypress(Key::Char('C'))).unwrap();
+        }
+        assert_eq!(ui.status, "Theme: default");
+        ui.process_action(Action::Keypress(Key::Char('C'))).unwrap();
+        assert_eq!(ui.status, "Theme: your own");
+        assert_eq!(ui.config.read().unwrap().theme_preset, "default");
+        assert!(render(&mut ui).contains("\x1b[94mPhlog"));
+    }
+
+    #[test]
+    fn test_cycle_back_to_own_theme() {
+        let mut theme = Theme::preset("light").unwrap();
+        theme.item_menu = theme::to_color("red");
+        let mut ui = test_ui(Config {
+            theme,
+            theme_preset: "light".into(),
+            ..Config::default()
+        });
+        let url = format!(
+            "gopher://127.0.0.1:{}/1/",
+            serve("1Phlog\t/phlog\tphkt.io\t70\r\n")
+        );
+        ui.open("Home", &url).unwrap();
+        let render = |ui: &mut UI| {
+            ui.views[0].term_size(80, 24);
+            ui.views[0].render()
+        };
+        assert!(render(&mut ui).contains("\x1b[91mPhlog"));
+
+        // every built-in theme, starting after the user's...
+        for preset in &["high-contrast", "mono", "default", "light"] {
+            ui.process_action(Action::Keypress(Key::Char('C'))).unwrap();
+            assert_eq!(ui.status, format!("Theme: {}", preset));
+        }
+        assert!(!render(&mut ui).contains("\x1b[91mPhlog"));
+
+        // ...then the user's own again, overrides and all
+        ui.process_action(Action::Keypress(Key::Char('C'))).unwrap();
+        assert_eq!(ui.status, "Theme: your own");
+        assert_eq!(ui.config.read().unwrap().theme_preset, "light");
+        assert!(render(&mut ui).contains("\x1b[91mPhlog"));
+    }
+
+    #[test]
+    fn test_render_focus() {
+        let mut ui = test_ui(Config::default());
+        ui.add_view(text(&ui, "gopher://phkt.io/0/about.txt"));
+        assert_eq!(ui.render_focus(), None);
+
+        let mut ui = test_ui(Config {
+            focus_marker: ">".into(),
+            ..Config::default()
+        });
+        assert_eq!(ui.render_focus(), None);
+        ui.add_view(text(&ui, "gopher://phkt.io/0/about.txt"));
+        ui.set_status("Loaded.");
+        let marker = format!("{}>\x1b[0m ", ui.config.read().unwrap().theme.ui_focus);
+        assert_eq!(ui.render_focus().unwrap(), marker);
+        assert!(ui.render_status().contains(&format!("{}Loaded.", marker)));
+    }
+
+    #[test]
+    fn test_render_title() {
+        let mut ui = test_ui(Config::default());
+        ui.add_view(text(&ui, "gopher://phkt.io/0/about.txt"));
+        assert_eq!(ui.render_title(), None);
+
+        let mut ui = test_ui(Config {
+            set_title: true,
+            ..Config::default()
+        });
+        assert_eq!(ui.render_title(), None);
+        let url = format!("gopher://127.0.0.1:{}/1/", serve("iHi\r\n"));
+        ui.open("Home\x1b[2J", &url).unwrap();
+        assert_eq!(
+            ui.render_title().unwrap(),
+            "\x1b[22;0t\x1b]0;phetch: Home[2J\x07"
+        );
+        // only when it changes
+        assert_eq!(ui.render_title(), None);
+        ui.add_view(text(&ui, "gopher://phkt.io/0/about.txt"));
+        assert_eq!(
+            ui.render_title().unwrap(),
+            "\x1b]0;phetch: gopher://phkt.io/0/about.txt\x07"
+        );
+        // don't touch the real terminal's title on drop
+        ui.title.clear();
+    }
+
+    #[test]
+    fn test_format_status() {
+        let menu = Menu::from(
+            "gopher://phkt.io/1/",
+            "iHi\r\n1One\t/1\tphkt.io\t70\r\n0Two\t/2\tphkt.io\t70\r\n".into(),
+            SharedConfig::default(),
+            false,
+        );
+        assert_eq!(
+            format_status(
+                "{title} - {url} [{type}] {pos} {links} links {nope} {",
+                &menu,
+                "Home"
+            ),
+            "Home - gopher://phkt.io/1/ [Menu] 1/3 2 links {nope} {"
+        );
+
+        let mut ui = test_ui(Config {
+            status_format: "{title}: {pos}".into(),
+            ..Config::default()
+        });
+        ui.add_view(Box::new(menu));
+        assert_eq!(ui.idle_status().unwrap(), "gopher://phkt.io/1/: 1/3");
+        ui.titles
+            .insert("gopher://phkt.io/1/".into(), "Home".into());
+        assert!(ui.render_status().contains("Home: 1/3"));
+        // messages win
+        ui.set_status("Hello!");
+        assert!(!ui.render_status().contains("Home"));
+    }
+
+    #[test]
+    fn test_scrollbar() {
+        let mut ui = test_ui(Config::default());
+        let body = (0..40).map(|i| format!("line {}\n", i)).collect::<String>();
+        ui.add_view(Box::new(Text::from(
+            "gopher://phkt.io/0/long",
+            body.into_bytes(),
+            ui.config.clone(),
+            false,
+        )));
+        ui.term_size(40, 11);
+        assert_eq!(ui.render_scrollbar(), None);
+
+        ui.config.write().unwrap().scrollbar = true;
+        let bar = ui.render_scrollbar().unwrap();
+        let cells = |bar: &str, c: char| {
+            (1..=10)
+                .filter(|row| bar.contains(&format!("{}{}", terminal::Goto(40, *row), c)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(cells(&bar, '█'), vec![1, 2]);
+        assert_eq!(cells(&bar, '│'), (3..=10).collect::<Vec<_>>());
+
+        ui.views[0].term_size(39, 11);
+        ui.views[0].respond(Key::End);
+        let bar = ui.render_scrollbar().unwrap();
+        assert_eq!(cells(&bar, '█'), vec![8, 9]);
+        assert!(!bar.contains(&terminal::Goto(40, 11).to_string()));
+
+        // reader mode hides it
+        ui.views[0].respond(Key::Char('z'));
+        assert_eq!(ui.render_scrollbar(), None);
+    }
+
+    #[test]
+    fn test_bookmarks_alias() {
+        for url in ["gopher://bookmarks/", "GOPHER://bookmarks"] {
+            let mut ui = test_ui(Config::default());
+            ui.open("Bookmarks", url).unwrap();
+            assert_eq!(urls(&ui), vec!["gopher://phetch/1/bookmarks"]);
+        }
+        assert_eq!(
+            expand_alias("gopher://bookmarks.org/".into()),
+            "gopher://bookmarks.org/"
+        );
+    }
+
+    #[test]
+    fn test_merged_bookmarks() {
+        let (port, hits) = serve_counted("1phetch\t/phetch\tphkt.io\t70\r\n.\r\n");
+        let links = format!("gopher://127.0.0.1:{}/1/links", port);
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let down = format!("gopher://{}/1/links", server.local_addr().unwrap());
+        drop(server);
+
+        let mut ui = test_ui(Config {
+            bookmarks: vec![down.clone(), links.clone()],
+            ..Config::default()
+        });
+        ui.open("Bookmarks", "gopher://phetch/1/bookmarks").unwrap();
+        let raw = ui.views[0].raw().to_string();
+        let down_at = raw.find(&down).unwrap();
+        let links_at = raw.find(&links).unwrap();
+        assert!(down_at < links_at);
+        assert!(raw[down_at..links_at].contains("\n3Couldn't load bookmarks"));
+        assert!(raw[links_at..].contains("\n1phetch\t/phetch\tphkt.io\t70\r\n"));
+        assert!(!raw.contains("\n.\r\n"));
+
+        // fetched once a session
+        ui.open("Help", "gopher://phetch/1/help").unwrap();
+        ui.open("Bookmarks", "gopher://phetch/1/bookmarks").unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_remote_bookmarks_follow_host_rules() {
+        let (port, hits) = serve_counted("1secret\t/\t127.0.0.1\t70\r\n");
+        let source = format!("gopher://127.0.0.1:{}/1/links", port);
+        let mut ui = test_ui(Config {
+            bookmarks: vec![source],
+            deny_hosts: vec!["127.0.0.1".into()],
+            ..Config::default()
+        });
+        ui.open("Bookmarks", "gopher://phetch/1/bookmarks").unwrap();
+        let raw = ui.views[0].raw();
+        assert!(raw.contains("\n3Couldn't load bookmarks: Blocked by policy: 127.0.0.1"));
+        assert!(!raw.contains("secret"));
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_ctrl_l_repaints() {
+        let mut ui = test_ui(Config::default());
+        let view = text(&ui, "gopher://phetch/1/home");
+        ui.add_view(view);
+        ui.dirty = false;
+        ui.process_action(Action::Keypress(Key::Ctrl('l'))).unwrap();
+        assert!(ui.dirty);
+        assert_eq!(ui.views.len(), 1);
+    }
+
+    #[test]
+    fn test_reload_keeps_wide_per_url() {
+        let mut ui = test_ui(Config {
+            wide_per_url: true,
+            ..Config::default()
+        });
+        let url = format!("gopher://127.0.0.1:{}/0/phlog", serve("hi there"));
+        ui.open("test", &url).unwrap();
+        assert!(!ui.views[0].wide());
+
+        ui.process_action(Action::Keypress(Key::Char('w'))).unwrap();
+        assert!(ui.views[0].wide());
+        assert!(!ui.config.read().unwrap().wide);
+
+        ui.open("test", &url).unwrap(); // same URL = reload
+        assert_eq!(ui.views.len(), 1);
+        assert!(ui.views[0].wide());
+
+        // other pages use the session setting
+        let other = text(&ui, "gopher://127.0.0.1/0/other");
+        ui.add_view(other);
+        assert!(!ui.views[1].wide());
+    }
+
+    #[test]
+    fn test_keymap() {
+        let mut config = Config::default();
+        config.keymap.bind("back", "h").unwrap();
+        let mut ui = test_ui(config);
+        ui.open("Help", "gopher://phetch/1/help").unwrap();
+        ui.open("Keys", "gopher://phetch/1/help/keys").unwrap();
+        let (keys, receiver) = channel();
+        ui.keys = Arc::new(Mutex::new(receiver));
+        keys.send(Key::Char('h')).unwrap();
+        let action = ui.process_view_input();
+        ui.process_action(action).unwrap();
+        assert_eq!(ui.focused, 0);
+    }
+
+    #[test]
+    fn test_reload_keeps_place() {
+        let mut menu = String::new();
+        for i in 0..100 {
+            menu.push_str(&format!("1link {}\t/{}\t127.0.0.1\t70\r\n", i, i));
+        }
+        let url = format!("gopher://127.0.0.1:{}/1/", serve(Box::leak(menu.into())));
+        let mut ui = test_ui(Config::default());
+        ui.open("test", &url).unwrap();
+        ui.views[0].restore_place(40, Some("gopher://127.0.0.1/1/50"));
+        assert_eq!(
+            ui.views[0].place(),
+            (40, Some("gopher://127.0.0.1/1/50".into()))
+        );
+
+        ui.process_action(Action::Keypress(Key::Char('R'))).unwrap();
+        assert_eq!(ui.views.len(), 1);
+        assert_eq!(
+            ui.views[0].place(),
+            (40, Some("gopher://127.0.0.1/1/50".into()))
+        );
+
+        // text pages keep their scroll offset, but not past the end
+        let url = format!("gopher://127.0.0.1:{}/0/", serve("one\ntwo\nthree\n"));
+        ui.open("text", &url).unwrap();
+        ui.views[1].restore_place(2, None);
+        ui.process_action(Action::Keypress(Key::Char('R'))).unwrap();
+        assert_eq!(ui.views.len(), 2);
+        assert_eq!(ui.views[1].place(), (2, None));
+        ui.views[1].restore_place(99, None);
+        assert!(ui.views[1].place().0 < 5);
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let mut ui = test_ui(Config::default());
+        let res = ui.open("test", "gopher://phetch/1/nope");
+        assert!(matches!(res, Err(Error::NotFound(url)) if url == "gopher://phetch/1/nope"));
+
+        let url = format!("gopher://127.0.0.1:{}/T/ibm", serve("3270"));
+        let res = ui.open("test", &url);
+        assert!(matches!(res, Err(Error::Unsupported(Type::Telnet3270))));
+        assert!(ui.views.is_empty());
+    }
+
+    #[test]
+    fn test_reopen_closed() {
+        let mut ui = test_ui(Config::default());
+        let key = |ui: &mut UI, c| ui.process_action(Action::Keypress(Key::Char(c)));
+        assert!(key(&mut ui, 'T').is_err());
+
+        for url in &[
+            "gopher://phetch/1/home",
+            "gopher://phetch/1/help",
+            "gopher://phetch/1/help/keys",
+        ] {
+            let view = text(&ui, url);
+            ui.add_view(view);
+        }
+        back(&mut ui);
+        back(&mut ui);
+        let view = text(&ui, "gopher://phetch/1/about");
+        ui.add_view(view);
+        assert_eq!(
+            urls(&ui),
+            vec!["gopher://phetch/1/home", "gopher://phetch/1/about"]
+        );
+
+        // closest to where we were comes back first
+        key(&mut ui, 'T').unwrap();
+        assert_eq!(
+            urls(&ui),
+            vec![
+                "gopher://phetch/1/home",
+                "gopher://phetch/1/about",
+                "gopher://phetch/1/help"
+            ]
+        );
+        key(&mut ui, 'T').unwrap();
+        assert_eq!(ui.views[ui.focused].url(), "gopher://phetch/1/help/keys");
+
+        // going back doesn't close anything
+        back(&mut ui);
+        back(&mut ui);
+        assert!(key(&mut ui, 'T').is_err());
+
+        // closed while reopening: those come back last
+        let view = text(&ui, "gopher://phetch/1/history");
+        ui.add_view(view);
+        back(&mut ui);
+        key(&mut ui, 'T').unwrap();
+        assert_eq!(
+            urls(&ui),
+            vec![
+                "gopher://phetch/1/home",
+                "gopher://phetch/1/about",
+                "gopher://phetch/1/help"
+            ]
+        );
+        assert_eq!(
+            ui.closed,
+            vec!["gopher://phetch/1/history", "gopher://phetch/1/help/keys"]
+        );
+        key(&mut ui, 'T').unwrap();
+        assert_eq!(ui.views[ui.focused].url(), "gopher://phetch/1/help/keys");
+    }
+
+    #[test]
+    fn test_open_policy_push() {
+        let mut ui = test_ui(Config::default());
+        for url in &["gopher://a", "gopher://b", "gopher://c"] {
+            let view = text(&ui, url);
+            ui.add_view(view);
+        }
+        assert_eq!(urls(&ui), vec!["gopher://a", "gopher://b", "gopher://c"]);
+        assert_eq!(ui.focused, 2);
+
+        // opening from the middle of history drops forward history
+        back(&mut ui);
+        let view = text(&ui, "gopher://d");
+        ui.add_view(view);
+        assert_eq!(urls(&ui), vec!["gopher://a", "gopher://b", "gopher://d"]);
+        assert_eq!(ui.focused, 2);
+    }
+
+    #[test]
+    fn test_open_policy_same_host() {
+        let mut ui = test_ui(Config {
+            open: OpenPolicy::SameHost,
+            ..Config::default()
+        });
+        let here = serve("1there\t/\tlocalhost\t70\r\n");
+        let there = serve("iHi\r\n");
+        let home = format!("gopher://127.0.0.1:{}/1/", here);
+        let about = format!("gopher://127.0.0.1:{}/0/about", here);
+        let links = format!("gopher://127.0.0.1:{}/1/links", there);
+        let away = format!("gopher://localhost:{}/1/", there);
+
+        ui.open("home", &home).unwrap();
+        ui.open("links", &links).unwrap();
+        // same host, even on another port
+        assert_eq!(urls(&ui), vec![links.as_str()]);
+        // not a menu
+        ui.open("about", &about).unwrap();
+        assert_eq!(urls(&ui), vec![links.as_str(), about.as_str()]);
+        back(&mut ui);
+        // another host
+        ui.open("away", &away).unwrap();
+        assert_eq!(urls(&ui), vec![links.as_str(), away.as_str()]);
+        assert_eq!(ui.focused, 1);
+    }
+
+    #[test]
+    fn test_toggle_previous() {
+        let mut ui = test_ui(Config::default());
+        let view = text(&ui, "gopher://a");
+        ui.add_view(view);
+        let flip = |ui: &mut UI| ui.process_action(Action::Keypress(Key::Ctrl('6'))).unwrap();
+        flip(&mut ui);
+        assert_eq!(ui.focused, 0);
+
+        for url in &["gopher://b", "gopher://c", "gopher://d"] {
+            let view = text(&ui, url);
+            ui.add_view(view);
+        }
+        flip(&mut ui);
+        assert_eq!(ui.views[ui.focused].url(), "gopher://c");
+        flip(&mut ui);
+        assert_eq!(ui.views[ui.focused].url(), "gopher://d");
+
+        // jumps straight back, however far back you went
+        back(&mut ui);
+        back(&mut ui);
+        back(&mut ui);
+        ui.process_action(Action::Keypress(Key::Right)).unwrap();
+        assert_eq!(ui.views[ui.focused].url(), "gopher://b");
+        flip(&mut ui);
+        assert_eq!(ui.views[ui.focused].url(), "gopher://a");
+        flip(&mut ui);
+        assert_eq!(ui.views[ui.focused].url(), "gopher://b");
+
+        // a closed view can't be flipped back to
+        ui.process_action(Action::Keypress(Key::Right)).unwrap();
+        ui.process_action(Action::Keypress(Key::Right)).unwrap();
+        back(&mut ui);
+        let view = text(&ui, "gopher://e");
+        ui.replace_view(view);
+        flip(&mut ui);
+        assert_eq!(ui.views[ui.focused].url(), "gopher://e");
+    }
+
+    #[test]
+    fn test_open_policy_replace() {
+        let mut ui = test_ui(Config::default());
+        let view = text(&ui, "gopher://a");
+        ui.replace_view(view);
+        assert_eq!(urls(&ui), vec!["gopher://a"]);
+        assert_eq!(ui.focused, 0);
+
+        let view = text(&ui, "gopher://b");
+        ui.add_view(view);
+        let view = text(&ui, "gopher://c");
+        ui.replace_view(view);
+        assert_eq!(urls(&ui), vec!["gopher://a", "gopher://c"]);
+        assert_eq!(ui.focused, 1);
+
+        // back skips the replaced view, and there's nothing forward
+        back(&mut ui);
+        assert_eq!(ui.focused, 0);
+        let view = text(&ui, "gopher://d");
+        ui.replace_view(view);
+        assert_eq!(urls(&ui), vec!["gopher://d"]);
+        assert_eq!(ui.focused, 0);
+        ui.process_action(Action::Keypress(Key::Right)).unwrap();
+        assert_eq!(ui.focused, 0);
+    }
+}
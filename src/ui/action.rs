@@ -8,6 +8,9 @@ pub enum Action {
     None,
     /// Open a URL: open(title, url)
     Open(String, String),
+    /// Fetch the submenu at a URL so the focused Menu can show it
+    /// inline.
+    Expand(String),
     /// If the View doesn't know how to react, it returns the keypress.
     Keypress(Key),
     /// Redraw the screen. Can cause a flicker
@@ -24,6 +27,8 @@ pub enum Action {
     /// not run.
     /// Prompt(Prompt Query, Callback)
     Prompt(String, Box<dyn FnOnce(String) -> Action>),
+    /// Look for text in the focused View, with `View::find()`.
+    Find(String),
     /// Do more than one action.
     List(Vec<Action>),
     /// Display an error message.
@@ -42,11 +47,13 @@ impl fmt::Debug for Action {
         match self {
             Action::None => write!(f, "None"),
             Action::Open(title, url) => write!(f, "Open: {}, {}", title, url),
+            Action::Expand(url) => write!(f, "Expand: {}", url),
             Action::Keypress(key) => write!(f, "Keypress: {:?}", key),
             Action::Redraw => write!(f, "Redraw"),
             Action::Draw(s) => write!(f, "Draw: {:?}", s),
             Action::Status(s) => write!(f, "Status: {}", s),
             Action::Prompt(s, _) => write!(f, "Prompt: {}", s),
+            Action::Find(s) => write!(f, "Find: {}", s),
             Action::List(li) => {
                 writeln!(f, "List: ")?;
                 for a in li {
@@ -0,0 +1,98 @@
+use crate::{gopher::Type, ui::View};
+
+/// Parsed views we've already loaded, so going back to a page doesn't
+/// mean fetching and parsing it all over again. The cache keeps its
+/// own copies, apart from the views in the UI's history, so evicting
+/// one never touches what's on screen.
+///
+/// Entries are kept least recently used first, and the oldest goes
+/// once there are more than `size` of them.
+pub(crate) struct Cache {
+    /// How many views to keep. 0 = don't cache anything
+    size: usize,
+    /// (URL, type it was shown as, fresh copy of its view)
+    entries: Vec<(String, Type, Box<dyn View>)>,
+}
+
+impl Cache {
+    /// An empty cache that holds up to `size` views.
+    pub(crate) fn new(size: usize) -> Cache {
+        Cache {
+            size,
+            entries: vec![],
+        }
+    }
+
+    /// A copy of the view for `url` shown as `typ`, if we have one.
+    pub(crate) fn get(&mut self, url: &str, typ: Type) -> Option<Box<dyn View>> {
+        let idx = self.position(url, typ)?;
+        let entry = self.entries.remove(idx);
+        let view = entry.2.clone_view();
+        self.entries.push(entry);
+        Some(view)
+    }
+
+    /// Remember a copy of `view`, which was loaded from `url` and
+    /// shown as `typ`.
+    pub(crate) fn insert(&mut self, url: &str, typ: Type, view: &dyn View) {
+        if self.size == 0 {
+            return;
+        }
+        if let Some(idx) = self.position(url, typ) {
+            self.entries.remove(idx);
+        }
+        self.entries.push((url.to_string(), typ, view.clone_view()));
+        if self.entries.len() > self.size {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Do we have a copy of `url`, shown as anything?
+    pub(crate) fn contains(&self, url: &str) -> bool {
+        self.entries.iter().any(|(u, _, _)| u == url)
+    }
+
+    /// Forget every copy of `url`, so it's fetched fresh next time.
+    pub(crate) fn remove(&mut self, url: &str) {
+        self.entries.retain(|(u, _, _)| u != url);
+    }
+
+    /// Cached pages with `query` in them, ignoring case, newest first,
+    /// as (URL, first line it's on). For menus only the text of each
+    /// line is searched, not its selector or host.
+    pub(crate) fn search(&self, query: &str) -> Vec<(String, String)> {
+        let query = query.to_lowercase();
+        let mut found: Vec<(String, String)> = vec![];
+        if query.is_empty() {
+            return found;
+        }
+        for (url, typ, view) in self.entries.iter().rev() {
+            if found.iter().any(|(u, _)| u == url) {
+                continue;
+            }
+            let menu = matches!(typ, Type::Menu | Type::Search);
+            let hit = view
+                .raw()
+                .lines()
+                .map(|line| if menu { menu_text(line) } else { line })
+                .find(|line| line.to_lowercase().contains(&query));
+            if let Some(line) = hit {
+                found.push((url.clone(), line.trim().to_string()));
+            }
+        }
+        found
+    }
+
+    fn position(&self, url: &str, typ: Type) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(u, t, _)| u == url && *t == typ)
+    }
+}
+
+/// The text of a Gophermap line, without its type or selector.
+fn menu_text(line: &str) -> &str {
+    let mut chars = line.split('\t').next().unwrap_or_default().chars();
+    chars.next();
+    chars.as_str()
+}
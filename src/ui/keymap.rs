@@ -0,0 +1,187 @@
+use super::Key;
+
+/// Actions that can get extra keys with `key.NAME` in phetch.conf,
+/// and the key phetch already uses for each. A bound key acts just
+/// like pressing that one.
+const ACTIONS: &[(&str, Key)] = &[
+    ("back", Key::Left),
+    ("forward", Key::Right),
+    ("open", Key::Char('\n')),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("quit", Key::Char('q')),
+    ("reload", Key::Char('R')),
+    ("bookmark", Key::Char('s')),
+];
+
+/// Keys the user has bound to actions, on top of the usual ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keymap {
+    /// (key pressed, key it stands for)
+    bindings: Vec<(Key, Key)>,
+}
+
+impl Keymap {
+    /// Is `name` an action that can be bound?
+    pub fn is_action(name: &str) -> bool {
+        ACTIONS.iter().any(|(action, _)| *action == name)
+    }
+
+    /// Bind the space separated `keys` to `action`. A key that was
+    /// bound to something else before is moved.
+    pub fn bind(&mut self, action: &str, keys: &str) -> Result<(), String> {
+        let target = ACTIONS
+            .iter()
+            .find(|(name, _)| *name == action)
+            .map(|(_, key)| *key)
+            .ok_or_else(|| format!("Unknown action: {}", action))?;
+        let keys = keys
+            .split_whitespace()
+            .map(|name| parse_key(name).ok_or_else(|| format!("Unknown key: {}", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(format!("No keys given for {}", action));
+        }
+        for key in keys {
+            self.bindings.retain(|(k, _)| *k != key);
+            self.bindings.push((key, target));
+        }
+        Ok(())
+    }
+
+    /// Each bound key, named the way phetch.conf writes it, and the
+    /// action it's bound to, in the order they were bound.
+    pub fn bindings(&self) -> Vec<(String, &'static str)> {
+        self.bindings
+            .iter()
+            .filter_map(|(key, target)| {
+                let (action, _) = ACTIONS.iter().find(|(_, k)| k == target)?;
+                Some((key_name(*key), *action))
+            })
+            .collect()
+    }
+
+    /// The key phetch should act on when `key` is pressed.
+    pub fn translate(&self, key: Key) -> Key {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map_or(key, |(_, target)| *target)
+    }
+}
+
+/// Parse a key the way phetch.conf writes them: a single character,
+/// `ctrl-x`, or the name of a special key like `left` or `enter`.
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c));
+    }
+    let lower = name.to_ascii_lowercase();
+    if let Some(rest) = lower.strip_prefix("ctrl-") {
+        let mut chars = rest.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(Key::Ctrl(c)),
+            _ => None,
+        };
+    }
+    Some(match lower.as_str() {
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "enter" | "return" => Key::Char('\n'),
+        "space" => Key::Char(' '),
+        "tab" => Key::Char('\t'),
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "esc" | "escape" => Key::Esc,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pgup" | "pageup" => Key::PageUp,
+        "pgdown" | "pagedown" => Key::PageDown,
+        _ => return None,
+    })
+}
+
+/// The name `parse_key()` would read back as `key`.
+fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\n') => "enter".into(),
+        Key::Char(' ') => "space".into(),
+        Key::Char('\t') => "tab".into(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("ctrl-{}", c),
+        Key::Left => "left".into(),
+        Key::Right => "right".into(),
+        Key::Up => "up".into(),
+        Key::Down => "down".into(),
+        Key::Backspace => "backspace".into(),
+        Key::Delete => "delete".into(),
+        Key::Esc => "esc".into(),
+        Key::Home => "home".into(),
+        Key::End => "end".into(),
+        Key::PageUp => "pgup".into(),
+        Key::PageDown => "pgdown".into(),
+        key => format!("{:?}", key).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("h"), Some(Key::Char('h')));
+        assert_eq!(parse_key("H"), Some(Key::Char('H')));
+        assert_eq!(parse_key("ctrl-b"), Some(Key::Ctrl('b')));
+        assert_eq!(parse_key("Ctrl-B"), Some(Key::Ctrl('b')));
+        assert_eq!(parse_key("LEFT"), Some(Key::Left));
+        assert_eq!(parse_key("enter"), Some(Key::Char('\n')));
+        assert_eq!(parse_key("pgdown"), Some(Key::PageDown));
+        assert_eq!(parse_key("ctrl-"), None);
+        assert_eq!(parse_key("hyper-x"), None);
+    }
+
+    #[test]
+    fn test_bind() {
+        let mut keymap = Keymap::default();
+        keymap.bind("back", "h ctrl-h").unwrap();
+        keymap.bind("forward", "l").unwrap();
+        assert_eq!(keymap.translate(Key::Char('h')), Key::Left);
+        assert_eq!(keymap.translate(Key::Ctrl('h')), Key::Left);
+        assert_eq!(keymap.translate(Key::Char('l')), Key::Right);
+        // unbound keys are left alone
+        assert_eq!(keymap.translate(Key::Left), Key::Left);
+        assert_eq!(keymap.translate(Key::Char('x')), Key::Char('x'));
+        // binding a key again moves it
+        keymap.bind("quit", "h").unwrap();
+        assert_eq!(keymap.translate(Key::Char('h')), Key::Char('q'));
+
+        assert!(keymap.bind("dance", "d").is_err());
+        assert!(keymap.bind("up", "hyper-x").is_err());
+        assert!(keymap.bind("up", " ").is_err());
+    }
+
+    #[test]
+    fn test_bindings() {
+        let mut keymap = Keymap::default();
+        assert!(keymap.bindings().is_empty());
+        keymap.bind("back", "h ctrl-h").unwrap();
+        keymap.bind("open", "space").unwrap();
+        assert_eq!(
+            keymap.bindings(),
+            vec![
+                ("h".to_string(), "back"),
+                ("ctrl-h".to_string(), "back"),
+                ("space".to_string(), "open"),
+            ]
+        );
+        for name in &[
+            "h", "ctrl-h", "enter", "space", "tab", "left", "pgdown", "esc",
+        ] {
+            assert_eq!(key_name(parse_key(name).unwrap()), *name);
+        }
+    }
+}
@@ -1,9 +1,12 @@
+use crate::menu::LinkFormat;
+
 /// The mode our text UI is in. Run mode is the default while
 /// Print doesn't show the cursor, among other things.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
 pub enum Mode {
     /// Default, interactive mode.
     ///   phetch URL
+    #[default]
     Run,
 
     /// Just print a rendered version of a URL.
@@ -24,10 +27,10 @@ pub enum Mode {
     /// Print current theme
     ///   phetch --theme
     PrintTheme,
-}
-
-impl Default for Mode {
-    fn default() -> Self {
-        Mode::Run
-    }
+    /// Print the keyboard shortcuts as a Gophermap.
+    ///   phetch --print-keys
+    PrintKeys,
+    /// Print the links in a menu.
+    ///   phetch --dump-links [--json] URL
+    DumpLinks(LinkFormat),
 }
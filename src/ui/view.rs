@@ -1,13 +1,25 @@
 use {
-    crate::{encoding::Encoding, ui},
+    crate::{encoding::Encoding, ui, utils},
     std::fmt,
 };
 
+/// Whether rendered output keeps its colors.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum ColorMode {
+    /// Colors from the theme and the page itself.
+    #[default]
+    Color,
+    /// Plain text. Every color code is stripped out.
+    Mono,
+}
+
 /// Views represent what's on screen, a Gopher Menu/Text/etc item.
 pub trait View: fmt::Display {
     /// Respond to a user action, specifically a keypress, by
     /// returning an Action enum.
     fn respond(&mut self, key: ui::Key) -> ui::Action;
+    /// A fresh copy of this view, for the page cache.
+    fn clone_view(&self) -> Box<dyn View>;
     /// Create a String of the current view's state that can be
     /// printed to the screen.
     fn render(&mut self) -> String;
@@ -25,8 +37,76 @@ pub trait View: fmt::Display {
     fn wide(&mut self) -> bool;
     /// Set the current screen size.
     fn term_size(&mut self, cols: usize, rows: usize);
+    /// Render this view for a `cols` x `rows` screen without needing
+    /// a terminal, which is handy for tests or for programs embedding
+    /// phetch. Cursor movement and clearing codes are kept either way.
+    fn render_with(&mut self, cols: usize, rows: usize, color: ColorMode) -> String {
+        self.term_size(cols, rows);
+        let out = self.render();
+        match color {
+            ColorMode::Color => out,
+            ColorMode::Mono => utils::strip_colors(&out),
+        }
+    }
+    /// How far down the page we've scrolled and how many rows it has
+    /// in all, as (offset, total), for drawing a scrollbar.
+    fn scroll_position(&self) -> Option<(usize, usize)> {
+        None
+    }
+    /// Where we are on the page: the scroll offset and the URL of the
+    /// selected link, if any, so a reload can put us back there.
+    fn place(&self) -> (usize, Option<String>) {
+        (0, None)
+    }
+    /// Go back to a `place()` taken from an earlier copy of this page,
+    /// as close as its new content allows.
+    fn restore_place(&mut self, _offset: usize, _link: Option<&str>) {}
+    /// A short description of what's on the page, shown in the status
+    /// line when it's opened.
+    fn summary(&self) -> Option<String> {
+        None
+    }
+    /// In reader mode, which leaves out the status line and
+    /// scrollbar?
+    fn reader(&self) -> bool {
+        false
+    }
+    /// Every link on the page as (title, URL), in order.
+    fn links(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+    /// How many links are on the page.
+    fn link_count(&self) -> usize {
+        self.links().len()
+    }
+    /// Show the submenu at `url`, fetched as `raw`, inline under its
+    /// link.
+    fn expand(&mut self, _url: &str, _raw: String) {}
     /// The current encoding.
     fn encoding(&self) -> Encoding {
         Encoding::default()
     }
+    /// The bytes the server sent, before any decoding.
+    fn raw_bytes(&self) -> &[u8] {
+        self.raw().as_bytes()
+    }
+    /// The page as plain text, the way it reads on screen but without
+    /// colors or numbers. Just `raw()` for most pages.
+    fn plain_text(&mut self) -> String {
+        self.raw().to_string()
+    }
+    /// Is this a hex dump of another view, from `X`?
+    fn is_hex_dump(&self) -> bool {
+        false
+    }
+    /// Is the View reading typed text, like a filter, so keys should
+    /// reach it as they are instead of going through the keymap?
+    fn taking_input(&self) -> bool {
+        false
+    }
+    /// Jump to the first match for `query` from `/`, if the View can
+    /// search its own text.
+    fn find(&mut self, _query: &str) -> ui::Action {
+        ui::Action::None
+    }
 }
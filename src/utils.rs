@@ -1,7 +1,9 @@
 //! Helper functions and macros.
+use crate::config::Clipboard;
 use std::{
     borrow::Cow,
     io::{Result, Write},
+    ops::Range,
     process::{self, Stdio},
 };
 
@@ -30,13 +32,13 @@ macro_rules! log {
     };
 }
 
-/// Creates an Other kind of io::Error.
+/// Creates an `Error::Other` with a message for the user.
 macro_rules! error {
     ($e:expr) => {
-        std::io::Error::new(std::io::ErrorKind::Other, $e)
+        $crate::error::Error::Other($e.to_string())
     };
     ($e:expr, $($y:expr),*) => {
-        error!(format!($e, $($y),*))
+        $crate::error::Error::Other(format!($e, $($y),*))
     };
 }
 
@@ -55,15 +57,155 @@ pub fn human_bytes(bytes: usize) -> String {
     format!("{}{}", count, tag)
 }
 
-/// Copies data to the system clipboard, if possible.
-/// Uses `pbcopy` on macOS or `xclip -sel clip` on Linux.
-pub fn copy_to_clipboard(data: &str) -> Result<()> {
+/// Removes ANSI color codes, like `\x1b[91m`, from a string. Other
+/// escape sequences are left alone.
+pub fn strip_colors(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("\x1b[") {
+        out.push_str(&rest[..start]);
+        match color_code_len(&rest[start..]) {
+            Some(len) => rest = &rest[start + len..],
+            None => {
+                out.push_str("\x1b[");
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the ANSI color code `s` starts with, if it starts with
+/// one.
+fn color_code_len(s: &str) -> Option<usize> {
+    let code = s.strip_prefix("\x1b[")?;
+    let end = code.find(|c: char| !c.is_ascii_digit() && c != ';')?;
+    if code[end..].starts_with('m') {
+        Some(end + 3)
+    } else {
+        None
+    }
+}
+
+/// Where `find` shows up in `s`, ignoring case in any language, as
+/// byte ranges of `s`. Color codes in `s` are skipped, so they're
+/// never matched or cut in half.
+pub fn find_matches(s: &str, find: &str) -> Vec<Range<usize>> {
+    let needle = find.to_lowercase().chars().collect::<Vec<_>>();
+    if needle.is_empty() {
+        return vec![];
+    }
+    // where each lowercased char came from in `s`
+    let mut chars = vec![];
+    let mut i = 0;
+    while let Some(c) = s[i..].chars().next() {
+        if let Some(len) = color_code_len(&s[i..]) {
+            i += len;
+            continue;
+        }
+        let end = i + c.len_utf8();
+        chars.extend(c.to_lowercase().map(|lower| (i..end, lower)));
+        i = end;
+    }
+
+    let mut out = vec![];
+    let mut k = 0;
+    while k + needle.len() <= chars.len() {
+        let window = &chars[k..k + needle.len()];
+        if window.iter().map(|(_, c)| c).eq(needle.iter()) {
+            out.push(window[0].0.start..window[needle.len() - 1].0.end);
+            k += needle.len();
+        } else {
+            k += 1;
+        }
+    }
+    out
+}
+
+/// Show every case-insensitive match for `find` in `line` in reverse
+/// video.
+pub fn highlight<'a>(line: &'a str, find: &str) -> Cow<'a, str> {
+    let matches = find_matches(line, find);
+    if matches.is_empty() {
+        return line.into();
+    }
+    let mut out = String::new();
+    let mut last = 0;
+    for m in matches {
+        out.push_str(&line[last..m.start]);
+        out.push_str("\x1b[7m");
+        out.push_str(&line[m.clone()]);
+        out.push_str("\x1b[27m");
+        last = m.end;
+    }
+    out.push_str(&line[last..]);
+    out.into()
+}
+
+/// How many columns `c` takes up in a terminal: two for wide
+/// characters like CJK and most emoji, none for control characters
+/// and combining marks, and one for everything else.
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0..=0x1f | 0x7f..=0x9f => 0,
+        0x300..=0x36f | 0x200b..=0x200f | 0xfe00..=0xfe0f => 0,
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f900..=0x1f9ff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// Copies data to the system clipboard and/or primary selection,
+/// according to `target`. Uses `pbcopy` on macOS, `wl-copy` on
+/// Wayland, or `xclip` on X11. If setting one selection fails, the
+/// other is still tried.
+pub fn copy_to_clipboard(data: &str, target: Clipboard) -> Result<()> {
+    let selections: &[&str] = match target {
+        Clipboard::Clipboard => &["clipboard"],
+        Clipboard::Primary => &["primary"],
+        Clipboard::Both => &["clipboard", "primary"],
+    };
+    let mut res = Ok(());
+    for selection in selections {
+        if let Err(e) = copy_to_selection(data, selection) {
+            res = res.and(Err(e));
+        }
+    }
+    res
+}
+
+/// Copies data to one `selection`: "clipboard" or "primary".
+fn copy_to_selection(data: &str, selection: &str) -> Result<()> {
     #[cfg(target_os = "macos")]
-    let mut cmd = process::Command::new("pbcopy");
-    #[cfg(not(target_os = "macos"))]
-    let mut cmd = process::Command::new("xclip");
+    let mut cmd = {
+        let _ = selection;
+        process::Command::new("pbcopy")
+    };
     #[cfg(not(target_os = "macos"))]
-    let cmd = cmd.args(&["-sel", "clip"]);
+    let mut cmd = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut cmd = process::Command::new("wl-copy");
+        if selection == "primary" {
+            cmd.arg("--primary");
+        }
+        cmd
+    } else {
+        let mut cmd = process::Command::new("xclip");
+        cmd.args(["-sel", selection]);
+        cmd
+    };
 
     cmd.stdin(Stdio::piped())
         .spawn()
@@ -71,7 +213,7 @@ pub fn copy_to_clipboard(data: &str) -> Result<()> {
             let child_stdin = child.stdin.as_mut().unwrap();
             child_stdin.write_all(data.as_bytes())
         })
-        .map_err(|e| error!("Clipboard error: {}", e))
+        .map_err(|e| error!("Clipboard error ({}): {}", selection, e).into())
 }
 
 /// Used to open non-Gopher URLs.
@@ -93,16 +235,54 @@ pub fn open_external(url: &str) -> Result<()> {
         Err(error!(
             "`open` error: {}",
             String::from_utf8_lossy(&output.stderr).trim_end()
-        ))
+        )
+        .into())
+    }
+}
+
+/// Opens `raw` in `$EDITOR` for viewing. It's written to a temporary
+/// file which is removed once the editor exits, so changes are lost.
+pub fn open_editor(raw: &str) -> Result<()> {
+    use {crate::terminal, std::io};
+
+    let editor = match std::env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => editor,
+        _ => return Err(error!("$EDITOR isn't set").into()),
+    };
+
+    let path = std::env::temp_dir().join(format!("phetch-{}.gph", process::id()));
+    std::fs::write(&path, raw)?;
+
+    let mut stdout = io::stdout();
+    write!(stdout, "{}{}", terminal::ClearAll, terminal::Goto(1, 1))?;
+    stdout.flush()?;
+
+    // $EDITOR may include arguments, like `emacs -nw`
+    let mut args = editor.split_whitespace();
+    terminal::disable_raw_mode()?;
+    let res = process::Command::new(args.next().unwrap_or_default())
+        .args(args)
+        .arg(&path)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .status();
+    terminal::enable_raw_mode()?;
+    let _ = std::fs::remove_file(&path);
+
+    match res {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(error!("{} exited with {}", editor, status).into()),
+        Err(e) => Err(error!("Editor error: {}", e).into()),
     }
 }
 
-/// Opens a media file with `mpv` or `--media`.
+/// Opens a media file with `mpv` or `--media`. `url` can be a URL or
+/// the path to a downloaded file.
 pub fn open_media(program: &str, url: &str) -> Result<()> {
     use {crate::terminal, std::io};
 
     // mpv only supports /9/
-    let url = if program.ends_with("mpv") {
+    let url = if program.ends_with("mpv") && url.starts_with("gopher://") {
         Cow::from(url.replace("/;/", "/9/").replace("/s/", "/9/"))
     } else {
         Cow::from(url)
@@ -132,8 +312,12 @@ pub fn open_media(program: &str, url: &str) -> Result<()> {
         .stdout(Stdio::inherit())
         .spawn()
         .map_err(errfn)?;
-    cmd.wait().map_err(errfn)?;
+    let status = cmd.wait().map_err(errfn)?;
     terminal::enable_raw_mode()?;
 
-    Ok(())
+    if status.success() {
+        Ok(())
+    } else {
+        Err(error!("Media player error: {} exited with {}", program, status).into())
+    }
 }